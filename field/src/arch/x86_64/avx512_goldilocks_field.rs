@@ -411,8 +411,9 @@ mod tests {
     use crate::arch::x86_64::avx512_goldilocks_field::Avx512GoldilocksField;
     use crate::goldilocks_field::GoldilocksField;
     use crate::ops::Square;
+    use crate::packable::Packable;
     use crate::packed::PackedField;
-    use crate::types::Field64;
+    use crate::types::{Field, Field64, Sample};
 
     fn test_vals_a() -> [GoldilocksField; 8] {
         [
@@ -515,6 +516,57 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_packing_is_avx512() {
+        // Batched constraint evaluation packs by `Packable::Packing`, so it uses all eight lanes.
+        assert_eq!(<GoldilocksField as Packable>::Packing::WIDTH, 8);
+    }
+
+    #[test]
+    fn test_matches_scalar_random() {
+        type F = GoldilocksField;
+        type P = Avx512GoldilocksField;
+
+        // Values near the modulus, including non-canonical ones, exercise the reductions.
+        let edge_vals = [
+            F::ZERO,
+            F::ONE,
+            F::NEG_ONE,
+            GoldilocksField(F::ORDER),
+            GoldilocksField(F::ORDER + 1),
+            GoldilocksField(u64::MAX),
+            GoldilocksField(1 << 63),
+            GoldilocksField((1 << 32) - 1),
+        ];
+        let inputs = [(edge_vals, F::rand_array()), (F::rand_array(), edge_vals)]
+            .into_iter()
+            .chain((0..1000).map(|_| (F::rand_array(), F::rand_array())));
+
+        for (a_arr, b_arr) in inputs {
+            let c = F::rand();
+            let a = P::from_arr(a_arr);
+            let b = P::from_arr(b_arr);
+
+            let check = |res: P, op: &dyn Fn(F, F) -> F| {
+                for ((&x, &y), r) in a_arr.iter().zip(&b_arr).zip(res.as_arr()) {
+                    assert_eq!(r, op(x, y), "a = {a_arr:?}, b = {b_arr:?}");
+                }
+            };
+            check(a + b, &|x, y| x + y);
+            check(a - b, &|x, y| x - y);
+            check(a * b, &|x, y| x * y);
+            check(a.square(), &|x, _| x.square());
+            check(-a, &|x, _| -x);
+            check(a * c - b, &|x, y| x * c - y);
+            check(a + c, &|x, _| x + c);
+
+            let sum: P = [a, b].into_iter().sum();
+            check(sum, &|x, y| x + y);
+            let product: P = [a, b].into_iter().product();
+            check(product, &|x, y| x * y);
+        }
+    }
+
     #[test]
     fn test_interleave_is_involution() {
         let a_arr = test_vals_a();
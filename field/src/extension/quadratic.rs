@@ -58,6 +58,19 @@ impl<F: Extendable<2>> Sample for QuadraticExtension<F> {
     }
 }
 
+/// Samples each coordinate independently from the base field's `Standard` distribution, so the
+/// result is uniform over the extension whenever the base field sampler is.
+impl<F: Extendable<2>> rand::distributions::Distribution<QuadraticExtension<F>>
+    for rand::distributions::Standard
+where
+    rand::distributions::Standard: rand::distributions::Distribution<F>,
+{
+    #[inline]
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> QuadraticExtension<F> {
+        QuadraticExtension([rng.gen(), rng.gen()])
+    }
+}
+
 impl<F: Extendable<2>> Field for QuadraticExtension<F> {
     const ZERO: Self = Self([F::ZERO; 2]);
     const ONE: Self = Self([F::ONE, F::ZERO]);
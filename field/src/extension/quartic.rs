@@ -64,6 +64,18 @@ impl<F: Extendable<4>> Sample for QuarticExtension<F> {
     }
 }
 
+/// Samples each coordinate independently from the base field's `Standard` distribution.
+impl<F: Extendable<4>> rand::distributions::Distribution<QuarticExtension<F>>
+    for rand::distributions::Standard
+where
+    rand::distributions::Standard: rand::distributions::Distribution<F>,
+{
+    #[inline]
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> QuarticExtension<F> {
+        QuarticExtension::from_basefield_array([rng.gen(), rng.gen(), rng.gen(), rng.gen()])
+    }
+}
+
 impl<F: Extendable<4>> Field for QuarticExtension<F> {
     const ZERO: Self = Self([F::ZERO; 4]);
     const ONE: Self = Self([F::ONE, F::ZERO, F::ZERO, F::ZERO]);
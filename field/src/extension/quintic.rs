@@ -65,6 +65,24 @@ impl<F: Extendable<5>> Sample for QuinticExtension<F> {
     }
 }
 
+/// Samples each coordinate independently from the base field's `Standard` distribution.
+impl<F: Extendable<5>> rand::distributions::Distribution<QuinticExtension<F>>
+    for rand::distributions::Standard
+where
+    rand::distributions::Standard: rand::distributions::Distribution<F>,
+{
+    #[inline]
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> QuinticExtension<F> {
+        QuinticExtension::from_basefield_array([
+            rng.gen(),
+            rng.gen(),
+            rng.gen(),
+            rng.gen(),
+            rng.gen(),
+        ])
+    }
+}
+
 impl<F: Extendable<5>> Field for QuinticExtension<F> {
     const ZERO: Self = Self([F::ZERO; 5]);
     const ONE: Self = Self([F::ONE, F::ZERO, F::ZERO, F::ZERO, F::ZERO]);
@@ -1,6 +1,7 @@
 use alloc::vec::Vec;
 use core::cmp::{max, min};
 
+use plonky2_maybe_rayon::*;
 use plonky2_util::{log2_strict, reverse_index_bits_in_place};
 use unroll::unroll_for_loops;
 
@@ -9,6 +10,9 @@ use crate::packed::PackedField;
 use crate::polynomial::{PolynomialCoeffs, PolynomialValues};
 use crate::types::Field;
 
+/// Row `i` holds the powers of a primitive `2^(i+1)`-th root of unity, so it doesn't depend on the
+/// domain size. A table built for a domain of size `n` therefore also works for every smaller
+/// power-of-two domain, and one table can be shared by all the FFTs of a circuit.
 pub type FftRootTable<F> = Vec<Vec<F>>;
 
 pub fn fft_root_table<F: Field>(n: usize) -> FftRootTable<F> {
@@ -64,6 +68,38 @@ pub fn fft_with_options<F: Field>(
     PolynomialValues::new(buffer)
 }
 
+/// Like `fft`, but with radix-4 butterflies if `radix_4` is set and radix-2 butterflies otherwise,
+/// instead of choosing based on the domain size. The output is the same either way; this is meant
+/// for comparing the two, e.g. in benchmarks.
+pub fn fft_with_radix<F: Field>(poly: PolynomialCoeffs<F>, radix_4: bool) -> PolynomialValues<F> {
+    let PolynomialCoeffs { coeffs: mut buffer } = poly;
+    let root_table = fft_root_table(buffer.len());
+    fft_classic_with_radix(&mut buffer, 0, &root_table, radix_4);
+    PolynomialValues::new(buffer)
+}
+
+/// Applies `fft` to each of a batch of polynomials of the same length. The twiddle factors are
+/// computed once and shared by every column, and with the `parallel` feature the columns are
+/// split across threads.
+pub fn fft_batch<F: Field>(polys: Vec<PolynomialCoeffs<F>>) -> Vec<PolynomialValues<F>> {
+    let n = polys.first().map_or(0, |p| p.len());
+    assert!(
+        polys.iter().all(|p| p.len() == n),
+        "Polynomial lengths inconsistent"
+    );
+    if n == 0 {
+        return polys
+            .into_iter()
+            .map(|_| PolynomialValues::new(Vec::new()))
+            .collect();
+    }
+    let root_table = fft_root_table(n);
+    polys
+        .into_par_iter()
+        .map(|p| fft_with_options(p, None, Some(&root_table)))
+        .collect()
+}
+
 #[inline]
 pub fn ifft<F: Field>(poly: PolynomialValues<F>) -> PolynomialCoeffs<F> {
     ifft_with_options(poly, None, None)
@@ -95,12 +131,16 @@ pub fn ifft_with_options<F: Field>(
 }
 
 /// Generic FFT implementation that works with both scalar and packed inputs.
+///
+/// If `radix_4` is set, the stages operating on whole vectors are fused in pairs; the output is the
+/// same either way.
 #[unroll_for_loops]
 fn fft_classic_simd<P: PackedField>(
     values: &mut [P::Scalar],
     r: usize,
     lg_n: usize,
     root_table: &FftRootTable<P::Scalar>,
+    radix_4: bool,
 ) {
     let lg_packed_width = log2_strict(P::WIDTH); // 0 when P is a scalar.
     let packed_values = P::pack_slice_mut(values);
@@ -137,25 +177,92 @@ fn fft_classic_simd<P: PackedField>(
     }
 
     // We've already done the first lg_packed_width (if they were required) iterations.
-    let s = max(r, lg_packed_width);
-
-    for lg_half_m in s..lg_n {
-        let lg_m = lg_half_m + 1;
-        let m = 1 << lg_m; // Subarray size (in field elements).
-        let packed_m = m >> lg_packed_width; // Subarray size (in vectors).
-        let half_packed_m = packed_m / 2;
-        debug_assert!(half_packed_m != 0);
-
-        // omega values for this iteration, as slice of vectors
-        let omega_table = P::pack_slice(&root_table[lg_half_m][..]);
-        for k in (0..packed_n).step_by(packed_m) {
-            for j in 0..half_packed_m {
-                let omega = omega_table[j];
-                let t = omega * packed_values[k + half_packed_m + j];
-                let u = packed_values[k + j];
-                packed_values[k + j] = u + t;
-                packed_values[k + half_packed_m + j] = u - t;
-            }
+    let mut s = max(r, lg_packed_width);
+
+    if radix_4 {
+        // Fuse pairs of radix-2 stages into radix-4 stages, which halves the number of passes over
+        // `values`. If an odd number of stages remain, do one radix-2 stage first.
+        if (lg_n - s) % 2 == 1 {
+            fft_radix_2_stage::<P>(packed_values, s, lg_packed_width, root_table);
+            s += 1;
+        }
+        for lg_quarter_m in (s..lg_n).step_by(2) {
+            fft_radix_4_stage::<P>(packed_values, lg_quarter_m, lg_packed_width, root_table);
+        }
+    } else {
+        for lg_half_m in s..lg_n {
+            fft_radix_2_stage::<P>(packed_values, lg_half_m, lg_packed_width, root_table);
+        }
+    }
+}
+
+/// Performs the radix-2 butterflies for subarrays of size `2^(lg_half_m + 1)`.
+#[inline]
+fn fft_radix_2_stage<P: PackedField>(
+    packed_values: &mut [P],
+    lg_half_m: usize,
+    lg_packed_width: usize,
+    root_table: &FftRootTable<P::Scalar>,
+) {
+    let packed_n = packed_values.len();
+    let lg_m = lg_half_m + 1;
+    let m = 1 << lg_m; // Subarray size (in field elements).
+    let packed_m = m >> lg_packed_width; // Subarray size (in vectors).
+    let half_packed_m = packed_m / 2;
+    debug_assert!(half_packed_m != 0);
+
+    // omega values for this iteration, as slice of vectors
+    let omega_table = P::pack_slice(&root_table[lg_half_m][..]);
+    for k in (0..packed_n).step_by(packed_m) {
+        for j in 0..half_packed_m {
+            let omega = omega_table[j];
+            let t = omega * packed_values[k + half_packed_m + j];
+            let u = packed_values[k + j];
+            packed_values[k + j] = u + t;
+            packed_values[k + half_packed_m + j] = u - t;
+        }
+    }
+}
+
+/// Performs the radix-2 stages for subarrays of size `2^(lg_quarter_m + 1)` and
+/// `2^(lg_quarter_m + 2)` in a single pass. The result is identical to calling
+/// `fft_radix_2_stage` for `lg_quarter_m` and then `lg_quarter_m + 1`.
+#[inline]
+fn fft_radix_4_stage<P: PackedField>(
+    packed_values: &mut [P],
+    lg_quarter_m: usize,
+    lg_packed_width: usize,
+    root_table: &FftRootTable<P::Scalar>,
+) {
+    let packed_n = packed_values.len();
+    let m = 1 << (lg_quarter_m + 2); // Subarray size (in field elements).
+    let packed_m = m >> lg_packed_width; // Subarray size (in vectors).
+    let quarter_packed_m = packed_m / 4;
+    debug_assert!(quarter_packed_m != 0);
+
+    // The first stage works on pairs of quarters, with roots of order `m / 2`; the second on pairs
+    // of halves, with roots of order `m`.
+    let omega_table_lo = P::pack_slice(&root_table[lg_quarter_m][..]);
+    let omega_table_hi = P::pack_slice(&root_table[lg_quarter_m + 1][..]);
+    for k in (0..packed_n).step_by(packed_m) {
+        for j in 0..quarter_packed_m {
+            let i0 = k + j;
+            let i1 = i0 + quarter_packed_m;
+            let i2 = i1 + quarter_packed_m;
+            let i3 = i2 + quarter_packed_m;
+
+            let omega_lo = omega_table_lo[j];
+            let t0 = omega_lo * packed_values[i1];
+            let t1 = omega_lo * packed_values[i3];
+            let (a, b) = (packed_values[i0] + t0, packed_values[i0] - t0);
+            let (c, d) = (packed_values[i2] + t1, packed_values[i2] - t1);
+
+            let t2 = omega_table_hi[j] * c;
+            let t3 = omega_table_hi[quarter_packed_m + j] * d;
+            packed_values[i0] = a + t2;
+            packed_values[i1] = b + t3;
+            packed_values[i2] = a - t2;
+            packed_values[i3] = b - t3;
         }
     }
 }
@@ -167,14 +274,25 @@ fn fft_classic_simd<P: PackedField>(
 /// input may be non-zero, but the last 1 - 1/2^r entries are
 /// definitely zero.
 pub(crate) fn fft_classic<F: Field>(values: &mut [F], r: usize, root_table: &FftRootTable<F>) {
+    // Use radix-4 butterflies when the domain size is a power of 4, and radix-2 otherwise.
+    let radix_4 = log2_strict(values.len()) % 2 == 0;
+    fft_classic_with_radix(values, r, root_table, radix_4);
+}
+
+fn fft_classic_with_radix<F: Field>(
+    values: &mut [F],
+    r: usize,
+    root_table: &FftRootTable<F>,
+    radix_4: bool,
+) {
     reverse_index_bits_in_place(values);
 
     let n = values.len();
     let lg_n = log2_strict(n);
 
-    if root_table.len() != lg_n {
+    if root_table.len() < lg_n {
         panic!(
-            "Expected root table of length {}, but it was {}.",
+            "Expected root table of length at least {}, but it was {}.",
             lg_n,
             root_table.len()
         );
@@ -199,22 +317,28 @@ pub(crate) fn fft_classic<F: Field>(values: &mut [F], r: usize, root_table: &Fft
     if lg_n <= lg_packed_width {
         // Need the slice to be at least the width of two packed vectors for the vectorized version
         // to work. Do this tiny problem in scalar.
-        fft_classic_simd::<F>(values, r, lg_n, root_table);
+        fft_classic_simd::<F>(values, r, lg_n, root_table, radix_4);
     } else {
-        fft_classic_simd::<<F as Packable>::Packing>(values, r, lg_n, root_table);
+        fft_classic_simd::<<F as Packable>::Packing>(values, r, lg_n, root_table, radix_4);
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec;
     use alloc::vec::Vec;
 
-    use plonky2_util::{log2_ceil, log2_strict};
+    use plonky2_util::{log2_ceil, log2_strict, reverse_index_bits_in_place};
 
-    use crate::fft::{fft, fft_with_options, ifft};
+    use crate::fft::{
+        fft, fft_batch, fft_classic_simd, fft_root_table, fft_with_options, fft_with_radix, ifft,
+        ifft_with_options,
+    };
     use crate::goldilocks_field::GoldilocksField;
+    use crate::packable::Packable;
+    use crate::packed::PackedField;
     use crate::polynomial::{PolynomialCoeffs, PolynomialValues};
-    use crate::types::Field;
+    use crate::types::{Field, Sample};
 
     #[test]
     fn fft_and_ifft() {
@@ -252,6 +376,80 @@ mod tests {
         }
     }
 
+    #[test]
+    fn radix_4_matches_radix_2() {
+        type F = GoldilocksField;
+        type P = <F as Packable>::Packing;
+        let lg_packed_width = log2_strict(P::WIDTH);
+
+        for lg_n in 1..=10 {
+            let root_table = fft_root_table::<F>(1 << lg_n);
+            for r in 0..lg_n.min(3) {
+                let mut input = F::rand_vec(1 << lg_n);
+                reverse_index_bits_in_place(&mut input);
+                let mask = !((1 << r) - 1);
+                for i in 0..input.len() {
+                    input[i] = input[i & mask];
+                }
+
+                let mut radix_2 = input.clone();
+                fft_classic_simd::<F>(&mut radix_2, r, lg_n, &root_table, false);
+                let mut radix_4 = input.clone();
+                fft_classic_simd::<F>(&mut radix_4, r, lg_n, &root_table, true);
+                assert_eq!(radix_2, radix_4);
+
+                if lg_n > lg_packed_width {
+                    let mut packed_radix_4 = input;
+                    fft_classic_simd::<P>(&mut packed_radix_4, r, lg_n, &root_table, true);
+                    assert_eq!(radix_2, packed_radix_4);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fft_ifft_round_trip() {
+        type F = GoldilocksField;
+        // Covers both powers of 4, which use radix-4 butterflies, and odd powers of 2.
+        for lg_n in 0..=12 {
+            let coeffs = PolynomialCoeffs::new(F::rand_vec(1 << lg_n));
+            let values = fft(coeffs.clone());
+            if lg_n <= 6 {
+                assert_eq!(values, evaluate_naive(&coeffs));
+            }
+            assert_eq!(fft_with_radix(coeffs.clone(), false), values);
+            assert_eq!(fft_with_radix(coeffs.clone(), true), values);
+            assert_eq!(ifft(values), coeffs);
+        }
+    }
+
+    #[test]
+    fn larger_root_table() {
+        type F = GoldilocksField;
+        // A table for a larger domain gives the same results as an exact one.
+        let root_table = fft_root_table::<F>(1 << 12);
+        for lg_n in 0..=12 {
+            let coeffs = PolynomialCoeffs::new(F::rand_vec(1 << lg_n));
+            let values = fft_with_options(coeffs.clone(), None, Some(&root_table));
+            assert_eq!(values, fft(coeffs.clone()));
+            assert_eq!(ifft_with_options(values, None, Some(&root_table)), coeffs);
+        }
+    }
+
+    #[test]
+    fn batch() {
+        type F = GoldilocksField;
+        let polys = (0..10)
+            .map(|_| PolynomialCoeffs::new(F::rand_vec(1 << 6)))
+            .collect::<Vec<_>>();
+        let expected = polys.iter().map(|p| fft(p.clone())).collect::<Vec<_>>();
+        assert_eq!(fft_batch(polys), expected);
+
+        assert!(fft_batch::<F>(Vec::new()).is_empty());
+        let empty = fft_batch::<F>(vec![PolynomialCoeffs::empty(); 3]);
+        assert_eq!(empty, vec![PolynomialValues::new(Vec::new()); 3]);
+    }
+
     fn evaluate_naive<F: Field>(coefficients: &PolynomialCoeffs<F>) -> PolynomialValues<F> {
         let degree = coefficients.len();
         let degree_padded = 1 << log2_ceil(degree);
@@ -100,6 +100,41 @@ macro_rules! test_field_arithmetic {
                 assert_ne!(base.exp_biguint(&pow), base.exp_biguint(&big_pow_wrong));
             }
 
+            #[test]
+            fn biguint_conversion() {
+                type F = $field;
+
+                let p = F::characteristic();
+                for k in [0u32, 1, 2, 12345] {
+                    let n = BigUint::from(k);
+                    let expected = F::from_canonical_u32(k);
+                    assert_eq!(F::from_canonical_biguint(n.clone()).unwrap(), expected);
+                    assert_eq!(F::from_noncanonical_biguint(&p + &n), expected);
+                    assert_eq!(F::from_noncanonical_biguint(&p * 3u32 + &n), expected);
+                    assert!(F::from_canonical_biguint(&p + &n).is_err());
+                }
+                assert_eq!(F::from_canonical_biguint(&p - 1u32).unwrap(), F::NEG_ONE);
+            }
+
+            #[test]
+            fn powers() {
+                type F = $field;
+
+                let x = F::rand();
+                let mut expected = F::ONE;
+                for power in x.powers().take(10) {
+                    assert_eq!(power, expected);
+                    expected *= x;
+                }
+
+                let powers = x.powers_n(10);
+                assert_eq!(powers.len(), 10);
+                for (i, &power) in powers.iter().enumerate() {
+                    assert_eq!(power, x.exp_u64(i as u64));
+                }
+                assert!(x.powers_n(0).is_empty());
+            }
+
             #[test]
             fn inverses() {
                 type F = $field;
@@ -1,9 +1,11 @@
+use alloc::vec::Vec;
 use core::fmt::{self, Debug, Display, Formatter};
 use core::hash::{Hash, Hasher};
 use core::iter::{Product, Sum};
 use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
-use num::{BigUint, Integer};
+use anyhow::{anyhow, ensure, Result};
+use num::{BigUint, Integer, ToPrimitive};
 use plonky2_util::{assume, branch_hint};
 use serde::{Deserialize, Serialize};
 
@@ -67,6 +69,21 @@ impl Sample for GoldilocksField {
     }
 }
 
+/// Samples a uniformly distributed element by rejection sampling: a uniform `u64` is accepted iff
+/// it is canonical. Since `ORDER > 2^64 - 2^32`, a draw is rejected with probability below
+/// `2^-32`.
+impl rand::distributions::Distribution<GoldilocksField> for rand::distributions::Standard {
+    #[inline]
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> GoldilocksField {
+        loop {
+            let x = rng.next_u64();
+            if x < GoldilocksField::ORDER {
+                return GoldilocksField(x);
+            }
+        }
+    }
+}
+
 impl Field for GoldilocksField {
     const ZERO: Self = Self(0);
     const ONE: Self = Self(1);
@@ -101,7 +118,7 @@ impl Field for GoldilocksField {
     }
 
     fn from_noncanonical_biguint(n: BigUint) -> Self {
-        Self(n.mod_floor(&Self::order()).to_u64_digits()[0])
+        Self(n.mod_floor(&Self::order()).to_u64().unwrap())
     }
 
     #[inline(always)]
@@ -125,10 +142,76 @@ impl Field for GoldilocksField {
     }
 }
 
+/// Arithmetic usable in `const` contexts, e.g. to initialize `const` tables of field elements.
+/// These give exactly the same results as the corresponding `Field` operations, including the
+/// (possibly non-canonical) representation, but don't benefit from architecture-specific code.
+impl GoldilocksField {
+    /// A `const` version of `Field::from_canonical_u64`.
+    pub const fn from_canonical_u64_const(n: u64) -> Self {
+        debug_assert!(n < Self::ORDER);
+        Self(n)
+    }
+
+    /// A `const` version of `Add::add`.
+    pub const fn add_const(self, rhs: Self) -> Self {
+        let (sum, over) = self.0.overflowing_add(rhs.0);
+        let (mut sum, over) = sum.overflowing_add((over as u64) * EPSILON);
+        if over {
+            sum += EPSILON; // Cannot overflow.
+        }
+        Self(sum)
+    }
+
+    /// A `const` version of `Sub::sub`.
+    pub const fn sub_const(self, rhs: Self) -> Self {
+        let (diff, under) = self.0.overflowing_sub(rhs.0);
+        let (mut diff, under) = diff.overflowing_sub((under as u64) * EPSILON);
+        if under {
+            diff -= EPSILON; // Cannot underflow.
+        }
+        Self(diff)
+    }
+
+    /// A `const` version of `Mul::mul`.
+    pub const fn mul_const(self, rhs: Self) -> Self {
+        reduce128_const((self.0 as u128) * (rhs.0 as u128))
+    }
+
+    /// A `const` version of `Field::exp_u64`.
+    pub const fn exp_u64_const(self, power: u64) -> Self {
+        let mut current = self;
+        let mut product = Self::ONE;
+
+        let bits = 64 - power.leading_zeros();
+        let mut j = 0;
+        while j < bits {
+            if (power >> j) & 1 != 0 {
+                product = product.mul_const(current);
+            }
+            current = current.mul_const(current);
+            j += 1;
+        }
+        product
+    }
+}
+
 impl PrimeField for GoldilocksField {
     fn to_canonical_biguint(&self) -> BigUint {
         self.to_canonical_u64().into()
     }
+
+    fn to_bytes_le(&self) -> Vec<u8> {
+        self.to_canonical_u64().to_le_bytes().to_vec()
+    }
+
+    fn from_bytes_le(bytes: &[u8]) -> Result<Self> {
+        let bytes: [u8; 8] = bytes
+            .try_into()
+            .map_err(|_| anyhow!("Expected 8 bytes, got {}", bytes.len()))?;
+        let n = u64::from_le_bytes(bytes);
+        ensure!(n < Self::ORDER, "Non-canonical field element encoding");
+        Ok(Self(n))
+    }
 }
 
 impl Field64 for GoldilocksField {
@@ -368,6 +451,21 @@ fn reduce128(x: u128) -> GoldilocksField {
     GoldilocksField(t2)
 }
 
+/// Same as `reduce128`, but usable in `const` contexts.
+const fn reduce128_const(x: u128) -> GoldilocksField {
+    let (x_lo, x_hi) = (x as u64, (x >> 64) as u64);
+    let x_hi_hi = x_hi >> 32;
+    let x_hi_lo = x_hi & EPSILON;
+
+    let (mut t0, borrow) = x_lo.overflowing_sub(x_hi_hi);
+    if borrow {
+        t0 -= EPSILON; // Cannot underflow.
+    }
+    let t1 = x_hi_lo * EPSILON;
+    let (res_wrapped, carry) = t0.overflowing_add(t1);
+    GoldilocksField(res_wrapped + EPSILON * (carry as u64))
+}
+
 #[inline]
 fn split(x: u128) -> (u64, u64) {
     (x as u64, (x >> 64) as u64)
@@ -404,8 +502,143 @@ pub(crate) unsafe fn reduce160(x_lo: u128, x_hi: u32) -> GoldilocksField {
 
 #[cfg(test)]
 mod tests {
+    use crate::goldilocks_field::{GoldilocksField, EPSILON};
+    use crate::types::{Field, Field64, PrimeField, Sample};
     use crate::{test_field_arithmetic, test_prime_field_arithmetic};
 
     test_prime_field_arithmetic!(crate::goldilocks_field::GoldilocksField);
     test_field_arithmetic!(crate::goldilocks_field::GoldilocksField);
+
+    #[test]
+    fn const_arithmetic_matches_runtime() {
+        type F = GoldilocksField;
+
+        const SEED: F = F::from_canonical_u64_const(0x1234_5678_9abc_def0);
+        const fn const_table() -> [F; 32] {
+            let mut table = [F::ZERO; 32];
+            let mut x = SEED;
+            let mut i = 0;
+            while i < table.len() {
+                table[i] = x;
+                x = x
+                    .mul_const(x)
+                    .add_const(F::MULTIPLICATIVE_GROUP_GENERATOR)
+                    .sub_const(SEED);
+                i += 1;
+            }
+            table
+        }
+        const TABLE: [F; 32] = const_table();
+        const GENERATOR: F = F::MULTIPLICATIVE_GROUP_GENERATOR.exp_u64_const((F::ORDER - 1) >> 32);
+
+        let mut x = F::from_canonical_u64(0x1234_5678_9abc_def0);
+        for &entry in &TABLE {
+            assert_eq!(entry.0, x.0);
+            x = x * x + F::MULTIPLICATIVE_GROUP_GENERATOR - SEED;
+        }
+        assert_eq!(GENERATOR, F::POWER_OF_TWO_GENERATOR);
+
+        // Include non-canonical representations, which can overflow.
+        let values = [
+            F::ZERO,
+            F::ONE,
+            F::NEG_ONE,
+            GoldilocksField(F::ORDER),
+            GoldilocksField(u64::MAX),
+            GoldilocksField(EPSILON),
+            F::rand(),
+            F::rand(),
+        ];
+        for &a in &values {
+            for &b in &values {
+                assert_eq!(a.add_const(b).0, (a + b).0);
+                assert_eq!(a.sub_const(b).0, (a - b).0);
+                assert_eq!(a.mul_const(b).0, (a * b).0);
+            }
+            assert_eq!(a.exp_u64_const(0x1234_5678).0, a.exp_u64(0x1234_5678).0);
+        }
+    }
+
+    #[test]
+    fn bytes_le_round_trip() {
+        type F = GoldilocksField;
+        for x in [F::ZERO, F::ONE, F::NEG_ONE, F::rand()] {
+            let bytes = x.to_bytes_le();
+            assert_eq!(bytes.len(), 8);
+            assert_eq!(F::from_bytes_le(&bytes).unwrap(), x);
+        }
+
+        // Encodings of integers at or above the order must be rejected rather than reduced.
+        assert!(F::from_bytes_le(&F::ORDER.to_le_bytes()).is_err());
+        assert!(F::from_bytes_le(&u64::MAX.to_le_bytes()).is_err());
+        assert!(F::from_bytes_le(&[0; 7]).is_err());
+    }
+
+    /// An RNG which replays a fixed sequence of words.
+    struct ReplayRng(alloc::vec::IntoIter<u64>);
+
+    impl rand::RngCore for ReplayRng {
+        fn next_u32(&mut self) -> u32 {
+            self.next_u64() as u32
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.0.next().expect("ran out of words")
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            rand_core::impls::fill_bytes_via_next(self, dest)
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn standard_rejects_non_canonical() {
+        use rand::Rng;
+
+        type F = GoldilocksField;
+        let words = vec![u64::MAX, F::ORDER, F::ORDER + 1, F::ORDER - 1, 5];
+        let mut rng = ReplayRng(words.into_iter());
+        let x: F = rng.gen();
+        assert_eq!(x.0, F::ORDER - 1);
+        let y: F = rng.gen();
+        assert_eq!(y.0, 5);
+    }
+
+    #[test]
+    fn standard_is_uniform() {
+        use rand::rngs::OsRng;
+        use rand::Rng;
+
+        use crate::extension::quadratic::QuadraticExtension;
+
+        type F = GoldilocksField;
+        const SAMPLES: usize = 1 << 16;
+        const BUCKETS: usize = 16;
+
+        let mut counts = [0usize; BUCKETS];
+        let mut coordinates = Vec::with_capacity(2 * SAMPLES);
+        for _ in 0..SAMPLES {
+            let x: F = OsRng.gen();
+            let y: QuadraticExtension<F> = OsRng.gen();
+            coordinates.push(x);
+            coordinates.extend(y.0);
+        }
+        for x in coordinates {
+            assert!(x.0 < F::ORDER);
+            counts[(x.0 as u128 * BUCKETS as u128 / F::ORDER as u128) as usize] += 1;
+        }
+
+        // Each bucket expects `3 * SAMPLES / BUCKETS = 12288` hits with a standard deviation of
+        // about 107, so a deviation of 1000 is vanishingly unlikely for a uniform sampler, while a
+        // sampler biased toward small values would overfill the low buckets.
+        let expected = 3 * SAMPLES / BUCKETS;
+        for count in counts {
+            assert!(count.abs_diff(expected) < 1000, "{counts:?}");
+        }
+    }
 }
@@ -23,6 +23,8 @@ pub mod fft;
 pub mod goldilocks_extensions;
 pub mod goldilocks_field;
 pub mod interpolation;
+pub mod mersenne31_complex;
+pub mod mersenne31_field;
 pub mod ops;
 pub mod packable;
 pub mod packed;
@@ -0,0 +1,270 @@
+use core::fmt::{self, Debug, Display, Formatter};
+use core::iter::{Product, Sum};
+use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use num::BigUint;
+use serde::{Deserialize, Serialize};
+
+use crate::mersenne31_field::Mersenne31;
+use crate::types::{Field, Sample};
+
+/// The "complex" extension `F_p[i] / (i^2 + 1)` of `Mersenne31`. Since `p = 3 mod 4`, `-1` is not
+/// a square, so this is a field of order `p^2`.
+///
+/// Its multiplicative group has order `p^2 - 1 = (p - 1)(p + 1) = (p - 1) * 2^31`, giving a
+/// two-adicity of 32, which is what makes it usable for NTTs where the base field (two-adicity 1)
+/// is not. This doesn't go through `Extendable`, whose generic quadratic extension assumes a base
+/// field with two-adicity at least 2.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Mersenne31Complex(pub [Mersenne31; 2]);
+
+impl Mersenne31Complex {
+    pub const fn new(real: Mersenne31, imag: Mersenne31) -> Self {
+        Self([real, imag])
+    }
+
+    pub fn real(&self) -> Mersenne31 {
+        self.0[0]
+    }
+
+    pub fn imag(&self) -> Mersenne31 {
+        self.0[1]
+    }
+
+    /// Returns `a - bi` for `self = a + bi`. This is also the Frobenius automorphism `x -> x^p`.
+    pub fn conjugate(&self) -> Self {
+        Self([self.0[0], -self.0[1]])
+    }
+
+    /// Returns `a^2 + b^2` for `self = a + bi`, which is nonzero unless `self` is zero.
+    pub fn norm(&self) -> Mersenne31 {
+        let [a, b] = self.0;
+        a * a + b * b
+    }
+}
+
+impl Default for Mersenne31Complex {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl From<Mersenne31> for Mersenne31Complex {
+    fn from(x: Mersenne31) -> Self {
+        Self([x, Mersenne31::ZERO])
+    }
+}
+
+impl Display for Mersenne31Complex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} + {}*i", self.0[0], self.0[1])
+    }
+}
+
+impl Debug for Mersenne31Complex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Sample for Mersenne31Complex {
+    #[inline]
+    fn sample<R>(rng: &mut R) -> Self
+    where
+        R: rand::RngCore + ?Sized,
+    {
+        Self([Mersenne31::sample(rng), Mersenne31::sample(rng)])
+    }
+}
+
+impl Field for Mersenne31Complex {
+    const ZERO: Self = Self([Mersenne31::ZERO; 2]);
+    const ONE: Self = Self([Mersenne31::ONE, Mersenne31::ZERO]);
+    const TWO: Self = Self([Mersenne31::TWO, Mersenne31::ZERO]);
+    const NEG_ONE: Self = Self([Mersenne31::NEG_ONE, Mersenne31::ZERO]);
+
+    const TWO_ADICITY: usize = 32;
+    const CHARACTERISTIC_TWO_ADICITY: usize = Mersenne31::CHARACTERISTIC_TWO_ADICITY;
+
+    // Sage:
+    // ```
+    // K.<i> = GF(p^2, modulus=x^2 + 1)
+    // g = 2 + 7*i
+    // assert g.multiplicative_order() == p^2 - 1
+    // ```
+    const MULTIPLICATIVE_GROUP_GENERATOR: Self = Self([Mersenne31(2), Mersenne31(7)]);
+
+    // `g^((p^2 - 1) / 2^32)`, so that its `2^31`st power is `-1`, the base field's generator.
+    const POWER_OF_TWO_GENERATOR: Self = Self([Mersenne31(26306578), Mersenne31(1848216434)]);
+
+    const BITS: usize = Mersenne31::BITS * 2;
+
+    fn order() -> BigUint {
+        Mersenne31::order() * Mersenne31::order()
+    }
+    fn characteristic() -> BigUint {
+        Mersenne31::characteristic()
+    }
+
+    fn try_inverse(&self) -> Option<Self> {
+        // `1 / (a + bi) = (a - bi) / (a^2 + b^2)`.
+        let norm_inv = self.norm().try_inverse()?;
+        let [a, b] = self.conjugate().0;
+        Some(Self([a * norm_inv, b * norm_inv]))
+    }
+
+    fn from_noncanonical_biguint(n: BigUint) -> Self {
+        Mersenne31::from_noncanonical_biguint(n).into()
+    }
+
+    fn from_canonical_u64(n: u64) -> Self {
+        Mersenne31::from_canonical_u64(n).into()
+    }
+
+    fn from_noncanonical_u128(n: u128) -> Self {
+        Mersenne31::from_noncanonical_u128(n).into()
+    }
+}
+
+impl Neg for Mersenne31Complex {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        Self([-self.0[0], -self.0[1]])
+    }
+}
+
+impl Add for Mersenne31Complex {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Self([self.0[0] + rhs.0[0], self.0[1] + rhs.0[1]])
+    }
+}
+
+impl AddAssign for Mersenne31Complex {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sum for Mersenne31Complex {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, |acc, x| acc + x)
+    }
+}
+
+impl Sub for Mersenne31Complex {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        Self([self.0[0] - rhs.0[0], self.0[1] - rhs.0[1]])
+    }
+}
+
+impl SubAssign for Mersenne31Complex {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul for Mersenne31Complex {
+    type Output = Self;
+
+    #[inline]
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn mul(self, rhs: Self) -> Self {
+        let [a, b] = self.0;
+        let [c, d] = rhs.0;
+        Self([a * c - b * d, a * d + b * c])
+    }
+}
+
+impl MulAssign for Mersenne31Complex {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl Product for Mersenne31Complex {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ONE, |acc, x| acc * x)
+    }
+}
+
+impl Div for Mersenne31Complex {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self::Output {
+        self * rhs.inverse()
+    }
+}
+
+impl DivAssign for Mersenne31Complex {
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::fft::{fft, ifft};
+    use crate::mersenne31_complex::Mersenne31Complex;
+    use crate::mersenne31_field::Mersenne31;
+    use crate::polynomial::PolynomialCoeffs;
+    use crate::test_field_arithmetic;
+    use crate::types::{Field, Sample};
+
+    test_field_arithmetic!(crate::mersenne31_complex::Mersenne31Complex);
+
+    #[test]
+    fn power_of_two_generator() {
+        type F = Mersenne31Complex;
+
+        assert_eq!(
+            F::MULTIPLICATIVE_GROUP_GENERATOR.exp_biguint(&((F::order() - 1u32) >> F::TWO_ADICITY)),
+            F::POWER_OF_TWO_GENERATOR
+        );
+        // The generator has order exactly `2^32`, and is coherent with the base field.
+        assert_eq!(
+            F::POWER_OF_TWO_GENERATOR.exp_power_of_2(F::TWO_ADICITY - 1),
+            Mersenne31::POWER_OF_TWO_GENERATOR.into()
+        );
+    }
+
+    #[test]
+    fn conjugate_is_frobenius() {
+        type F = Mersenne31Complex;
+
+        let x = F::rand();
+        assert_eq!(x.exp_biguint(&Mersenne31::order()), x.conjugate());
+        assert_eq!(x * x.conjugate(), x.norm().into());
+    }
+
+    #[test]
+    fn fft_and_ifft() {
+        type F = Mersenne31Complex;
+
+        // Larger than the base field's two-adicity allows.
+        let log_n = 6;
+        let n = 1 << log_n;
+        let coeffs = PolynomialCoeffs::new(F::rand_vec(n));
+
+        let values = fft(coeffs.clone());
+        let expected = F::two_adic_subgroup(log_n)
+            .into_iter()
+            .map(|x| coeffs.eval(x))
+            .collect::<Vec<_>>();
+        assert_eq!(values.values, expected);
+        assert_eq!(ifft(values), coeffs);
+    }
+}
@@ -0,0 +1,318 @@
+use core::fmt::{self, Debug, Display, Formatter};
+use core::iter::{Product, Sum};
+use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use num::{BigUint, Integer, ToPrimitive};
+use serde::{Deserialize, Serialize};
+
+use crate::inversion::try_inverse_u64;
+use crate::types::{Field, Field64, PrimeField, PrimeField64, Sample};
+
+/// The Mersenne prime field, of order `2^31 - 1`. Since `2^31 = 1` in this field, reduction is
+/// just folding the bits above position 31 back onto the low bits.
+///
+/// Note that `p - 1 = 2 * 3^2 * 7 * 11 * 31 * 151 * 331` has a two-adicity of only 1, so this field
+/// is not FFT-friendly on its own. For NTTs, use `Mersenne31Complex`, the degree two extension
+/// `F_p[i] / (i^2 + 1)`, whose multiplicative group has order `p^2 - 1 = (p - 1) * 2^31`.
+///
+/// Elements are always stored in canonical form.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[repr(transparent)]
+pub struct Mersenne31(pub u32);
+
+const P: u32 = (1 << 31) - 1;
+
+impl Default for Mersenne31 {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl Display for Mersenne31 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl Debug for Mersenne31 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl Sample for Mersenne31 {
+    #[inline]
+    fn sample<R>(rng: &mut R) -> Self
+    where
+        R: rand::RngCore + ?Sized,
+    {
+        use rand::Rng;
+        Self(rng.gen_range(0..P))
+    }
+}
+
+/// Samples a uniformly distributed element by rejection sampling on 31 random bits; only the single
+/// value `2^31 - 1` is rejected.
+impl rand::distributions::Distribution<Mersenne31> for rand::distributions::Standard {
+    #[inline]
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Mersenne31 {
+        loop {
+            let x = rng.next_u32() >> 1;
+            if x < P {
+                return Mersenne31(x);
+            }
+        }
+    }
+}
+
+impl Field for Mersenne31 {
+    const ZERO: Self = Self(0);
+    const ONE: Self = Self(1);
+    const TWO: Self = Self(2);
+    const NEG_ONE: Self = Self(P - 1);
+
+    const TWO_ADICITY: usize = 1;
+    const CHARACTERISTIC_TWO_ADICITY: usize = Self::TWO_ADICITY;
+
+    // Sage: `g = GF(p).multiplicative_generator()`
+    const MULTIPLICATIVE_GROUP_GENERATOR: Self = Self(7);
+
+    // The only element of order 2.
+    const POWER_OF_TWO_GENERATOR: Self = Self::NEG_ONE;
+
+    const BITS: usize = 31;
+
+    fn order() -> BigUint {
+        P.into()
+    }
+    fn characteristic() -> BigUint {
+        Self::order()
+    }
+
+    #[inline]
+    fn try_inverse(&self) -> Option<Self> {
+        try_inverse_u64(self)
+    }
+
+    fn from_noncanonical_biguint(n: BigUint) -> Self {
+        Self(n.mod_floor(&Self::order()).to_u32().unwrap())
+    }
+
+    #[inline]
+    fn from_canonical_u64(n: u64) -> Self {
+        debug_assert!(n < Self::ORDER);
+        Self(n as u32)
+    }
+
+    fn from_noncanonical_u128(n: u128) -> Self {
+        // Since `2^62 = 1`, we can sum the 62-bit limbs of `n`. There are at most three, so the sum
+        // fits in a `u64`.
+        const MASK: u128 = (1 << 62) - 1;
+        let sum = (n & MASK) + ((n >> 62) & MASK) + (n >> 124);
+        reduce64(sum as u64)
+    }
+
+    #[inline]
+    fn multiply_accumulate(&self, x: Self, y: Self) -> Self {
+        // u31 + u31 * u31 cannot overflow a u64.
+        reduce64(self.0 as u64 + x.0 as u64 * y.0 as u64)
+    }
+}
+
+impl PrimeField for Mersenne31 {
+    fn to_canonical_biguint(&self) -> BigUint {
+        self.0.into()
+    }
+}
+
+impl Field64 for Mersenne31 {
+    const ORDER: u64 = P as u64;
+
+    #[inline]
+    fn from_noncanonical_u64(n: u64) -> Self {
+        reduce64(n)
+    }
+
+    #[inline]
+    fn from_noncanonical_i64(n: i64) -> Self {
+        let abs = reduce64(n.unsigned_abs());
+        if n < 0 {
+            -abs
+        } else {
+            abs
+        }
+    }
+}
+
+impl PrimeField64 for Mersenne31 {
+    #[inline]
+    fn to_canonical_u64(&self) -> u64 {
+        self.0 as u64
+    }
+
+    #[inline]
+    fn to_noncanonical_u64(&self) -> u64 {
+        self.0 as u64
+    }
+}
+
+impl Neg for Mersenne31 {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        if self.0 == 0 {
+            self
+        } else {
+            Self(P - self.0)
+        }
+    }
+}
+
+impl Add for Mersenne31 {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        // Both summands are below 2^31, so this cannot overflow.
+        let sum = self.0 + rhs.0;
+        if sum >= P {
+            Self(sum - P)
+        } else {
+            Self(sum)
+        }
+    }
+}
+
+impl AddAssign for Mersenne31 {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sum for Mersenne31 {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, |acc, x| acc + x)
+    }
+}
+
+impl Sub for Mersenne31 {
+    type Output = Self;
+
+    #[inline]
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, rhs: Self) -> Self {
+        if self.0 >= rhs.0 {
+            Self(self.0 - rhs.0)
+        } else {
+            Self(self.0 + P - rhs.0)
+        }
+    }
+}
+
+impl SubAssign for Mersenne31 {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul for Mersenne31 {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        reduce64(self.0 as u64 * rhs.0 as u64)
+    }
+}
+
+impl MulAssign for Mersenne31 {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl Product for Mersenne31 {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ONE, |acc, x| acc * x)
+    }
+}
+
+impl Div for Mersenne31 {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self::Output {
+        self * rhs.inverse()
+    }
+}
+
+impl DivAssign for Mersenne31 {
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+/// Reduces any `u64` to canonical form.
+#[inline]
+fn reduce64(x: u64) -> Mersenne31 {
+    // After the first fold the value is below 2^34, and after the second it is at most `P + 7`, so
+    // a single conditional subtraction suffices.
+    let x = (x & P as u64) + (x >> 31);
+    let x = ((x & P as u64) + (x >> 31)) as u32;
+    if x >= P {
+        Mersenne31(x - P)
+    } else {
+        Mersenne31(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mersenne31_field::Mersenne31;
+    use crate::prime_field_testing::test_inputs;
+    use crate::types::{Field, Field64, PrimeField64, Sample};
+    use crate::{test_field_arithmetic, test_prime_field_arithmetic};
+
+    test_prime_field_arithmetic!(crate::mersenne31_field::Mersenne31);
+    test_field_arithmetic!(crate::mersenne31_field::Mersenne31);
+
+    #[test]
+    fn canonical_round_trip() {
+        type F = Mersenne31;
+
+        let inputs = test_inputs(F::ORDER)
+            .into_iter()
+            .chain(F::rand_vec(100).into_iter().map(|x| x.to_canonical_u64()));
+        for n in inputs {
+            assert_eq!(F::from_canonical_u64(n).to_canonical_u64(), n);
+        }
+    }
+
+    #[test]
+    fn noncanonical_reduction() {
+        type F = Mersenne31;
+
+        for n in [
+            F::ORDER,
+            F::ORDER + 1,
+            2 * F::ORDER,
+            u32::MAX as u64,
+            u64::MAX,
+        ] {
+            let expected = n % F::ORDER;
+            assert_eq!(F::from_noncanonical_u64(n).to_canonical_u64(), expected);
+            assert_eq!(
+                F::from_noncanonical_u128(n as u128 * n as u128).to_canonical_u64(),
+                (n as u128 * n as u128 % F::ORDER as u128) as u64
+            );
+        }
+        assert_eq!(F::from_noncanonical_i64(-1), F::NEG_ONE);
+        assert_eq!(
+            F::from_noncanonical_i64(i64::MIN),
+            -F::from_noncanonical_u64(1 << 63)
+        );
+    }
+}
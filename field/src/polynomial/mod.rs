@@ -12,7 +12,7 @@ use plonky2_util::log2_strict;
 use serde::{Deserialize, Serialize};
 
 use crate::extension::{Extendable, FieldExtension};
-use crate::fft::{fft, fft_with_options, ifft, FftRootTable};
+use crate::fft::{fft, fft_with_options, ifft, ifft_with_options, FftRootTable};
 use crate::types::Field;
 
 /// A polynomial in point-value form.
@@ -59,9 +59,26 @@ impl<F: Field> PolynomialValues<F> {
         ifft(self)
     }
 
+    pub fn ifft_with_options(
+        self,
+        zero_factor: Option<usize>,
+        root_table: Option<&FftRootTable<F>>,
+    ) -> PolynomialCoeffs<F> {
+        ifft_with_options(self, zero_factor, root_table)
+    }
+
     /// Returns the polynomial whose evaluation on the coset `shift*H` is `self`.
     pub fn coset_ifft(self, shift: F) -> PolynomialCoeffs<F> {
-        let mut shifted_coeffs = self.ifft();
+        self.coset_ifft_with_options(shift, None)
+    }
+
+    /// Returns the polynomial whose evaluation on the coset `shift*H` is `self`.
+    pub fn coset_ifft_with_options(
+        self,
+        shift: F,
+        root_table: Option<&FftRootTable<F>>,
+    ) -> PolynomialCoeffs<F> {
+        let mut shifted_coeffs = self.ifft_with_options(None, root_table);
         shifted_coeffs
             .coeffs
             .iter_mut()
@@ -72,8 +72,9 @@ macro_rules! test_prime_field_arithmetic {
         mod prime_field_arithmetic {
             use core::ops::{Add, Mul, Neg, Sub};
 
+            use num::bigint::BigUint;
             use $crate::ops::Square;
-            use $crate::types::{Field, Field64};
+            use $crate::types::{Field, Field64, PrimeField};
 
             #[test]
             fn arithmetic_addition() {
@@ -143,13 +144,38 @@ macro_rules! test_prime_field_arithmetic {
                 }
             }
 
+            #[test]
+            fn biguint_round_trip() {
+                let modulus = <$field>::ORDER;
+                for x in $crate::prime_field_testing::test_inputs(modulus) {
+                    let n = <$field>::from_canonical_u64(x).to_canonical_biguint();
+                    assert_eq!(n, BigUint::from(x));
+                    assert_eq!(
+                        <$field>::from_canonical_biguint(n).unwrap(),
+                        <$field>::from_canonical_u64(x)
+                    );
+                }
+            }
+
             #[test]
             fn inverse_2exp() {
                 type F = $field;
 
                 let v = <F as Field>::TWO_ADICITY;
 
-                for e in [0, 1, 2, 3, 4, v - 2, v - 1, v, v + 1, v + 2, 123 * v] {
+                for e in [
+                    0,
+                    1,
+                    2,
+                    3,
+                    4,
+                    v.saturating_sub(2),
+                    v - 1,
+                    v,
+                    v + 1,
+                    v + 2,
+                    123 * v,
+                ] {
                     let x = F::TWO.exp_u64(e as u64);
                     let y = F::inverse_2exp(e);
                     assert_eq!(x * y, F::ONE);
@@ -170,7 +196,7 @@ macro_rules! test_prime_field_arithmetic {
             fn addition_double_wraparound() {
                 type F = $field;
 
-                let a = F::from_canonical_u64(u64::MAX - F::ORDER);
+                let a = F::from_noncanonical_u64(u64::MAX - F::ORDER);
                 let b = F::NEG_ONE;
 
                 let c = (a + a) + (b + b);
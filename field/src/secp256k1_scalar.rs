@@ -127,7 +127,8 @@ impl Field for Secp256K1Scalar {
 
     fn from_noncanonical_biguint(val: BigUint) -> Self {
         Self(
-            val.to_u64_digits()
+            val.mod_floor(&Self::order())
+                .to_u64_digits()
                 .into_iter()
                 .pad_using(4, |_| 0)
                 .collect::<Vec<_>>()[..]
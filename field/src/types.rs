@@ -5,9 +5,10 @@ use core::hash::Hash;
 use core::iter::{Product, Sum};
 use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
+use anyhow::{ensure, Result};
 use num::bigint::BigUint;
 use num::{Integer, One, ToPrimitive, Zero};
-use plonky2_util::bits_u64;
+use plonky2_util::{bits_u64, ceil_div_usize};
 use rand::rngs::OsRng;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -306,6 +307,17 @@ pub trait Field:
     /// Returns `n % Self::characteristic()`.
     fn from_noncanonical_biguint(n: BigUint) -> Self;
 
+    /// Returns `n`, or an error if it is not in canonical form, i.e. if
+    /// `n >= Self::characteristic()`.
+    fn from_canonical_biguint(n: BigUint) -> Result<Self> {
+        ensure!(
+            n < Self::characteristic(),
+            "{} is not less than the field characteristic",
+            n
+        );
+        Ok(Self::from_noncanonical_biguint(n))
+    }
+
     /// Returns `n`. Assumes that `n` is already in canonical form, i.e. `n < Self::order()`.
     // TODO: Should probably be unsafe.
     fn from_canonical_u64(n: u64) -> Self;
@@ -420,6 +432,8 @@ pub trait Field:
         self.kth_root_u64(3)
     }
 
+    /// An infinite iterator over `1, self, self^2, ...`, computed lazily by repeated
+    /// multiplication.
     fn powers(&self) -> Powers<Self> {
         Powers {
             base: *self,
@@ -427,6 +441,11 @@ pub trait Field:
         }
     }
 
+    /// The first `n` powers of `self`, i.e. `[1, self, ..., self^(n-1)]`.
+    fn powers_n(&self, n: usize) -> Vec<Self> {
+        self.powers().take(n).collect()
+    }
+
     /// Representative `g` of the coset used in FRI, so that LDEs in FRI are done over `gH`.
     fn coset_shift() -> Self {
         Self::MULTIPLICATIVE_GROUP_GENERATOR
@@ -443,6 +462,28 @@ pub trait Field:
 pub trait PrimeField: Field {
     fn to_canonical_biguint(&self) -> BigUint;
 
+    /// Returns the canonical representation of this element as `ceil(BITS / 8)` little-endian
+    /// bytes.
+    fn to_bytes_le(&self) -> Vec<u8> {
+        let mut bytes = self.to_canonical_biguint().to_bytes_le();
+        bytes.resize(ceil_div_usize(Self::BITS, 8), 0);
+        bytes
+    }
+
+    /// Parses the encoding produced by `to_bytes_le`. Fails if `bytes` has the wrong length, or if
+    /// it encodes an integer which is not less than the field order.
+    fn from_bytes_le(bytes: &[u8]) -> Result<Self> {
+        ensure!(
+            bytes.len() == ceil_div_usize(Self::BITS, 8),
+            "Expected {} bytes, got {}",
+            ceil_div_usize(Self::BITS, 8),
+            bytes.len()
+        );
+        let n = BigUint::from_bytes_le(bytes);
+        ensure!(n < Self::order(), "Non-canonical field element encoding");
+        Ok(Self::from_noncanonical_biguint(n))
+    }
+
     fn is_quadratic_residue(&self) -> bool {
         if self.is_zero() {
             return true;
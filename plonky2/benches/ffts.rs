@@ -1,9 +1,11 @@
 mod allocator;
 
-use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use plonky2::field::fft::{fft, fft_batch, fft_with_radix};
 use plonky2::field::goldilocks_field::GoldilocksField;
 use plonky2::field::polynomial::PolynomialCoeffs;
 use plonky2::field::types::Field;
+use plonky2_maybe_rayon::*;
 use tynm::type_name;
 
 pub(crate) fn bench_ffts<F: Field>(c: &mut Criterion) {
@@ -18,6 +20,54 @@ pub(crate) fn bench_ffts<F: Field>(c: &mut Criterion) {
     }
 }
 
+/// Large domains, where the radix-4 path (used for powers of 4) saves a noticeable number of passes
+/// over memory compared to the radix-2 path (used for odd powers of 2). Each size is transformed
+/// with both radixes.
+pub(crate) fn bench_large_ffts<F: Field>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(&format!("large-fft<{}>", type_name::<F>()));
+    group.sample_size(10);
+
+    for size_log in [22, 23, 24] {
+        let size = 1 << size_log;
+        for (name, radix_4) in [("radix-2", false), ("radix-4", true)] {
+            group.bench_with_input(BenchmarkId::new(name, size), &size, |b, _| {
+                let coeffs = PolynomialCoeffs::new(F::rand_vec(size));
+                b.iter_batched(
+                    || coeffs.clone(),
+                    |coeffs| fft_with_radix(coeffs, radix_4),
+                    BatchSize::LargeInput,
+                );
+            });
+        }
+    }
+}
+
+/// Transforms every column of a 2^20 x 100 trace, computing the twiddle factors for each column
+/// versus sharing them with `fft_batch`. Columns are split across threads the same way in both
+/// cases, and the columns are cloned outside of the timed section.
+pub(crate) fn bench_fft_batch<F: Field>(c: &mut Criterion) {
+    const LOG_ROWS: usize = 20;
+    const NUM_COLUMNS: usize = 100;
+
+    let mut group = c.benchmark_group(&format!("fft-batch<{}>", type_name::<F>()));
+    group.sample_size(10);
+
+    let columns = (0..NUM_COLUMNS)
+        .map(|_| PolynomialCoeffs::new(F::rand_vec(1 << LOG_ROWS)))
+        .collect::<Vec<_>>();
+
+    group.bench_function("root-table-per-column", |b| {
+        b.iter_batched(
+            || columns.clone(),
+            |columns| columns.into_par_iter().map(fft).collect::<Vec<_>>(),
+            BatchSize::LargeInput,
+        );
+    });
+    group.bench_function("shared-root-table", |b| {
+        b.iter_batched(|| columns.clone(), fft_batch, BatchSize::LargeInput);
+    });
+}
+
 pub(crate) fn bench_ldes<F: Field>(c: &mut Criterion) {
     const RATE_BITS: usize = 3;
 
@@ -39,6 +89,8 @@ pub(crate) fn bench_ldes<F: Field>(c: &mut Criterion) {
 
 fn criterion_benchmark(c: &mut Criterion) {
     bench_ffts::<GoldilocksField>(c);
+    bench_large_ffts::<GoldilocksField>(c);
+    bench_fft_batch::<GoldilocksField>(c);
     bench_ldes::<GoldilocksField>(c);
 }
 
@@ -6,7 +6,7 @@ use plonky2::field::types::Sample;
 use plonky2::hash::hash_types::{BytesHash, RichField};
 use plonky2::hash::hashing::SPONGE_WIDTH;
 use plonky2::hash::keccak::KeccakHash;
-use plonky2::hash::poseidon::Poseidon;
+use plonky2::hash::poseidon::{Poseidon, PoseidonHash};
 use plonky2::plonk::config::Hasher;
 use tynm::type_name;
 
@@ -33,8 +33,48 @@ pub(crate) fn bench_poseidon<F: Poseidon>(c: &mut Criterion) {
     );
 }
 
+pub(crate) fn bench_poseidon_batch<F: Poseidon>(c: &mut Criterion) {
+    const NUM_STATES: usize = 1 << 10;
+
+    c.bench_function(
+        &format!("poseidon_batch<{}, {NUM_STATES}>", type_name::<F>()),
+        |b| {
+            b.iter_batched(
+                || {
+                    (0..NUM_STATES)
+                        .map(|_| F::rand_array::<SPONGE_WIDTH>())
+                        .collect::<Vec<_>>()
+                },
+                |mut states| F::poseidon_batch(&mut states),
+                BatchSize::LargeInput,
+            )
+        },
+    );
+}
+
+pub(crate) fn bench_poseidon_hash_many<F: RichField>(c: &mut Criterion) {
+    const NUM_RECORDS: usize = 100_000;
+    const RECORD_LEN: usize = 10;
+
+    c.bench_function(
+        &format!("poseidon_hash_many<{}, {NUM_RECORDS}>", type_name::<F>()),
+        |b| {
+            b.iter_batched(
+                || F::rand_vec(NUM_RECORDS * RECORD_LEN),
+                |elements| {
+                    let inputs = elements.chunks(RECORD_LEN).collect::<Vec<_>>();
+                    PoseidonHash::hash_many(&inputs)
+                },
+                BatchSize::LargeInput,
+            )
+        },
+    );
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     bench_poseidon::<GoldilocksField>(c);
+    bench_poseidon_batch::<GoldilocksField>(c);
+    bench_poseidon_hash_many::<GoldilocksField>(c);
     bench_keccak::<GoldilocksField>(c);
 }
 
@@ -0,0 +1,42 @@
+mod allocator;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use plonky2::field::types::Field;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// Proves the same circuit repeatedly, so that any per-proof setup which could have been done once
+/// at build time shows up in every sample.
+pub(crate) fn bench_repeated_proofs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("prove");
+    group.sample_size(100);
+
+    let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+    let x = builder.add_virtual_target();
+    let mut acc = x;
+    for _ in 0..(1 << 14) {
+        acc = builder.mul_add(acc, acc, x);
+    }
+    builder.register_public_input(acc);
+    let data = builder.build::<C>();
+
+    let mut pw = PartialWitness::new();
+    pw.set_target(x, F::TWO);
+
+    group.bench_function("mul-add-chain", |b| {
+        b.iter(|| data.prove(pw.clone()).unwrap())
+    });
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    bench_repeated_proofs(c);
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -1,6 +1,6 @@
 use crate::field::extension::Extendable;
 use crate::field::polynomial::PolynomialCoeffs;
-use crate::fri::proof::{FriChallenges, FriChallengesTarget};
+use crate::fri::proof::{FriChallenges, FriChallengesTarget, FriProof, FriProofTarget};
 use crate::fri::structure::{FriOpenings, FriOpeningsTarget};
 use crate::fri::FriConfig;
 use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
@@ -21,6 +21,27 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
         }
     }
 
+    /// Returns the FRI challenges for `proof`, observing its commitments along the way. Since each
+    /// reduction challenge is drawn right after its commit-phase cap is observed, the two can't be
+    /// separated.
+    pub fn fri_proof_challenges<C: GenericConfig<D, F = F>, const D: usize>(
+        &mut self,
+        proof: &FriProof<F, C::Hasher, D>,
+        degree_bits: usize,
+        config: &FriConfig,
+    ) -> FriChallenges<F, D>
+    where
+        F: RichField + Extendable<D>,
+    {
+        self.fri_challenges::<C, D>(
+            &proof.commit_phase_merkle_caps,
+            &proof.final_poly,
+            proof.pow_witness,
+            degree_bits,
+            config,
+        )
+    }
+
     pub fn fri_challenges<C: GenericConfig<D, F = F>, const D: usize>(
         &mut self,
         commit_phase_merkle_caps: &[MerkleCap<F, C::Hasher>],
@@ -73,6 +94,22 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
         }
     }
 
+    /// In-circuit version of `Challenger::fri_proof_challenges`.
+    pub fn fri_proof_challenges(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        proof: &FriProofTarget<D>,
+        inner_fri_config: &FriConfig,
+    ) -> FriChallengesTarget<D> {
+        self.fri_challenges(
+            builder,
+            &proof.commit_phase_merkle_caps,
+            &proof.final_poly,
+            proof.pow_witness,
+            inner_fri_config,
+        )
+    }
+
     pub fn fri_challenges(
         &mut self,
         builder: &mut CircuitBuilder<F, D>,
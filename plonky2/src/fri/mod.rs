@@ -1,6 +1,13 @@
 use alloc::vec::Vec;
 
+use anyhow::{ensure, Result};
+use num::BigUint;
+
+use crate::field::extension::Extendable;
+use crate::field::types::Field;
 use crate::fri::reduction_strategies::FriReductionStrategy;
+use crate::hash::hash_types::RichField;
+use crate::util::ceil_div_usize;
 
 mod challenges;
 pub mod oracle;
@@ -18,7 +25,10 @@ pub struct FriConfig {
     /// `rate = 2^{-rate_bits}`.
     pub rate_bits: usize,
 
-    /// Height of Merkle tree caps.
+    /// Height of Merkle tree caps. Each tree is committed to by the `2^cap_height` digests at this
+    /// height rather than by its root, which makes commitments larger but shortens every Merkle
+    /// proof by `cap_height` siblings. It can be at most the height of the smallest tree committed
+    /// to; see `FriParams::validate_cap_height`.
     pub cap_height: usize,
 
     pub proof_of_work_bits: u32,
@@ -29,7 +39,63 @@ pub struct FriConfig {
     pub num_query_rounds: usize,
 }
 
+/// How much security each FRI query round is assumed to provide.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FriSoundness {
+    /// The ethSTARK conjecture, under which each query contributes `rate_bits` bits.
+    Conjectured,
+    /// The unique decoding bound, under which each query is passed by a far codeword with
+    /// probability at most `(1 + rate) / 2`.
+    Provable,
+}
+
 impl FriConfig {
+    /// The recursion-friendly parameters of `CircuitConfig::standard_recursion_config`, with the
+    /// number of query rounds chosen to reach `security_bits` bits of FRI security. Fails if no
+    /// number of queries can reach that level, since the challenges are drawn from
+    /// `F::Extension`.
+    pub fn standard_recursion_config_with_security<F: RichField + Extendable<D>, const D: usize>(
+        security_bits: usize,
+        soundness: FriSoundness,
+    ) -> Result<Self> {
+        let rate_bits = 3;
+        let proof_of_work_bits = 16.min(security_bits as u32);
+
+        let field_bits = F::Extension::order().bits() as usize;
+        ensure!(
+            security_bits <= field_bits,
+            "Cannot reach {} bits of security with a {}-bit challenge field",
+            security_bits,
+            field_bits
+        );
+
+        let query_security_bits = security_bits - proof_of_work_bits as usize;
+        let num_query_rounds = match soundness {
+            FriSoundness::Conjectured => ceil_div_usize(query_security_bits, rate_bits),
+            FriSoundness::Provable => {
+                // The smallest `q` with `((1 + rate) / 2)^q <= 2^-query_security_bits`, i.e.
+                // `(2^rate_bits + 1)^q * 2^query_security_bits <= 2^((rate_bits + 1) q)`.
+                let base = BigUint::from((1u32 << rate_bits) + 1);
+                let mut num_query_rounds = 0;
+                let mut lhs = BigUint::from(1u32) << query_security_bits;
+                while lhs > BigUint::from(1u32) << ((rate_bits + 1) * num_query_rounds) {
+                    lhs *= &base;
+                    num_query_rounds += 1;
+                }
+                num_query_rounds
+            }
+        }
+        .max(1);
+
+        Ok(Self {
+            rate_bits,
+            cap_height: 4,
+            proof_of_work_bits,
+            reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
+            num_query_rounds,
+        })
+    }
+
     pub fn rate(&self) -> f64 {
         1.0 / ((1 << self.rate_bits) as f64)
     }
@@ -98,4 +164,71 @@ impl FriParams {
     pub fn final_poly_len(&self) -> usize {
         1 << self.final_poly_bits()
     }
+
+    /// Checks that every Merkle tree has at least `2^cap_height` leaves. The smallest is the one
+    /// committing to the last folded codeword, with `2^(lde_bits - total_arities)` leaves.
+    pub fn validate_cap_height(&self) -> Result<()> {
+        let cap_height = self.config.cap_height;
+        let min_tree_height = self.lde_bits().saturating_sub(self.total_arities());
+        ensure!(
+            self.total_arities() <= self.lde_bits() && cap_height <= min_tree_height,
+            "cap_height={} is too large for degree_bits={}, rate_bits={} and reduction arities {:?}; \
+             it can be at most {}",
+            cap_height,
+            self.degree_bits,
+            self.config.rate_bits,
+            self.reduction_arity_bits,
+            min_tree_height
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::plonk::circuit_data::CircuitConfig;
+
+    type F = GoldilocksField;
+    const D: usize = 2;
+
+    fn num_query_rounds(security_bits: usize, soundness: FriSoundness) -> usize {
+        FriConfig::standard_recursion_config_with_security::<F, D>(security_bits, soundness)
+            .unwrap()
+            .num_query_rounds
+    }
+
+    #[test]
+    fn test_config_with_security() {
+        assert_eq!(
+            FriConfig::standard_recursion_config_with_security::<F, D>(
+                100,
+                FriSoundness::Conjectured
+            )
+            .unwrap(),
+            CircuitConfig::standard_recursion_config().fri_config
+        );
+
+        for (security_bits, conjectured, provable) in [(80, 22, 78), (100, 28, 102), (128, 38, 135)]
+        {
+            assert_eq!(
+                num_query_rounds(security_bits, FriSoundness::Conjectured),
+                conjectured
+            );
+            assert_eq!(
+                num_query_rounds(security_bits, FriSoundness::Provable),
+                provable
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_with_security_unreachable() {
+        assert!(FriConfig::standard_recursion_config_with_security::<F, D>(
+            129,
+            FriSoundness::Conjectured
+        )
+        .is_err());
+    }
 }
@@ -4,6 +4,7 @@ use alloc::vec::Vec;
 use itertools::Itertools;
 use plonky2_field::types::Field;
 use plonky2_maybe_rayon::*;
+use rand::RngCore;
 
 use crate::field::extension::Extendable;
 use crate::field::fft::FftRootTable;
@@ -46,20 +47,46 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         cap_height: usize,
         timing: &mut TimingTree,
         fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Self {
+        Self::from_values_with_rng(
+            values,
+            rate_bits,
+            blinding,
+            cap_height,
+            timing,
+            fft_root_table,
+            None,
+        )
+    }
+
+    /// Same as `from_values`, but if `rng` is given, any blinding salt is sampled from it rather
+    /// than from the OS's randomness.
+    pub fn from_values_with_rng(
+        values: Vec<PolynomialValues<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+        rng: Option<&mut dyn RngCore>,
     ) -> Self {
         let coeffs = timed!(
             timing,
             "IFFT",
-            values.into_par_iter().map(|v| v.ifft()).collect::<Vec<_>>()
+            values
+                .into_par_iter()
+                .map(|v| v.ifft_with_options(None, fft_root_table))
+                .collect::<Vec<_>>()
         );
 
-        Self::from_coeffs(
+        Self::from_coeffs_with_rng(
             coeffs,
             rate_bits,
             blinding,
             cap_height,
             timing,
             fft_root_table,
+            rng,
         )
     }
 
@@ -71,12 +98,34 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         cap_height: usize,
         timing: &mut TimingTree,
         fft_root_table: Option<&FftRootTable<F>>,
+    ) -> Self {
+        Self::from_coeffs_with_rng(
+            polynomials,
+            rate_bits,
+            blinding,
+            cap_height,
+            timing,
+            fft_root_table,
+            None,
+        )
+    }
+
+    /// Same as `from_coeffs`, but if `rng` is given, any blinding salt is sampled from it rather
+    /// than from the OS's randomness.
+    pub fn from_coeffs_with_rng(
+        polynomials: Vec<PolynomialCoeffs<F>>,
+        rate_bits: usize,
+        blinding: bool,
+        cap_height: usize,
+        timing: &mut TimingTree,
+        fft_root_table: Option<&FftRootTable<F>>,
+        rng: Option<&mut dyn RngCore>,
     ) -> Self {
         let degree = polynomials[0].len();
         let lde_values = timed!(
             timing,
             "FFT + blinding",
-            Self::lde_values(&polynomials, rate_bits, blinding, fft_root_table)
+            Self::lde_values(&polynomials, rate_bits, blinding, fft_root_table, rng)
         );
 
         let mut leaves = timed!(timing, "transpose LDEs", transpose(&lde_values));
@@ -101,26 +150,39 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         rate_bits: usize,
         blinding: bool,
         fft_root_table: Option<&FftRootTable<F>>,
+        rng: Option<&mut dyn RngCore>,
     ) -> Vec<Vec<F>> {
         let degree = polynomials[0].len();
 
         // If blinding, salt with two random elements to each leaf vector.
         let salt_size = if blinding { SALT_SIZE } else { 0 };
 
-        polynomials
-            .par_iter()
-            .map(|p| {
-                assert_eq!(p.len(), degree, "Polynomial degrees inconsistent");
-                p.lde(rate_bits)
-                    .coset_fft_with_options(F::coset_shift(), Some(rate_bits), fft_root_table)
-                    .values
-            })
-            .chain(
-                (0..salt_size)
-                    .into_par_iter()
-                    .map(|_| F::rand_vec(degree << rate_bits)),
-            )
-            .collect()
+        let ldes = polynomials.par_iter().map(|p| {
+            assert_eq!(p.len(), degree, "Polynomial degrees inconsistent");
+            p.lde(rate_bits)
+                .coset_fft_with_options(F::coset_shift(), Some(rate_bits), fft_root_table)
+                .values
+        });
+
+        match rng {
+            // A shared RNG has to be sampled sequentially for the salt to be reproducible.
+            Some(rng) => {
+                let mut values: Vec<Vec<F>> = ldes.collect();
+                values.extend((0..salt_size).map(|_| {
+                    (0..degree << rate_bits)
+                        .map(|_| F::sample(&mut *rng))
+                        .collect()
+                }));
+                values
+            }
+            None => ldes
+                .chain(
+                    (0..salt_size)
+                        .into_par_iter()
+                        .map(|_| F::rand_vec(degree << rate_bits)),
+                )
+                .collect(),
+        }
     }
 
     /// Fetches LDE values at the `index * step`th point.
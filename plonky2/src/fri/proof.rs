@@ -12,7 +12,7 @@ use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
 use crate::hash::hash_types::{MerkleCapTarget, RichField};
 use crate::hash::merkle_proofs::{MerkleProof, MerkleProofTarget};
 use crate::hash::merkle_tree::MerkleCap;
-use crate::hash::path_compression::{compress_merkle_proofs, decompress_merkle_proofs};
+use crate::hash::path_compression::{compress_merkle_proofs, CompressedMerkleProofs};
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
 use crate::plonk::config::Hasher;
@@ -134,6 +134,27 @@ pub struct CompressedFriProof<F: RichField + Extendable<D>, H: Hasher<F>, const
 }
 
 impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> FriProof<F, H, D> {
+    /// The Merkle caps committing to each folded polynomial, one per reduction step, in the order
+    /// of `FriParams::reduction_arity_bits`. For a step with arity `2^k`, each leaf holds the `2^k`
+    /// evaluations of one coset, flattened into `2^k * D` base field elements.
+    pub fn commit_phase_caps(&self) -> &[MerkleCap<F, H>] {
+        &self.commit_phase_merkle_caps
+    }
+
+    /// One proof per query round, in the order of the sampled query indices. Each contains the
+    /// opened leaves of the initial oracles, in the order in which they were committed to, followed
+    /// by one `FriQueryStep` per reduction step. The opened leaf of step `i` is at the query index
+    /// shifted right by the total arity bits of steps `0..=i`.
+    pub fn query_round_proofs(&self) -> &[FriQueryRound<F, H, D>] {
+        &self.query_round_proofs
+    }
+
+    /// The fully folded polynomial, in coefficient form, with `FriParams::final_poly_len`
+    /// coefficients.
+    pub fn final_poly(&self) -> &PolynomialCoeffs<F::Extension> {
+        &self.final_poly
+    }
+
     /// Compress all the Merkle paths in the FRI proof and remove duplicate indices.
     pub fn compress(self, indices: &[usize], params: &FriParams) -> CompressedFriProof<F, H, D> {
         let FriProof {
@@ -143,7 +164,6 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> FriProof<F, H,
             pow_witness,
             ..
         } = self;
-        let cap_height = params.config.cap_height;
         let reduction_arity_bits = &params.reduction_arity_bits;
         let num_reductions = reduction_arity_bits.len();
         let num_initial_trees = query_round_proofs[0].initial_trees_proof.evals_proofs.len();
@@ -184,12 +204,12 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> FriProof<F, H,
         let initial_trees_proofs = initial_trees_indices
             .iter()
             .zip(initial_trees_proofs)
-            .map(|(is, ps)| compress_merkle_proofs(cap_height, is, &ps))
+            .map(|(is, ps)| compress_merkle_proofs(is, &ps).proofs)
             .collect::<Vec<_>>();
         let steps_proofs = steps_indices
             .iter()
             .zip(steps_proofs)
-            .map(|(is, ps)| compress_merkle_proofs(cap_height, is, &ps))
+            .map(|(is, ps)| compress_merkle_proofs(is, &ps).proofs)
             .collect::<Vec<_>>();
 
         let mut compressed_query_proofs = CompressedFriQueryRounds {
@@ -321,10 +341,24 @@ impl<F: RichField + Extendable<D>, H: Hasher<F>, const D: usize> CompressedFriPr
             &initial_trees_indices,
             initial_trees_proofs
         )
-        .map(|(ls, is, ps)| decompress_merkle_proofs(ls, is, &ps, height, cap_height))
+        .map(|(ls, is, ps)| {
+            CompressedMerkleProofs {
+                indices: is.clone(),
+                proof_len: height - cap_height,
+                proofs: ps,
+            }
+            .decompress(ls)
+        })
         .collect::<Vec<_>>();
         let steps_proofs = izip!(&steps_evals, &steps_indices, steps_proofs, heights)
-            .map(|(ls, is, ps, h)| decompress_merkle_proofs(ls, is, &ps, h, cap_height))
+            .map(|(ls, is, ps, h)| {
+                CompressedMerkleProofs {
+                    indices: is.clone(),
+                    proof_len: h - cap_height,
+                    proofs: ps,
+                }
+                .decompress(ls)
+            })
             .collect::<Vec<_>>();
 
         let mut decompressed_query_proofs = Vec::with_capacity(num_reductions);
@@ -379,3 +413,95 @@ pub struct FriChallengesTarget<const D: usize> {
     pub fri_pow_response: Target,
     pub fri_query_indices: Vec<Target>,
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use itertools::izip;
+
+    use crate::field::extension::flatten;
+    use crate::field::types::Field;
+    use crate::gates::noop::NoopGate;
+    use crate::hash::merkle_proofs::verify_merkle_proof_to_cap;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn test_accessors_consistent_with_verification() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.mul(x, x);
+        builder.register_public_input(y);
+        // Enough rows for at least one reduction step.
+        for _ in 0..200 {
+            builder.add_gate(NoopGate, vec![]);
+        }
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        let proof = data.prove(pw)?;
+        data.verify(proof.clone())?;
+
+        let params = &data.common.fri_params;
+        let fri_proof = &proof.proof.opening_proof;
+        let challenges = proof.get_challenges(
+            proof.get_public_inputs_hash(),
+            &data.verifier_only.circuit_digest,
+            &data.common,
+        )?;
+        let query_indices = &challenges.fri_challenges.fri_query_indices;
+
+        assert!(!params.reduction_arity_bits.is_empty());
+        assert_eq!(
+            fri_proof.commit_phase_caps().len(),
+            params.reduction_arity_bits.len()
+        );
+        for cap in fri_proof.commit_phase_caps() {
+            assert_eq!(cap.len(), 1 << params.config.cap_height);
+        }
+        assert_eq!(fri_proof.final_poly().len(), params.final_poly_len());
+        assert_eq!(
+            fri_proof.query_round_proofs().len(),
+            params.config.num_query_rounds
+        );
+
+        // Check every opening against its commitment, as a custom verifier would.
+        let initial_caps = [
+            &data.verifier_only.constants_sigmas_cap,
+            &proof.proof.wires_cap,
+            &proof.proof.plonk_zs_partial_products_cap,
+            &proof.proof.quotient_polys_cap,
+        ];
+        for (&x_index, round) in query_indices.iter().zip(fri_proof.query_round_proofs()) {
+            let evals_proofs = &round.initial_trees_proof.evals_proofs;
+            assert_eq!(evals_proofs.len(), initial_caps.len());
+            for ((evals, merkle_proof), cap) in evals_proofs.iter().zip(initial_caps) {
+                verify_merkle_proof_to_cap(evals.clone(), x_index, cap, merkle_proof)?;
+            }
+
+            let mut index = x_index;
+            for (step, cap, &arity_bits) in izip!(
+                &round.steps,
+                fri_proof.commit_phase_caps(),
+                &params.reduction_arity_bits
+            ) {
+                assert_eq!(step.evals.len(), 1 << arity_bits);
+                index >>= arity_bits;
+                verify_merkle_proof_to_cap(
+                    flatten::<F, D>(&step.evals),
+                    index,
+                    cap,
+                    &step.merkle_proof,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
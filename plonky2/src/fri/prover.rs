@@ -7,7 +7,7 @@ use crate::field::polynomial::{PolynomialCoeffs, PolynomialValues};
 use crate::fri::proof::{FriInitialTreeProof, FriProof, FriQueryRound, FriQueryStep};
 use crate::fri::{FriConfig, FriParams};
 use crate::hash::hash_types::RichField;
-use crate::hash::hashing::{PlonkyPermutation, SPONGE_RATE};
+use crate::hash::hashing::PlonkyPermutation;
 use crate::hash::merkle_tree::MerkleTree;
 use crate::iop::challenger::Challenger;
 use crate::plonk::config::{GenericConfig, Hasher};
@@ -111,6 +111,9 @@ fn fri_committed_trees<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>,
     (trees, coeffs)
 }
 
+/// The number of proof-of-work candidates checked in parallel before looking at the results.
+const POW_BATCH_SIZE: u64 = 1 << 14;
+
 /// Performs the proof-of-work (a.k.a. grinding) step of the FRI protocol. Returns the PoW witness.
 fn fri_proof_of_work<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     challenger: &mut Challenger<F, C::Hasher>,
@@ -140,16 +143,26 @@ fn fri_proof_of_work<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, c
         duplex_intermediate_state[i] = *input;
     }
 
-    let pow_witness = (0..=F::NEG_ONE.to_canonical_u64())
-        .into_par_iter()
-        .find_any(|&candidate| {
-            let mut duplex_state = duplex_intermediate_state;
-            duplex_state[witness_input_pos] = F::from_canonical_u64(candidate);
-            duplex_state =
-                <<C as GenericConfig<D>>::Hasher as Hasher<F>>::Permutation::permute(duplex_state);
-            let pow_response = duplex_state[SPONGE_RATE - 1];
-            let leading_zeros = pow_response.to_canonical_u64().leading_zeros();
-            leading_zeros >= min_leading_zeros
+    let is_valid = |&candidate: &u64| {
+        let mut duplex_state = duplex_intermediate_state;
+        duplex_state[witness_input_pos] = F::from_canonical_u64(candidate);
+        duplex_state =
+            <<C as GenericConfig<D>>::Hasher as Hasher<F>>::Permutation::permute(duplex_state);
+        // The challenger squeezes from the end of the rate, so this is its next challenge.
+        let pow_response =
+            duplex_state[<<C as GenericConfig<D>>::Hasher as Hasher<F>>::Permutation::RATE - 1];
+        let leading_zeros = pow_response.to_canonical_u64().leading_zeros();
+        leading_zeros >= min_leading_zeros
+    };
+
+    // Candidates are searched in parallel one batch at a time, keeping the smallest valid candidate
+    // of the first batch containing one. Unlike taking whichever candidate a thread finds first,
+    // this doesn't depend on scheduling, so proving the same witness twice gives the same proof.
+    let pow_witness = (0..F::ORDER)
+        .step_by(POW_BATCH_SIZE as usize)
+        .find_map(|start| {
+            let end = start.saturating_add(POW_BATCH_SIZE).min(F::ORDER);
+            (start..end).into_par_iter().filter(is_valid).min()
         })
         .map(F::from_canonical_u64)
         .expect("Proof of work failed. This is highly unlikely!");
@@ -6,11 +6,16 @@ use crate::field::extension::Extendable;
 use crate::field::types::Field64;
 use crate::gates::arithmetic_base::ArithmeticGate;
 use crate::gates::exponentiation::ExponentiationGate;
+use crate::gates::inner_product::InnerProductGate;
+use crate::gates::mul_add_mul::MulAddMulGate;
+use crate::gates::xor::XorGate;
 use crate::hash::hash_types::RichField;
 use crate::iop::generator::{GeneratedValues, SimpleGenerator};
 use crate::iop::target::{BoolTarget, Target};
 use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
 use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::util::log_floor;
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// Computes `-x`.
@@ -83,7 +88,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     fn add_base_arithmetic_operation(&mut self, operation: BaseArithmeticOperation<F>) -> Target {
         let gate = ArithmeticGate::new_from_config(&self.config);
         let constants = vec![operation.const_0, operation.const_1];
-        let (gate, i) = self.find_slot(gate, &constants, &constants);
+        let (gate, i) = self.find_relocatable_slot(gate, &constants, &constants);
         let wires_multiplicand_0 = Target::wire(gate, ArithmeticGate::wire_ith_multiplicand_0(i));
         let wires_multiplicand_1 = Target::wire(gate, ArithmeticGate::wire_ith_multiplicand_1(i));
         let wires_addend = Target::wire(gate, ArithmeticGate::wire_ith_addend(i));
@@ -92,7 +97,14 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.connect(operation.multiplicand_1, wires_multiplicand_1);
         self.connect(operation.addend, wires_addend);
 
-        Target::wire(gate, ArithmeticGate::wire_ith_output(i))
+        // The output is only handed out through a copy, so that `build` may move this operation to
+        // another row.
+        let output = self.add_virtual_target();
+        self.connect(
+            output,
+            Target::wire(gate, ArithmeticGate::wire_ith_output(i)),
+        );
+        output
     }
 
     /// Checks for special cases where the value of
@@ -153,6 +165,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         None
     }
 
+    /// Asserts that `x` equals the constant `c`, by connecting it to `constant(c)`.
+    pub fn assert_equal_constant(&mut self, x: Target, c: F) {
+        let c = self.constant(c);
+        self.connect(x, c);
+    }
+
     /// Computes `x * y + z`.
     pub fn mul_add(&mut self, x: Target, y: Target, z: Target) -> Target {
         self.arithmetic(F::ONE, F::ONE, x, y, z)
@@ -181,6 +199,19 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.arithmetic(F::ONE, F::NEG_ONE, x, y, z)
     }
 
+    /// Computes `a * b + c * d` in a single `MulAddMulGate` operation, rather than the two
+    /// `ArithmeticGate` operations that `mul` followed by `mul_add` would take.
+    pub fn mul_add_mul(&mut self, a: Target, b: Target, c: Target, d: Target) -> Target {
+        let gate = MulAddMulGate::new_from_config(&self.config);
+        let (row, i) = self.find_slot(gate, &[], &[]);
+        self.connect(a, Target::wire(row, MulAddMulGate::wire_ith_a(i)));
+        self.connect(b, Target::wire(row, MulAddMulGate::wire_ith_b(i)));
+        self.connect(c, Target::wire(row, MulAddMulGate::wire_ith_c(i)));
+        self.connect(d, Target::wire(row, MulAddMulGate::wire_ith_d(i)));
+
+        Target::wire(row, MulAddMulGate::wire_ith_output(i))
+    }
+
     /// Computes `x + y`.
     pub fn add(&mut self, x: Target, y: Target) -> Target {
         let one = self.one();
@@ -188,14 +219,44 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.arithmetic(F::ONE, F::ONE, x, one, y)
     }
 
-    /// Add `n` `Target`s.
+    /// Computes `(a - b) mod 2^n` along with a borrow bit, i.e. returns `(diff, borrow)` such that
+    /// `a + borrow * 2^n = diff + b`. `diff` is range-checked to `n` bits, while `a` and `b` are
+    /// assumed to be range-checked for `n` bits by the caller.
+    pub fn sub_with_borrow(&mut self, a: Target, b: Target, n: usize) -> (Target, BoolTarget) {
+        assert!(
+            n < F::BITS - 1,
+            "{} bits may overflow the field in sub_with_borrow",
+            n
+        );
+
+        let diff = self.add_virtual_target();
+        let borrow = self.add_virtual_bool_target_safe();
+        self.add_simple_generator(SubWithBorrowGenerator {
+            a,
+            b,
+            n,
+            diff,
+            borrow,
+        });
+        self.range_check(diff, n);
+
+        // a + borrow * 2^n == diff + b
+        let pow2 = self.constant(F::from_canonical_u64(1 << n));
+        let lhs = self.mul_add(borrow.target, pow2, a);
+        let rhs = self.add(diff, b);
+        self.connect(lhs, rhs);
+
+        (diff, borrow)
+    }
+
+    /// Add `n` `Target`s, pairing them up in a balanced tree so that the result has depth
+    /// `log n` rather than `n`. Returns zero if there are no terms.
     pub fn add_many<T>(&mut self, terms: impl IntoIterator<Item = T>) -> Target
     where
         T: Borrow<Target>,
     {
-        terms
-            .into_iter()
-            .fold(self.zero(), |acc, t| self.add(acc, *t.borrow()))
+        let zero = self.zero();
+        self.reduce_balanced(terms, zero, Self::add)
     }
 
     /// Computes `x - y`.
@@ -211,14 +272,90 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.arithmetic(F::ONE, F::ZERO, x, y, x)
     }
 
-    /// Multiply `n` `Target`s.
+    /// Multiply `n` `Target`s, pairing them up in a balanced tree as in `add_many`. Returns one if
+    /// there are no terms.
     pub fn mul_many<T>(&mut self, terms: impl IntoIterator<Item = T>) -> Target
     where
         T: Borrow<Target>,
     {
-        terms
-            .into_iter()
-            .fold(self.one(), |acc, t| self.mul(acc, *t.borrow()))
+        let one = self.one();
+        self.reduce_balanced(terms, one, Self::mul)
+    }
+
+    /// Combines `terms` with the associative `op`, one layer of adjacent pairs at a time. An odd
+    /// term out is carried up to the next layer unchanged.
+    fn reduce_balanced<T>(
+        &mut self,
+        terms: impl IntoIterator<Item = T>,
+        identity: Target,
+        mut op: impl FnMut(&mut Self, Target, Target) -> Target,
+    ) -> Target
+    where
+        T: Borrow<Target>,
+    {
+        let mut layer = terms.into_iter().map(|t| *t.borrow()).collect::<Vec<_>>();
+        if layer.is_empty() {
+            return identity;
+        }
+        while layer.len() > 1 {
+            layer = layer
+                .chunks(2)
+                .map(|pair| match *pair {
+                    [x, y] => op(self, x, y),
+                    [x] => x,
+                    _ => unreachable!(),
+                })
+                .collect();
+        }
+        layer[0]
+    }
+
+    /// Computes the inner product `sum a_i * b_i`, using one `InnerProductGate` per `N` terms. The
+    /// last chunk is padded with zeros if `N` doesn't divide the length.
+    ///
+    /// An `ArithmeticGate` row holds `num_routed_wires / 4` multiply-adds, so a chain of `mul_add`s
+    /// takes about `n / (num_routed_wires / 4)` rows for `n` terms, against `n / N` rows here. This
+    /// only saves rows if `N` is larger than `num_routed_wires / 4`, i.e. 20 with the standard
+    /// recursion config, which allows `N` up to 39.
+    pub fn inner_product<const N: usize>(&mut self, a: &[Target], b: &[Target]) -> Target {
+        assert!(N > 0, "InnerProductGate needs N > 0");
+        assert_eq!(a.len(), b.len(), "Vectors must have the same length");
+        let num_wires = 2 * N + 1;
+        assert!(
+            num_wires <= self.config.num_routed_wires,
+            "InnerProductGate with N = {} needs {} routed wires, but the config has {}",
+            N,
+            num_wires,
+            self.config.num_routed_wires
+        );
+
+        let zero = self.zero();
+        let chunk_outputs = a
+            .chunks(N)
+            .zip(b.chunks(N))
+            .map(|(a_chunk, b_chunk)| {
+                let row = self.add_gate(InnerProductGate::<F, D, N>::new(), vec![]);
+                for i in 0..N {
+                    let a_i = a_chunk.get(i).copied().unwrap_or(zero);
+                    let b_i = b_chunk.get(i).copied().unwrap_or(zero);
+                    self.connect(
+                        a_i,
+                        Target::wire(row, InnerProductGate::<F, D, N>::wires_a(i)),
+                    );
+                    self.connect(
+                        b_i,
+                        Target::wire(row, InnerProductGate::<F, D, N>::wires_b(i)),
+                    );
+                }
+                Target::wire(row, InnerProductGate::<F, D, N>::wire_output())
+            })
+            .collect::<Vec<_>>();
+
+        match chunk_outputs[..] {
+            [] => zero,
+            [output] => output,
+            _ => self.add_many(chunk_outputs),
+        }
     }
 
     /// Exponentiate `base` to the power of `2^power_log`.
@@ -235,18 +372,38 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         product
     }
 
-    // TODO: Test
-    /// Exponentiate `base` to the power of `exponent`, given by its little-endian bits.
+    /// Exponentiate `base` to the power of `exponent`, given by its little-endian bits. An empty
+    /// list of bits gives `1`.
+    ///
+    /// Exponents which fit in a single `ExponentiationGate` use one; longer ones fall back to
+    /// square-and-multiply, which costs a constant number of arithmetic operations per bit.
     pub fn exp_from_bits(
         &mut self,
         base: Target,
         exponent_bits: impl IntoIterator<Item = impl Borrow<BoolTarget>>,
     ) -> Target {
-        let _false = self._false();
-        let gate = ExponentiationGate::new_from_config(&self.config);
-        let num_power_bits = gate.num_power_bits;
         let mut exp_bits_vec: Vec<BoolTarget> =
             exponent_bits.into_iter().map(|b| *b.borrow()).collect();
+        if exp_bits_vec.is_empty() {
+            return self.one();
+        }
+
+        let gate = ExponentiationGate::new_from_config(&self.config);
+        let num_power_bits = gate.num_power_bits;
+        if exp_bits_vec.len() > num_power_bits {
+            let mut current = base;
+            let mut product = self.one();
+            for (i, bit) in exp_bits_vec.into_iter().enumerate() {
+                if i != 0 {
+                    current = self.square(current);
+                }
+                let multiplied = self.mul(product, current);
+                product = self.select(bit, multiplied, product);
+            }
+            return product;
+        }
+
+        let _false = self._false();
         while exp_bits_vec.len() < num_power_bits {
             exp_bits_vec.push(_false);
         }
@@ -314,6 +471,29 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.exp_from_bits(base, exp_bits)
     }
 
+    /// Exponentiate `base` to the power of a known `exponent`, using a multiplication chain.
+    ///
+    /// Small exponents, such as S-box exponents, use a short addition chain, which costs a few
+    /// arithmetic operations instead of a whole `ExponentiationGate`. Exponents whose chain needs
+    /// more than one gate's worth of operations fall back to `exp_u64`.
+    pub fn exp_const_u64(&mut self, base: Target, exponent: u64) -> Target {
+        if exponent == 0 {
+            return self.one();
+        }
+
+        let chain = addition_chain(exponent);
+        if chain.len() > self.num_base_arithmetic_ops_per_gate() {
+            return self.exp_u64(base, exponent);
+        }
+
+        let mut powers = vec![base];
+        for (i, j) in chain {
+            let power = self.mul(powers[i], powers[j]);
+            powers.push(power);
+        }
+        *powers.last().unwrap()
+    }
+
     /// Computes `x / y`. Results in an unsatisfiable instance if `y = 0`.
     pub fn div(&mut self, x: Target, y: Target) -> Target {
         let x = self.convert_to_ext(x);
@@ -321,10 +501,64 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.div_extension(x, y).0[0]
     }
 
-    /// Computes `1 / x`. Results in an unsatisfiable instance if `x = 0`.
+    /// Computes `1 / x`, constraining `x * inv = 1`. Witness generation panics if `x = 0`.
     pub fn inverse(&mut self, x: Target) -> Target {
-        let x_ext = self.convert_to_ext(x);
-        self.inverse_extension(x_ext).0[0]
+        let inv = self.add_virtual_target();
+        self.add_simple_generator(InverseGenerator {
+            x,
+            inv,
+            allow_zero: false,
+        });
+
+        let x_inv = self.mul(x, inv);
+        self.assert_one(x_inv);
+        inv
+    }
+
+    /// Computes `1 / x`, or zero if `x = 0`.
+    ///
+    /// This constrains `x * x * inv = x` and `inv * inv * x = inv`. The first alone pins down `inv`
+    /// for nonzero `x`, but leaves it free when `x = 0`; the second then forces it to zero.
+    pub fn inverse_or_zero(&mut self, x: Target) -> Target {
+        let inv = self.add_virtual_target();
+        self.add_simple_generator(InverseGenerator {
+            x,
+            inv,
+            allow_zero: true,
+        });
+
+        let x_inv = self.mul(x, inv);
+        let x_x_inv = self.mul(x_inv, x);
+        self.connect(x_x_inv, x);
+        let inv_x_inv = self.mul(x_inv, inv);
+        self.connect(inv_x_inv, inv);
+        inv
+    }
+
+    /// Computes a square root of `x`. Of the two roots, this returns the one whose canonical value
+    /// is at most `(p - 1) / 2`, so the result is uniquely determined by `x`. Witness generation
+    /// panics if `x` is not a quadratic residue.
+    pub fn sqrt(&mut self, x: Target) -> Target {
+        let root = self.add_virtual_target();
+        self.add_simple_generator(SqrtGenerator { x, root });
+
+        let root_squared = self.square(root);
+        self.connect(root_squared, x);
+
+        // Range check both `root` and `max_root - root`. If `root > max_root`, the difference wraps
+        // around to at least `p - (2^num_bits - 1 - max_root)`, which must then be out of range.
+        let max_root = F::ORDER / 2;
+        let num_bits = (u64::BITS - max_root.leading_zeros()) as usize;
+        assert!(
+            F::ORDER as u128 + max_root as u128 + 1 >= 1 << (num_bits + 1),
+            "Field order is too far from a power of two for this range check"
+        );
+        self.range_check(root, num_bits);
+        let max_root = self.constant(F::from_canonical_u64(max_root));
+        let diff = self.sub(max_root, root);
+        self.range_check(diff, num_bits);
+
+        root
     }
 
     pub fn not(&mut self, b: BoolTarget) -> BoolTarget {
@@ -343,6 +577,52 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         BoolTarget::new_unsafe(self.add(res_minus_b2, b2.target))
     }
 
+    /// Computes `b1 XOR b2`, i.e. `b1 + b2 - 2 b1 b2`, using a slot in an `XorGate`. The result is
+    /// boolean whenever the inputs are.
+    pub fn xor(&mut self, b1: BoolTarget, b2: BoolTarget) -> BoolTarget {
+        if let (Some(c1), Some(c2)) = (
+            self.target_as_constant(b1.target),
+            self.target_as_constant(b2.target),
+        ) {
+            return self.constant_bool(c1 != c2);
+        }
+
+        let gate = XorGate::new_from_config(&self.config);
+        let (row, i) = self.find_slot(gate, &[], &[]);
+        self.connect(b1.target, Target::wire(row, XorGate::wire_ith_a(i)));
+        self.connect(b2.target, Target::wire(row, XorGate::wire_ith_b(i)));
+
+        BoolTarget::new_unsafe(Target::wire(row, XorGate::wire_ith_output(i)))
+    }
+
+    /// Computes the parity of `bits`, i.e. their XOR. Returns false if there are no bits.
+    pub fn xor_many(&mut self, bits: &[BoolTarget]) -> BoolTarget {
+        let _false = self._false();
+        bits.iter().fold(_false, |acc, &b| self.xor(acc, b))
+    }
+
+    /// Computes `rot(x, k) XOR y`, where `x` and `y` are given by their little-endian bits and
+    /// `rot` rotates towards the most significant bit, like `u64::rotate_left`.
+    pub fn rot_xor(
+        &mut self,
+        x_bits: &[BoolTarget],
+        k: usize,
+        y_bits: &[BoolTarget],
+    ) -> Vec<BoolTarget> {
+        let n = x_bits.len();
+        assert_eq!(
+            n,
+            y_bits.len(),
+            "Operands must have the same number of bits"
+        );
+        (0..n)
+            .map(|i| {
+                let x = x_bits[(i + n - k % n) % n];
+                self.xor(x, y_bits[i])
+            })
+            .collect()
+    }
+
     pub fn _if(&mut self, b: BoolTarget, x: Target, y: Target) -> Target {
         let not_b = self.not(b);
         let maybe_x = self.mul(b.target, x);
@@ -371,7 +651,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 }
 
 #[derive(Debug)]
-struct EqualityGenerator {
+pub(crate) struct EqualityGenerator {
     x: Target,
     y: Target,
     equal: BoolTarget,
@@ -392,6 +672,216 @@ impl<F: RichField> SimpleGenerator<F> for EqualityGenerator {
         out_buffer.set_bool_target(self.equal, x == y);
         out_buffer.set_target(self.inv, inv);
     }
+
+    fn serialize(&self, dst: &mut Vec<u8>) -> IoResult<()> {
+        dst.write_target(self.x)?;
+        dst.write_target(self.y)?;
+        dst.write_bool_target(self.equal)?;
+        dst.write_target(self.inv)
+    }
+
+    fn deserialize(src: &mut Buffer) -> IoResult<Self> {
+        let x = src.read_target()?;
+        let y = src.read_target()?;
+        let equal = src.read_bool_target()?;
+        let inv = src.read_target()?;
+        Ok(Self { x, y, equal, inv })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SqrtGenerator {
+    x: Target,
+    root: Target,
+}
+
+impl<F: RichField> SimpleGenerator<F> for SqrtGenerator {
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.x]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let x = witness.get_target(self.x);
+        let root = x
+            .sqrt()
+            .unwrap_or_else(|| panic!("{x} is not a quadratic residue"));
+        let root = if root.to_canonical_u64() <= F::ORDER / 2 {
+            root
+        } else {
+            -root
+        };
+
+        out_buffer.set_target(self.root, root);
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>) -> IoResult<()> {
+        dst.write_target(self.x)?;
+        dst.write_target(self.root)
+    }
+
+    fn deserialize(src: &mut Buffer) -> IoResult<Self> {
+        let x = src.read_target()?;
+        let root = src.read_target()?;
+        Ok(Self { x, root })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct InverseGenerator {
+    x: Target,
+    inv: Target,
+    /// Whether to output zero for `x = 0`, rather than panicking.
+    allow_zero: bool,
+}
+
+impl<F: RichField> SimpleGenerator<F> for InverseGenerator {
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.x]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let x = witness.get_target(self.x);
+        let inv = match x.try_inverse() {
+            Some(inv) => inv,
+            None if self.allow_zero => F::ZERO,
+            None => panic!("Tried to invert zero"),
+        };
+
+        out_buffer.set_target(self.inv, inv);
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>) -> IoResult<()> {
+        dst.write_target(self.x)?;
+        dst.write_target(self.inv)?;
+        dst.write_bool(self.allow_zero)
+    }
+
+    fn deserialize(src: &mut Buffer) -> IoResult<Self> {
+        let x = src.read_target()?;
+        let inv = src.read_target()?;
+        let allow_zero = src.read_bool()?;
+        Ok(Self { x, inv, allow_zero })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SubWithBorrowGenerator {
+    a: Target,
+    b: Target,
+    n: usize,
+    diff: Target,
+    borrow: BoolTarget,
+}
+
+impl<F: RichField> SimpleGenerator<F> for SubWithBorrowGenerator {
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.a, self.b]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let a = witness.get_target(self.a).to_canonical_u64();
+        let b = witness.get_target(self.b).to_canonical_u64();
+
+        let mask = (1u64 << self.n) - 1;
+        let diff = a.wrapping_sub(b) & mask;
+
+        out_buffer.set_target(self.diff, F::from_canonical_u64(diff));
+        out_buffer.set_bool_target(self.borrow, a < b);
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>) -> IoResult<()> {
+        dst.write_target(self.a)?;
+        dst.write_target(self.b)?;
+        dst.write_u32(self.n as u32)?;
+        dst.write_target(self.diff)?;
+        dst.write_bool_target(self.borrow)
+    }
+
+    fn deserialize(src: &mut Buffer) -> IoResult<Self> {
+        let a = src.read_target()?;
+        let b = src.read_target()?;
+        let n = src.read_u32()? as usize;
+        let diff = src.read_target()?;
+        let borrow = src.read_bool_target()?;
+        Ok(Self {
+            a,
+            b,
+            n,
+            diff,
+            borrow,
+        })
+    }
+}
+
+/// Exponents up to this bound get a shortest star addition chain; larger ones use the binary method.
+const MAX_SEARCHED_ADDITION_CHAIN_EXPONENT: u64 = 1 << 8;
+
+/// Returns an addition chain for `exponent`, which must be nonzero. The chain starts at `1`, and
+/// each pair `(i, j)` appends the sum of the `i`th and `j`th elements computed so far.
+fn addition_chain(exponent: u64) -> Vec<(usize, usize)> {
+    debug_assert_ne!(exponent, 0);
+    if exponent > MAX_SEARCHED_ADDITION_CHAIN_EXPONENT {
+        return binary_addition_chain(exponent);
+    }
+
+    // Iterative deepening search over star chains, i.e. chains where each step extends the
+    // previous element. These are optimal for all exponents in the searched range.
+    let mut values = vec![1];
+    let mut steps = Vec::new();
+    for max_len in log_floor(exponent, 2).. {
+        if star_chain_search(exponent, max_len, &mut values, &mut steps) {
+            return steps;
+        }
+    }
+    unreachable!()
+}
+
+fn star_chain_search(
+    exponent: u64,
+    max_len: usize,
+    values: &mut Vec<u64>,
+    steps: &mut Vec<(usize, usize)>,
+) -> bool {
+    let last = *values.last().unwrap();
+    if last == exponent {
+        return true;
+    }
+    let remaining = max_len - steps.len();
+    // Even doubling at every remaining step can't reach the exponent.
+    if remaining == 0 || last << remaining < exponent {
+        return false;
+    }
+
+    let k = values.len() - 1;
+    for j in (0..values.len()).rev() {
+        let next = last + values[j];
+        if next > exponent {
+            continue;
+        }
+        values.push(next);
+        steps.push((k, j));
+        if star_chain_search(exponent, max_len, values, steps) {
+            return true;
+        }
+        values.pop();
+        steps.pop();
+    }
+    false
+}
+
+/// Left-to-right square-and-multiply, written as an addition chain.
+fn binary_addition_chain(exponent: u64) -> Vec<(usize, usize)> {
+    let mut steps = Vec::new();
+    let mut last = 0;
+    for i in (0..log_floor(exponent, 2)).rev() {
+        steps.push((last, last));
+        last += 1;
+        if (exponent >> i) & 1 == 1 {
+            steps.push((last, 0));
+            last += 1;
+        }
+    }
+    steps
 }
 
 /// Represents a base arithmetic operation in the circuit. Used to memoize results.
@@ -403,3 +893,533 @@ pub(crate) struct BaseArithmeticOperation<F: Field64> {
     multiplicand_1: Target,
     addend: Target,
 }
+
+impl<F: RichField> BaseArithmeticOperation<F> {
+    /// Writes this operation to `dst`, for a serialized `CircuitBuilder`.
+    pub(crate) fn write(&self, dst: &mut Vec<u8>) -> IoResult<()> {
+        dst.write_field(self.const_0)?;
+        dst.write_field(self.const_1)?;
+        dst.write_target(self.multiplicand_0)?;
+        dst.write_target(self.multiplicand_1)?;
+        dst.write_target(self.addend)
+    }
+
+    /// Reads an operation written by `write`.
+    pub(crate) fn read(src: &mut Buffer) -> IoResult<Self> {
+        Ok(Self {
+            const_0: src.read_field()?,
+            const_1: src.read_field()?,
+            multiplicand_0: src.read_target()?,
+            multiplicand_1: src.read_target()?,
+            addend: src.read_target()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use num::{BigUint, ToPrimitive, Zero};
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::addition_chain;
+    use crate::field::types::{Field, Field64, PrimeField64, Sample};
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    #[test]
+    fn test_mul_add_mul() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // Enough operations to spill over into a second `MulAddMulGate`.
+        for _ in 0..20 {
+            let [a, b, c, d] = [(); 4].map(|_| {
+                let t = builder.add_virtual_target();
+                pw.set_target(t, F::rand());
+                t
+            });
+            let cd = builder.mul(c, d);
+            let expected = builder.mul_add(a, b, cd);
+            let result = builder.mul_add_mul(a, b, c, d);
+            builder.connect(result, expected);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_exp_from_bits() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // The longest exponent doesn't fit in an `ExponentiationGate`.
+        for num_bits in [0, 1, 10, 64, 100] {
+            let bits = (0..num_bits)
+                .map(|_| OsRng.gen::<bool>())
+                .collect::<Vec<_>>();
+            let exponent = bits.iter().rev().fold(BigUint::zero(), |acc, &b| {
+                (acc << 1u32) + BigUint::from(b as u8)
+            });
+            let bits_t = bits
+                .iter()
+                .map(|&b| {
+                    let b_t = builder.add_virtual_bool_target_safe();
+                    pw.set_bool_target(b_t, b);
+                    b_t
+                })
+                .collect::<Vec<_>>();
+
+            let base = F::rand();
+            let base_t = builder.add_virtual_target();
+            pw.set_target(base_t, base);
+            let expected = if num_bits <= 64 {
+                base.exp_u64(exponent.to_u64().unwrap())
+            } else {
+                base.exp_biguint(&exponent)
+            };
+            let result = builder.exp_from_bits(base_t, &bits_t);
+            let expected_t = builder.constant(expected);
+            builder.connect(result, expected_t);
+
+            let base_ext = FF::rand();
+            let base_ext_t = builder.add_virtual_extension_target();
+            pw.set_extension_target(base_ext_t, base_ext);
+            let result_ext = builder.exp_from_bits_extension(base_ext_t, &bits_t);
+            let expected_ext_t = builder.constant_extension(base_ext.exp_biguint(&exponent));
+            builder.connect_extension(result_ext, expected_ext_t);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_addition_chain() {
+        for exponent in 1..=300 {
+            let mut values = vec![1u64];
+            for (i, j) in addition_chain(exponent) {
+                values.push(values[i] + values[j]);
+            }
+            assert_eq!(*values.last().unwrap(), exponent);
+        }
+
+        // Known shortest chain lengths.
+        for (exponent, len) in [(1, 0), (5, 3), (7, 4), (15, 5), (17, 5), (127, 10)] {
+            assert_eq!(addition_chain(exponent).len(), len);
+        }
+    }
+
+    #[test]
+    fn test_exp_const_u64() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        // As many bases as a Poseidon state, like a full S-box layer.
+        const NUM_BASES: usize = 12;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+
+        for exponent in [0, 1, 5, 7, 17, 255, u64::MAX] {
+            let base = F::rand();
+            let base_t = builder.add_virtual_target();
+            pw.set_target(base_t, base);
+
+            let result = builder.exp_const_u64(base_t, exponent);
+            let generic_result = builder.exp_u64(base_t, exponent);
+            builder.connect(result, generic_result);
+            let expected_t = builder.constant(base.exp_u64(exponent));
+            builder.connect(result, expected_t);
+        }
+
+        for exponent in [5, 7, 17] {
+            let num_gates = |exp: fn(&mut CircuitBuilder<F, D>, Target, u64) -> Target| {
+                let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+                for _ in 0..NUM_BASES {
+                    let base_t = builder.add_virtual_target();
+                    exp(&mut builder, base_t, exponent);
+                }
+                builder.num_gates()
+            };
+            let const_gates = num_gates(CircuitBuilder::exp_const_u64);
+            let generic_gates = num_gates(CircuitBuilder::exp_u64);
+            assert!(
+                const_gates < generic_gates,
+                "exponent {}: {} gates with exp_const_u64, {} with exp_u64",
+                exponent,
+                const_gates,
+                generic_gates
+            );
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_rot_xor() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = 0x9e37_79b9u32;
+        let y = 0x7f4a_7c15u32;
+        let bits = |builder: &mut CircuitBuilder<F, D>, v: u32| {
+            (0..32)
+                .map(|i| builder.constant_bool((v >> i) & 1 == 1))
+                .collect::<Vec<_>>()
+        };
+        let x_bits = bits(&mut builder, x);
+        let y_bits = bits(&mut builder, y);
+        for k in [0, 1, 7, 31] {
+            let result = builder.rot_xor(&x_bits, k, &y_bits);
+            let expected = bits(&mut builder, x.rotate_left(k as u32) ^ y);
+            for (r, e) in result.into_iter().zip(expected) {
+                builder.connect(r.target, e.target);
+            }
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_xor() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+            let a_t = builder.add_virtual_bool_target_safe();
+            let b_t = builder.add_virtual_bool_target_safe();
+            pw.set_bool_target(a_t, a);
+            pw.set_bool_target(b_t, b);
+            let result = builder.xor(a_t, b_t);
+            let expected = builder.constant_bool(a ^ b);
+            builder.connect(result.target, expected.target);
+        }
+
+        for n in [0, 1, 5, 40] {
+            let bits = (0..n).map(|_| OsRng.gen::<bool>()).collect::<Vec<_>>();
+            let bits_t = bits
+                .iter()
+                .map(|&b| {
+                    let b_t = builder.add_virtual_bool_target_safe();
+                    pw.set_bool_target(b_t, b);
+                    b_t
+                })
+                .collect::<Vec<_>>();
+            let parity = builder.xor_many(&bits_t);
+            let expected = builder.constant_bool(bits.iter().fold(false, |acc, &b| acc ^ b));
+            builder.connect(parity.target, expected.target);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_assert_equal_constant() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let z = builder.add_virtual_target();
+        builder.assert_zero(x);
+        builder.assert_one(y);
+        builder.assert_equal_constant(z, F::from_canonical_u64(12345));
+        pw.set_target(x, F::ZERO);
+        pw.set_target(y, F::ONE);
+        pw.set_target(z, F::from_canonical_u64(12345));
+
+        let v = FF::rand();
+        let xt = builder.add_virtual_extension_target();
+        let yt = builder.add_virtual_extension_target();
+        let zt = builder.add_virtual_extension_target();
+        builder.assert_zero_extension(xt);
+        builder.assert_one_extension(yt);
+        builder.assert_equal_constant_extension(zt, v);
+        pw.set_extension_target(xt, FF::ZERO);
+        pw.set_extension_target(yt, FF::ONE);
+        pw.set_extension_target(zt, v);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_inverse() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        for x in [F::ONE, F::NEG_ONE, F::rand()] {
+            let xt = builder.add_virtual_target();
+            pw.set_target(xt, x);
+            let expected = builder.constant(x.inverse());
+            let inv = builder.inverse(xt);
+            builder.connect(inv, expected);
+            let inv = builder.inverse_or_zero(xt);
+            builder.connect(inv, expected);
+        }
+
+        let zero = builder.add_virtual_target();
+        pw.set_target(zero, F::ZERO);
+        let inv = builder.inverse_or_zero(zero);
+        builder.assert_zero(inv);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    #[should_panic(expected = "Tried to invert zero")]
+    fn test_inverse_of_zero() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let zero = builder.add_virtual_target();
+        pw.set_target(zero, F::ZERO);
+        builder.inverse(zero);
+
+        let data = builder.build::<C>();
+        data.prove(pw).unwrap();
+    }
+
+    #[test]
+    fn test_sqrt() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let roots = [F::ZERO, F::ONE, F::NEG_ONE]
+            .into_iter()
+            .chain(F::rand_vec(8))
+            .collect::<Vec<_>>();
+        for y in roots {
+            let x = builder.add_virtual_target();
+            pw.set_target(x, y * y);
+
+            // The root with the smaller canonical value should be chosen.
+            let expected = if y.to_canonical_u64() <= F::ORDER / 2 {
+                y
+            } else {
+                -y
+            };
+            let root = builder.sqrt(x);
+            let expected = builder.constant(expected);
+            builder.connect(root, expected);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    #[should_panic(expected = "is not a quadratic residue")]
+    fn test_sqrt_non_residue() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_target();
+        builder.sqrt(x);
+        // A generator of the multiplicative group is never a square.
+        pw.set_target(x, F::MULTIPLICATIVE_GROUP_GENERATOR);
+
+        let data = builder.build::<C>();
+        data.prove(pw).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_assert_zero_violated() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_target();
+        builder.assert_zero(x);
+        pw.set_target(x, F::ONE);
+
+        let data = builder.build::<C>();
+        data.prove(pw).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "is unsatisfied")]
+    fn test_assert_equal_constant_violated() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_target();
+        builder.assert_equal_constant(x, F::from_canonical_u64(12345));
+        pw.set_target(x, F::from_canonical_u64(12346));
+
+        let data = builder.build::<C>();
+        data.prove(pw).unwrap();
+    }
+
+    #[test]
+    fn test_sub_with_borrow() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let n = 32;
+        let max = (1u64 << n) - 1;
+        // (a, b, expected diff, expected borrow), with a focus on the borrow boundary.
+        let cases = [
+            (5, 3, 2, false),
+            (7, 7, 0, false),
+            (6, 7, max, true),
+            (0, 1, max, true),
+            (0, max, 1, true),
+            (max, 0, max, false),
+            (max, max, 0, false),
+            (1 << 31, (1 << 31) + 1, max, true),
+        ];
+
+        for (a, b, expected_diff, expected_borrow) in cases {
+            let at = builder.add_virtual_target();
+            let bt = builder.add_virtual_target();
+            pw.set_target(at, F::from_canonical_u64(a));
+            pw.set_target(bt, F::from_canonical_u64(b));
+
+            let (diff, borrow) = builder.sub_with_borrow(at, bt, n);
+            let expected_diff = builder.constant(F::from_canonical_u64(expected_diff));
+            let expected_borrow = builder.constant_bool(expected_borrow);
+            builder.connect(diff, expected_diff);
+            builder.connect(borrow.target, expected_borrow.target);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_add_many_mul_many() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+
+        for len in [0, 1, 2, 3, 7, 8, 21] {
+            let xs = F::rand_vec(len);
+            let mut pw = PartialWitness::new();
+            let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+            let mut naive = CircuitBuilder::<F, D>::new(config.clone());
+            let xs_t = builder.add_virtual_targets(len);
+            let naive_xs_t = naive.add_virtual_targets(len);
+            for (&t, &x) in xs_t.iter().zip(&xs) {
+                pw.set_target(t, x);
+            }
+
+            let sum = builder.add_many(&xs_t);
+            let product = builder.mul_many(&xs_t);
+            let expected_sum = builder.constant(xs.iter().copied().sum());
+            let expected_product = builder.constant(xs.iter().copied().product());
+            builder.connect(sum, expected_sum);
+            builder.connect(product, expected_product);
+
+            // A left fold uses the same number of operations, so the tree shouldn't cost any more.
+            let zero = naive.zero();
+            let one = naive.one();
+            naive_xs_t.iter().fold(zero, |acc, &x| naive.add(acc, x));
+            naive_xs_t.iter().fold(one, |acc, &x| naive.mul(acc, x));
+            assert_eq!(builder.num_gates(), naive.num_gates());
+
+            let data = builder.build::<C>();
+            let proof = data.prove(pw)?;
+            verify(proof, &data.verifier_only, &data.common)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sub_with_borrow_wrong_borrow() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let at = builder.add_virtual_target();
+        let bt = builder.add_virtual_target();
+        pw.set_target(at, F::from_canonical_u64(3));
+        pw.set_target(bt, F::from_canonical_u64(4));
+
+        // The generator sets the borrow bit, which conflicts with a claimed borrow of `false`.
+        let (_diff, borrow) = builder.sub_with_borrow(at, bt, 8);
+        let _false = builder._false();
+        builder.connect(borrow.target, _false.target);
+
+        let data = builder.build::<C>();
+        data.prove(pw).unwrap();
+    }
+}
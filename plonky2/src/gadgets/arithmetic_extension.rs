@@ -9,10 +9,11 @@ use crate::gates::multiplication_extension::MulExtensionGate;
 use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::{ExtensionAlgebraTarget, ExtensionTarget};
 use crate::iop::generator::{GeneratedValues, SimpleGenerator};
-use crate::iop::target::Target;
+use crate::iop::target::{BoolTarget, Target};
 use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::util::bits_u64;
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     pub fn arithmetic_extension(
@@ -64,7 +65,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     ) -> ExtensionTarget<D> {
         let gate = ArithmeticExtensionGate::new_from_config(&self.config);
         let constants = vec![operation.const_0, operation.const_1];
-        let (gate, i) = self.find_slot(gate, &constants, &constants);
+        let (gate, i) = self.find_relocatable_slot(gate, &constants, &constants);
         let wires_multiplicand_0 = ExtensionTarget::from_range(
             gate,
             ArithmeticExtensionGate::<D>::wires_ith_multiplicand_0(i),
@@ -80,7 +81,14 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.connect_extension(operation.multiplicand_1, wires_multiplicand_1);
         self.connect_extension(operation.addend, wires_addend);
 
-        ExtensionTarget::from_range(gate, ArithmeticExtensionGate::<D>::wires_ith_output(i))
+        // The output is only handed out through a copy, so that `build` may move this operation to
+        // another row.
+        let output = self.add_virtual_extension_target();
+        self.connect_extension(
+            output,
+            ExtensionTarget::from_range(gate, ArithmeticExtensionGate::<D>::wires_ith_output(i)),
+        );
+        output
     }
 
     fn compute_mul_extension_operation(
@@ -329,6 +337,23 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.arithmetic_extension(F::ONE, F::ONE, a, b, c)
     }
 
+    /// Like `assert_equal_constant`, but for `ExtensionTarget`s.
+    pub fn assert_equal_constant_extension(&mut self, x: ExtensionTarget<D>, c: F::Extension) {
+        for (&t, c) in x.0.iter().zip(c.to_basefield_array()) {
+            self.assert_equal_constant(t, c);
+        }
+    }
+
+    /// Like `assert_zero`, but for `ExtensionTarget`s.
+    pub fn assert_zero_extension(&mut self, x: ExtensionTarget<D>) {
+        self.assert_equal_constant_extension(x, F::Extension::ZERO);
+    }
+
+    /// Like `assert_one`, but for `ExtensionTarget`s.
+    pub fn assert_one_extension(&mut self, x: ExtensionTarget<D>) {
+        self.assert_equal_constant_extension(x, F::Extension::ONE);
+    }
+
     /// Like `add_const`, but for `ExtensionTarget`s.
     pub fn add_const_extension(&mut self, x: ExtensionTarget<D>, c: F) -> ExtensionTarget<D> {
         let c = self.constant_extension(c.into());
@@ -454,6 +479,25 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         product
     }
 
+    /// Exponentiate `base` to the power of `exponent`, given by its little-endian bits, using
+    /// square-and-multiply. An empty list of bits gives `1`.
+    pub fn exp_from_bits_extension(
+        &mut self,
+        base: ExtensionTarget<D>,
+        exponent_bits: impl IntoIterator<Item = impl Borrow<BoolTarget>>,
+    ) -> ExtensionTarget<D> {
+        let mut current = base;
+        let mut product = self.one_extension();
+        for (i, bit) in exponent_bits.into_iter().enumerate() {
+            if i != 0 {
+                current = self.square_extension(current);
+            }
+            let multiplied = self.mul_extension(product, current);
+            product = self.select_ext(*bit.borrow(), multiplied, product);
+        }
+        product
+    }
+
     /// Computes `x / y`. Results in an unsatisfiable instance if `y = 0`.
     pub fn div_extension(
         &mut self,
@@ -494,7 +538,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 }
 
 #[derive(Debug)]
-struct QuotientGeneratorExtension<const D: usize> {
+pub(crate) struct QuotientGeneratorExtension<const D: usize> {
     numerator: ExtensionTarget<D>,
     denominator: ExtensionTarget<D>,
     quotient: ExtensionTarget<D>,
@@ -515,6 +559,23 @@ impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F>
         let quotient = num / dem;
         out_buffer.set_extension_target(self.quotient, quotient)
     }
+
+    fn serialize(&self, dst: &mut Vec<u8>) -> IoResult<()> {
+        dst.write_target_ext(self.numerator)?;
+        dst.write_target_ext(self.denominator)?;
+        dst.write_target_ext(self.quotient)
+    }
+
+    fn deserialize(src: &mut Buffer) -> IoResult<Self> {
+        let numerator = src.read_target_ext()?;
+        let denominator = src.read_target_ext()?;
+        let quotient = src.read_target_ext()?;
+        Ok(Self {
+            numerator,
+            denominator,
+            quotient,
+        })
+    }
 }
 
 /// An iterator over the powers of a certain base element `b`: `b^0, b^1, b^2, ...`.
@@ -566,12 +627,36 @@ pub(crate) struct ExtensionArithmeticOperation<F: Field64 + Extendable<D>, const
     addend: ExtensionTarget<D>,
 }
 
+impl<F: RichField + Extendable<D>, const D: usize> ExtensionArithmeticOperation<F, D> {
+    /// Writes this operation to `dst`, for a serialized `CircuitBuilder`.
+    pub(crate) fn write(&self, dst: &mut Vec<u8>) -> IoResult<()> {
+        dst.write_field(self.const_0)?;
+        dst.write_field(self.const_1)?;
+        dst.write_target_ext(self.multiplicand_0)?;
+        dst.write_target_ext(self.multiplicand_1)?;
+        dst.write_target_ext(self.addend)
+    }
+
+    /// Reads an operation written by `write`.
+    pub(crate) fn read(src: &mut Buffer) -> IoResult<Self> {
+        Ok(Self {
+            const_0: src.read_field()?,
+            const_1: src.read_field()?,
+            multiplicand_0: src.read_target_ext()?,
+            multiplicand_1: src.read_target_ext()?,
+            addend: src.read_target_ext()?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
 
     use crate::field::extension::algebra::ExtensionAlgebra;
-    use crate::field::types::Sample;
+    use crate::field::extension::Frobenius;
+    use crate::field::types::{Field, Sample};
+    use crate::gates::arithmetic_extension::ArithmeticExtensionGate;
     use crate::iop::ext_target::ExtensionAlgebraTarget;
     use crate::iop::witness::{PartialWitness, WitnessWrite};
     use crate::plonk::circuit_builder::CircuitBuilder;
@@ -615,6 +700,50 @@ mod tests {
         verify(proof, &data.verifier_only, &data.common)
     }
 
+    #[test]
+    fn test_arithmetic_extension_packing() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let ops_per_row = ArithmeticExtensionGate::<D>::num_ops(&config);
+        let num_ops = 2 * ops_per_row + 3;
+        let (const_0, const_1) = (F::from_canonical_u64(3), F::from_canonical_u64(5));
+
+        let mut pw = PartialWitness::<F>::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let inputs = FF::rand_vec(3 * num_ops);
+        let ts = builder.add_virtual_extension_targets(3 * num_ops);
+        for (&v, &t) in inputs.iter().zip(&ts) {
+            pw.set_extension_target(t, v);
+        }
+
+        let gates_before = builder.num_gates();
+        let results = ts
+            .chunks(3)
+            .map(|c| builder.arithmetic_extension(const_0, const_1, c[0], c[1], c[2]))
+            .collect::<Vec<_>>();
+        // Operations with the same constants share rows, filling each before starting the next.
+        assert_eq!(
+            builder.num_gates() - gates_before,
+            num_ops.div_ceil(ops_per_row)
+        );
+
+        for (&result, c) in results.iter().zip(inputs.chunks(3)) {
+            let expected = c[0] * c[1] * const_0.into() + c[2] * const_1.into();
+            let expected = builder.constant_extension(expected);
+            builder.connect_extension(result, expected);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
     #[test]
     fn test_div_extension() -> Result<()> {
         const D: usize = 2;
@@ -642,6 +771,47 @@ mod tests {
         verify(proof, &data.verifier_only, &data.common)
     }
 
+    #[test]
+    fn test_quartic_extension() -> Result<()> {
+        const D: usize = 4;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+
+        let config = CircuitConfig::standard_recursion_config();
+
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = FF::rand();
+        let y = FF::rand();
+        let xt = builder.add_virtual_extension_target();
+        let yt = builder.add_virtual_extension_target();
+        pw.set_extension_target(xt, x);
+        pw.set_extension_target(yt, y);
+
+        let prod = builder.mul_extension(xt, yt);
+        let expected_prod = builder.constant_extension(x * y);
+        builder.connect_extension(prod, expected_prod);
+
+        let quotient = builder.div_extension(xt, yt);
+        let expected_quotient = builder.constant_extension(x * y.inverse());
+        builder.connect_extension(quotient, expected_quotient);
+
+        let frob = prod.frobenius(&mut builder);
+        let expected_frob = builder.constant_extension(x.frobenius() * y.frobenius());
+        builder.connect_extension(frob, expected_frob);
+        let frob_d = xt
+            .repeated_frobenius(D - 1, &mut builder)
+            .frobenius(&mut builder);
+        builder.connect_extension(frob_d, xt);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
     #[test]
     fn test_mul_algebra() -> Result<()> {
         const D: usize = 2;
@@ -1,8 +1,13 @@
 use alloc::vec;
+use core::cmp::Reverse;
 
 use plonky2_field::extension::Extendable;
+use plonky2_util::log2_strict;
 
+use crate::gates::barycentric_interpolation::BarycentricInterpolationGate;
 use crate::gates::coset_interpolation::CosetInterpolationGate;
+use crate::gates::gate::Gate;
+use crate::gates::interpolation::InterpolationGate;
 use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
@@ -12,9 +17,55 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// Interpolates a polynomial, whose points are a coset of the multiplicative subgroup with the
     /// given size, and whose values are given. Returns the evaluation of the interpolant at
     /// `evaluation_point`.
-    pub(crate) fn interpolate_coset(
+    ///
+    /// This picks whichever of `CosetInterpolationGate` and `BarycentricInterpolationGate` fits
+    /// this circuit's config with the lower degree, then the fewer wires. As with the latter,
+    /// `evaluation_point` should not lie in the coset.
+    pub fn interpolate_coset(
         &mut self,
-        gate: CosetInterpolationGate<F, D>,
+        coset_shift: Target,
+        values: &[ExtensionTarget<D>],
+        evaluation_point: ExtensionTarget<D>,
+    ) -> ExtensionTarget<D> {
+        let subgroup_bits = log2_strict(values.len());
+        let max_degree = self.config.max_quotient_degree_factor;
+        let coset_gate = CosetInterpolationGate::<F, D>::with_max_degree(subgroup_bits, max_degree);
+        let barycentric_gate =
+            BarycentricInterpolationGate::<F, D>::with_max_degree(subgroup_bits, max_degree);
+
+        // Gates which don't fit the config get no cost, which `Option`'s ordering puts first.
+        let cost = |num_wires: usize, num_routed_wires: usize, degree: usize| {
+            let fits = num_wires <= self.config.num_wires
+                && num_routed_wires <= self.config.num_routed_wires;
+            fits.then_some(Reverse((degree, num_wires)))
+        };
+        let coset_cost = cost(
+            coset_gate.num_wires(),
+            coset_gate.num_routed_wires(),
+            coset_gate.degree(),
+        );
+        let barycentric_cost = cost(
+            barycentric_gate.num_wires(),
+            barycentric_gate.num_routed_wires(),
+            barycentric_gate.degree(),
+        );
+
+        if barycentric_cost > coset_cost {
+            self.interpolate_coset_with_gate(
+                barycentric_gate,
+                coset_shift,
+                values,
+                evaluation_point,
+            )
+        } else {
+            self.interpolate_coset_with_gate(coset_gate, coset_shift, values, evaluation_point)
+        }
+    }
+
+    /// Like `interpolate_coset`, but using the given interpolation gate.
+    pub(crate) fn interpolate_coset_with_gate<G: InterpolationGate<F, D>>(
+        &mut self,
+        gate: G,
         coset_shift: Target,
         values: &[ExtensionTarget<D>],
         evaluation_point: ExtensionTarget<D>,
@@ -43,6 +94,7 @@ mod tests {
     use crate::field::extension::FieldExtension;
     use crate::field::interpolation::interpolant;
     use crate::field::types::{Field, Sample};
+    use crate::gates::barycentric_interpolation::BarycentricInterpolationGate;
     use crate::gates::coset_interpolation::CosetInterpolationGate;
     use crate::iop::witness::PartialWitness;
     use crate::plonk::circuit_builder::CircuitBuilder;
@@ -60,7 +112,7 @@ mod tests {
         let pw = PartialWitness::new();
         let mut builder = CircuitBuilder::<F, D>::new(config);
 
-        let subgroup_bits = 2;
+        let subgroup_bits = 3;
         let len = 1 << subgroup_bits;
         let coset_shift = F::rand();
         let g = F::primitive_root_of_unity(subgroup_bits);
@@ -87,19 +139,28 @@ mod tests {
 
         let zt = builder.constant_extension(z);
 
-        let evals_coset_gates = (2..=4)
-            .map(|max_degree| {
-                builder.interpolate_coset(
-                    CosetInterpolationGate::with_max_degree(subgroup_bits, max_degree),
-                    coset_shift_target,
-                    &value_targets,
-                    zt,
-                )
+        let mut evals = (2..=4)
+            .flat_map(|max_degree| {
+                [
+                    builder.interpolate_coset_with_gate(
+                        CosetInterpolationGate::with_max_degree(subgroup_bits, max_degree),
+                        coset_shift_target,
+                        &value_targets,
+                        zt,
+                    ),
+                    builder.interpolate_coset_with_gate(
+                        BarycentricInterpolationGate::with_max_degree(subgroup_bits, max_degree),
+                        coset_shift_target,
+                        &value_targets,
+                        zt,
+                    ),
+                ]
             })
             .collect::<Vec<_>>();
+        evals.push(builder.interpolate_coset(coset_shift_target, &value_targets, zt));
         let true_eval_target = builder.constant_extension(true_eval);
-        for &eval_coset_gate in evals_coset_gates.iter() {
-            builder.connect_extension(eval_coset_gate, true_eval_target);
+        for &eval in evals.iter() {
+            builder.connect_extension(eval, true_eval_target);
         }
 
         let data = builder.build::<C>();
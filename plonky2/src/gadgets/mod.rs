@@ -2,9 +2,12 @@ pub mod arithmetic;
 pub mod arithmetic_extension;
 pub mod hash;
 pub mod interpolation;
+pub mod nonnative;
 pub mod polynomial;
 pub mod random_access;
 pub mod range_check;
 pub mod select;
+pub mod set_membership;
+pub mod sorted_set;
 pub mod split_base;
 pub(crate) mod split_join;
@@ -0,0 +1,508 @@
+//! Emulated arithmetic over a prime field `FF` other than the native field, such as the base field
+//! of secp256k1.
+//!
+//! Elements are represented by 16-bit limbs, so that sums of a few products of two limbs still fit
+//! in the native field without wrapping around. Every `NonNativeTarget` is constrained to be in
+//! canonical form, i.e. less than the order of `FF`, so two targets representing the same value
+//! can simply be connected limb by limb.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use num::{BigUint, Integer, One, Zero};
+
+use crate::field::extension::Extendable;
+use crate::field::types::{PrimeField, PrimeField64};
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+use crate::util::{ceil_div_usize, log2_ceil};
+
+const LIMB_BITS: usize = 16;
+
+/// An element of the foreign field `FF`, as little-endian 16-bit limbs.
+#[derive(Clone, Debug)]
+pub struct NonNativeTarget<FF: PrimeField> {
+    pub limbs: Vec<Target>,
+    _phantom: PhantomData<FF>,
+}
+
+impl<FF: PrimeField> NonNativeTarget<FF> {
+    /// The number of limbs needed to represent an element of `FF`.
+    pub fn num_limbs() -> usize {
+        ceil_div_usize(FF::BITS, LIMB_BITS)
+    }
+}
+
+fn biguint_to_limbs(n: &BigUint, num_limbs: usize) -> Vec<u64> {
+    let mask = BigUint::from((1u64 << LIMB_BITS) - 1);
+    (0..num_limbs)
+        .map(|i| {
+            let limb = (n >> (i * LIMB_BITS)) & &mask;
+            limb.to_u64_digits().first().copied().unwrap_or(0)
+        })
+        .collect()
+}
+
+fn limbs_to_biguint<F: PrimeField64>(limbs: &[F]) -> BigUint {
+    limbs.iter().rev().fold(BigUint::zero(), |acc, limb| {
+        (acc << LIMB_BITS) + limb.to_canonical_u64()
+    })
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Adds a `NonNativeTarget` whose limbs are range-checked and which is constrained to be in
+    /// canonical form.
+    pub fn add_virtual_nonnative_target<FF: PrimeField>(&mut self) -> NonNativeTarget<FF> {
+        let x = self.add_virtual_nonnative_target_unsafe();
+        self.assert_canonical_nonnative(&x);
+        x
+    }
+
+    /// Adds a `NonNativeTarget` with range-checked limbs, which isn't constrained to be in
+    /// canonical form.
+    fn add_virtual_nonnative_target_unsafe<FF: PrimeField>(&mut self) -> NonNativeTarget<FF> {
+        let limbs = self.add_virtual_targets(NonNativeTarget::<FF>::num_limbs());
+        for &limb in &limbs {
+            self.range_check(limb, LIMB_BITS);
+        }
+        NonNativeTarget {
+            limbs,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn constant_nonnative<FF: PrimeField>(&mut self, x: FF) -> NonNativeTarget<FF> {
+        let limbs = biguint_to_limbs(
+            &x.to_canonical_biguint(),
+            NonNativeTarget::<FF>::num_limbs(),
+        )
+        .into_iter()
+        .map(|limb| self.constant(F::from_canonical_u64(limb)))
+        .collect();
+        NonNativeTarget {
+            limbs,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn connect_nonnative<FF: PrimeField>(
+        &mut self,
+        x: &NonNativeTarget<FF>,
+        y: &NonNativeTarget<FF>,
+    ) {
+        for (&l, &r) in x.limbs.iter().zip(&y.limbs) {
+            self.connect(l, r);
+        }
+    }
+
+    /// Computes `x + y` in `FF`.
+    pub fn add_nonnative<FF: PrimeField>(
+        &mut self,
+        x: &NonNativeTarget<FF>,
+        y: &NonNativeTarget<FF>,
+    ) -> NonNativeTarget<FF> {
+        self.nonnative_op(NonNativeOp::Add, &x.limbs, &y.limbs)
+    }
+
+    /// Computes `x - y` in `FF`.
+    pub fn sub_nonnative<FF: PrimeField>(
+        &mut self,
+        x: &NonNativeTarget<FF>,
+        y: &NonNativeTarget<FF>,
+    ) -> NonNativeTarget<FF> {
+        self.nonnative_op(NonNativeOp::Sub, &x.limbs, &y.limbs)
+    }
+
+    /// Computes `x * y` in `FF`.
+    pub fn mul_nonnative<FF: PrimeField>(
+        &mut self,
+        x: &NonNativeTarget<FF>,
+        y: &NonNativeTarget<FF>,
+    ) -> NonNativeTarget<FF> {
+        self.nonnative_op(NonNativeOp::Mul, &x.limbs, &y.limbs)
+    }
+
+    /// Reduces the integer with the given little-endian 16-bit limbs modulo the order of `FF`. The
+    /// limbs are assumed to be range-checked already; there may be any number of them.
+    pub fn reduce_nonnative<FF: PrimeField>(&mut self, limbs: &[Target]) -> NonNativeTarget<FF> {
+        self.nonnative_op(NonNativeOp::Reduce, limbs, &[])
+    }
+
+    /// Computes the canonical result `r` of `op`, along with a quotient `q`, and constrains
+    /// `op(x, y) = q * p + r` over the integers, where `p` is the order of `FF`.
+    fn nonnative_op<FF: PrimeField>(
+        &mut self,
+        op: NonNativeOp,
+        x: &[Target],
+        y: &[Target],
+    ) -> NonNativeTarget<FF> {
+        let n = NonNativeTarget::<FF>::num_limbs();
+        let modulus = biguint_to_limbs(&FF::order(), n);
+
+        let result = self.add_virtual_nonnative_target::<FF>();
+        // For additions and subtractions the quotient is a single bit; otherwise it's bounded by
+        // the size of the unreduced value.
+        let num_quotient_limbs = match op {
+            NonNativeOp::Add | NonNativeOp::Sub => 1,
+            NonNativeOp::Mul => n,
+            NonNativeOp::Reduce => x.len(),
+        };
+        let quotient = self.add_virtual_targets(num_quotient_limbs);
+        match op {
+            NonNativeOp::Add | NonNativeOp::Sub => {
+                self.range_check(quotient[0], 1);
+            }
+            NonNativeOp::Mul | NonNativeOp::Reduce => {
+                for &limb in &quotient {
+                    self.range_check(limb, LIMB_BITS);
+                }
+            }
+        }
+
+        self.add_simple_generator(NonNativeOpGenerator::<FF> {
+            op,
+            x: x.to_vec(),
+            y: y.to_vec(),
+            quotient: quotient.clone(),
+            result: result.limbs.clone(),
+            _phantom: PhantomData,
+        });
+
+        // Coefficients of `op(x, y) - q * p - r`, or `x - y + q * p - r` for a subtraction, in
+        // which case `q` is the borrow.
+        let num_terms = match op {
+            NonNativeOp::Mul => 2 * n - 1,
+            _ => x.len().max(num_quotient_limbs + n - 1),
+        };
+        let zero = self.zero();
+        let mut terms = vec![zero; num_terms];
+        match op {
+            NonNativeOp::Add => {
+                self.accumulate_limbs(&mut terms, x, F::ONE);
+                self.accumulate_limbs(&mut terms, y, F::ONE);
+            }
+            NonNativeOp::Sub => {
+                self.accumulate_limbs(&mut terms, x, F::ONE);
+                self.accumulate_limbs(&mut terms, y, F::NEG_ONE);
+            }
+            NonNativeOp::Mul => {
+                for (i, &x_i) in x.iter().enumerate() {
+                    for (j, &y_j) in y.iter().enumerate() {
+                        terms[i + j] = self.mul_add(x_i, y_j, terms[i + j]);
+                    }
+                }
+            }
+            NonNativeOp::Reduce => self.accumulate_limbs(&mut terms, x, F::ONE),
+        }
+        let quotient_sign = match op {
+            NonNativeOp::Sub => F::ONE,
+            _ => F::NEG_ONE,
+        };
+        let one = self.one();
+        for (i, &q_i) in quotient.iter().enumerate() {
+            for (j, &p_j) in modulus.iter().enumerate() {
+                let c = quotient_sign * F::from_canonical_u64(p_j);
+                terms[i + j] = self.arithmetic(c, F::ONE, q_i, one, terms[i + j]);
+            }
+        }
+        self.accumulate_limbs(&mut terms, &result.limbs, F::NEG_ONE);
+
+        // Each term is a sum of at most `2 * n` products of two limbs, or of limbs and constants.
+        let max_term_bits = 2 * LIMB_BITS + log2_ceil(2 * n.max(x.len())) + 1;
+        self.assert_limbs_sum_zero(terms, max_term_bits);
+
+        result
+    }
+
+    /// Adds `coeff * limbs[i]` to `terms[i]`.
+    fn accumulate_limbs(&mut self, terms: &mut [Target], limbs: &[Target], coeff: F) {
+        let one = self.one();
+        for (term, &limb) in terms.iter_mut().zip(limbs) {
+            *term = self.arithmetic(coeff, F::ONE, limb, one, *term);
+        }
+    }
+
+    /// Asserts that `x` is less than the order of `FF`, by showing that `p - 1 - x` has a
+    /// representation with non-negative limbs.
+    fn assert_canonical_nonnative<FF: PrimeField>(&mut self, x: &NonNativeTarget<FF>) {
+        let n = NonNativeTarget::<FF>::num_limbs();
+        let complement = self.add_virtual_nonnative_target_unsafe::<FF>();
+        self.add_simple_generator(NonNativeComplementGenerator::<FF> {
+            x: x.limbs.clone(),
+            complement: complement.limbs.clone(),
+            _phantom: PhantomData,
+        });
+
+        let p_minus_one = biguint_to_limbs(&(FF::order() - BigUint::one()), n);
+        let one = self.one();
+        let terms = (0..n)
+            .map(|i| {
+                let sum = self.add(x.limbs[i], complement.limbs[i]);
+                let c = F::from_canonical_u64(p_minus_one[i]);
+                self.arithmetic(F::ONE, -c, sum, one, one)
+            })
+            .collect();
+        self.assert_limbs_sum_zero(terms, LIMB_BITS + 2);
+    }
+
+    /// Asserts that `sum_i terms[i] 2^(16 i) = 0` over the integers, where each term is a
+    /// possibly negative integer whose absolute value is less than `2^max_term_bits`.
+    fn assert_limbs_sum_zero(&mut self, terms: Vec<Target>, max_term_bits: usize) {
+        // Each carry's absolute value is less than `2^carry_bits`, so that the constraints below
+        // can't wrap around the native field.
+        let carry_bits = max_term_bits + 1 - LIMB_BITS;
+        debug_assert!(max_term_bits + 2 < F::BITS);
+
+        let carries = self.add_virtual_targets(terms.len() - 1);
+        self.add_simple_generator(CarryGenerator {
+            terms: terms.clone(),
+            carries: carries.clone(),
+        });
+
+        let base = F::from_canonical_u64(1 << LIMB_BITS);
+        let offset = F::from_canonical_u64(1 << carry_bits);
+        let one = self.one();
+        let mut carry_in = self.zero();
+        for (i, &term) in terms.iter().enumerate() {
+            let sum = self.add(term, carry_in);
+            if i == terms.len() - 1 {
+                self.assert_zero(sum);
+            } else {
+                // sum = carry * 2^16
+                let carry = carries[i];
+                let diff = self.arithmetic(-base, F::ONE, carry, one, sum);
+                self.assert_zero(diff);
+                let shifted_carry = self.arithmetic(F::ONE, offset, carry, one, one);
+                self.range_check(shifted_carry, carry_bits + 1);
+                carry_in = carry;
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum NonNativeOp {
+    Add,
+    Sub,
+    Mul,
+    Reduce,
+}
+
+#[derive(Debug)]
+struct NonNativeOpGenerator<FF: PrimeField> {
+    op: NonNativeOp,
+    x: Vec<Target>,
+    y: Vec<Target>,
+    quotient: Vec<Target>,
+    result: Vec<Target>,
+    _phantom: PhantomData<FF>,
+}
+
+impl<F: RichField, FF: PrimeField> SimpleGenerator<F> for NonNativeOpGenerator<FF> {
+    fn dependencies(&self) -> Vec<Target> {
+        self.x.iter().chain(&self.y).copied().collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let x = limbs_to_biguint(&witness.get_targets(&self.x));
+        let y = limbs_to_biguint(&witness.get_targets(&self.y));
+        let modulus = FF::order();
+
+        let (quotient, result) = match self.op {
+            NonNativeOp::Add => (&x + &y).div_rem(&modulus),
+            NonNativeOp::Sub => {
+                if x >= y {
+                    (BigUint::zero(), x - y)
+                } else {
+                    (BigUint::one(), x + &modulus - y)
+                }
+            }
+            NonNativeOp::Mul => (&x * &y).div_rem(&modulus),
+            NonNativeOp::Reduce => x.div_rem(&modulus),
+        };
+
+        let quotient_limbs = biguint_to_limbs(&quotient, self.quotient.len());
+        for (&t, limb) in self.quotient.iter().zip(quotient_limbs) {
+            out_buffer.set_target(t, F::from_canonical_u64(limb));
+        }
+        let result_limbs = biguint_to_limbs(&result, self.result.len());
+        for (&t, limb) in self.result.iter().zip(result_limbs) {
+            out_buffer.set_target(t, F::from_canonical_u64(limb));
+        }
+    }
+}
+
+#[derive(Debug)]
+struct NonNativeComplementGenerator<FF: PrimeField> {
+    x: Vec<Target>,
+    complement: Vec<Target>,
+    _phantom: PhantomData<FF>,
+}
+
+impl<F: RichField, FF: PrimeField> SimpleGenerator<F> for NonNativeComplementGenerator<FF> {
+    fn dependencies(&self) -> Vec<Target> {
+        self.x.clone()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let x = limbs_to_biguint(&witness.get_targets(&self.x));
+        let p_minus_one = FF::order() - BigUint::one();
+        assert!(x <= p_minus_one, "Non-canonical foreign field element");
+        let complement_limbs = biguint_to_limbs(&(p_minus_one - x), self.complement.len());
+        for (&t, limb) in self.complement.iter().zip(complement_limbs) {
+            out_buffer.set_target(t, F::from_canonical_u64(limb));
+        }
+    }
+}
+
+/// Computes the carries for `assert_limbs_sum_zero`.
+#[derive(Debug)]
+pub(crate) struct CarryGenerator {
+    terms: Vec<Target>,
+    carries: Vec<Target>,
+}
+
+impl<F: RichField> SimpleGenerator<F> for CarryGenerator {
+    fn dependencies(&self) -> Vec<Target> {
+        self.terms.clone()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        // Interpret field elements above `p / 2` as negative integers.
+        let to_signed = |x: F| {
+            let x = x.to_canonical_u64();
+            if x > F::ORDER / 2 {
+                x as i128 - F::ORDER as i128
+            } else {
+                x as i128
+            }
+        };
+
+        let mut carry = 0i128;
+        for (&term, &carry_target) in self.terms.iter().zip(&self.carries) {
+            // If the sum doesn't divide evenly, the constraints can't be satisfied anyway.
+            carry = (to_signed(witness.get_target(term)) + carry) >> LIMB_BITS;
+            out_buffer.set_target(carry_target, F::from_noncanonical_i64(carry as i64));
+        }
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>) -> IoResult<()> {
+        dst.write_target_vec(&self.terms)?;
+        dst.write_target_vec(&self.carries)
+    }
+
+    fn deserialize(src: &mut Buffer) -> IoResult<Self> {
+        let terms = src.read_target_vec()?;
+        let carries = src.read_target_vec()?;
+        Ok(Self { terms, carries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::secp256k1_base::Secp256K1Base;
+    use crate::field::types::{Field, PrimeField, Sample};
+    use crate::gadgets::nonnative::{biguint_to_limbs, NonNativeTarget};
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type FF = Secp256K1Base;
+
+    fn set_nonnative(pw: &mut PartialWitness<F>, x: &NonNativeTarget<FF>, value: FF) {
+        let limbs = biguint_to_limbs(&value.to_canonical_biguint(), x.limbs.len());
+        for (&t, limb) in x.limbs.iter().zip(limbs) {
+            pw.set_target(t, F::from_canonical_u64(limb));
+        }
+    }
+
+    #[test]
+    fn test_nonnative_arithmetic() -> Result<()> {
+        let config = CircuitConfig::standard_ecc_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = FF::rand();
+        let y = FF::rand();
+        let x_t = builder.constant_nonnative(x);
+        let y_t = builder.add_virtual_nonnative_target();
+        set_nonnative(&mut pw, &y_t, y);
+
+        let sum = builder.add_nonnative(&x_t, &y_t);
+        let expected_sum = builder.constant_nonnative(x + y);
+        builder.connect_nonnative(&sum, &expected_sum);
+
+        let diff = builder.sub_nonnative(&x_t, &y_t);
+        let expected_diff = builder.constant_nonnative(x - y);
+        builder.connect_nonnative(&diff, &expected_diff);
+
+        let product = builder.mul_nonnative(&x_t, &y_t);
+        let expected_product = builder.constant_nonnative(x * y);
+        builder.connect_nonnative(&product, &expected_product);
+
+        // `-1 + -1` wraps around the modulus.
+        let neg_one = builder.constant_nonnative(FF::NEG_ONE);
+        let neg_two = builder.add_nonnative(&neg_one, &neg_one);
+        let expected_neg_two = builder.constant_nonnative(-FF::TWO);
+        builder.connect_nonnative(&neg_two, &expected_neg_two);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_reduce_nonnative() -> Result<()> {
+        let config = CircuitConfig::standard_ecc_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // `x + p` is a non-canonical representation of `x`, which needs an extra limb.
+        let x = FF::rand();
+        let n = NonNativeTarget::<FF>::num_limbs();
+        let limbs = biguint_to_limbs(&(x.to_canonical_biguint() + FF::order()), n + 1);
+        let limbs_t = builder.add_virtual_targets(n + 1);
+        for (&t, &limb) in limbs_t.iter().zip(&limbs) {
+            builder.range_check(t, 16);
+            pw.set_target(t, F::from_canonical_u64(limb));
+        }
+
+        let reduced = builder.reduce_nonnative::<FF>(&limbs_t);
+        let expected = builder.constant_nonnative(x);
+        builder.connect_nonnative(&reduced, &expected);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_non_canonical_nonnative() {
+        let config = CircuitConfig::standard_ecc_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // The order itself fits in the limbs, but isn't canonical.
+        let x_t = builder.add_virtual_nonnative_target::<FF>();
+        let limbs = biguint_to_limbs(&FF::order(), x_t.limbs.len());
+        for (&t, limb) in x_t.limbs.iter().zip(limbs) {
+            pw.set_target(t, F::from_canonical_u64(limb));
+        }
+
+        let data = builder.build::<C>();
+        data.prove(pw).unwrap();
+    }
+}
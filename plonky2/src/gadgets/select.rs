@@ -34,13 +34,43 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let tmp = self.mul_sub(b.target, y, y);
         self.mul_sub(b.target, x, tmp)
     }
+
+    /// Computes `if b { acc + x } else { acc }`, i.e. `acc + b * x`, in a single arithmetic
+    /// operation.
+    ///
+    /// This relies on `b` being boolean, as `BoolTarget`s are by convention; one from
+    /// `add_virtual_bool_target_unsafe` must be checked with `assert_bool` first.
+    pub fn conditional_add(&mut self, acc: Target, b: BoolTarget, x: Target) -> Target {
+        self.mul_add(b.target, x, acc)
+    }
+
+    /// Like `conditional_add`, but for `ExtensionTarget`s.
+    pub fn conditional_add_extension(
+        &mut self,
+        acc: ExtensionTarget<D>,
+        b: BoolTarget,
+        x: ExtensionTarget<D>,
+    ) -> ExtensionTarget<D> {
+        self.scalar_mul_add_extension(b.target, x, acc)
+    }
+
+    /// Selects between two pairs of u32 limbs, i.e., this returns `if b { x } else { y }`
+    /// limb-wise.
+    pub fn select_u32_pair(
+        &mut self,
+        b: BoolTarget,
+        x: (Target, Target),
+        y: (Target, Target),
+    ) -> (Target, Target) {
+        (self.select(b, x.0, y.0), self.select(b, x.1, y.1))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
 
-    use crate::field::types::Sample;
+    use crate::field::types::{Field, Sample};
     use crate::iop::witness::{PartialWitness, WitnessWrite};
     use crate::plonk::circuit_builder::CircuitBuilder;
     use crate::plonk::circuit_data::CircuitConfig;
@@ -77,4 +107,80 @@ mod tests {
 
         verify(proof, &data.verifier_only, &data.common)
     }
+
+    #[test]
+    fn test_conditional_add() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::<F>::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let n = 16;
+        let xs = F::rand_vec(n);
+        let xs_ext = FF::rand_vec(n);
+        let mask = (0..n).map(|i| i % 3 != 1).collect::<Vec<_>>();
+
+        let mut acc = builder.zero();
+        let mut acc_ext = builder.zero_extension();
+        for i in 0..n {
+            let b = builder.add_virtual_bool_target_safe();
+            pw.set_bool_target(b, mask[i]);
+            let x = builder.add_virtual_target();
+            pw.set_target(x, xs[i]);
+            let x_ext = builder.add_virtual_extension_target();
+            pw.set_extension_target(x_ext, xs_ext[i]);
+
+            acc = builder.conditional_add(acc, b, x);
+            acc_ext = builder.conditional_add_extension(acc_ext, b, x_ext);
+        }
+
+        let masked_sum = (0..n).filter(|&i| mask[i]).map(|i| xs[i]).sum::<F>();
+        let masked_sum_ext = (0..n).filter(|&i| mask[i]).map(|i| xs_ext[i]).sum::<FF>();
+        let expected = builder.constant(masked_sum);
+        let expected_ext = builder.constant_extension(masked_sum_ext);
+        builder.connect(acc, expected);
+        builder.connect_extension(acc_ext, expected_ext);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_select_u32_pair() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let pw = PartialWitness::<F>::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = (
+            builder.constant(F::from_canonical_u32(0xdeadbeef)),
+            builder.constant(F::from_canonical_u32(u32::MAX)),
+        );
+        let y = (
+            builder.constant(F::from_canonical_u32(7)),
+            builder.constant(F::from_canonical_u32(0)),
+        );
+        let truet = builder._true();
+        let falset = builder._false();
+
+        let should_be_x = builder.select_u32_pair(truet, x, y);
+        let should_be_y = builder.select_u32_pair(falset, x, y);
+
+        builder.connect(should_be_x.0, x.0);
+        builder.connect(should_be_x.1, x.1);
+        builder.connect(should_be_y.0, y.0);
+        builder.connect(should_be_y.1, y.1);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
 }
@@ -0,0 +1,117 @@
+use crate::field::extension::Extendable;
+use crate::gates::set_membership::SetMembershipGate;
+use crate::hash::hash_types::RichField;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Asserts that `x` is an element of `set`.
+    ///
+    /// Sets of at most `max_quotient_degree_factor` distinct elements are checked by a
+    /// `SetMembershipGate`, which constrains `prod (x - c) = 0` directly and is shared by all the
+    /// values checked against the same set. The degree of that constraint is the size of the set,
+    /// so larger sets fall back to `assert_in_sorted_set`, which looks `x` up in a table instead.
+    pub fn assert_in_set(&mut self, x: Target, set: &[F]) {
+        assert!(!set.is_empty(), "The set must not be empty");
+        let mut set = set.to_vec();
+        set.sort_by_key(|c| c.to_canonical_u64());
+        set.dedup();
+
+        if set.len() == 1 {
+            self.assert_equal_constant(x, set[0]);
+        } else if set.len() > self.config.max_quotient_degree_factor {
+            self.assert_in_sorted_set(x, &set);
+        } else {
+            // The gate's set must contain zero, so shift everything by the smallest element, which
+            // is zero already if the set contains it.
+            let shift = set[0];
+            let shifted_x = if shift.is_zero() {
+                x
+            } else {
+                self.add_const(x, -shift)
+            };
+            let shifted_set = set.iter().map(|&c| c - shift).collect();
+
+            let gate = SetMembershipGate::new_from_config(&self.config, shifted_set);
+            let (row, op) = self.find_slot(gate, &[], &[]);
+            self.connect(
+                shifted_x,
+                Target::wire(row, SetMembershipGate::<F>::wire_ith_element(op)),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::types::{Field, Sample};
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn prove_membership(xs: &[F], set: &[F]) -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        for &x in xs {
+            let xt = builder.add_virtual_target();
+            builder.assert_in_set(xt, set);
+            pw.set_target(xt, x);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_assert_in_set() -> Result<()> {
+        // A set containing zero, one which needs shifting, and one too large for the gate.
+        let opcodes = [0x00, 0x01, 0x02, 0x10, 0x56, 0xf3].map(F::from_canonical_u64);
+        let small = F::rand_vec(5);
+        let large = F::rand_vec(20);
+        for set in [&opcodes[..], &small, &large] {
+            prove_membership(set, set)?;
+        }
+
+        // Duplicates and a single element are handled too.
+        prove_membership(&[F::TWO], &[F::TWO, F::TWO])?;
+        prove_membership(&[F::ONE, F::TWO], &[F::TWO, F::ONE, F::TWO])
+    }
+
+    #[test]
+    fn test_assert_in_set_shares_gates() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let set = [0, 3, 7].map(F::from_canonical_u64);
+        for _ in 0..10 {
+            let x = builder.add_virtual_target();
+            builder.assert_in_set(x, &set);
+        }
+        // One `SetMembershipGate` and nothing else, as the set contains zero.
+        assert_eq!(builder.num_gates(), 1);
+    }
+
+    #[test]
+    fn test_assert_in_set_non_member() {
+        // The witness can be generated, but the proof doesn't verify.
+        let set = F::rand_vec(5);
+        assert!(prove_membership(&[F::rand()], &set).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_assert_in_large_set_non_member() {
+        let set = F::rand_vec(20);
+        prove_membership(&[F::rand()], &set).unwrap();
+    }
+}
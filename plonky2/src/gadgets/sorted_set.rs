@@ -0,0 +1,174 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::field::extension::Extendable;
+use crate::field::types::Field;
+use crate::hash::hash_types::RichField;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator};
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::util::log2_strict;
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Asserts that `x` is an element of `sorted`, which must be sorted in increasing order of
+    /// canonical value. The index of `x` is witnessed and `sorted[index] == x` is checked with
+    /// `random_access` over a table of constants, so the cost grows linearly with the size of the
+    /// set but the constraint degree does not.
+    pub fn assert_in_sorted_set(&mut self, x: Target, sorted: &[F]) {
+        assert!(!sorted.is_empty(), "The set must not be empty");
+        debug_assert!(
+            sorted
+                .windows(2)
+                .all(|w| w[0].to_canonical_u64() <= w[1].to_canonical_u64()),
+            "The set must be sorted"
+        );
+
+        // Pad the table to a power of two by repeating the last element.
+        let mut table = sorted.to_vec();
+        table.resize(sorted.len().next_power_of_two(), sorted[sorted.len() - 1]);
+        let bits = log2_strict(table.len());
+
+        let index = self.add_virtual_target();
+        self.add_simple_generator(SortedSetIndexGenerator {
+            x,
+            sorted: table.clone(),
+            index,
+        });
+        let index_bits = self.split_le(index, bits);
+
+        let table = table.into_iter().map(|c| self.constant(c)).collect();
+        let claimed = self.random_access_by_bits(&index_bits, table);
+        self.connect(claimed, x);
+    }
+
+    /// Like `random_access`, but with the index given in little-endian bits, and with no limit on
+    /// the size of `v` other than it being a power of two. A single `RandomAccessGate` can only
+    /// hold as many items as fit in a row's routed wires, so larger vectors are accessed in two
+    /// steps: within each chunk using the low bits of the index, then among the chunks' results
+    /// using the high bits.
+    fn random_access_by_bits(&mut self, index_bits: &[BoolTarget], v: Vec<Target>) -> Target {
+        debug_assert_eq!(v.len(), 1 << index_bits.len());
+        let max_bits = self.max_random_access_bits();
+        if index_bits.len() <= max_bits {
+            let index = self.le_sum(index_bits.iter());
+            return self.random_access(index, v);
+        }
+
+        let (low_bits, high_bits) = index_bits.split_at(max_bits);
+        let low_index = self.le_sum(low_bits.iter());
+        let chunk_results = v
+            .chunks(1 << max_bits)
+            .map(|chunk| self.random_access(low_index, chunk.to_vec()))
+            .collect();
+        self.random_access_by_bits(high_bits, chunk_results)
+    }
+
+    /// The largest `bits` such that a `RandomAccessGate` over `2^bits` items fits in one row.
+    fn max_random_access_bits(&self) -> usize {
+        let fits = |bits: usize| {
+            let vec_size = 1 << bits;
+            2 + vec_size <= self.config.num_routed_wires
+                && 2 + vec_size + bits <= self.config.num_wires
+        };
+        (1..).take_while(|&bits| fits(bits)).last().unwrap_or(0)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SortedSetIndexGenerator<F: Field> {
+    x: Target,
+    sorted: Vec<F>,
+    index: Target,
+}
+
+impl<F: RichField> SimpleGenerator<F> for SortedSetIndexGenerator<F> {
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.x]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let x = witness.get_target(self.x).to_canonical_u64();
+        // If `x` is not in the set, any index will do, since the circuit is unsatisfiable anyway.
+        let index = self
+            .sorted
+            .binary_search_by_key(&x, |c| c.to_canonical_u64())
+            .unwrap_or(0);
+        out_buffer.set_target(self.index, F::from_canonical_usize(index));
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>) -> IoResult<()> {
+        dst.write_target(self.x)?;
+        dst.write_u32(self.sorted.len() as u32)?;
+        dst.write_field_vec(&self.sorted)?;
+        dst.write_target(self.index)
+    }
+
+    fn deserialize(src: &mut Buffer) -> IoResult<Self> {
+        let x = src.read_target()?;
+        let len = src.read_u32()? as usize;
+        let sorted = src.read_field_vec(len)?;
+        let index = src.read_target()?;
+        Ok(Self { x, sorted, index })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use anyhow::Result;
+
+    use crate::field::types::{PrimeField64, Sample};
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn prove_membership(x: F, set: &[F]) -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let xt = builder.add_virtual_target();
+        builder.assert_in_sorted_set(xt, set);
+        pw.set_target(xt, x);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    fn sorted_set(n: usize) -> Vec<F> {
+        let mut set = F::rand_vec(n);
+        set.sort_by_key(|x| x.to_canonical_u64());
+        set
+    }
+
+    #[test]
+    fn test_assert_in_sorted_set() -> Result<()> {
+        // A set that fits in a single `RandomAccessGate`, and one which doesn't.
+        for n in [5, 100] {
+            let set = sorted_set(n);
+            for i in [0, n / 2, n - 1] {
+                prove_membership(set[i], &set)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_assert_in_sorted_set_non_member() {
+        let set = sorted_set(5);
+        let x = F::rand();
+        assert!(!set.contains(&x));
+        prove_membership(x, &set).unwrap();
+    }
+}
@@ -13,6 +13,7 @@ use crate::iop::target::{BoolTarget, Target};
 use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::util::log_floor;
+use crate::util::serialization::{Buffer, IoResult, Read, Write};
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// Split the given element into a list of targets, where each one represents a
@@ -77,10 +78,17 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
         Target::wire(row, BaseSumGate::<2>::WIRE_SUM)
     }
+
+    /// Takes an iterator of bits `(b_i)` of length `n` and returns `sum b_i * 2^(n-1-i)`, i.e.,
+    /// the number with big-endian bit representation given by `bits`.
+    pub fn be_sum(&mut self, bits: impl Iterator<Item = impl Borrow<BoolTarget>>) -> Target {
+        let bits = bits.map(|b| *b.borrow()).collect_vec();
+        self.le_sum(bits.iter().rev())
+    }
 }
 
 #[derive(Debug)]
-struct BaseSumGenerator<const B: usize> {
+pub(crate) struct BaseSumGenerator<const B: usize> {
     row: usize,
     limbs: Vec<BoolTarget>,
 }
@@ -102,6 +110,21 @@ impl<F: Field, const B: usize> SimpleGenerator<F> for BaseSumGenerator<B> {
 
         out_buffer.set_target(Target::wire(self.row, BaseSumGate::<B>::WIRE_SUM), sum);
     }
+
+    fn serialize(&self, dst: &mut Vec<u8>) -> IoResult<()> {
+        dst.write_u32(self.row as u32)?;
+        dst.write_target_vec(&self.limbs.iter().map(|b| b.target).collect::<Vec<_>>())
+    }
+
+    fn deserialize(src: &mut Buffer) -> IoResult<Self> {
+        let row = src.read_u32()? as usize;
+        let limbs = src
+            .read_target_vec()?
+            .into_iter()
+            .map(BoolTarget::new_unsafe)
+            .collect();
+        Ok(Self { row, limbs })
+    }
 }
 
 #[cfg(test)]
@@ -111,7 +134,7 @@ mod tests {
     use rand::Rng;
 
     use super::*;
-    use crate::iop::witness::PartialWitness;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
     use crate::plonk::circuit_data::CircuitConfig;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
     use crate::plonk::verifier::verify;
@@ -178,4 +201,38 @@ mod tests {
 
         verify(proof, &data.verifier_only, &data.common)
     }
+
+    #[test]
+    fn test_big_endian() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let n = OsRng.gen_range(0..(1u64 << 40));
+        let x = builder.add_virtual_target();
+        pw.set_target(x, F::from_canonical_u64(n));
+
+        let be_bits = builder.split_be(x, 40);
+        let le_bits = builder.split_le(x, 40);
+        for (be, le) in be_bits.iter().zip(le_bits.iter().rev()) {
+            builder.connect(be.target, le.target);
+        }
+        let y = builder.be_sum(be_bits.iter());
+        builder.connect(x, y);
+
+        // 0b1101 read most significant bit first.
+        let zero = builder._false();
+        let one = builder._true();
+        let thirteen = builder.be_sum([one, one, zero, one].iter());
+        let expected = builder.constant(F::from_canonical_u64(13));
+        builder.connect(thirteen, expected);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
 }
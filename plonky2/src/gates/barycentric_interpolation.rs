@@ -0,0 +1,698 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::marker::PhantomData;
+use core::ops::Range;
+
+use crate::field::extension::algebra::ExtensionAlgebra;
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::interpolation::barycentric_weights;
+use crate::field::types::Field;
+use crate::gates::coset_interpolation::{
+    partial_interpolate, partial_interpolate_ext_algebra, partial_interpolate_ext_algebra_target,
+};
+use crate::gates::gate::Gate;
+use crate::gates::interpolation::InterpolationGate;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGenerator};
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase};
+
+/// An instantiation of `InterpolationGate` based on the second ("true") form of the Barycentric
+/// formula. Like `CosetInterpolationGate`, it interpolates over the subgroup H after unshifting
+/// the evaluation point, and so hardcodes the domain and weights.
+///
+/// Let x[] be the points of H, v[] the values, w[] the Barycentric weights and z the (unshifted)
+/// evaluation point. Since the vanishing polynomial of H is X^n - 1,
+///
+/// P(z) = (z^n - 1) * sum_i w[i] * v[i] / (z - x[i]).
+///
+/// The points are split into groups of `degree - 1` consecutive points, and for each group a
+/// non-routed wire holds the inverse of `prod (z - x[i])` over the group, which clears all the
+/// denominators in that group's part of the sum with a single degree `degree` constraint. Further
+/// non-routed wires hold the sum and a chain of repeated squarings of z, up to z^n.
+///
+/// With a degree of 2 this needs about half the intermediate wires and constraints of
+/// `CosetInterpolationGate`, which needs two intermediates per point. The price is that the
+/// evaluation point must not lie in the coset, as the witness generator would then have to invert
+/// zero. This is fine for random evaluation points in an extension field.
+#[derive(Clone, Debug)]
+pub struct BarycentricInterpolationGate<F: RichField + Extendable<D>, const D: usize> {
+    pub subgroup_bits: usize,
+    pub degree: usize,
+    /// The number of points in each group, except possibly the last one.
+    pub group_size: usize,
+    /// The number of squarings in each step of the chain computing z^n, except possibly the last.
+    pub power_step_bits: usize,
+    pub barycentric_weights: Vec<F>,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> BarycentricInterpolationGate<F, D> {
+    pub fn new(subgroup_bits: usize) -> Self {
+        Self::with_max_degree(subgroup_bits, 1 << subgroup_bits)
+    }
+
+    pub(crate) fn with_max_degree(subgroup_bits: usize, max_degree: usize) -> Self {
+        assert!(max_degree > 1, "need at least quadratic constraints");
+
+        let n_points: usize = 1 << subgroup_bits;
+
+        // Each group of up to `max_degree - 1` points costs one inverse.
+        let num_groups = n_points.div_ceil(max_degree - 1);
+
+        // Spread the points evenly over the groups, which minimizes the degree without requiring
+        // any more groups.
+        let group_size = n_points.div_ceil(num_groups);
+        let degree = group_size + 1;
+
+        // Square as many times per step as the degree allows.
+        let power_step_bits = degree.ilog2() as usize;
+
+        let barycentric_weights = barycentric_weights(
+            &F::two_adic_subgroup(subgroup_bits)
+                .into_iter()
+                .map(|x| (x, F::ZERO))
+                .collect::<Vec<_>>(),
+        );
+
+        Self {
+            subgroup_bits,
+            degree,
+            group_size,
+            power_step_bits,
+            barycentric_weights,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn num_groups(&self) -> usize {
+        self.num_points().div_ceil(self.group_size)
+    }
+
+    /// The indices of the points in the `i`th group.
+    fn group(&self, i: usize) -> Range<usize> {
+        debug_assert!(i < self.num_groups());
+        let start = i * self.group_size;
+        start..(start + self.group_size).min(self.num_points())
+    }
+
+    fn num_power_steps(&self) -> usize {
+        self.subgroup_bits.div_ceil(self.power_step_bits)
+    }
+
+    /// The number of squarings in the `i`th step of the chain.
+    fn power_step_bits(&self, i: usize) -> usize {
+        debug_assert!(i < self.num_power_steps());
+        (self.subgroup_bits - i * self.power_step_bits).min(self.power_step_bits)
+    }
+
+    /// Wire indices of the shifted point to evaluate the interpolant at.
+    fn wires_shifted_evaluation_point(&self) -> Range<usize> {
+        let start = self.start_intermediates();
+        start..start + D
+    }
+
+    /// Wire indices of the inverse of the product of `z - x[j]` over the `i`th group.
+    fn wires_group_inverse(&self, i: usize) -> Range<usize> {
+        debug_assert!(i < self.num_groups());
+        let start = self.start_intermediates() + D * (1 + i);
+        start..start + D
+    }
+
+    /// Wire indices of the shifted evaluation point raised to the power of `2^b` after the `i`th
+    /// step of the chain, where `b` is the number of squarings so far.
+    fn wires_power(&self, i: usize) -> Range<usize> {
+        debug_assert!(i < self.num_power_steps());
+        let start = self.start_intermediates() + D * (1 + self.num_groups() + i);
+        start..start + D
+    }
+
+    /// Wire indices of `sum_i w[i] * v[i] / (z - x[i])`.
+    fn wires_weighted_sum(&self) -> Range<usize> {
+        let start =
+            self.start_intermediates() + D * (1 + self.num_groups() + self.num_power_steps());
+        start..start + D
+    }
+
+    /// End of wire indices, exclusive.
+    fn end(&self) -> usize {
+        self.wires_weighted_sum().end
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> InterpolationGate<F, D>
+    for BarycentricInterpolationGate<F, D>
+{
+    fn num_points(&self) -> usize {
+        1 << self.subgroup_bits
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D>
+    for BarycentricInterpolationGate<F, D>
+{
+    fn id(&self) -> String {
+        format!("{self:?}<D={D}>")
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+
+        let shift = vars.local_wires[self.wire_shift()];
+        let evaluation_point = vars.get_local_ext_algebra(self.wires_evaluation_point());
+        let shifted_evaluation_point =
+            vars.get_local_ext_algebra(self.wires_shifted_evaluation_point());
+        constraints.extend(
+            (evaluation_point - shifted_evaluation_point.scalar_mul(shift)).to_basefield_array(),
+        );
+
+        let domain = F::two_adic_subgroup(self.subgroup_bits);
+        let values = (0..self.num_points())
+            .map(|i| vars.get_local_ext_algebra(self.wires_value(i)))
+            .collect::<Vec<_>>();
+        let weights = &self.barycentric_weights;
+
+        let mut computed_sum = ExtensionAlgebra::ZERO;
+        for i in 0..self.num_groups() {
+            let group = self.group(i);
+            let (numerator, denominator) = partial_interpolate_ext_algebra(
+                &domain[group.clone()],
+                &values[group.clone()],
+                &weights[group],
+                shifted_evaluation_point,
+                ExtensionAlgebra::ZERO,
+                ExtensionAlgebra::one(),
+            );
+            let inverse = vars.get_local_ext_algebra(self.wires_group_inverse(i));
+            constraints
+                .extend((inverse * denominator - ExtensionAlgebra::one()).to_basefield_array());
+            computed_sum += inverse * numerator;
+        }
+
+        let mut power = shifted_evaluation_point;
+        for i in 0..self.num_power_steps() {
+            let mut computed_power = power;
+            for _ in 0..self.power_step_bits(i) {
+                computed_power = computed_power * computed_power;
+            }
+            power = vars.get_local_ext_algebra(self.wires_power(i));
+            constraints.extend((power - computed_power).to_basefield_array());
+        }
+
+        let sum = vars.get_local_ext_algebra(self.wires_weighted_sum());
+        constraints.extend((sum - computed_sum).to_basefield_array());
+
+        let evaluation_value = vars.get_local_ext_algebra(self.wires_evaluation_value());
+        constraints.extend(
+            (evaluation_value - (power - ExtensionAlgebra::one()) * sum).to_basefield_array(),
+        );
+
+        constraints
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        vars: EvaluationVarsBase<F>,
+        mut yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        let shift = vars.local_wires[self.wire_shift()];
+        let evaluation_point = vars.get_local_ext(self.wires_evaluation_point());
+        let shifted_evaluation_point = vars.get_local_ext(self.wires_shifted_evaluation_point());
+        yield_constr.many(
+            (evaluation_point - shifted_evaluation_point.scalar_mul(shift)).to_basefield_array(),
+        );
+
+        let domain = F::two_adic_subgroup(self.subgroup_bits);
+        let values = (0..self.num_points())
+            .map(|i| vars.get_local_ext(self.wires_value(i)))
+            .collect::<Vec<_>>();
+        let weights = &self.barycentric_weights;
+
+        let mut computed_sum = F::Extension::ZERO;
+        for i in 0..self.num_groups() {
+            let group = self.group(i);
+            let (numerator, denominator) = partial_interpolate(
+                &domain[group.clone()],
+                &values[group.clone()],
+                &weights[group],
+                shifted_evaluation_point,
+                F::Extension::ZERO,
+                F::Extension::ONE,
+            );
+            let inverse = vars.get_local_ext(self.wires_group_inverse(i));
+            yield_constr.many((inverse * denominator - F::Extension::ONE).to_basefield_array());
+            computed_sum += inverse * numerator;
+        }
+
+        let mut power = shifted_evaluation_point;
+        for i in 0..self.num_power_steps() {
+            let computed_power = power.exp_power_of_2(self.power_step_bits(i));
+            power = vars.get_local_ext(self.wires_power(i));
+            yield_constr.many((power - computed_power).to_basefield_array());
+        }
+
+        let sum = vars.get_local_ext(self.wires_weighted_sum());
+        yield_constr.many((sum - computed_sum).to_basefield_array());
+
+        let evaluation_value = vars.get_local_ext(self.wires_evaluation_value());
+        yield_constr
+            .many((evaluation_value - (power - F::Extension::ONE) * sum).to_basefield_array());
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+
+        let shift = vars.local_wires[self.wire_shift()];
+        let evaluation_point = vars.get_local_ext_algebra(self.wires_evaluation_point());
+        let shifted_evaluation_point =
+            vars.get_local_ext_algebra(self.wires_shifted_evaluation_point());
+
+        let neg_one = builder.neg_one();
+        let neg_shift = builder.scalar_mul_ext(neg_one, shift);
+        constraints.extend(
+            builder
+                .scalar_mul_add_ext_algebra(neg_shift, shifted_evaluation_point, evaluation_point)
+                .to_ext_target_array(),
+        );
+
+        let domain = F::two_adic_subgroup(self.subgroup_bits);
+        let values = (0..self.num_points())
+            .map(|i| vars.get_local_ext_algebra(self.wires_value(i)))
+            .collect::<Vec<_>>();
+        let weights = &self.barycentric_weights;
+
+        let zero = builder.zero_ext_algebra();
+        let one = builder.constant_ext_algebra(F::Extension::ONE.into());
+        let mut computed_sum = zero;
+        for i in 0..self.num_groups() {
+            let group = self.group(i);
+            let (numerator, denominator) = partial_interpolate_ext_algebra_target(
+                builder,
+                &domain[group.clone()],
+                &values[group.clone()],
+                &weights[group],
+                shifted_evaluation_point,
+                zero,
+                one,
+            );
+            let inverse = vars.get_local_ext_algebra(self.wires_group_inverse(i));
+            let product = builder.mul_ext_algebra(inverse, denominator);
+            constraints.extend(builder.sub_ext_algebra(product, one).to_ext_target_array());
+            computed_sum = builder.mul_add_ext_algebra(inverse, numerator, computed_sum);
+        }
+
+        let mut power = shifted_evaluation_point;
+        for i in 0..self.num_power_steps() {
+            let mut computed_power = power;
+            for _ in 0..self.power_step_bits(i) {
+                computed_power = builder.mul_ext_algebra(computed_power, computed_power);
+            }
+            power = vars.get_local_ext_algebra(self.wires_power(i));
+            constraints.extend(
+                builder
+                    .sub_ext_algebra(power, computed_power)
+                    .to_ext_target_array(),
+            );
+        }
+
+        let sum = vars.get_local_ext_algebra(self.wires_weighted_sum());
+        constraints.extend(
+            builder
+                .sub_ext_algebra(sum, computed_sum)
+                .to_ext_target_array(),
+        );
+
+        let evaluation_value = vars.get_local_ext_algebra(self.wires_evaluation_value());
+        let vanishing = builder.sub_ext_algebra(power, one);
+        let computed_eval = builder.mul_ext_algebra(vanishing, sum);
+        constraints.extend(
+            builder
+                .sub_ext_algebra(evaluation_value, computed_eval)
+                .to_ext_target_array(),
+        );
+
+        constraints
+    }
+
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<Box<dyn WitnessGenerator<F>>> {
+        let gen = BarycentricInterpolationGenerator::<F, D>::new(row, self.clone());
+        vec![Box::new(gen.adapter())]
+    }
+
+    fn num_wires(&self) -> usize {
+        self.end()
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        self.degree
+    }
+
+    fn num_constraints(&self) -> usize {
+        // D constraints each for the shifted evaluation point, the weighted sum and the evaluation
+        // value, plus D for each group inverse and each step of the power chain.
+        D * (3 + self.num_groups() + self.num_power_steps())
+    }
+}
+
+#[derive(Debug)]
+struct BarycentricInterpolationGenerator<F: RichField + Extendable<D>, const D: usize> {
+    row: usize,
+    gate: BarycentricInterpolationGate<F, D>,
+    interpolation_domain: Vec<F>,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> BarycentricInterpolationGenerator<F, D> {
+    fn new(row: usize, gate: BarycentricInterpolationGate<F, D>) -> Self {
+        let interpolation_domain = F::two_adic_subgroup(gate.subgroup_bits);
+        BarycentricInterpolationGenerator {
+            row,
+            gate,
+            interpolation_domain,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F>
+    for BarycentricInterpolationGenerator<F, D>
+{
+    fn dependencies(&self) -> Vec<Target> {
+        let local_target = |column| {
+            Target::Wire(Wire {
+                row: self.row,
+                column,
+            })
+        };
+
+        let local_targets = |columns: Range<usize>| columns.map(local_target);
+
+        let num_points = self.gate.num_points();
+        let mut deps = Vec::with_capacity(1 + D + num_points * D);
+
+        deps.push(local_target(self.gate.wire_shift()));
+        deps.extend(local_targets(self.gate.wires_evaluation_point()));
+        for i in 0..num_points {
+            deps.extend(local_targets(self.gate.wires_value(i)));
+        }
+        deps
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let local_wire = |column| Wire {
+            row: self.row,
+            column,
+        };
+
+        let get_local_wire = |column| witness.get_wire(local_wire(column));
+
+        let get_local_ext = |wire_range: Range<usize>| {
+            debug_assert_eq!(wire_range.len(), D);
+            let values = wire_range.map(get_local_wire).collect::<Vec<_>>();
+            let arr = values.try_into().unwrap();
+            F::Extension::from_basefield_array(arr)
+        };
+
+        let evaluation_point = get_local_ext(self.gate.wires_evaluation_point());
+        let shift = get_local_wire(self.gate.wire_shift());
+        let shifted_evaluation_point = evaluation_point.scalar_mul(shift.inverse());
+
+        out_buffer.set_ext_wires(
+            self.gate.wires_shifted_evaluation_point().map(local_wire),
+            shifted_evaluation_point,
+        );
+
+        let domain = &self.interpolation_domain;
+        let values = (0..self.gate.num_points())
+            .map(|i| get_local_ext(self.gate.wires_value(i)))
+            .collect::<Vec<_>>();
+        let weights = &self.gate.barycentric_weights;
+
+        let mut sum = F::Extension::ZERO;
+        for i in 0..self.gate.num_groups() {
+            let group = self.gate.group(i);
+            let (numerator, denominator) = partial_interpolate(
+                &domain[group.clone()],
+                &values[group.clone()],
+                &weights[group],
+                shifted_evaluation_point,
+                F::Extension::ZERO,
+                F::Extension::ONE,
+            );
+            let inverse = denominator
+                .try_inverse()
+                .expect("Evaluation point lies in the interpolation coset");
+            out_buffer.set_ext_wires(self.gate.wires_group_inverse(i).map(local_wire), inverse);
+            sum += inverse * numerator;
+        }
+
+        let mut power = shifted_evaluation_point;
+        for i in 0..self.gate.num_power_steps() {
+            power = power.exp_power_of_2(self.gate.power_step_bits(i));
+            out_buffer.set_ext_wires(self.gate.wires_power(i).map(local_wire), power);
+        }
+
+        out_buffer.set_ext_wires(self.gate.wires_weighted_sum().map(local_wire), sum);
+
+        let evaluation_value_wires = self.gate.wires_evaluation_value().map(local_wire);
+        out_buffer.set_ext_wires(evaluation_value_wires, (power - F::Extension::ONE) * sum);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::iter::repeat_with;
+
+    use anyhow::Result;
+    use plonky2_field::polynomial::PolynomialValues;
+
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::Sample;
+    use crate::gates::coset_interpolation::{interpolate_over_base_domain, CosetInterpolationGate};
+    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::hash::hash_types::HashOut;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn test_degree_and_wires_minimized() {
+        type G = BarycentricInterpolationGate<GoldilocksField, 2>;
+
+        let gate = G::with_max_degree(3, 2);
+        assert_eq!(gate.degree(), 2);
+        assert_eq!(gate.num_groups(), 8);
+        assert_eq!(gate.num_power_steps(), 3);
+
+        let gate = G::with_max_degree(3, 3);
+        assert_eq!(gate.degree(), 3);
+        assert_eq!(gate.num_groups(), 4);
+        assert_eq!(gate.num_power_steps(), 3);
+
+        // Three groups, the last one with only two points.
+        let gate = G::with_max_degree(3, 4);
+        assert_eq!(gate.degree(), 4);
+        assert_eq!(gate.num_groups(), 3);
+        assert_eq!(gate.group(2), 6..8);
+        assert_eq!(gate.num_power_steps(), 2);
+        assert_eq!(gate.power_step_bits(1), 1);
+
+        let gate = G::with_max_degree(3, 5);
+        assert_eq!(gate.degree(), 5);
+        assert_eq!(gate.num_groups(), 2);
+        assert_eq!(gate.num_power_steps(), 2);
+
+        let gate = G::with_max_degree(3, 8);
+        assert_eq!(gate.degree(), 5);
+        assert_eq!(gate.num_groups(), 2);
+
+        let gate = G::with_max_degree(3, 9);
+        assert_eq!(gate.degree(), 9);
+        assert_eq!(gate.num_groups(), 1);
+        assert_eq!(gate.num_power_steps(), 1);
+
+        let gate = G::with_max_degree(4, 8);
+        assert_eq!(gate.degree(), 7);
+        assert_eq!(gate.num_groups(), 3);
+        assert_eq!(gate.group(2), 12..16);
+        assert_eq!(gate.num_power_steps(), 2);
+        assert_eq!(gate.power_step_bits(0), 2);
+        assert_eq!(gate.power_step_bits(1), 2);
+    }
+
+    #[test]
+    fn wire_indices() {
+        let gate = BarycentricInterpolationGate::<GoldilocksField, 4>::with_max_degree(2, 3);
+
+        // The exact indices aren't really important, but we want to make sure we don't have any
+        // overlaps or gaps.
+        assert_eq!(gate.wire_shift(), 0);
+        assert_eq!(gate.wires_value(0), 1..5);
+        assert_eq!(gate.wires_value(3), 13..17);
+        assert_eq!(gate.wires_evaluation_point(), 17..21);
+        assert_eq!(gate.wires_evaluation_value(), 21..25);
+        assert_eq!(gate.wires_shifted_evaluation_point(), 25..29);
+        assert_eq!(gate.wires_group_inverse(0), 29..33);
+        assert_eq!(gate.wires_group_inverse(1), 33..37);
+        assert_eq!(gate.wires_power(0), 37..41);
+        assert_eq!(gate.wires_power(1), 41..45);
+        assert_eq!(gate.wires_weighted_sum(), 45..49);
+        assert_eq!(gate.num_wires(), 49);
+    }
+
+    #[test]
+    fn low_degree() {
+        test_low_degree::<GoldilocksField, _, 4>(BarycentricInterpolationGate::new(2));
+        test_low_degree::<GoldilocksField, _, 4>(BarycentricInterpolationGate::with_max_degree(
+            3, 2,
+        ));
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        for degree in 2..=5 {
+            test_eval_fns::<F, C, _, D>(BarycentricInterpolationGate::with_max_degree(3, degree))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_gate_constraint() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+
+        let gate = BarycentricInterpolationGate::<F, D>::with_max_degree(3, 3);
+
+        /// Returns the local wires for the gate above for given values, shift and eval point.
+        fn get_wires(shift: F, values: PolynomialValues<FF>, eval_point: FF) -> Vec<FF> {
+            let domain = F::two_adic_subgroup(3);
+            let shifted_eval_point =
+                <FF as FieldExtension<2>>::scalar_mul(&eval_point, shift.inverse());
+            let weights =
+                barycentric_weights(&domain.iter().map(|&x| (x, F::ZERO)).collect::<Vec<_>>());
+            let eval = interpolate_over_base_domain::<_, D>(
+                &domain,
+                &values.values,
+                &weights,
+                shifted_eval_point,
+            );
+
+            let mut v = vec![shift];
+            for val in values.values.iter() {
+                v.extend(val.0);
+            }
+            v.extend(eval_point.0);
+            v.extend(eval.0);
+            v.extend(shifted_eval_point.0);
+            // Four groups of two points each.
+            let mut sum = FF::ZERO;
+            for i in 0..4 {
+                let x0 = <FF as FieldExtension<D>>::from_basefield(domain[2 * i]);
+                let x1 = <FF as FieldExtension<D>>::from_basefield(domain[2 * i + 1]);
+                let inverse = ((shifted_eval_point - x0) * (shifted_eval_point - x1)).inverse();
+                v.extend(inverse.0);
+                for (j, x) in [(2 * i, x0), (2 * i + 1, x1)] {
+                    let weighted_value =
+                        <FF as FieldExtension<D>>::scalar_mul(&values.values[j], weights[j]);
+                    sum += weighted_value / (shifted_eval_point - x);
+                }
+            }
+            // One squaring per step.
+            for i in 1..=3 {
+                v.extend(shifted_eval_point.exp_power_of_2(i).0);
+            }
+            v.extend(sum.0);
+            v.iter().map(|&x| x.into()).collect()
+        }
+
+        let shift = F::rand();
+        let values = PolynomialValues::new(repeat_with(FF::rand).take(8).collect());
+        let eval_point = FF::rand();
+        let wires = get_wires(shift, values, eval_point);
+        assert_eq!(wires.len(), gate.num_wires());
+        let vars = EvaluationVars {
+            local_constants: &[],
+            local_wires: &wires,
+            public_inputs_hash: &HashOut::rand(),
+        };
+
+        assert!(
+            gate.eval_unfiltered(vars).iter().all(|x| x.is_zero()),
+            "Gate constraints are not satisfied."
+        );
+    }
+
+    /// Returns the largest number of points, as a power of two, which each gate can interpolate
+    /// within the standard recursion config at a given degree bound.
+    fn max_subgroup_bits(max_degree: usize) -> (usize, usize) {
+        const D: usize = 2;
+        type F = GoldilocksField;
+        let config = CircuitConfig::standard_recursion_config();
+        let fits = |num_wires: usize, num_routed_wires: usize| {
+            num_wires <= config.num_wires && num_routed_wires <= config.num_routed_wires
+        };
+
+        let max_bits = |fits_with_bits: &dyn Fn(usize) -> bool| {
+            (1..)
+                .take_while(|&bits| fits_with_bits(bits))
+                .last()
+                .unwrap()
+        };
+        let coset_bits = max_bits(&|bits| {
+            let gate = CosetInterpolationGate::<F, D>::with_max_degree(bits, max_degree);
+            fits(gate.num_wires(), gate.num_routed_wires())
+        });
+        let barycentric_bits = max_bits(&|bits| {
+            let gate = BarycentricInterpolationGate::<F, D>::with_max_degree(bits, max_degree);
+            fits(gate.num_wires(), gate.num_routed_wires())
+        });
+        (coset_bits, barycentric_bits)
+    }
+
+    #[test]
+    fn test_max_points_at_standard_config() {
+        // Both gates are eventually limited by the routed wires, which hold two extension elements
+        // per point, so neither can go beyond 32 points.
+        for max_degree in 2..=8 {
+            let (coset_bits, barycentric_bits) = max_subgroup_bits(max_degree);
+            assert!(barycentric_bits >= coset_bits);
+            assert!(barycentric_bits <= 5);
+        }
+
+        // At a degree bound of 2, 16 points take fewer wires and constraints.
+        let coset = CosetInterpolationGate::<GoldilocksField, 2>::with_max_degree(4, 2);
+        let barycentric = BarycentricInterpolationGate::<GoldilocksField, 2>::with_max_degree(4, 2);
+        assert_eq!(coset.degree(), 2);
+        assert_eq!(barycentric.degree(), 2);
+        assert_eq!(coset.num_wires(), 95);
+        assert_eq!(barycentric.num_wires(), 81);
+        assert_eq!(coset.num_constraints(), 60);
+        assert_eq!(barycentric.num_constraints(), 46);
+
+        // With 8 points and a degree bound of 8, the degree drops from 8 to 5.
+        let coset = CosetInterpolationGate::<GoldilocksField, 2>::with_max_degree(3, 8);
+        let barycentric = BarycentricInterpolationGate::<GoldilocksField, 2>::with_max_degree(3, 8);
+        assert_eq!(coset.degree(), 8);
+        assert_eq!(barycentric.degree(), 5);
+    }
+}
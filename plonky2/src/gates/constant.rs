@@ -17,13 +17,21 @@ use crate::plonk::vars::{
     EvaluationVarsBasePacked,
 };
 
-/// A gate which takes a single constant parameter and outputs that value.
+/// A gate which takes `num_consts` constant parameters and outputs their values on routed wires.
+/// Each constant needs its own constant polynomial, so a row holds at most
+/// `CircuitConfig::num_constants` of them; `CircuitBuilder` fills every slot of a row before adding
+/// another.
 #[derive(Copy, Clone, Debug)]
 pub struct ConstantGate {
     pub(crate) num_consts: usize,
 }
 
 impl ConstantGate {
+    pub fn new(num_consts: usize) -> Self {
+        assert!(num_consts > 0, "A ConstantGate needs at least one constant");
+        Self { num_consts }
+    }
+
     pub fn const_input(&self, i: usize) -> usize {
         debug_assert!(i < self.num_consts);
         i
@@ -127,17 +135,30 @@ mod tests {
     #[test]
     fn low_degree() {
         let num_consts = CircuitConfig::standard_recursion_config().num_constants;
-        let gate = ConstantGate { num_consts };
+        let gate = ConstantGate::new(num_consts);
         test_low_degree::<GoldilocksField, _, 2>(gate)
     }
 
+    #[test]
+    fn low_degree_many_consts() {
+        test_low_degree::<GoldilocksField, _, 2>(ConstantGate::new(12))
+    }
+
     #[test]
     fn eval_fns() -> Result<()> {
         const D: usize = 2;
         type C = PoseidonGoldilocksConfig;
         type F = <C as GenericConfig<D>>::F;
         let num_consts = CircuitConfig::standard_recursion_config().num_constants;
-        let gate = ConstantGate { num_consts };
+        let gate = ConstantGate::new(num_consts);
         test_eval_fns::<F, C, _, D>(gate)
     }
+
+    #[test]
+    fn eval_fns_many_consts() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        test_eval_fns::<F, C, _, D>(ConstantGate::new(12))
+    }
 }
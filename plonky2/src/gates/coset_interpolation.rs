@@ -10,6 +10,7 @@ use crate::field::extension::{Extendable, FieldExtension, OEF};
 use crate::field::interpolation::barycentric_weights;
 use crate::field::types::Field;
 use crate::gates::gate::Gate;
+use crate::gates::interpolation::InterpolationGate;
 use crate::gates::util::StridedConstraintConsumer;
 use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::{ExtensionAlgebraTarget, ExtensionTarget};
@@ -23,11 +24,6 @@ use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase};
 /// One of the instantiations of `InterpolationGate`: allows constraints of variable
 /// degree, up to `1<<subgroup_bits`.
 ///
-/// This gate has as routed wires
-/// - the coset shift from subgroup H
-/// - the values that the interpolated polynomial takes on the coset
-/// - the evaluation point
-///
 /// The evaluation strategy is based on the observation that if P(X) is the interpolant of some
 /// values over a coset and P'(X) is the interpolant of those values over the subgroup, then
 /// P(X) = P'(X `shift`^{-1}). Interpolating P'(X) is preferable because when subgroup is fixed
@@ -85,54 +81,6 @@ impl<F: RichField + Extendable<D>, const D: usize> CosetInterpolationGate<F, D>
         }
     }
 
-    fn num_points(&self) -> usize {
-        1 << self.subgroup_bits
-    }
-
-    /// Wire index of the coset shift.
-    pub(crate) fn wire_shift(&self) -> usize {
-        0
-    }
-
-    fn start_values(&self) -> usize {
-        1
-    }
-
-    /// Wire indices of the `i`th interpolant value.
-    pub(crate) fn wires_value(&self, i: usize) -> Range<usize> {
-        debug_assert!(i < self.num_points());
-        let start = self.start_values() + i * D;
-        start..start + D
-    }
-
-    fn start_evaluation_point(&self) -> usize {
-        self.start_values() + self.num_points() * D
-    }
-
-    /// Wire indices of the point to evaluate the interpolant at.
-    pub(crate) fn wires_evaluation_point(&self) -> Range<usize> {
-        let start = self.start_evaluation_point();
-        start..start + D
-    }
-
-    fn start_evaluation_value(&self) -> usize {
-        self.start_evaluation_point() + D
-    }
-
-    /// Wire indices of the interpolated value.
-    pub(crate) fn wires_evaluation_value(&self) -> Range<usize> {
-        let start = self.start_evaluation_value();
-        start..start + D
-    }
-
-    fn start_intermediates(&self) -> usize {
-        self.start_evaluation_value() + D
-    }
-
-    pub fn num_routed_wires(&self) -> usize {
-        self.start_intermediates()
-    }
-
     fn num_intermediates(&self) -> usize {
         (self.num_points() - 2) / (self.degree() - 1)
     }
@@ -163,6 +111,14 @@ impl<F: RichField + Extendable<D>, const D: usize> CosetInterpolationGate<F, D>
     }
 }
 
+impl<F: RichField + Extendable<D>, const D: usize> InterpolationGate<F, D>
+    for CosetInterpolationGate<F, D>
+{
+    fn num_points(&self) -> usize {
+        1 << self.subgroup_bits
+    }
+}
+
 impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for CosetInterpolationGate<F, D> {
     fn id(&self) -> String {
         format!("{self:?}<D={D}>")
@@ -501,7 +457,7 @@ pub fn interpolate_over_base_domain<F: Field + Extendable<D>, const D: usize>(
 /// accumulated values, a partial evaluation and a partial product. This partially updates the
 /// accumulated values, so that starting with an initial evaluation of 0 and a partial evaluation
 /// of 1 and running over the whole domain is a full interpolation.
-fn partial_interpolate<F: Field + Extendable<D>, const D: usize>(
+pub(crate) fn partial_interpolate<F: Field + Extendable<D>, const D: usize>(
     domain: &[F],
     values: &[F::Extension],
     barycentric_weights: &[F],
@@ -530,7 +486,7 @@ fn partial_interpolate<F: Field + Extendable<D>, const D: usize>(
     )
 }
 
-fn partial_interpolate_ext_algebra<F: OEF<D>, const D: usize>(
+pub(crate) fn partial_interpolate_ext_algebra<F: OEF<D>, const D: usize>(
     domain: &[F::BaseField],
     values: &[ExtensionAlgebra<F, D>],
     barycentric_weights: &[F::BaseField],
@@ -559,7 +515,10 @@ fn partial_interpolate_ext_algebra<F: OEF<D>, const D: usize>(
     )
 }
 
-fn partial_interpolate_ext_algebra_target<F: RichField + Extendable<D>, const D: usize>(
+pub(crate) fn partial_interpolate_ext_algebra_target<
+    F: RichField + Extendable<D>,
+    const D: usize,
+>(
     builder: &mut CircuitBuilder<F, D>,
     domain: &[F],
     values: &[ExtensionAlgebraTarget<D>],
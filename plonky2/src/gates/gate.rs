@@ -11,16 +11,18 @@ use hashbrown::HashMap;
 
 use crate::field::batch_util::batch_multiply_inplace;
 use crate::field::extension::{Extendable, FieldExtension};
-use crate::field::types::Field;
+use crate::field::polynomial::{PolynomialCoeffs, PolynomialValues};
+use crate::field::types::{Field, Sample};
 use crate::gates::selectors::UNUSED_SELECTOR;
 use crate::gates::util::StridedConstraintConsumer;
-use crate::hash::hash_types::RichField;
+use crate::hash::hash_types::{HashOut, RichField};
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::generator::WitnessGenerator;
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::vars::{
     EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
 };
+use crate::util::transpose;
 
 /// A custom gate.
 pub trait Gate<F: RichField + Extendable<D>, const D: usize>: 'static + Send + Sync {
@@ -243,6 +245,58 @@ pub struct PrefixedGate<F: RichField + Extendable<D>, const D: usize> {
     pub prefix: Vec<bool>,
 }
 
+/// Evaluates the constraints of `gate` with each wire and constant set to a random polynomial of
+/// degree less than `witness_size`, on a domain `2^rate_bits` times larger, and returns the values
+/// of each constraint. The degree of a constraint's values is then `gate.degree()` times that of
+/// the witness, provided the domain is large enough to hold it.
+pub(crate) fn eval_on_random_low_degree_witness<
+    F: RichField + Extendable<D>,
+    G: Gate<F, D> + ?Sized,
+    const D: usize,
+>(
+    gate: &G,
+    witness_size: usize,
+    rate_bits: usize,
+) -> Vec<PolynomialValues<F::Extension>> {
+    let num_points = witness_size << rate_bits;
+    let random_low_degree_matrix = |num_polys: usize| {
+        let polys = (0..num_polys)
+            .map(|_| {
+                PolynomialCoeffs::new(F::Extension::rand_vec(witness_size))
+                    .lde(rate_bits)
+                    .fft()
+                    .values
+            })
+            .collect::<Vec<_>>();
+        if polys.is_empty() {
+            // We want a Vec of many empty Vecs, whereas transpose would just give an empty Vec.
+            vec![Vec::new(); num_points]
+        } else {
+            transpose(&polys)
+        }
+    };
+
+    let wire_ldes = random_low_degree_matrix(gate.num_wires());
+    let constant_ldes = random_low_degree_matrix(gate.num_constants());
+    let public_inputs_hash = &HashOut::rand();
+
+    let constraint_evals = wire_ldes
+        .iter()
+        .zip(constant_ldes.iter())
+        .map(|(local_wires, local_constants)| EvaluationVars {
+            local_constants,
+            local_wires,
+            public_inputs_hash,
+        })
+        .map(|vars| gate.eval_unfiltered(vars))
+        .collect::<Vec<_>>();
+
+    transpose(&constraint_evals)
+        .into_iter()
+        .map(PolynomialValues::new)
+        .collect()
+}
+
 /// A gate's filter designed so that it is non-zero if `s = row`.
 fn compute_filter<K: Field>(row: usize, group_range: Range<usize>, s: K, many_selector: bool) -> K {
     debug_assert!(group_range.contains(&row));
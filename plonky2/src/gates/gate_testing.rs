@@ -1,12 +1,10 @@
-use alloc::vec;
 use alloc::vec::Vec;
 
 use anyhow::{ensure, Result};
 
 use crate::field::extension::{Extendable, FieldExtension};
-use crate::field::polynomial::{PolynomialCoeffs, PolynomialValues};
-use crate::field::types::{Field, Sample};
-use crate::gates::gate::Gate;
+use crate::field::types::Sample;
+use crate::gates::gate::{eval_on_random_low_degree_witness, Gate};
 use crate::hash::hash_types::{HashOut, RichField};
 use crate::iop::witness::{PartialWitness, WitnessWrite};
 use crate::plonk::circuit_builder::CircuitBuilder;
@@ -14,7 +12,7 @@ use crate::plonk::circuit_data::CircuitConfig;
 use crate::plonk::config::GenericConfig;
 use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBaseBatch};
 use crate::plonk::verifier::verify;
-use crate::util::{log2_ceil, transpose};
+use crate::util::log2_ceil;
 
 const WITNESS_SIZE: usize = 1 << 5;
 const WITNESS_DEGREE: usize = WITNESS_SIZE - 1;
@@ -24,25 +22,8 @@ const WITNESS_DEGREE: usize = WITNESS_SIZE - 1;
 pub fn test_low_degree<F: RichField + Extendable<D>, G: Gate<F, D>, const D: usize>(gate: G) {
     let rate_bits = log2_ceil(gate.degree() + 1);
 
-    let wire_ldes = random_low_degree_matrix::<F::Extension>(gate.num_wires(), rate_bits);
-    let constant_ldes = random_low_degree_matrix::<F::Extension>(gate.num_constants(), rate_bits);
-    assert_eq!(wire_ldes.len(), constant_ldes.len());
-    let public_inputs_hash = &HashOut::rand();
-
-    let constraint_evals = wire_ldes
-        .iter()
-        .zip(constant_ldes.iter())
-        .map(|(local_wires, local_constants)| EvaluationVars {
-            local_constants,
-            local_wires,
-            public_inputs_hash,
-        })
-        .map(|vars| gate.eval_unfiltered(vars))
-        .collect::<Vec<_>>();
-
-    let constraint_eval_degrees = transpose(&constraint_evals)
+    let constraint_eval_degrees = eval_on_random_low_degree_witness(&gate, WITNESS_SIZE, rate_bits)
         .into_iter()
-        .map(PolynomialValues::new)
         .map(|p| p.degree())
         .collect::<Vec<_>>();
 
@@ -66,26 +47,6 @@ pub fn test_low_degree<F: RichField + Extendable<D>, G: Gate<F, D>, const D: usi
     );
 }
 
-fn random_low_degree_matrix<F: Field>(num_polys: usize, rate_bits: usize) -> Vec<Vec<F>> {
-    let polys = (0..num_polys)
-        .map(|_| random_low_degree_values(rate_bits))
-        .collect::<Vec<_>>();
-
-    if polys.is_empty() {
-        // We want a Vec of many empty Vecs, whereas transpose would just give an empty Vec.
-        vec![Vec::new(); WITNESS_SIZE << rate_bits]
-    } else {
-        transpose(&polys)
-    }
-}
-
-fn random_low_degree_values<F: Field>(rate_bits: usize) -> Vec<F> {
-    PolynomialCoeffs::new(F::rand_vec(WITNESS_SIZE))
-        .lde(rate_bits)
-        .fft()
-        .values
-}
-
 pub fn test_eval_fns<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
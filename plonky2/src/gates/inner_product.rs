@@ -0,0 +1,262 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::marker::PhantomData;
+
+use crate::field::extension::Extendable;
+use crate::field::packed::PackedField;
+use crate::field::types::Field;
+use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
+
+/// A gate which computes the inner product `sum a_i * b_i` of two vectors of length `N` in a
+/// single row. All `2N + 1` wires are routed.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct InnerProductGate<F: RichField + Extendable<D>, const D: usize, const N: usize> {
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize, const N: usize> InnerProductGate<F, D, N> {
+    pub fn new() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The `i`th entry of the first vector.
+    pub fn wires_a(i: usize) -> usize {
+        debug_assert!(i < N);
+        i
+    }
+
+    /// The `i`th entry of the second vector.
+    pub fn wires_b(i: usize) -> usize {
+        debug_assert!(i < N);
+        N + i
+    }
+
+    pub fn wire_output() -> usize {
+        2 * N
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize, const N: usize> Gate<F, D>
+    for InnerProductGate<F, D, N>
+{
+    fn id(&self) -> String {
+        format!("{self:?}<D={D}, N={N}>")
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let computed_output = (0..N)
+            .map(|i| vars.local_wires[Self::wires_a(i)] * vars.local_wires[Self::wires_b(i)])
+            .fold(F::Extension::ZERO, |acc, x| acc + x);
+        let output = vars.local_wires[Self::wire_output()];
+
+        vec![output - computed_output]
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        panic!("use eval_unfiltered_base_packed instead");
+    }
+
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let output = vars.local_wires[Self::wire_output()];
+        let diff = (0..N).fold(output, |acc, i| {
+            // acc - a_i * b_i
+            builder.arithmetic_extension(
+                F::NEG_ONE,
+                F::ONE,
+                vars.local_wires[Self::wires_a(i)],
+                vars.local_wires[Self::wires_b(i)],
+                acc,
+            )
+        });
+
+        vec![diff]
+    }
+
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<Box<dyn WitnessGenerator<F>>> {
+        let gen = InnerProductGenerator::<F, D, N> {
+            row,
+            _phantom: PhantomData,
+        };
+        vec![Box::new(gen.adapter())]
+    }
+
+    fn num_wires(&self) -> usize {
+        2 * N + 1
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        2
+    }
+
+    fn num_constraints(&self) -> usize {
+        1
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize, const N: usize> PackedEvaluableBase<F, D>
+    for InnerProductGate<F, D, N>
+{
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        let computed_output = (0..N)
+            .map(|i| vars.local_wires[Self::wires_a(i)] * vars.local_wires[Self::wires_b(i)])
+            .fold(P::ZEROS, |acc, x| acc + x);
+        let output = vars.local_wires[Self::wire_output()];
+
+        yield_constr.one(output - computed_output);
+    }
+}
+
+#[derive(Debug)]
+struct InnerProductGenerator<F: RichField + Extendable<D>, const D: usize, const N: usize> {
+    row: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize, const N: usize> SimpleGenerator<F>
+    for InnerProductGenerator<F, D, N>
+{
+    fn dependencies(&self) -> Vec<Target> {
+        (0..N)
+            .flat_map(|i| {
+                [
+                    InnerProductGate::<F, D, N>::wires_a(i),
+                    InnerProductGate::<F, D, N>::wires_b(i),
+                ]
+            })
+            .map(|column| Target::wire(self.row, column))
+            .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let get_wire = |column| witness.get_target(Target::wire(self.row, column));
+
+        let output = (0..N)
+            .map(|i| {
+                get_wire(InnerProductGate::<F, D, N>::wires_a(i))
+                    * get_wire(InnerProductGate::<F, D, N>::wires_b(i))
+            })
+            .fold(F::ZERO, |acc, x| acc + x);
+
+        let output_target = Target::wire(self.row, InnerProductGate::<F, D, N>::wire_output());
+        out_buffer.set_target(output_target, output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::Sample;
+    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::iop::witness::PartialWitness;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn wire_indices() {
+        type G = InnerProductGate<GoldilocksField, 4, 5>;
+        assert_eq!(G::wires_a(0), 0);
+        assert_eq!(G::wires_a(4), 4);
+        assert_eq!(G::wires_b(0), 5);
+        assert_eq!(G::wires_b(4), 9);
+        assert_eq!(G::wire_output(), 10);
+    }
+
+    #[test]
+    fn low_degree() {
+        // The single constraint is quadratic in the wires.
+        test_low_degree::<GoldilocksField, _, 4>(InnerProductGate::<GoldilocksField, 4, 8>::new())
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        test_eval_fns::<F, C, _, D>(InnerProductGate::<F, D, 8>::new())
+    }
+
+    #[test]
+    fn test_inner_product_matches_mul_add_chain() -> Result<()> {
+        const D: usize = 2;
+        const N: usize = 8;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // Not a multiple of `N`, so the last chunk is padded.
+        let len = 2 * N + 3;
+        let a = F::rand_vec(len);
+        let b = F::rand_vec(len);
+        let a_t = a.iter().map(|&x| builder.constant(x)).collect::<Vec<_>>();
+        let b_t = b.iter().map(|&x| builder.constant(x)).collect::<Vec<_>>();
+
+        let result = builder.inner_product::<N>(&a_t, &b_t);
+        let naive = a_t
+            .iter()
+            .zip(&b_t)
+            .fold(builder.zero(), |acc, (&x, &y)| builder.mul_add(x, y, acc));
+        builder.connect(result, naive);
+
+        let expected = a.iter().zip(&b).fold(F::ZERO, |acc, (&x, &y)| acc + x * y);
+        let expected_t = builder.constant(expected);
+        builder.connect(result, expected_t);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+    #[test]
+    #[should_panic(expected = "InnerProductGate needs N > 0")]
+    fn test_inner_product_zero_width() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let a = builder.add_virtual_targets(2);
+        let b = builder.add_virtual_targets(2);
+        builder.inner_product::<0>(&a, &b);
+    }
+}
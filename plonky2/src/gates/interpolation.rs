@@ -0,0 +1,64 @@
+use core::ops::Range;
+
+use crate::field::extension::Extendable;
+use crate::gates::gate::Gate;
+use crate::hash::hash_types::RichField;
+
+/// A gate which interpolates values given on a coset of a two-adic subgroup, and evaluates the
+/// interpolant at a given point.
+///
+/// All instantiations share the same layout of routed wires:
+/// - the coset shift,
+/// - the values that the interpolated polynomial takes on the coset,
+/// - the evaluation point,
+/// - the interpolated value,
+///
+/// followed by whatever non-routed wires the instantiation needs, starting at
+/// `start_intermediates`.
+pub trait InterpolationGate<F: RichField + Extendable<D>, const D: usize>: Gate<F, D> {
+    fn num_points(&self) -> usize;
+
+    /// Wire index of the coset shift.
+    fn wire_shift(&self) -> usize {
+        0
+    }
+
+    fn start_values(&self) -> usize {
+        1
+    }
+
+    /// Wire indices of the `i`th interpolant value.
+    fn wires_value(&self, i: usize) -> Range<usize> {
+        debug_assert!(i < self.num_points());
+        let start = self.start_values() + i * D;
+        start..start + D
+    }
+
+    fn start_evaluation_point(&self) -> usize {
+        self.start_values() + self.num_points() * D
+    }
+
+    /// Wire indices of the point to evaluate the interpolant at.
+    fn wires_evaluation_point(&self) -> Range<usize> {
+        let start = self.start_evaluation_point();
+        start..start + D
+    }
+
+    fn start_evaluation_value(&self) -> usize {
+        self.start_evaluation_point() + D
+    }
+
+    /// Wire indices of the interpolated value.
+    fn wires_evaluation_value(&self) -> Range<usize> {
+        let start = self.start_evaluation_value();
+        start..start + D
+    }
+
+    fn start_intermediates(&self) -> usize {
+        self.start_evaluation_value() + D
+    }
+
+    fn num_routed_wires(&self) -> usize {
+        self.start_intermediates()
+    }
+}
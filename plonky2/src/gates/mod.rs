@@ -3,22 +3,29 @@
 
 pub mod arithmetic_base;
 pub mod arithmetic_extension;
+pub mod barycentric_interpolation;
 pub mod base_sum;
 pub mod constant;
 pub mod coset_interpolation;
 pub mod exponentiation;
 pub mod gate;
+pub mod inner_product;
+pub mod interpolation;
+pub mod mul_add_mul;
 pub mod multiplication_extension;
 pub mod noop;
 pub mod packed_util;
 pub mod poseidon;
+pub mod poseidon8;
 pub mod poseidon_mds;
 pub mod public_input;
 pub mod random_access;
 pub mod reducing;
 pub mod reducing_extension;
 pub(crate) mod selectors;
+pub mod set_membership;
 pub mod util;
+pub mod xor;
 
 // Can't use #[cfg(test)] here because it needs to be visible to other crates.
 // See https://github.com/rust-lang/cargo/issues/8379
@@ -0,0 +1,220 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::field::extension::Extendable;
+use crate::field::packed::PackedField;
+use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGenerator};
+use crate::iop::target::Target;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CircuitConfig;
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
+
+/// A gate which computes a sum of two products, i.e. `result = a b + c d`. Unlike
+/// `ArithmeticGate`, it has no constants, so its constraints have degree 2. If the config supports
+/// enough routed wires, it can support several such operations in one gate.
+#[derive(Debug, Clone)]
+pub struct MulAddMulGate {
+    /// Number of operations performed by the gate.
+    pub num_ops: usize,
+}
+
+impl MulAddMulGate {
+    pub fn new_from_config(config: &CircuitConfig) -> Self {
+        Self {
+            num_ops: Self::num_ops(config),
+        }
+    }
+
+    /// Determine the maximum number of operations that can fit in one gate for the given config.
+    pub(crate) fn num_ops(config: &CircuitConfig) -> usize {
+        let wires_per_op = 5;
+        config.num_routed_wires / wires_per_op
+    }
+
+    pub fn wire_ith_a(i: usize) -> usize {
+        5 * i
+    }
+    pub fn wire_ith_b(i: usize) -> usize {
+        5 * i + 1
+    }
+    pub fn wire_ith_c(i: usize) -> usize {
+        5 * i + 2
+    }
+    pub fn wire_ith_d(i: usize) -> usize {
+        5 * i + 3
+    }
+    pub fn wire_ith_output(i: usize) -> usize {
+        5 * i + 4
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for MulAddMulGate {
+    fn id(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        let mut constraints = Vec::new();
+        for i in 0..self.num_ops {
+            let a = vars.local_wires[Self::wire_ith_a(i)];
+            let b = vars.local_wires[Self::wire_ith_b(i)];
+            let c = vars.local_wires[Self::wire_ith_c(i)];
+            let d = vars.local_wires[Self::wire_ith_d(i)];
+            let output = vars.local_wires[Self::wire_ith_output(i)];
+
+            constraints.push(output - (a * b + c * d));
+        }
+
+        constraints
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        panic!("use eval_unfiltered_base_packed instead");
+    }
+
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let mut constraints = Vec::new();
+        for i in 0..self.num_ops {
+            let a = vars.local_wires[Self::wire_ith_a(i)];
+            let b = vars.local_wires[Self::wire_ith_b(i)];
+            let c = vars.local_wires[Self::wire_ith_c(i)];
+            let d = vars.local_wires[Self::wire_ith_d(i)];
+            let output = vars.local_wires[Self::wire_ith_output(i)];
+            let computed_output = {
+                let cd = builder.mul_extension(c, d);
+                builder.mul_add_extension(a, b, cd)
+            };
+
+            let diff = builder.sub_extension(output, computed_output);
+            constraints.push(diff);
+        }
+
+        constraints
+    }
+
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<Box<dyn WitnessGenerator<F>>> {
+        (0..self.num_ops)
+            .map(|i| {
+                let g: Box<dyn WitnessGenerator<F>> =
+                    Box::new(MulAddMulGenerator { row, i }.adapter());
+                g
+            })
+            .collect()
+    }
+
+    fn num_wires(&self) -> usize {
+        self.num_ops * 5
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        2
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.num_ops
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D> for MulAddMulGate {
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        for i in 0..self.num_ops {
+            let a = vars.local_wires[Self::wire_ith_a(i)];
+            let b = vars.local_wires[Self::wire_ith_b(i)];
+            let c = vars.local_wires[Self::wire_ith_c(i)];
+            let d = vars.local_wires[Self::wire_ith_d(i)];
+            let output = vars.local_wires[Self::wire_ith_output(i)];
+
+            yield_constr.one(output - (a * b + c * d));
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct MulAddMulGenerator {
+    row: usize,
+    i: usize,
+}
+
+impl<F: RichField> SimpleGenerator<F> for MulAddMulGenerator {
+    fn dependencies(&self) -> Vec<Target> {
+        [
+            MulAddMulGate::wire_ith_a(self.i),
+            MulAddMulGate::wire_ith_b(self.i),
+            MulAddMulGate::wire_ith_c(self.i),
+            MulAddMulGate::wire_ith_d(self.i),
+        ]
+        .iter()
+        .map(|&i| Target::wire(self.row, i))
+        .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let get_wire = |wire: usize| -> F { witness.get_target(Target::wire(self.row, wire)) };
+
+        let a = get_wire(MulAddMulGate::wire_ith_a(self.i));
+        let b = get_wire(MulAddMulGate::wire_ith_b(self.i));
+        let c = get_wire(MulAddMulGate::wire_ith_c(self.i));
+        let d = get_wire(MulAddMulGate::wire_ith_d(self.i));
+
+        let output_target = Target::wire(self.row, MulAddMulGate::wire_ith_output(self.i));
+
+        out_buffer.set_target(output_target, a * b + c * d)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::mul_add_mul::MulAddMulGate;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn low_degree() {
+        let gate = MulAddMulGate::new_from_config(&CircuitConfig::standard_recursion_config());
+        test_low_degree::<GoldilocksField, _, 4>(gate);
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = MulAddMulGate::new_from_config(&CircuitConfig::standard_recursion_config());
+        test_eval_fns::<F, C, _, D>(gate)
+    }
+}
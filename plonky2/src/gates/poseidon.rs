@@ -336,8 +336,7 @@ impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for PoseidonGate<F
                 constraints.push(builder.sub_extension(state[0], sbox_in));
                 state[0] = <F as Poseidon>::sbox_monomial_circuit(builder, sbox_in);
                 let c = <F as Poseidon>::FAST_PARTIAL_ROUND_CONSTANTS[r];
-                let c = F::Extension::from_canonical_u64(c);
-                let c = builder.constant_extension(c);
+                let c = builder.constant_ext_from_base(F::from_canonical_u64(c));
                 state[0] = builder.add_extension(state[0], c);
                 state = <F as Poseidon>::mds_partial_layer_fast_circuit(builder, &state, r);
             }
@@ -519,6 +518,8 @@ mod tests {
     use crate::plonk::circuit_data::CircuitConfig;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
+    // The indices below assume the full number of partial rounds.
+    #[cfg(not(feature = "poseidon_fast_insecure"))]
     #[test]
     fn wire_indices() {
         type F = GoldilocksField;
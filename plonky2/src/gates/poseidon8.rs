@@ -0,0 +1,329 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::marker::PhantomData;
+
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::gates::gate::Gate;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::hash::poseidon::{HALF_N_FULL_ROUNDS, N_FULL_ROUNDS_TOTAL, N_PARTIAL_ROUNDS, N_ROUNDS};
+use crate::hash::poseidon8::{is_full_round, Poseidon8, POSEIDON8_WIDTH};
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::{GeneratedValues, SimpleGenerator, WitnessGenerator};
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+use crate::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase};
+
+const WIDTH: usize = POSEIDON8_WIDTH;
+
+/// Evaluates a full Poseidon permutation with 8 state elements.
+///
+/// Unlike `PoseidonGate`, there is no flag to swap inputs, and partial rounds are constrained
+/// directly rather than with the fast partial round constants.
+#[derive(Debug, Default)]
+pub struct Poseidon8Gate<F: RichField + Extendable<D>, const D: usize>(PhantomData<F>);
+
+impl<F: RichField + Extendable<D> + Poseidon8, const D: usize> Poseidon8Gate<F, D> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+
+    /// The wire index for the `i`th input to the permutation.
+    pub fn wire_input(i: usize) -> usize {
+        i
+    }
+
+    /// The wire index for the `i`th output to the permutation.
+    pub fn wire_output(i: usize) -> usize {
+        WIDTH + i
+    }
+
+    const START_FULL: usize = 2 * WIDTH;
+
+    /// A wire which stores the input of the `i`-th S-box of the `round_ctr`-th round, which must
+    /// be a full round.
+    fn wire_full_sbox(round_ctr: usize, i: usize) -> usize {
+        debug_assert!(
+            round_ctr != 0,
+            "First round S-box inputs are not stored as wires"
+        );
+        debug_assert!(is_full_round(round_ctr));
+        debug_assert!(i < WIDTH);
+        let full_round = if round_ctr < HALF_N_FULL_ROUNDS {
+            round_ctr
+        } else {
+            round_ctr - N_PARTIAL_ROUNDS
+        };
+        Self::START_FULL + WIDTH * (full_round - 1) + i
+    }
+
+    const START_PARTIAL: usize = Self::START_FULL + WIDTH * (N_FULL_ROUNDS_TOTAL - 1);
+
+    /// A wire which stores the input of the S-box of the `round_ctr`-th round, which must be a
+    /// partial round.
+    fn wire_partial_sbox(round_ctr: usize) -> usize {
+        debug_assert!(!is_full_round(round_ctr));
+        Self::START_PARTIAL + round_ctr - HALF_N_FULL_ROUNDS
+    }
+
+    /// End of wire indices, exclusive.
+    fn end() -> usize {
+        Self::START_PARTIAL + N_PARTIAL_ROUNDS
+    }
+
+    /// Evaluates the constraints over any extension of `F`, given the wire values in `wires`.
+    fn eval_field<FE: FieldExtension<E, BaseField = F>, const E: usize>(wires: &[FE]) -> Vec<FE> {
+        let mut constraints = Vec::with_capacity(WIDTH * N_FULL_ROUNDS_TOTAL + N_PARTIAL_ROUNDS);
+        let mut state: [FE; WIDTH] = core::array::from_fn(|i| wires[Self::wire_input(i)]);
+
+        for round_ctr in 0..N_ROUNDS {
+            <F as Poseidon8>::constant_layer_field(&mut state, round_ctr);
+            if is_full_round(round_ctr) {
+                for i in 0..WIDTH {
+                    if round_ctr != 0 {
+                        let sbox_in = wires[Self::wire_full_sbox(round_ctr, i)];
+                        constraints.push(state[i] - sbox_in);
+                        state[i] = sbox_in;
+                    }
+                    state[i] = <F as Poseidon8>::sbox_monomial(state[i]);
+                }
+            } else {
+                let sbox_in = wires[Self::wire_partial_sbox(round_ctr)];
+                constraints.push(state[0] - sbox_in);
+                state[0] = <F as Poseidon8>::sbox_monomial(sbox_in);
+            }
+            state = <F as Poseidon8>::mds_layer_field(&state);
+        }
+
+        for i in 0..WIDTH {
+            constraints.push(state[i] - wires[Self::wire_output(i)]);
+        }
+
+        constraints
+    }
+}
+
+impl<F: RichField + Extendable<D> + Poseidon8, const D: usize> Gate<F, D> for Poseidon8Gate<F, D> {
+    fn id(&self) -> String {
+        format!("{self:?}<WIDTH={WIDTH}>")
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        Self::eval_field(vars.local_wires)
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        vars: EvaluationVarsBase<F>,
+        mut yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        let wires = (0..Self::end())
+            .map(|i| vars.local_wires[i])
+            .collect::<Vec<_>>();
+        yield_constr.many(Self::eval_field::<F, 1>(&wires));
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let mut constraints = Vec::with_capacity(self.num_constraints());
+        let mut state: [ExtensionTarget<D>; WIDTH] =
+            core::array::from_fn(|i| vars.local_wires[Self::wire_input(i)]);
+
+        for round_ctr in 0..N_ROUNDS {
+            <F as Poseidon8>::constant_layer_circuit(builder, &mut state, round_ctr);
+            if is_full_round(round_ctr) {
+                for i in 0..WIDTH {
+                    if round_ctr != 0 {
+                        let sbox_in = vars.local_wires[Self::wire_full_sbox(round_ctr, i)];
+                        constraints.push(builder.sub_extension(state[i], sbox_in));
+                        state[i] = sbox_in;
+                    }
+                    state[i] = <F as Poseidon8>::sbox_monomial_circuit(builder, state[i]);
+                }
+            } else {
+                let sbox_in = vars.local_wires[Self::wire_partial_sbox(round_ctr)];
+                constraints.push(builder.sub_extension(state[0], sbox_in));
+                state[0] = <F as Poseidon8>::sbox_monomial_circuit(builder, sbox_in);
+            }
+            state = <F as Poseidon8>::mds_layer_circuit(builder, &state);
+        }
+
+        for i in 0..WIDTH {
+            constraints
+                .push(builder.sub_extension(state[i], vars.local_wires[Self::wire_output(i)]));
+        }
+
+        constraints
+    }
+
+    fn generators(&self, row: usize, _local_constants: &[F]) -> Vec<Box<dyn WitnessGenerator<F>>> {
+        let gen = Poseidon8Generator::<F, D> {
+            row,
+            _phantom: PhantomData,
+        };
+        vec![Box::new(gen.adapter())]
+    }
+
+    fn num_wires(&self) -> usize {
+        Self::end()
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        7
+    }
+
+    fn num_constraints(&self) -> usize {
+        WIDTH * (N_FULL_ROUNDS_TOTAL - 1) + N_PARTIAL_ROUNDS + WIDTH
+    }
+}
+
+#[derive(Debug)]
+struct Poseidon8Generator<F: RichField + Extendable<D> + Poseidon8, const D: usize> {
+    row: usize,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: RichField + Extendable<D> + Poseidon8, const D: usize> SimpleGenerator<F>
+    for Poseidon8Generator<F, D>
+{
+    fn dependencies(&self) -> Vec<Target> {
+        (0..WIDTH)
+            .map(|i| Target::wire(self.row, Poseidon8Gate::<F, D>::wire_input(i)))
+            .collect()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        let local_wire = |column| Wire {
+            row: self.row,
+            column,
+        };
+
+        let mut state: [F; WIDTH] = core::array::from_fn(|i| {
+            witness.get_wire(local_wire(Poseidon8Gate::<F, D>::wire_input(i)))
+        });
+
+        for round_ctr in 0..N_ROUNDS {
+            <F as Poseidon8>::constant_layer_field::<F, 1>(&mut state, round_ctr);
+            if is_full_round(round_ctr) {
+                for i in 0..WIDTH {
+                    if round_ctr != 0 {
+                        out_buffer.set_wire(
+                            local_wire(Poseidon8Gate::<F, D>::wire_full_sbox(round_ctr, i)),
+                            state[i],
+                        );
+                    }
+                    state[i] = <F as Poseidon8>::sbox_monomial::<F, 1>(state[i]);
+                }
+            } else {
+                out_buffer.set_wire(
+                    local_wire(Poseidon8Gate::<F, D>::wire_partial_sbox(round_ctr)),
+                    state[0],
+                );
+                state[0] = <F as Poseidon8>::sbox_monomial::<F, 1>(state[0]);
+            }
+            state = <F as Poseidon8>::mds_layer(&state);
+        }
+
+        for i in 0..WIDTH {
+            out_buffer.set_wire(local_wire(Poseidon8Gate::<F, D>::wire_output(i)), state[i]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::Field;
+    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::poseidon8::Poseidon8Gate;
+    use crate::hash::poseidon8::{Poseidon8, POSEIDON8_WIDTH};
+    use crate::iop::generator::generate_partial_witness;
+    use crate::iop::wire::Wire;
+    use crate::iop::witness::{PartialWitness, Witness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    // The indices below assume the full number of partial rounds.
+    #[cfg(not(feature = "poseidon_fast_insecure"))]
+    #[test]
+    fn wire_indices() {
+        type F = GoldilocksField;
+        type Gate = Poseidon8Gate<F, 4>;
+
+        assert_eq!(Gate::wire_input(7), 7);
+        assert_eq!(Gate::wire_output(0), 8);
+        assert_eq!(Gate::wire_full_sbox(1, 0), 16);
+        assert_eq!(Gate::wire_full_sbox(3, 7), 39);
+        assert_eq!(Gate::wire_full_sbox(26, 0), 40);
+        assert_eq!(Gate::wire_full_sbox(29, 7), 71);
+        assert_eq!(Gate::wire_partial_sbox(4), 72);
+        assert_eq!(Gate::wire_partial_sbox(25), 93);
+        assert_eq!(Gate::end(), 94);
+    }
+
+    #[test]
+    fn generated_output() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type Gate = Poseidon8Gate<F, D>;
+
+        let mut builder = CircuitBuilder::new(CircuitConfig::standard_recursion_config());
+        let row = builder.add_gate(Gate::new(), vec![]);
+        let circuit = builder.build_prover::<C>();
+
+        let permutation_inputs: [F; POSEIDON8_WIDTH] =
+            core::array::from_fn(F::from_canonical_usize);
+        let mut inputs = PartialWitness::new();
+        for i in 0..POSEIDON8_WIDTH {
+            inputs.set_wire(
+                Wire {
+                    row,
+                    column: Gate::wire_input(i),
+                },
+                permutation_inputs[i],
+            );
+        }
+
+        let witness = generate_partial_witness(inputs, &circuit.prover_only, &circuit.common);
+
+        let expected_outputs = F::poseidon8(permutation_inputs);
+        for i in 0..POSEIDON8_WIDTH {
+            let out = witness.get_wire(Wire {
+                row,
+                column: Gate::wire_output(i),
+            });
+            assert_eq!(out, expected_outputs[i]);
+        }
+    }
+
+    #[test]
+    fn low_degree() {
+        type F = GoldilocksField;
+        let gate = Poseidon8Gate::<F, 4>::new();
+        test_low_degree(gate)
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let gate = Poseidon8Gate::<F, 2>::new();
+        test_eval_fns::<F, C, _, D>(gate)
+    }
+}
@@ -0,0 +1,202 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::packed::PackedField;
+use crate::field::types::Field;
+use crate::gates::gate::Gate;
+use crate::gates::packed_util::PackedEvaluableBase;
+use crate::gates::util::StridedConstraintConsumer;
+use crate::hash::hash_types::RichField;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::generator::WitnessGenerator;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::circuit_data::CircuitConfig;
+use crate::plonk::vars::{
+    EvaluationTargets, EvaluationVars, EvaluationVarsBase, EvaluationVarsBaseBatch,
+    EvaluationVarsBasePacked,
+};
+
+/// A gate which checks that each of its wires holds an element of a fixed set, by constraining
+/// `prod_{c in set} (x - c) = 0`. The degree of the gate is the size of the set.
+///
+/// The set must contain zero, so that the wires of unused operations, which are left at zero, are
+/// valid. Membership in other sets can be checked by shifting them, and the value, by one of their
+/// elements.
+#[derive(Debug, Clone)]
+pub struct SetMembershipGate<F: Field> {
+    pub set: Vec<F>,
+    /// Number of values checked by a single gate.
+    pub num_ops: usize,
+}
+
+impl<F: Field> SetMembershipGate<F> {
+    pub fn new_from_config(config: &CircuitConfig, set: Vec<F>) -> Self {
+        assert!(set.contains(&F::ZERO), "The set must contain zero");
+        Self {
+            set,
+            num_ops: config.num_routed_wires,
+        }
+    }
+
+    pub fn wire_ith_element(i: usize) -> usize {
+        i
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for SetMembershipGate<F> {
+    fn id(&self) -> String {
+        format!("{self:?}")
+    }
+
+    fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+        (0..self.num_ops)
+            .map(|i| {
+                let x = vars.local_wires[Self::wire_ith_element(i)];
+                self.set
+                    .iter()
+                    .map(|&c| x - F::Extension::from_basefield(c))
+                    .product()
+            })
+            .collect()
+    }
+
+    fn eval_unfiltered_base_one(
+        &self,
+        _vars: EvaluationVarsBase<F>,
+        _yield_constr: StridedConstraintConsumer<F>,
+    ) {
+        panic!("use eval_unfiltered_base_packed instead");
+    }
+
+    fn eval_unfiltered_base_batch(&self, vars_base: EvaluationVarsBaseBatch<F>) -> Vec<F> {
+        self.eval_unfiltered_base_batch_packed(vars_base)
+    }
+
+    fn eval_unfiltered_circuit(
+        &self,
+        builder: &mut CircuitBuilder<F, D>,
+        vars: EvaluationTargets<D>,
+    ) -> Vec<ExtensionTarget<D>> {
+        let set = self
+            .set
+            .iter()
+            .map(|&c| builder.constant_extension(F::Extension::from_basefield(c)))
+            .collect::<Vec<_>>();
+
+        (0..self.num_ops)
+            .map(|i| {
+                let x = vars.local_wires[Self::wire_ith_element(i)];
+                let diffs = set
+                    .iter()
+                    .map(|&c| builder.sub_extension(x, c))
+                    .collect::<Vec<_>>();
+                builder.mul_many_extension(diffs)
+            })
+            .collect()
+    }
+
+    fn generators(&self, _row: usize, _local_constants: &[F]) -> Vec<Box<dyn WitnessGenerator<F>>> {
+        Vec::new()
+    }
+
+    fn num_wires(&self) -> usize {
+        self.num_ops
+    }
+
+    fn num_constants(&self) -> usize {
+        0
+    }
+
+    fn degree(&self) -> usize {
+        self.set.len()
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.num_ops
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> PackedEvaluableBase<F, D>
+    for SetMembershipGate<F>
+{
+    fn eval_unfiltered_base_packed<P: PackedField<Scalar = F>>(
+        &self,
+        vars: EvaluationVarsBasePacked<P>,
+        mut yield_constr: StridedConstraintConsumer<P>,
+    ) {
+        for i in 0..self.num_ops {
+            let x = vars.local_wires[Self::wire_ith_element(i)];
+            yield_constr.one(self.set.iter().map(|&c| x - c).product());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use anyhow::Result;
+
+    use crate::field::extension::FieldExtension;
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::{Field, Sample};
+    use crate::gates::gate::Gate;
+    use crate::gates::gate_testing::{test_eval_fns, test_low_degree};
+    use crate::gates::set_membership::SetMembershipGate;
+    use crate::hash::hash_types::HashOut;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::vars::EvaluationVars;
+
+    fn gate<F: Field + Sample>() -> SetMembershipGate<F> {
+        let mut set = F::rand_vec(4);
+        set.push(F::ZERO);
+        SetMembershipGate::new_from_config(&CircuitConfig::standard_recursion_config(), set)
+    }
+
+    #[test]
+    fn low_degree() {
+        test_low_degree::<GoldilocksField, _, 4>(gate());
+    }
+
+    #[test]
+    fn eval_fns() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        test_eval_fns::<F, C, _, D>(gate())
+    }
+
+    #[test]
+    fn test_gate_constraint() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+
+        let gate = gate::<F>();
+        let eval = |wires: &[FF]| {
+            let vars = EvaluationVars {
+                local_constants: &[],
+                local_wires: wires,
+                public_inputs_hash: &HashOut::rand(),
+            };
+            <SetMembershipGate<F> as Gate<F, D>>::eval_unfiltered(&gate, vars)
+        };
+
+        // Members of the set, including the zeros of unused operations, are valid.
+        let mut wires = vec![FF::ZERO; gate.num_ops];
+        wires[0] = <FF as FieldExtension<D>>::from_basefield(gate.set[2]);
+        wires[1] = <FF as FieldExtension<D>>::from_basefield(gate.set[0]);
+        assert!(eval(&wires).iter().all(|c| c.is_zero()));
+
+        // A non-member isn't.
+        wires[1] = FF::rand();
+        let constraints = eval(&wires);
+        assert!(!constraints[1].is_zero());
+        assert!(constraints[0].is_zero());
+    }
+}
@@ -0,0 +1,147 @@
+use alloc::vec::Vec;
+use core::iter;
+use core::mem::size_of;
+
+use itertools::Itertools;
+
+use crate::hash::hash_types::{BytesHash, RichField};
+use crate::hash::hashing::{PlonkyPermutation, SPONGE_WIDTH};
+use crate::plonk::config::Hasher;
+use crate::util::serialization::Write;
+
+/// BLAKE3 pseudo-permutation (not necessarily one-to-one), built like `KeccakPermutation`: a state
+/// `input: [F; 12]` is sent to the field elements parsed, by rejection sampling, from the stream
+/// `H(input) || H(H(input)) || ...`, where `H` is BLAKE3.
+pub struct Blake3Permutation;
+impl<F: RichField> PlonkyPermutation<F> for Blake3Permutation {
+    fn permute(input: [F; SPONGE_WIDTH]) -> [F; SPONGE_WIDTH] {
+        let mut state = Vec::with_capacity(SPONGE_WIDTH * size_of::<u64>());
+        for x in input {
+            state.extend_from_slice(&x.to_canonical_u64().to_le_bytes());
+        }
+
+        let hash_onion = iter::repeat_with(|| {
+            let output = *blake3::hash(&state).as_bytes();
+            state = output.to_vec();
+            output
+        });
+
+        let hash_onion_u64s = hash_onion.flat_map(|output| {
+            output
+                .chunks_exact(size_of::<u64>())
+                .map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+                .collect_vec()
+        });
+
+        hash_onion_u64s
+            .filter(|&word| word < F::ORDER)
+            .map(F::from_canonical_u64)
+            .take(SPONGE_WIDTH)
+            .collect_vec()
+            .try_into()
+            .unwrap()
+    }
+}
+
+/// BLAKE3 hash function, with outputs truncated to `N` bytes.
+///
+/// Field elements are hashed as their canonical little-endian `u64` encodings, so this is much
+/// faster than Poseidon natively, but it has no `AlgebraicHasher` implementation and can't be
+/// verified in a circuit. It suits commitments which are only ever checked outside of circuits.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Blake3Hash<const N: usize>;
+
+impl<F: RichField, const N: usize> Hasher<F> for Blake3Hash<N> {
+    const HASH_SIZE: usize = N;
+    type Hash = BytesHash<N>;
+    type Permutation = Blake3Permutation;
+
+    fn hash_no_pad(input: &[F]) -> Self::Hash {
+        let mut buffer = Vec::with_capacity(input.len() * size_of::<u64>());
+        buffer.write_field_vec(input).unwrap();
+        let mut arr = [0; N];
+        arr.copy_from_slice(&blake3::hash(&buffer).as_bytes()[..N]);
+        BytesHash(arr)
+    }
+
+    fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&left.0);
+        hasher.update(&right.0);
+        let mut arr = [0; N];
+        arr.copy_from_slice(&hasher.finalize().as_bytes()[..N]);
+        BytesHash(arr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField as F;
+    use crate::field::types::{Field, Field64, PrimeField64, Sample};
+    use crate::hash::merkle_proofs::verify_merkle_proof_to_cap;
+    use crate::hash::merkle_tree::MerkleTree;
+
+    type H = Blake3Hash<32>;
+
+    #[test]
+    fn test_hash_no_pad() {
+        let input = F::rand_vec(10);
+        let hash = <H as Hasher<F>>::hash_no_pad(&input);
+        assert_eq!(hash, <H as Hasher<F>>::hash_no_pad(&input));
+
+        // Elements are hashed as canonical little-endian words.
+        let bytes = input
+            .iter()
+            .flat_map(|x| x.to_canonical_u64().to_le_bytes())
+            .collect::<Vec<_>>();
+        assert_eq!(hash.0, *blake3::hash(&bytes).as_bytes());
+
+        // Equal elements hash equally however they are represented.
+        let noncanonical = F::from_noncanonical_u64(F::ORDER + 1);
+        assert_eq!(
+            <H as Hasher<F>>::hash_no_pad(&[noncanonical]),
+            <H as Hasher<F>>::hash_no_pad(&[F::ONE])
+        );
+
+        let truncated = <Blake3Hash<25> as Hasher<F>>::hash_no_pad(&input);
+        assert_eq!(truncated.0, hash.0[..25]);
+    }
+
+    #[test]
+    fn test_two_to_one() {
+        let left = BytesHash::<32>::rand();
+        let right = BytesHash::<32>::rand();
+        let hash = <H as Hasher<F>>::two_to_one(left, right);
+        assert_eq!(hash, <H as Hasher<F>>::two_to_one(left, right));
+        assert_ne!(hash, <H as Hasher<F>>::two_to_one(right, left));
+
+        let concatenated = [left.0, right.0].concat();
+        assert_eq!(hash.0, *blake3::hash(&concatenated).as_bytes());
+    }
+
+    #[test]
+    fn test_merkle_tree() -> Result<()> {
+        let log_n = 6;
+        let cap_height = 2;
+        let leaves = (0..1 << log_n).map(|_| F::rand_vec(7)).collect::<Vec<_>>();
+        let tree = MerkleTree::<F, H>::new(leaves.clone(), cap_height);
+        assert_eq!(
+            tree.cap,
+            MerkleTree::<F, H>::new(leaves.clone(), cap_height).cap
+        );
+
+        for (i, leaf) in leaves.iter().enumerate() {
+            let proof = tree.prove(i);
+            verify_merkle_proof_to_cap(leaf.clone(), i, &tree.cap, &proof)?;
+        }
+
+        // A tampered leaf doesn't verify.
+        let mut leaf = leaves[3].clone();
+        leaf[0] += F::ONE;
+        assert!(verify_merkle_proof_to_cap(leaf, 3, &tree.cap, &tree.prove(3)).is_err());
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use alloc::vec::Vec;
 
-use anyhow::ensure;
+use anyhow::{ensure, Result};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::field::goldilocks_field::GoldilocksField;
@@ -78,6 +78,29 @@ where
     }
 }
 
+impl<F: RichField> HashOut<F> {
+    /// Returns the canonical little-endian encoding of each element, concatenated.
+    pub fn to_canonical_bytes(&self) -> Vec<u8> {
+        self.elements.iter().flat_map(|x| x.to_bytes_le()).collect()
+    }
+
+    /// Parses the encoding produced by `to_canonical_bytes`. Unlike `GenericHashOut::from_bytes`,
+    /// this fails on inputs of the wrong length or with non-canonical elements, rather than
+    /// reducing them.
+    pub fn try_from_canonical_bytes(bytes: &[u8]) -> Result<Self> {
+        ensure!(
+            bytes.len() == 4 * 8,
+            "Expected 32 bytes, got {}",
+            bytes.len()
+        );
+        let elements = bytes
+            .chunks(8)
+            .map(F::from_bytes_le)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::from_vec(elements))
+    }
+}
+
 impl<F: RichField> GenericHashOut<F> for HashOut<F> {
     fn to_bytes(&self) -> Vec<u8> {
         self.elements
@@ -206,3 +229,24 @@ impl<'de, const N: usize> Deserialize<'de> for BytesHash<N> {
         todo!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::{Field64, Sample};
+    use crate::hash::hash_types::HashOut;
+
+    #[test]
+    fn test_hash_out_bytes_round_trip() {
+        type F = GoldilocksField;
+        let h = HashOut::<F>::rand();
+        let bytes = h.to_canonical_bytes();
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(HashOut::<F>::try_from_canonical_bytes(&bytes).unwrap(), h);
+
+        let mut non_canonical = bytes.clone();
+        non_canonical[8..16].copy_from_slice(&F::ORDER.to_le_bytes());
+        assert!(HashOut::<F>::try_from_canonical_bytes(&non_canonical).is_err());
+        assert!(HashOut::<F>::try_from_canonical_bytes(&bytes[..24]).is_err());
+    }
+}
@@ -1,17 +1,28 @@
 //! Concrete instantiation of a hash function.
 
 use alloc::vec::Vec;
+use core::marker::PhantomData;
 
 use crate::field::extension::Extendable;
 use crate::hash::hash_types::{HashOut, HashOutTarget, RichField};
-use crate::iop::target::Target;
+use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::AlgebraicHasher;
 
 pub(crate) const SPONGE_RATE: usize = 8;
-pub(crate) const SPONGE_CAPACITY: usize = 4;
+pub const SPONGE_CAPACITY: usize = 4;
 pub const SPONGE_WIDTH: usize = SPONGE_RATE + SPONGE_CAPACITY;
 
+/// The field element a domain is placed in the capacity as. Domains must be less than the field
+/// order, since reducing them would make e.g. `p` and `0` the same domain.
+fn domain_element<F: RichField>(domain: u64) -> F {
+    assert!(
+        domain < F::ORDER,
+        "Domain {domain} is not less than the field order"
+    );
+    F::from_canonical_u64(domain)
+}
+
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     pub fn hash_or_noop<H: AlgebraicHasher<F>>(&mut self, inputs: Vec<Target>) -> HashOutTarget {
         let zero = self.zero();
@@ -29,15 +40,119 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         HashOutTarget::from_vec(self.hash_n_to_m_no_pad::<H>(inputs, 4))
     }
 
+    /// Returns the hash of `inputs` if `cond` is set, or the zero hash otherwise. If `cond` is a
+    /// constant, only the selected branch is built, so no hashing constraints are added for an
+    /// absent input.
+    pub fn conditional_hash<H: AlgebraicHasher<F>>(
+        &mut self,
+        cond: BoolTarget,
+        inputs: &[Target],
+    ) -> HashOutTarget {
+        match self.target_as_constant(cond.target) {
+            Some(c) if c.is_zero() => return self.constant_hash(HashOut::ZERO),
+            Some(_) => return self.hash_n_to_hash_no_pad::<H>(inputs.to_vec()),
+            None => {}
+        }
+
+        let hash = self.hash_n_to_hash_no_pad::<H>(inputs.to_vec());
+        // Selecting between `x` and zero is just `cond * x`.
+        HashOutTarget {
+            elements: hash.elements.map(|x| self.mul(cond.target, x)),
+        }
+    }
+
+    /// Hashes each row of `matrix` with `hash_n_to_hash_no_pad`, giving one commitment per row.
+    pub fn hash_rows<H: AlgebraicHasher<F>>(
+        &mut self,
+        matrix: &[Vec<Target>],
+    ) -> Vec<HashOutTarget> {
+        matrix
+            .iter()
+            .map(|row| self.hash_n_to_hash_no_pad::<H>(row.clone()))
+            .collect()
+    }
+
+    /// In-circuit version of `hashing::hash_n_to_hash_no_pad_with_domain`.
+    pub fn hash_n_to_hash_no_pad_with_domain<H: AlgebraicHasher<F>>(
+        &mut self,
+        domain: u64,
+        inputs: Vec<Target>,
+    ) -> HashOutTarget {
+        HashOutTarget::from_vec(self.hash_n_to_m_no_pad_with_domain::<H>(domain, inputs, 4))
+    }
+
+    /// In-circuit version of `hashing::hash_n_to_hash_no_pad_with_iv`.
+    pub fn hash_n_to_hash_no_pad_with_iv<H: AlgebraicHasher<F>>(
+        &mut self,
+        iv: [Target; SPONGE_CAPACITY],
+        inputs: Vec<Target>,
+    ) -> HashOutTarget {
+        HashOutTarget::from_vec(self.hash_n_to_m_no_pad_with_iv::<H>(iv, inputs, 4))
+    }
+
+    /// In-circuit version of `hashing::compress_with_domain`.
+    pub fn two_to_one_with_domain<H: AlgebraicHasher<F>>(
+        &mut self,
+        domain: u64,
+        x: HashOutTarget,
+        y: HashOutTarget,
+    ) -> HashOutTarget {
+        let zero = self.zero();
+        let mut perm_inputs = [zero; SPONGE_WIDTH];
+        perm_inputs[SPONGE_RATE] = self.constant(domain_element(domain));
+        perm_inputs[..4].copy_from_slice(&x.elements);
+        perm_inputs[4..8].copy_from_slice(&y.elements);
+        HashOutTarget::from_vec(self.permute::<H>(perm_inputs)[..4].to_vec())
+    }
+
     pub fn hash_n_to_m_no_pad<H: AlgebraicHasher<F>>(
         &mut self,
         inputs: Vec<Target>,
         num_outputs: usize,
+    ) -> Vec<Target> {
+        self.hash_n_to_m_no_pad_with_domain::<H>(0, inputs, num_outputs)
+    }
+
+    /// In-circuit version of `hashing::hash_n_to_m_no_pad_with_domain`.
+    pub fn hash_n_to_m_no_pad_with_domain<H: AlgebraicHasher<F>>(
+        &mut self,
+        domain: u64,
+        inputs: Vec<Target>,
+        num_outputs: usize,
     ) -> Vec<Target> {
         let zero = self.zero();
+        let mut state = [zero; SPONGE_WIDTH];
+        state[SPONGE_RATE] = self.constant(domain_element(domain));
+        if inputs.is_empty() && domain != 0 {
+            state = self.permute::<H>(state);
+        }
+        self.sponge_no_pad::<H>(state, &inputs, num_outputs)
+    }
 
+    /// In-circuit version of `hashing::hash_n_to_m_no_pad_with_iv`. The IV may be any targets, not
+    /// just constants.
+    pub fn hash_n_to_m_no_pad_with_iv<H: AlgebraicHasher<F>>(
+        &mut self,
+        iv: [Target; SPONGE_CAPACITY],
+        inputs: Vec<Target>,
+        num_outputs: usize,
+    ) -> Vec<Target> {
+        let zero = self.zero();
         let mut state = [zero; SPONGE_WIDTH];
+        state[SPONGE_RATE..].copy_from_slice(&iv);
+        if inputs.is_empty() {
+            state = self.permute::<H>(state);
+        }
+        self.sponge_no_pad::<H>(state, &inputs, num_outputs)
+    }
 
+    /// Absorbs `inputs` into `state` and squeezes `num_outputs` elements.
+    fn sponge_no_pad<H: AlgebraicHasher<F>>(
+        &mut self,
+        mut state: [Target; SPONGE_WIDTH],
+        inputs: &[Target],
+        num_outputs: usize,
+    ) -> Vec<Target> {
         // Absorb all input chunks.
         for input_chunk in inputs.chunks(SPONGE_RATE) {
             // Overwrite the first r elements with the inputs. This differs from a standard sponge,
@@ -63,7 +178,26 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
 /// A one-way compression function which takes two ~256 bit inputs and returns a ~256 bit output.
 pub fn compress<F: RichField, P: PlonkyPermutation<F>>(x: HashOut<F>, y: HashOut<F>) -> HashOut<F> {
+    compress_with_domain::<F, P>(0, x, y)
+}
+
+/// Like `compress`, but with `domain` placed in the capacity, so that different domains give
+/// unrelated compression functions. Domain 0 is the same as `compress`.
+///
+/// Panics if `domain` is not less than the field order, or if it is nonzero and `P` is too narrow
+/// to leave room for it next to the two digests.
+pub fn compress_with_domain<F: RichField, P: PlonkyPermutation<F>>(
+    domain: u64,
+    x: HashOut<F>,
+    y: HashOut<F>,
+) -> HashOut<F> {
+    assert!(
+        domain == 0 || P::WIDTH > 8,
+        "A width-{} permutation has no room for a domain",
+        P::WIDTH
+    );
     let mut perm_inputs = [F::ZERO; SPONGE_WIDTH];
+    perm_inputs[SPONGE_RATE] = domain_element(domain);
     perm_inputs[..4].copy_from_slice(&x.elements);
     perm_inputs[4..8].copy_from_slice(&y.elements);
     HashOut {
@@ -72,7 +206,14 @@ pub fn compress<F: RichField, P: PlonkyPermutation<F>>(x: HashOut<F>, y: HashOut
 }
 
 /// Permutation that can be used in the sponge construction for an algebraic hash.
+///
+/// States are always `SPONGE_WIDTH` elements long. A narrower permutation only acts on the first
+/// `WIDTH` of them and leaves the rest untouched, so sponges over it absorb `RATE` elements at a
+/// time and keep their capacity in `RATE..WIDTH`.
 pub trait PlonkyPermutation<F: RichField> {
+    const WIDTH: usize = SPONGE_WIDTH;
+    const RATE: usize = SPONGE_RATE;
+
     fn permute(input: [F; SPONGE_WIDTH]) -> [F; SPONGE_WIDTH];
 }
 
@@ -81,11 +222,55 @@ pub trait PlonkyPermutation<F: RichField> {
 pub fn hash_n_to_m_no_pad<F: RichField, P: PlonkyPermutation<F>>(
     inputs: &[F],
     num_outputs: usize,
+) -> Vec<F> {
+    hash_n_to_m_no_pad_with_domain::<F, P>(0, inputs, num_outputs)
+}
+
+/// Like `hash_n_to_m_no_pad`, but with `domain` absorbed into the capacity before the message, so
+/// that different domains give unrelated hash functions. Domain 0 is the same as
+/// `hash_n_to_m_no_pad`. For a nonzero domain, an empty message is permuted once so that its
+/// digest depends on the domain too.
+///
+/// Panics if `domain` is not less than the field order.
+pub fn hash_n_to_m_no_pad_with_domain<F: RichField, P: PlonkyPermutation<F>>(
+    domain: u64,
+    inputs: &[F],
+    num_outputs: usize,
 ) -> Vec<F> {
     let mut state = [F::ZERO; SPONGE_WIDTH];
+    state[P::RATE] = domain_element(domain);
+    if inputs.is_empty() && domain != 0 {
+        state = P::permute(state);
+    }
+    sponge_no_pad::<F, P>(state, inputs, num_outputs)
+}
+
+/// Like `hash_n_to_m_no_pad`, but starting from `iv` in the capacity rather than zero. Inputs are
+/// only ever written to the rate, so the IV is never overwritten before the first permutation.
+/// The state is always permuted at least once, so the digest of an empty message depends on the
+/// IV too. An all-zero IV is therefore the same as `hash_n_to_m_no_pad` for non-empty messages
+/// only.
+pub fn hash_n_to_m_no_pad_with_iv<F: RichField, P: PlonkyPermutation<F>>(
+    iv: [F; SPONGE_CAPACITY],
+    inputs: &[F],
+    num_outputs: usize,
+) -> Vec<F> {
+    let mut state = [F::ZERO; SPONGE_WIDTH];
+    state[P::RATE..P::RATE + SPONGE_CAPACITY].copy_from_slice(&iv);
+    if inputs.is_empty() {
+        state = P::permute(state);
+    }
+    sponge_no_pad::<F, P>(state, inputs, num_outputs)
+}
 
+/// Absorbs `inputs` into `state` and squeezes `num_outputs` elements.
+fn sponge_no_pad<F: RichField, P: PlonkyPermutation<F>>(
+    mut state: [F; SPONGE_WIDTH],
+    inputs: &[F],
+    num_outputs: usize,
+) -> Vec<F> {
     // Absorb all input chunks.
-    for input_chunk in inputs.chunks(SPONGE_RATE) {
+    for input_chunk in inputs.chunks(P::RATE) {
         state[..input_chunk.len()].copy_from_slice(input_chunk);
         state = P::permute(state);
     }
@@ -93,7 +278,7 @@ pub fn hash_n_to_m_no_pad<F: RichField, P: PlonkyPermutation<F>>(
     // Squeeze until we have the desired number of outputs.
     let mut outputs = Vec::new();
     loop {
-        for &item in state.iter().take(SPONGE_RATE) {
+        for &item in state.iter().take(P::RATE) {
             outputs.push(item);
             if outputs.len() == num_outputs {
                 return outputs;
@@ -106,3 +291,330 @@ pub fn hash_n_to_m_no_pad<F: RichField, P: PlonkyPermutation<F>>(
 pub fn hash_n_to_hash_no_pad<F: RichField, P: PlonkyPermutation<F>>(inputs: &[F]) -> HashOut<F> {
     HashOut::from_vec(hash_n_to_m_no_pad::<F, P>(inputs, 4))
 }
+
+pub fn hash_n_to_hash_no_pad_with_domain<F: RichField, P: PlonkyPermutation<F>>(
+    domain: u64,
+    inputs: &[F],
+) -> HashOut<F> {
+    HashOut::from_vec(hash_n_to_m_no_pad_with_domain::<F, P>(domain, inputs, 4))
+}
+
+/// Hashes `inputs` to a `HashOut`, starting from `iv` in the sponge's capacity; see
+/// `hash_n_to_m_no_pad_with_iv`.
+pub fn hash_n_to_hash_no_pad_with_iv<F: RichField, P: PlonkyPermutation<F>>(
+    iv: [F; SPONGE_CAPACITY],
+    inputs: &[F],
+) -> HashOut<F> {
+    HashOut::from_vec(hash_n_to_m_no_pad_with_iv::<F, P>(iv, inputs, 4))
+}
+
+/// An incremental version of `hash_n_to_hash_no_pad`, for inputs which are not available all at
+/// once. Input is absorbed one full rate-sized chunk at a time, with any remainder buffered until
+/// the next `update` or `finalize`, so the digest doesn't depend on how the input was split.
+pub struct HasherState<F: RichField, P: PlonkyPermutation<F>> {
+    state: [F; SPONGE_WIDTH],
+    /// Input which hasn't been absorbed yet; always shorter than `P::RATE`.
+    buffer: Vec<F>,
+    _phantom: PhantomData<P>,
+}
+
+impl<F: RichField, P: PlonkyPermutation<F>> HasherState<F, P> {
+    pub fn new() -> Self {
+        Self {
+            state: [F::ZERO; SPONGE_WIDTH],
+            buffer: Vec::with_capacity(P::RATE),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn update(&mut self, mut inputs: &[F]) {
+        while !inputs.is_empty() {
+            let n = (P::RATE - self.buffer.len()).min(inputs.len());
+            self.buffer.extend_from_slice(&inputs[..n]);
+            inputs = &inputs[n..];
+            if self.buffer.len() == P::RATE {
+                self.absorb_buffer();
+            }
+        }
+    }
+
+    pub fn finalize(mut self) -> HashOut<F> {
+        if !self.buffer.is_empty() {
+            self.absorb_buffer();
+        }
+        HashOut {
+            elements: self.state[..4].try_into().unwrap(),
+        }
+    }
+
+    fn absorb_buffer(&mut self) {
+        // Overwrite mode, as in `hash_n_to_m_no_pad`.
+        self.state[..self.buffer.len()].copy_from_slice(&self.buffer);
+        self.state = P::permute(self.state);
+        self.buffer.clear();
+    }
+}
+
+impl<F: RichField, P: PlonkyPermutation<F>> Default for HasherState<F, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::{Field, Field64, Sample};
+    use crate::hash::hash_types::HashOut;
+    use crate::hash::hashing::{hash_n_to_hash_no_pad, HasherState, SPONGE_CAPACITY};
+    use crate::hash::poseidon::{PoseidonHash, PoseidonPermutation};
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{AlgebraicHasher, GenericConfig, Hasher, PoseidonGoldilocksConfig};
+    use crate::plonk::verifier::verify;
+
+    #[test]
+    fn test_conditional_hash() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let inputs = F::rand_vec(10);
+        let inputs_t = builder.add_virtual_targets(inputs.len());
+        for (&t, &x) in inputs_t.iter().zip(&inputs) {
+            pw.set_target(t, x);
+        }
+        let hash = PoseidonHash::hash_no_pad(&inputs);
+
+        // A constant `false` condition doesn't add any gates.
+        let num_gates = builder.num_gates();
+        let _false = builder._false();
+        let absent = builder.conditional_hash::<PoseidonHash>(_false, &inputs_t);
+        assert_eq!(builder.num_gates(), num_gates);
+        let zero_hash = builder.constant_hash(HashOut::ZERO);
+        builder.connect_hashes(absent, zero_hash);
+
+        let expected_t = builder.constant_hash(hash);
+        let _true = builder._true();
+        let present = builder.conditional_hash::<PoseidonHash>(_true, &inputs_t);
+        builder.connect_hashes(present, expected_t);
+
+        for (cond, expected) in [(true, hash), (false, HashOut::ZERO)] {
+            let cond_t = builder.add_virtual_bool_target_safe();
+            pw.set_bool_target(cond_t, cond);
+            let result = builder.conditional_hash::<PoseidonHash>(cond_t, &inputs_t);
+            let expected_t = builder.constant_hash(expected);
+            builder.connect_hashes(result, expected_t);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_hash_rows_circuit() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let matrix = (0..4).map(|_| F::rand_vec(10)).collect::<Vec<_>>();
+        let matrix_t = matrix
+            .iter()
+            .map(|row| {
+                let row_t = builder.add_virtual_targets(row.len());
+                for (&t, &x) in row_t.iter().zip(row) {
+                    pw.set_target(t, x);
+                }
+                row_t
+            })
+            .collect::<Vec<_>>();
+        let hashes_t = builder.hash_rows::<PoseidonHash>(&matrix_t);
+        for (h_t, h) in hashes_t.into_iter().zip(PoseidonHash::hash_rows(&matrix)) {
+            let expected_t = builder.constant_hash(h);
+            builder.connect_hashes(h_t, expected_t);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_domain_separation() {
+        type F = GoldilocksField;
+        type H = PoseidonHash;
+        const DOMAINS: [u64; 4] = [0, 1, 2, 0xdead_beef];
+
+        for len in [0, 1, 8, 9, 20] {
+            let inputs = F::rand_vec(len);
+            assert_eq!(
+                H::hash_no_pad_with_domain(0, &inputs),
+                H::hash_no_pad(&inputs)
+            );
+            let digests = DOMAINS.map(|domain| H::hash_no_pad_with_domain(domain, &inputs));
+            for i in 0..DOMAINS.len() {
+                for j in 0..i {
+                    assert_ne!(digests[i], digests[j]);
+                }
+            }
+        }
+
+        let (left, right) = (HashOut::<F>::rand(), HashOut::<F>::rand());
+        assert_eq!(
+            H::two_to_one_with_domain(0, left, right),
+            H::two_to_one(left, right)
+        );
+        let digests = DOMAINS.map(|domain| H::two_to_one_with_domain(domain, left, right));
+        for i in 0..DOMAINS.len() {
+            for j in 0..i {
+                assert_ne!(digests[i], digests[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_domain_boundary() {
+        type F = GoldilocksField;
+        type H = PoseidonHash;
+
+        let inputs = F::rand_vec(3);
+        let (left, right) = (HashOut::<F>::rand(), HashOut::<F>::rand());
+        let max_domain = F::ORDER - 1;
+        assert_ne!(
+            H::hash_no_pad_with_domain(max_domain, &inputs),
+            H::hash_no_pad(&inputs)
+        );
+        assert_ne!(
+            H::two_to_one_with_domain(max_domain, left, right),
+            H::two_to_one(left, right)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "is not less than the field order")]
+    fn test_domain_equal_to_order() {
+        type F = GoldilocksField;
+        PoseidonHash::hash_no_pad_with_domain(F::ORDER, &F::rand_vec(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "is not less than the field order")]
+    fn test_compress_domain_equal_to_order() {
+        type F = GoldilocksField;
+        PoseidonHash::two_to_one_with_domain(F::ORDER, HashOut::<F>::ZERO, HashOut::<F>::ZERO);
+    }
+
+    #[test]
+    fn test_domain_separation_circuit() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = PoseidonHash;
+        const DOMAIN: u64 = 7;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let inputs = F::rand_vec(10);
+        let inputs_t = builder.add_virtual_targets(inputs.len());
+        for (&t, &x) in inputs_t.iter().zip(&inputs) {
+            pw.set_target(t, x);
+        }
+        let hash_t = builder.hash_n_to_hash_no_pad_with_domain::<H>(DOMAIN, inputs_t);
+        let expected_t = builder.constant_hash(H::hash_no_pad_with_domain(DOMAIN, &inputs));
+        builder.connect_hashes(hash_t, expected_t);
+
+        let empty_hash_t = builder.hash_n_to_hash_no_pad_with_domain::<H>(DOMAIN, vec![]);
+        let expected_t = builder.constant_hash(H::hash_no_pad_with_domain(DOMAIN, &[]));
+        builder.connect_hashes(empty_hash_t, expected_t);
+
+        let (left, right) = (HashOut::rand(), HashOut::rand());
+        let left_t = builder.add_virtual_hash();
+        let right_t = builder.add_virtual_hash();
+        pw.set_hash_target(left_t, left);
+        pw.set_hash_target(right_t, right);
+        let compressed_t = builder.two_to_one_with_domain::<H>(DOMAIN, left_t, right_t);
+        let expected_t = builder.constant_hash(H::two_to_one_with_domain(DOMAIN, left, right));
+        builder.connect_hashes(compressed_t, expected_t);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_hash_with_iv() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = PoseidonHash;
+
+        let inputs = F::rand_vec(10);
+        let iv: [F; SPONGE_CAPACITY] = F::rand_array();
+        assert_eq!(
+            H::hash_no_pad_with_iv([F::ZERO; SPONGE_CAPACITY], &inputs),
+            H::hash_no_pad(&inputs)
+        );
+        let hash = H::hash_no_pad_with_iv(iv, &inputs);
+        assert_ne!(hash, H::hash_no_pad(&inputs));
+        // The IV matters even for an empty message.
+        assert_ne!(
+            H::hash_no_pad_with_iv(iv, &[]),
+            H::hash_no_pad_with_iv([F::ZERO; SPONGE_CAPACITY], &[])
+        );
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let inputs_t = builder.add_virtual_targets(inputs.len());
+        for (&t, &x) in inputs_t.iter().zip(&inputs) {
+            pw.set_target(t, x);
+        }
+        let iv_t = builder.add_virtual_target_arr::<SPONGE_CAPACITY>();
+        pw.set_target_arr(iv_t, iv);
+        let hash_t = builder.hash_n_to_hash_no_pad_with_iv::<H>(iv_t, inputs_t);
+        let expected_t = builder.constant_hash(hash);
+        builder.connect_hashes(hash_t, expected_t);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_hasher_state() {
+        type F = GoldilocksField;
+        type P = PoseidonPermutation;
+
+        for len in [0, 1, 7, 8, 9, 16, 30] {
+            let inputs = F::rand_vec(len);
+            let expected = hash_n_to_hash_no_pad::<F, P>(&inputs);
+
+            let mut state = HasherState::<F, P>::new();
+            state.update(&inputs);
+            assert_eq!(state.finalize(), expected);
+
+            for split in 0..=len {
+                let (left, right) = inputs.split_at(split);
+                let mut state = HasherState::<F, P>::new();
+                state.update(left);
+                state.update(&[]);
+                // Also split the right half into small, unaligned pieces.
+                for piece in right.chunks(3) {
+                    state.update(piece);
+                }
+                assert_eq!(state.finalize(), expected);
+            }
+        }
+    }
+}
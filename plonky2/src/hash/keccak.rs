@@ -5,9 +5,13 @@ use core::mem::size_of;
 
 use itertools::Itertools;
 use keccak_hash::keccak;
+use tiny_keccak::keccakf;
 
+use crate::field::extension::Extendable;
 use crate::hash::hash_types::{BytesHash, RichField};
 use crate::hash::hashing::{PlonkyPermutation, SPONGE_WIDTH};
+use crate::iop::target::BoolTarget;
+use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::Hasher;
 use crate::util::serialization::Write;
 
@@ -68,7 +72,199 @@ impl<F: RichField, const N: usize> Hasher<F> for KeccakHash<N> {
         BytesHash(arr)
     }
 
+    /// Always hashes, even inputs which would fit in a digest. Leaves of a Keccak Merkle tree are
+    /// then `keccak(bytes)` whatever their length, as in the Merkle trees checked by Ethereum
+    /// contracts, rather than short leaves being embedded as zero-padded bytes.
+    fn hash_or_noop(inputs: &[F]) -> Self::Hash {
+        Self::hash_no_pad(inputs)
+    }
+
     fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash {
+        let hash_bytes = keccak_two_to_one(&left.0, &right.0);
+        let mut arr = [0; N];
+        arr.copy_from_slice(&hash_bytes[..N]);
+        BytesHash(arr)
+    }
+}
+
+/// The rate of Keccak-256, in bytes.
+const KECCAK_256_RATE: usize = 136;
+
+/// Computes the Keccak-256 hash of `left || right`. Two digests always fit in a single block, so
+/// this pads them in place and applies Keccak-f once, rather than going through a general sponge.
+fn keccak_two_to_one(left: &[u8], right: &[u8]) -> [u8; 32] {
+    let len = left.len() + right.len();
+    assert!(len < KECCAK_256_RATE, "Inputs do not fit in a single block");
+
+    let mut block = [0u8; KECCAK_256_RATE];
+    block[..left.len()].copy_from_slice(left);
+    block[left.len()..len].copy_from_slice(right);
+    // Keccak's pad10*1 rule, with the original Keccak (not SHA-3) domain separation.
+    block[len] ^= 0x01;
+    block[KECCAK_256_RATE - 1] ^= 0x80;
+
+    let mut state = [0u64; 25];
+    for (lane, bytes) in state.iter_mut().zip(block.chunks_exact(size_of::<u64>())) {
+        *lane = u64::from_le_bytes(bytes.try_into().unwrap());
+    }
+    keccakf(&mut state);
+
+    let mut output = [0u8; 32];
+    for (bytes, lane) in output.chunks_exact_mut(size_of::<u64>()).zip(state) {
+        bytes.copy_from_slice(&lane.to_le_bytes());
+    }
+    output
+}
+
+/// The number of rounds in Keccak-f[1600].
+pub const KECCAK_ROUNDS: usize = 24;
+
+/// The round constants added to lane `(0, 0)` by the iota step.
+const KECCAK_ROUND_CONSTANTS: [u64; KECCAK_ROUNDS] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// The rotation applied to lane `(x, y)` by the rho step, indexed by `[x][y]`.
+const KECCAK_RHO_OFFSETS: [[usize; 5]; 5] = [
+    [0, 36, 3, 41, 18],
+    [1, 44, 10, 45, 2],
+    [62, 6, 43, 15, 61],
+    [28, 55, 25, 21, 56],
+    [27, 20, 39, 8, 14],
+];
+
+/// Applies round `round` of Keccak-f[1600] to `state`, whose lane `(x, y)` is at index `x + 5y`.
+/// Applying rounds `0..KECCAK_ROUNDS` in order is the full Keccak-f permutation.
+pub fn keccak_round(state: &mut [u64; 25], round: usize) {
+    // Theta.
+    let c: [u64; 5] = core::array::from_fn(|x| (0..5).fold(0, |acc, y| acc ^ state[x + 5 * y]));
+    for x in 0..5 {
+        let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+        for y in 0..5 {
+            state[x + 5 * y] ^= d;
+        }
+    }
+
+    // Rho and pi.
+    let mut b = [0; 25];
+    for x in 0..5 {
+        for y in 0..5 {
+            b[y + 5 * ((2 * x + 3 * y) % 5)] =
+                state[x + 5 * y].rotate_left(KECCAK_RHO_OFFSETS[x][y] as u32);
+        }
+    }
+
+    // Chi.
+    for x in 0..5 {
+        for y in 0..5 {
+            state[x + 5 * y] = b[x + 5 * y] ^ (!b[(x + 1) % 5 + 5 * y] & b[(x + 2) % 5 + 5 * y]);
+        }
+    }
+
+    // Iota.
+    state[0] ^= KECCAK_ROUND_CONSTANTS[round];
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// In-circuit version of `keccak_round`. Each lane is given by its 64 little-endian bits, which
+    /// are assumed to be boolean; the output bits are then boolean too. This is meant as a building
+    /// block for custom sponge constructions, so a full permutation is just this applied for each
+    /// of the `KECCAK_ROUNDS` rounds.
+    pub fn keccak_round(
+        &mut self,
+        state: &[[BoolTarget; 64]; 25],
+        round: usize,
+    ) -> [[BoolTarget; 64]; 25] {
+        // Theta.
+        let c: [Vec<BoolTarget>; 5] = core::array::from_fn(|x| {
+            (1..5).fold(state[x].to_vec(), |acc, y| {
+                self.rot_xor(&acc, 0, &state[x + 5 * y])
+            })
+        });
+        let d: [Vec<BoolTarget>; 5] =
+            core::array::from_fn(|x| self.rot_xor(&c[(x + 1) % 5], 1, &c[(x + 4) % 5]));
+        let a: [Vec<BoolTarget>; 25] =
+            core::array::from_fn(|i| self.rot_xor(&state[i], 0, &d[i % 5]));
+
+        // Rho and pi only move bits around, so they cost nothing.
+        let mut b = [[self._false(); 64]; 25];
+        for x in 0..5 {
+            for y in 0..5 {
+                let offset = KECCAK_RHO_OFFSETS[x][y];
+                let lane = &mut b[y + 5 * ((2 * x + 3 * y) % 5)];
+                for (i, bit) in lane.iter_mut().enumerate() {
+                    *bit = a[x + 5 * y][(i + 64 - offset) % 64];
+                }
+            }
+        }
+
+        // Chi.
+        let mut out: [[BoolTarget; 64]; 25] = core::array::from_fn(|i| {
+            let (x, y) = (i % 5, i / 5);
+            core::array::from_fn(|j| {
+                let b0 = b[i][j].target;
+                let b1 = b[(x + 1) % 5 + 5 * y][j].target;
+                let b2 = b[(x + 2) % 5 + 5 * y][j].target;
+                // (NOT b1) AND b2 = b2 - b1 b2
+                let t = self.arithmetic(F::NEG_ONE, F::ONE, b1, b2, b2);
+                // b0 XOR t = b0 + t - 2 b0 t
+                let res_minus_t = self.arithmetic(-F::TWO, F::ONE, b0, t, b0);
+                BoolTarget::new_unsafe(self.add(res_minus_t, t))
+            })
+        });
+
+        // Iota flips the bits of lane (0, 0) where the round constant is set.
+        let rc = KECCAK_ROUND_CONSTANTS[round];
+        for (j, bit) in out[0].iter_mut().enumerate() {
+            if (rc >> j) & 1 == 1 {
+                *bit = self.not(*bit);
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
+    use super::*;
+    use crate::field::goldilocks_field::GoldilocksField as F;
+    use crate::field::types::{Field, PrimeField64, Sample};
+    use crate::hash::merkle_proofs::verify_merkle_proof_to_cap;
+    use crate::hash::merkle_tree::MerkleTree;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    /// Hashes `left || right` with the general Keccak-256 sponge.
+    fn two_to_one_generic<const N: usize>(left: BytesHash<N>, right: BytesHash<N>) -> BytesHash<N> {
         let mut v = vec![0; N * 2];
         v[0..N].copy_from_slice(&left.0);
         v[N..].copy_from_slice(&right.0);
@@ -76,4 +272,102 @@ impl<F: RichField, const N: usize> Hasher<F> for KeccakHash<N> {
         arr.copy_from_slice(&keccak(v).0[..N]);
         BytesHash(arr)
     }
+
+    fn check_two_to_one<const N: usize>() {
+        for _ in 0..100 {
+            let left = BytesHash::<N>::rand();
+            let right = BytesHash::<N>::rand();
+            assert_eq!(
+                <KeccakHash<N> as Hasher<F>>::two_to_one(left, right),
+                two_to_one_generic(left, right)
+            );
+        }
+    }
+
+    #[test]
+    fn test_two_to_one() {
+        check_two_to_one::<25>();
+        check_two_to_one::<32>();
+    }
+
+    #[test]
+    fn test_merkle_tree_matches_reference() -> Result<()> {
+        type H = KeccakHash<32>;
+
+        // Short leaves included, which fit in a digest but must still be hashed.
+        let leaves = vec![
+            vec![F::from_canonical_u64(1)],
+            vec![F::from_canonical_u64(2), F::from_canonical_u64(3)],
+            vec![F::NEG_ONE; 4],
+            (10..20).map(F::from_canonical_u64).collect(),
+        ];
+        let tree = MerkleTree::<F, H>::new(leaves.clone(), 0);
+
+        let leaf_hash = |leaf: &[F]| {
+            let bytes = leaf
+                .iter()
+                .flat_map(|x| x.to_canonical_u64().to_le_bytes())
+                .collect::<Vec<_>>();
+            keccak(bytes).0
+        };
+        let node_hash = |left: [u8; 32], right: [u8; 32]| keccak([left, right].concat()).0;
+        let expected_root = node_hash(
+            node_hash(leaf_hash(&leaves[0]), leaf_hash(&leaves[1])),
+            node_hash(leaf_hash(&leaves[2]), leaf_hash(&leaves[3])),
+        );
+        assert_eq!(tree.cap.0, vec![BytesHash(expected_root)]);
+
+        for (i, leaf) in leaves.into_iter().enumerate() {
+            verify_merkle_proof_to_cap(leaf, i, &tree.cap, &tree.prove(i))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_keccak_rounds_match_keccakf() {
+        let input: [u64; 25] = OsRng.gen();
+
+        let mut expected = input;
+        keccakf(&mut expected);
+        let mut state = input;
+        for round in 0..KECCAK_ROUNDS {
+            keccak_round(&mut state, round);
+        }
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn test_keccak_round_circuit() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<<C as GenericConfig<D>>::F, D>::new(config);
+
+        // Chain all the rounds, so that each round constant is applied to a random-looking state.
+        let mut state: [u64; 25] = OsRng.gen();
+        let mut state_t: [[BoolTarget; 64]; 25] = core::array::from_fn(|_| {
+            core::array::from_fn(|_| builder.add_virtual_bool_target_safe())
+        });
+        for (&lane, lane_t) in state.iter().zip(&state_t) {
+            for (j, &bit) in lane_t.iter().enumerate() {
+                pw.set_bool_target(bit, (lane >> j) & 1 == 1);
+            }
+        }
+
+        for round in 0..KECCAK_ROUNDS {
+            keccak_round(&mut state, round);
+            state_t = builder.keccak_round(&state_t, round);
+            for (&lane, lane_t) in state.iter().zip(&state_t) {
+                for (j, bit) in lane_t.iter().enumerate() {
+                    let expected = builder.constant_bool((lane >> j) & 1 == 1);
+                    builder.connect(bit.target, expected.target);
+                }
+            }
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
 }
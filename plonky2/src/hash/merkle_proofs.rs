@@ -1,18 +1,20 @@
-use alloc::vec;
 use alloc::vec::Vec;
+use alloc::{format, vec};
 
-use anyhow::{ensure, Result};
+use anyhow::{ensure, Context, Result};
 use itertools::Itertools;
+use plonky2_maybe_rayon::*;
 use serde::{Deserialize, Serialize};
 
 use crate::field::extension::Extendable;
-use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::hashing::SPONGE_WIDTH;
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::VerifierCircuitTarget;
 use crate::plonk::config::{AlgebraicHasher, Hasher};
+use crate::util::log2_strict;
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(bound = "")]
@@ -56,9 +58,37 @@ pub fn verify_merkle_proof_to_cap<F: RichField, H: Hasher<F>>(
     leaf_index: usize,
     merkle_cap: &MerkleCap<F, H>,
     proof: &MerkleProof<F, H>,
+) -> Result<()> {
+    verify_merkle_proof_to_cap_inner(&leaf_data, leaf_index, merkle_cap, proof)
+}
+
+/// Verifies several Merkle proofs against the same cap, in parallel. Each item consists of a leaf
+/// index, the leaf data and the proof. If any proof is invalid, the error names the first such
+/// item.
+pub fn verify_batch<F: RichField, H: Hasher<F>>(
+    merkle_cap: &MerkleCap<F, H>,
+    items: &[(usize, Vec<F>, MerkleProof<F, H>)],
+) -> Result<()> {
+    let results: Vec<Result<()>> = items
+        .par_iter()
+        .map(|(leaf_index, leaf_data, proof)| {
+            verify_merkle_proof_to_cap_inner(leaf_data, *leaf_index, merkle_cap, proof)
+        })
+        .collect();
+    for (i, result) in results.into_iter().enumerate() {
+        result.with_context(|| format!("Merkle proof {i} of the batch is invalid"))?;
+    }
+    Ok(())
+}
+
+fn verify_merkle_proof_to_cap_inner<F: RichField, H: Hasher<F>>(
+    leaf_data: &[F],
+    leaf_index: usize,
+    merkle_cap: &MerkleCap<F, H>,
+    proof: &MerkleProof<F, H>,
 ) -> Result<()> {
     let mut index = leaf_index;
-    let mut current_digest = H::hash_or_noop(&leaf_data);
+    let mut current_digest = H::hash_or_noop(leaf_data);
     for &sibling_digest in proof.siblings.iter() {
         let bit = index & 1;
         index >>= 1;
@@ -78,7 +108,7 @@ pub fn verify_merkle_proof_to_cap<F: RichField, H: Hasher<F>>(
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// Verifies that the given leaf data is present at the given index in the Merkle tree with the
-    /// given root. The index is given by its little-endian bits.
+    /// given root. The index is given by its little-endian bits, one per sibling in the proof.
     pub fn verify_merkle_proof<H: AlgebraicHasher<F>>(
         &mut self,
         leaf_data: Vec<Target>,
@@ -92,6 +122,11 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
     /// Verifies that the given leaf data is present at the given index in the Merkle tree with the
     /// given cap. The index is given by its little-endian bits.
+    ///
+    /// There must be exactly `proof.siblings.len() + cap_height` index bits, where the cap has
+    /// `2^cap_height` entries. The low bits select the path through the tree, while the top
+    /// `cap_height` bits select the cap entry, which is routed through `random_access`. A cap
+    /// height of zero is the single root case.
     pub fn verify_merkle_proof_to_cap<H: AlgebraicHasher<F>>(
         &mut self,
         leaf_data: Vec<Target>,
@@ -99,7 +134,15 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         merkle_cap: &MerkleCapTarget,
         proof: &MerkleProofTarget,
     ) {
-        let cap_index = self.le_sum(leaf_index_bits[proof.siblings.len()..].iter().copied());
+        let cap_height = log2_strict(merkle_cap.0.len());
+        assert_eq!(
+            leaf_index_bits.len(),
+            proof.siblings.len() + cap_height,
+            "Expected {} path bits and {} cap index bits",
+            proof.siblings.len(),
+            cap_height
+        );
+        let cap_index = self.le_sum(leaf_index_bits[proof.siblings.len()..].iter());
         self.verify_merkle_proof_to_cap_with_cap_index::<H>(
             leaf_data,
             leaf_index_bits,
@@ -119,10 +162,40 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         merkle_cap: &MerkleCapTarget,
         proof: &MerkleProofTarget,
     ) {
+        let state =
+            self.compute_merkle_root_from_leaf::<H>(&leaf_data, leaf_index_bits, &proof.siblings);
+
+        for i in 0..4 {
+            let result = self.random_access(
+                cap_index,
+                merkle_cap.0.iter().map(|h| h.elements[i]).collect(),
+            );
+            self.connect(result, state.elements[i]);
+        }
+    }
+
+    /// Hashes the given leaf data, then folds it up the authentication path given by `siblings`,
+    /// returning the resulting root. At each layer, the corresponding little-endian bit of
+    /// `leaf_index_bits` tells whether the current digest is the right child, in which case it is
+    /// compressed as `two_to_one(sibling, digest)` rather than `two_to_one(digest, sibling)`.
+    ///
+    /// Only the first `siblings.len()` bits are used; any remaining bits are ignored.
+    pub fn compute_merkle_root_from_leaf<H: AlgebraicHasher<F>>(
+        &mut self,
+        leaf_data: &[Target],
+        leaf_index_bits: &[BoolTarget],
+        siblings: &[HashOutTarget],
+    ) -> HashOutTarget {
+        assert!(
+            leaf_index_bits.len() >= siblings.len(),
+            "Expected at least {} index bits, got {}",
+            siblings.len(),
+            leaf_index_bits.len()
+        );
         let zero = self.zero();
-        let mut state: HashOutTarget = self.hash_or_noop::<H>(leaf_data);
+        let mut state: HashOutTarget = self.hash_or_noop::<H>(leaf_data.to_vec());
 
-        for (&bit, &sibling) in leaf_index_bits.iter().zip(&proof.siblings) {
+        for (&bit, &sibling) in leaf_index_bits.iter().zip(siblings) {
             let mut perm_inputs = [zero; SPONGE_WIDTH];
             perm_inputs[..4].copy_from_slice(&state.elements);
             perm_inputs[4..8].copy_from_slice(&sibling.elements);
@@ -133,13 +206,41 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             };
         }
 
-        for i in 0..4 {
-            let result = self.random_access(
-                cap_index,
-                merkle_cap.0.iter().map(|h| h.elements[i]).collect(),
-            );
-            self.connect(result, state.elements[i]);
+        state
+    }
+
+    /// Folds `hashes` into a single digest with a balanced binary tree of `two_to_one`
+    /// compressions, i.e. computes the root of the Merkle tree whose leaf digests are `hashes`.
+    ///
+    /// If the number of hashes isn't a power of two, the list is padded with zero hashes up to the
+    /// next power of two. A single hash is returned as is.
+    pub fn fold_hashes<H: AlgebraicHasher<F>>(
+        &mut self,
+        hashes: &[HashOutTarget],
+    ) -> HashOutTarget {
+        assert!(!hashes.is_empty(), "Cannot fold an empty list of hashes");
+        let zero = self.zero();
+        let zero_hash = HashOutTarget {
+            elements: [zero; 4],
+        };
+
+        let mut layer = hashes.to_vec();
+        layer.resize(hashes.len().next_power_of_two(), zero_hash);
+        while layer.len() > 1 {
+            layer = layer
+                .chunks_exact(2)
+                .map(|pair| {
+                    let mut perm_inputs = [zero; SPONGE_WIDTH];
+                    perm_inputs[..4].copy_from_slice(&pair[0].elements);
+                    perm_inputs[4..8].copy_from_slice(&pair[1].elements);
+                    let perm_outs = self.permute::<H>(perm_inputs);
+                    HashOutTarget {
+                        elements: perm_outs[0..4].try_into().unwrap(),
+                    }
+                })
+                .collect();
         }
+        layer[0]
     }
 
     pub fn connect_hashes(&mut self, x: HashOutTarget, y: HashOutTarget) {
@@ -148,6 +249,22 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
+    /// Connects `x` to the constant hash `c`, routing each limb through the constant cache.
+    pub fn connect_hashes_constant(&mut self, x: HashOutTarget, c: HashOut<F>) {
+        let c = self.constant_hash(c);
+        self.connect_hashes(x, c);
+    }
+
+    /// Asserts that `h` equals the known hash `c`. Unlike `connect_hashes_constant`, each limb of
+    /// `h` is copied straight into a `ConstantGate` slot holding the limb of `c`, so no target or
+    /// generator carries `c`. It takes as many `ConstantGate` slots, but four fewer targets and
+    /// generators.
+    pub fn assert_hash_equals_const(&mut self, h: HashOutTarget, c: HashOut<F>) {
+        for (x, c) in h.elements.into_iter().zip(c.elements) {
+            self.assert_equal_constant_slot(x, c);
+        }
+    }
+
     pub fn connect_merkle_caps(&mut self, x: &MerkleCapTarget, y: &MerkleCapTarget) {
         for (h0, h1) in x.0.iter().zip_eq(&y.0) {
             self.connect_hashes(*h0, *h1);
@@ -167,7 +284,7 @@ mod tests {
     use rand::Rng;
 
     use super::*;
-    use crate::field::types::Field;
+    use crate::field::types::{Field, Sample};
     use crate::hash::merkle_tree::MerkleTree;
     use crate::iop::witness::{PartialWitness, WitnessWrite};
     use crate::plonk::circuit_builder::CircuitBuilder;
@@ -223,4 +340,291 @@ mod tests {
 
         verify(proof, &data.verifier_only, &data.common)
     }
+
+    /// Verifies a Merkle proof in a circuit, against a cap of the given height. If
+    /// `tamper_sibling` is set, one of the siblings is replaced by a random hash.
+    fn prove_merkle_proof_to_cap(cap_height: usize, tamper_sibling: bool) -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let log_n = 6;
+        let n = 1 << log_n;
+        let tree = MerkleTree::<F, H>::new(random_data::<F>(n, 7), cap_height);
+        let i: usize = OsRng.gen_range(0..n);
+        let mut proof = tree.prove(i);
+        assert_eq!(proof.len(), log_n - cap_height);
+        if tamper_sibling {
+            let j = OsRng.gen_range(0..proof.len());
+            proof.siblings[j] = HashOut::rand();
+        }
+
+        let proof_t = MerkleProofTarget {
+            siblings: builder.add_virtual_hashes(proof.len()),
+        };
+        for (&s_t, &s) in proof_t.siblings.iter().zip(&proof.siblings) {
+            pw.set_hash_target(s_t, s);
+        }
+        let cap_t = builder.add_virtual_cap(cap_height);
+        pw.set_cap_target(&cap_t, &tree.cap);
+
+        let i_t = builder.add_virtual_target();
+        pw.set_target(i_t, F::from_canonical_usize(i));
+        let i_bits = builder.split_le(i_t, log_n);
+
+        let leaf = builder.add_virtual_targets(tree.leaves[i].len());
+        for (&t, &x) in leaf.iter().zip(&tree.leaves[i]) {
+            pw.set_target(t, x);
+        }
+
+        builder.verify_merkle_proof_to_cap::<<C as GenericConfig<D>>::InnerHasher>(
+            leaf, &i_bits, &cap_t, &proof_t,
+        );
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_merkle_proof_to_cap_heights() -> Result<()> {
+        for cap_height in [0, 2, 4] {
+            prove_merkle_proof_to_cap(cap_height, false)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "is unsatisfied")]
+    fn test_merkle_proof_to_cap_wrong_sibling_cap_height_0() {
+        prove_merkle_proof_to_cap(0, true).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "is unsatisfied")]
+    fn test_merkle_proof_to_cap_wrong_sibling_cap_height_2() {
+        prove_merkle_proof_to_cap(2, true).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "is unsatisfied")]
+    fn test_merkle_proof_to_cap_wrong_sibling_cap_height_4() {
+        prove_merkle_proof_to_cap(4, true).unwrap();
+    }
+
+    #[test]
+    fn test_verify_batch() -> Result<()> {
+        type H = <PoseidonGoldilocksConfig as GenericConfig<2>>::Hasher;
+        type F = <PoseidonGoldilocksConfig as GenericConfig<2>>::F;
+
+        let log_n = 6;
+        let n = 1 << log_n;
+        let tree = MerkleTree::<F, H>::new(random_data::<F>(n, 7), 2);
+        let mut items = (0..10)
+            .map(|_| {
+                let i = OsRng.gen_range(0..n);
+                (i, tree.leaves[i].clone(), tree.prove(i))
+            })
+            .collect::<Vec<_>>();
+        verify_batch(&tree.cap, &items)?;
+
+        items[3].1[0] += F::ONE;
+        items[7].1[0] += F::ONE;
+        let err = verify_batch(&tree.cap, &items).unwrap_err();
+        assert_eq!(err.to_string(), "Merkle proof 3 of the batch is invalid");
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_merkle_root_from_leaf() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let log_n = 6;
+        let n = 1 << log_n;
+        let leaves = random_data::<F>(n, 5);
+        let tree = MerkleTree::<F, H>::new(leaves, 0);
+        let i: usize = OsRng.gen_range(0..n);
+        let proof = tree.prove(i);
+        let root = tree.cap.0[0];
+        verify_merkle_proof(tree.leaves[i].clone(), i, root, &proof)?;
+
+        let siblings = builder.add_virtual_hashes(proof.siblings.len());
+        for (&s_t, &s) in siblings.iter().zip(&proof.siblings) {
+            pw.set_hash_target(s_t, s);
+        }
+        let leaf = builder.add_virtual_targets(tree.leaves[i].len());
+        for (&t, &x) in leaf.iter().zip(&tree.leaves[i]) {
+            pw.set_target(t, x);
+        }
+        let i_t = builder.add_virtual_target();
+        pw.set_target(i_t, F::from_canonical_usize(i));
+        let i_bits = builder.split_le(i_t, log_n);
+
+        let root_t = builder.compute_merkle_root_from_leaf::<<C as GenericConfig<D>>::InnerHasher>(
+            &leaf, &i_bits, &siblings,
+        );
+        let expected_root_t = builder.constant_hash(root);
+        builder.connect_hashes(root_t, expected_root_t);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    /// Proves knowledge of a preimage of a known hash, perturbed if `mismatch` is set.
+    fn prove_hash_equals_const(mismatch: bool) -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::InnerHasher;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let preimage = F::rand_vec(10);
+        let mut c = H::hash_no_pad(&preimage);
+        if mismatch {
+            c.elements[2] += F::ONE;
+        }
+
+        let preimage_t = builder.add_virtual_targets(preimage.len());
+        for (&t, &x) in preimage_t.iter().zip(&preimage) {
+            pw.set_target(t, x);
+        }
+        let h = builder.hash_n_to_hash_no_pad::<H>(preimage_t);
+        builder.assert_hash_equals_const(h, c);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_assert_hash_equals_const() -> Result<()> {
+        prove_hash_equals_const(false)
+    }
+
+    #[test]
+    fn test_assert_hash_equals_const_mismatch() {
+        // The witness is consistent, so a proof is produced, but the `ConstantGate` slot's
+        // constraint fails and the proof doesn't verify.
+        assert!(prove_hash_equals_const(true).is_err());
+    }
+
+    #[test]
+    fn test_assert_hash_equals_const_cost() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::InnerHasher;
+        let c = HashOut::<F>::rand();
+        let build = |bind: fn(&mut CircuitBuilder<F, D>, HashOutTarget, HashOut<F>)| {
+            let mut builder =
+                CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+            let preimage_t = builder.add_virtual_targets(10);
+            let h = builder.hash_n_to_hash_no_pad::<H>(preimage_t);
+            bind(&mut builder, h, c);
+            builder.build::<C>()
+        };
+
+        let baseline = build(CircuitBuilder::connect_hashes_constant);
+        let slots = build(CircuitBuilder::assert_hash_equals_const);
+        let (baseline_stats, slots_stats) = (baseline.stats(), slots.stats());
+        assert_eq!(slots_stats.gate_counts, baseline_stats.gate_counts);
+        assert_eq!(
+            slots_stats.num_routed_wires_used,
+            baseline_stats.num_routed_wires_used
+        );
+        assert_eq!(
+            slots_stats.num_generators + 4,
+            baseline_stats.num_generators
+        );
+        assert_eq!(
+            slots.prover_only.representative_map.len() + 4,
+            baseline.prover_only.representative_map.len()
+        );
+    }
+
+    /// Sets a virtual hash to a random value and connects it to a constant hash, which differs from
+    /// the witness if `mismatch` is set.
+    fn prove_connect_hashes_constant(mismatch: bool) -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let h = HashOut::<F>::rand();
+        let mut c = h;
+        if mismatch {
+            c.elements[0] += F::ONE;
+        }
+
+        let h_t = builder.add_virtual_hash();
+        pw.set_hash_target(h_t, h);
+        builder.connect_hashes_constant(h_t, c);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_connect_hashes_constant() -> Result<()> {
+        prove_connect_hashes_constant(false)
+    }
+
+    #[test]
+    #[should_panic(expected = "is unsatisfied")]
+    fn test_connect_hashes_constant_mismatch() {
+        prove_connect_hashes_constant(true).unwrap();
+    }
+
+    #[test]
+    fn test_fold_hashes() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::InnerHasher;
+        let config = CircuitConfig::standard_recursion_config();
+        let pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        for n in [1usize, 5, 8] {
+            let hashes = (0..n).map(|_| HashOut::<F>::rand()).collect::<Vec<_>>();
+
+            // Digests fit in a leaf without being hashed, so they serve directly as leaf digests.
+            let mut leaves = hashes
+                .iter()
+                .map(|h| h.elements.to_vec())
+                .collect::<Vec<_>>();
+            leaves.resize(n.next_power_of_two(), vec![F::ZERO; 4]);
+            let tree = MerkleTree::<F, H>::new(leaves, 0);
+
+            let hashes_t = hashes
+                .iter()
+                .map(|&h| builder.constant_hash(h))
+                .collect::<Vec<_>>();
+            let root_t = builder.fold_hashes::<H>(&hashes_t);
+            let expected_t = builder.constant_hash(tree.cap.0[0]);
+            builder.connect_hashes(root_t, expected_t);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        verify(proof, &data.verifier_only, &data.common)
+    }
 }
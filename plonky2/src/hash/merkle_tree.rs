@@ -1,3 +1,4 @@
+use alloc::vec;
 use alloc::vec::Vec;
 use core::mem::MaybeUninit;
 use core::slice;
@@ -48,6 +49,9 @@ pub struct MerkleTree<F: RichField, H: Hasher<F>> {
     /// left_child_digest and right_child_digest are H::Hash and left_child_subtree and
     /// right_child_subtree recurse. Observe that the digest of a node is stored by its _parent_.
     /// Consequently, the digests of the roots are not stored here (they can be found in `cap`).
+    ///
+    /// Empty if the tree was built by `new_with_storage` without storing its digests, in which case
+    /// they are recomputed as needed.
     pub digests: Vec<H::Hash>,
 
     /// The Merkle cap.
@@ -66,13 +70,20 @@ fn capacity_up_to_mut<T>(v: &mut Vec<T>, len: usize) -> &mut [MaybeUninit<T>] {
     }
 }
 
-fn fill_subtree<F: RichField, H: Hasher<F>>(
+/// Subtrees with at most this many leaves are filled one layer at a time rather than recursively,
+/// so that the compressions within each layer can be batched by `Hasher::two_to_one_pairs`.
+const MAX_LAYERED_SUBTREE_LEAVES: usize = 1 << 6;
+
+/// Fills `digests_buf` with the digests of the subtree with leaves `leaves`, each of which is hashed
+/// with `hash_leaf`, and returns the subtree's root.
+fn fill_subtree<F: RichField, H: Hasher<F>, L: Sync, G: Fn(&L) -> H::Hash + Sync>(
     digests_buf: &mut [MaybeUninit<H::Hash>],
-    leaves: &[Vec<F>],
+    leaves: &[L],
+    hash_leaf: &G,
 ) -> H::Hash {
     assert_eq!(leaves.len(), digests_buf.len() / 2 + 1);
-    if digests_buf.is_empty() {
-        H::hash_or_noop(&leaves[0])
+    if leaves.len() <= MAX_LAYERED_SUBTREE_LEAVES {
+        fill_subtree_by_layer::<F, H, L, G>(digests_buf, leaves, hash_leaf)
     } else {
         // Layout is: left recursive output || left child digest
         //             || right child digest || right recursive output.
@@ -85,8 +96,8 @@ fn fill_subtree<F: RichField, H: Hasher<F>>(
         let (left_leaves, right_leaves) = leaves.split_at(leaves.len() / 2);
 
         let (left_digest, right_digest) = plonky2_maybe_rayon::join(
-            || fill_subtree::<F, H>(left_digests_buf, left_leaves),
-            || fill_subtree::<F, H>(right_digests_buf, right_leaves),
+            || fill_subtree::<F, H, L, G>(left_digests_buf, left_leaves, hash_leaf),
+            || fill_subtree::<F, H, L, G>(right_digests_buf, right_leaves, hash_leaf),
         );
 
         left_digest_mem.write(left_digest);
@@ -95,10 +106,51 @@ fn fill_subtree<F: RichField, H: Hasher<F>>(
     }
 }
 
-fn fill_digests_buf<F: RichField, H: Hasher<F>>(
+/// Hashes the leaves of a subtree and computes its root one layer at a time, without storing the
+/// inner digests. Also returns the siblings along the path from the leaf at `leaf_index` to the root.
+fn subtree_root_and_path<F: RichField, H: Hasher<F>>(
+    leaves: &[Vec<F>],
+    mut leaf_index: usize,
+) -> (H::Hash, Vec<H::Hash>) {
+    let mut layer = leaves
+        .par_iter()
+        .map(|leaf| H::hash_or_noop(leaf))
+        .collect::<Vec<_>>();
+    let mut siblings = Vec::with_capacity(log2_strict(layer.len()));
+    while layer.len() > 1 {
+        siblings.push(layer[leaf_index ^ 1]);
+        leaf_index >>= 1;
+        layer = H::two_to_one_pairs(&layer);
+    }
+    (layer[0], siblings)
+}
+
+/// Same as `fill_subtree`, but computes the digests bottom-up, one layer at a time.
+fn fill_subtree_by_layer<F: RichField, H: Hasher<F>, L, G: Fn(&L) -> H::Hash>(
+    digests_buf: &mut [MaybeUninit<H::Hash>],
+    leaves: &[L],
+    hash_leaf: &G,
+) -> H::Hash {
+    let mut layer = leaves.iter().map(hash_leaf).collect::<Vec<_>>();
+    let mut layer_index = 0;
+    while layer.len() > 1 {
+        for (i, &digest) in layer.iter().enumerate() {
+            // The digests of siblings are stored in pairs; see `MerkleTree::prove` for how the
+            // pairs of each layer are interleaved.
+            let pair_index = ((i >> 1) << (layer_index + 1)) + (1 << layer_index) - 1;
+            digests_buf[2 * pair_index + (i & 1)].write(digest);
+        }
+        layer = H::two_to_one_pairs(&layer);
+        layer_index += 1;
+    }
+    layer[0]
+}
+
+fn fill_digests_buf<F: RichField, H: Hasher<F>, L: Sync, G: Fn(&L) -> H::Hash + Sync>(
     digests_buf: &mut [MaybeUninit<H::Hash>],
     cap_buf: &mut [MaybeUninit<H::Hash>],
-    leaves: &[Vec<F>],
+    leaves: &[L],
+    hash_leaf: &G,
     cap_height: usize,
 ) {
     // Special case of a tree that's all cap. The usual case will panic because we'll try to split
@@ -110,7 +162,7 @@ fn fill_digests_buf<F: RichField, H: Hasher<F>>(
             .par_iter_mut()
             .zip(leaves)
             .for_each(|(cap_buf, leaf)| {
-                cap_buf.write(H::hash_or_noop(leaf));
+                cap_buf.write(hash_leaf(leaf));
             });
         return;
     }
@@ -126,13 +178,81 @@ fn fill_digests_buf<F: RichField, H: Hasher<F>>(
             // We have `1 << cap_height` sub-trees, one for each entry in `cap`. They are totally
             // independent, so we schedule one task for each. `digests_buf` and `leaves` are split
             // into `1 << cap_height` slices, one for each sub-tree.
-            subtree_cap.write(fill_subtree::<F, H>(subtree_digests, subtree_leaves));
+            subtree_cap.write(fill_subtree::<F, H, L, G>(
+                subtree_digests,
+                subtree_leaves,
+                hash_leaf,
+            ));
         },
     );
 }
 
 impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
     pub fn new(leaves: Vec<Vec<F>>, cap_height: usize) -> Self {
+        let (digests, cap) =
+            Self::build_digests(&leaves, |leaf: &Vec<F>| H::hash_or_noop(leaf), cap_height);
+        Self {
+            leaves,
+            digests,
+            cap,
+        }
+    }
+
+    /// Same as `new`, but if `store_digests` is false, only the leaves and the cap are kept. This saves
+    /// the memory of `2 * (leaves.len() - 2^cap_height)` inner digests, at the cost of `prove`
+    /// rehashing the whole sub-tree below the cap element of the leaf being proven.
+    pub fn new_with_storage(leaves: Vec<Vec<F>>, cap_height: usize, store_digests: bool) -> Self {
+        if store_digests {
+            return Self::new(leaves, cap_height);
+        }
+
+        let log2_leaves_len = log2_strict(leaves.len());
+        assert!(
+            cap_height <= log2_leaves_len,
+            "cap_height={} should be at most log2(leaves.len())={}",
+            cap_height,
+            log2_leaves_len
+        );
+
+        let subtree_leaves_len = leaves.len() >> cap_height;
+        let cap = leaves
+            .chunks_exact(subtree_leaves_len)
+            .map(|subtree_leaves| subtree_root_and_path::<F, H>(subtree_leaves, 0).0)
+            .collect();
+
+        Self {
+            leaves,
+            digests: Vec::new(),
+            cap: MerkleCap(cap),
+        }
+    }
+
+    /// Builds a Merkle tree whose leaf digests are `digests`, without hashing them again. If the
+    /// number of digests isn't a power of two, the list is padded with zero digests up to the next
+    /// power of two.
+    ///
+    /// The leaves of the resulting tree are the digests' elements. Opening them is only consistent
+    /// with the tree if `H::hash_or_noop` leaves digests unchanged, as it does for `PoseidonHash`.
+    pub fn from_digests(mut digests: Vec<H::Hash>, cap_height: usize) -> Self {
+        assert!(!digests.is_empty(), "Cannot build a tree from no digests");
+        let zero_digest = H::Hash::from_bytes(&vec![0; H::HASH_SIZE]);
+        digests.resize(digests.len().next_power_of_two(), zero_digest);
+
+        let (tree_digests, cap) =
+            Self::build_digests(&digests, |&digest: &H::Hash| digest, cap_height);
+        Self {
+            leaves: digests.iter().map(|digest| digest.to_vec()).collect(),
+            digests: tree_digests,
+            cap,
+        }
+    }
+
+    /// Computes the inner digests and the cap of a tree whose leaves are hashed by `hash_leaf`.
+    fn build_digests<L: Sync, G: Fn(&L) -> H::Hash + Sync>(
+        leaves: &[L],
+        hash_leaf: G,
+        cap_height: usize,
+    ) -> (Vec<H::Hash>, MerkleCap<F, H>) {
         let log2_leaves_len = log2_strict(leaves.len());
         assert!(
             cap_height <= log2_leaves_len,
@@ -149,7 +269,7 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
 
         let digests_buf = capacity_up_to_mut(&mut digests, num_digests);
         let cap_buf = capacity_up_to_mut(&mut cap, len_cap);
-        fill_digests_buf::<F, H>(digests_buf, cap_buf, &leaves[..], cap_height);
+        fill_digests_buf::<F, H, L, G>(digests_buf, cap_buf, leaves, &hash_leaf, cap_height);
 
         unsafe {
             // SAFETY: `fill_digests_buf` and `cap` initialized the spare capacity up to
@@ -158,22 +278,34 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
             cap.set_len(len_cap);
         }
 
-        Self {
-            leaves,
-            digests,
-            cap: MerkleCap(cap),
-        }
+        (digests, MerkleCap(cap))
     }
 
     pub fn get(&self, i: usize) -> &[F] {
         &self.leaves[i]
     }
 
-    /// Create a Merkle proof from a leaf index.
+    /// Create a Merkle proof from a leaf index. The proof consists of the sibling digests from the
+    /// leaf up to, but excluding, the cap layer.
     pub fn prove(&self, leaf_index: usize) -> MerkleProof<F, H> {
+        assert!(
+            leaf_index < self.leaves.len(),
+            "Leaf index {} is out of range for a tree with {} leaves",
+            leaf_index,
+            self.leaves.len()
+        );
         let cap_height = log2_strict(self.cap.len());
         let num_layers = log2_strict(self.leaves.len()) - cap_height;
-        debug_assert_eq!(leaf_index >> (cap_height + num_layers), 0);
+
+        if self.digests.is_empty() && num_layers > 0 {
+            // The digests weren't stored, so recompute the sub-tree containing the leaf.
+            let tree_index = leaf_index >> num_layers;
+            let subtree_leaves =
+                &self.leaves[tree_index << num_layers..(tree_index + 1) << num_layers];
+            let (_, siblings) =
+                subtree_root_and_path::<F, H>(subtree_leaves, leaf_index & ((1 << num_layers) - 1));
+            return MerkleProof { siblings };
+        }
 
         let digest_tree = {
             let tree_index = leaf_index >> num_layers;
@@ -205,6 +337,20 @@ impl<F: RichField, H: Hasher<F>> MerkleTree<F, H> {
 
         MerkleProof { siblings }
     }
+
+    /// Returns the data of the leaf at `leaf_index`, along with a Merkle proof for it.
+    pub fn open(&self, leaf_index: usize) -> (&[F], MerkleProof<F, H>) {
+        let proof = self.prove(leaf_index);
+        (&self.leaves[leaf_index], proof)
+    }
+
+    /// Finds the first leaf equal to `leaf` and creates a Merkle proof for it, returning the leaf's
+    /// index along with the proof, or `None` if no leaf matches. This is a linear scan over the
+    /// leaves, so it takes `O(n)` time.
+    pub fn prove_by_value(&self, leaf: &[F]) -> Option<(usize, MerkleProof<F, H>)> {
+        let leaf_index = self.leaves.iter().position(|l| l == leaf)?;
+        Some((leaf_index, self.prove(leaf_index)))
+    }
 }
 
 #[cfg(test)]
@@ -213,6 +359,8 @@ mod tests {
 
     use super::*;
     use crate::field::extension::Extendable;
+    use crate::field::types::{Field, Sample};
+    use crate::hash::hash_types::HashOut;
     use crate::hash::merkle_proofs::verify_merkle_proof_to_cap;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
 
@@ -279,4 +427,116 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_open() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let log_n = 5;
+        let leaves = random_data::<F>(1 << log_n, 7);
+        for cap_height in 0..=log_n {
+            let tree =
+                MerkleTree::<F, <C as GenericConfig<D>>::Hasher>::new(leaves.clone(), cap_height);
+            for (i, leaf) in leaves.iter().enumerate() {
+                let (leaf_data, proof) = tree.open(i);
+                assert_eq!(leaf_data, &leaf[..]);
+                assert_eq!(proof.siblings.len(), log_n - cap_height);
+                verify_merkle_proof_to_cap(leaf_data.to_vec(), i, &tree.cap, &proof)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_without_stored_digests() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let log_n = 6;
+        let leaves = random_data::<F>(1 << log_n, 7);
+        for cap_height in 0..=log_n {
+            let full = MerkleTree::<F, H>::new(leaves.clone(), cap_height);
+            let light = MerkleTree::<F, H>::new_with_storage(leaves.clone(), cap_height, false);
+            assert!(light.digests.is_empty());
+            assert_eq!(light.cap, full.cap);
+            for (i, leaf) in leaves.iter().enumerate() {
+                let proof = light.prove(i);
+                assert_eq!(proof, full.prove(i));
+                verify_merkle_proof_to_cap(leaf.clone(), i, &light.cap, &proof)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_open_out_of_range() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let leaves = random_data::<F>(8, 7);
+        let tree = MerkleTree::<F, <C as GenericConfig<D>>::Hasher>::new(leaves, 1);
+        let _ = tree.open(8);
+    }
+
+    #[test]
+    fn test_from_digests() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::Hasher;
+
+        let digests = (0..16).map(|_| HashOut::<F>::rand()).collect::<Vec<_>>();
+        let leaves = digests
+            .iter()
+            .map(|d| d.elements.to_vec())
+            .collect::<Vec<_>>();
+        for cap_height in [0, 2, 4] {
+            let tree = MerkleTree::<F, H>::from_digests(digests.clone(), cap_height);
+            let expected = MerkleTree::<F, H>::new(leaves.clone(), cap_height);
+            assert_eq!(tree.cap, expected.cap);
+            assert_eq!(tree.digests, expected.digests);
+        }
+
+        // 11 digests are padded with zero digests up to 16.
+        let tree = MerkleTree::<F, H>::from_digests(digests[..11].to_vec(), 1);
+        let mut padded_leaves = leaves[..11].to_vec();
+        padded_leaves.resize(16, vec![F::ZERO; 4]);
+        assert_eq!(tree.cap, MerkleTree::<F, H>::new(padded_leaves, 1).cap);
+        for i in 0..16 {
+            let (leaf_data, proof) = tree.open(i);
+            verify_merkle_proof_to_cap(leaf_data.to_vec(), i, &tree.cap, &proof)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prove_by_value() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let log_n = 8;
+        let n = 1 << log_n;
+        let leaves = random_data::<F>(n, 7);
+        let tree = MerkleTree::<F, <C as GenericConfig<D>>::Hasher>::new(leaves.clone(), 1);
+
+        let i = 42;
+        let (index, proof) = tree.prove_by_value(&leaves[i]).unwrap();
+        assert_eq!(index, i);
+        verify_merkle_proof_to_cap(leaves[i].clone(), index, &tree.cap, &proof)?;
+
+        let absent = F::rand_vec(7);
+        assert!(tree.prove_by_value(&absent).is_none());
+
+        Ok(())
+    }
 }
@@ -1,4 +1,6 @@
 mod arch;
+#[cfg(feature = "blake3")]
+pub mod blake3;
 pub mod hash_types;
 pub mod hashing;
 pub mod keccak;
@@ -6,4 +8,5 @@ pub mod merkle_proofs;
 pub mod merkle_tree;
 pub mod path_compression;
 pub mod poseidon;
+pub mod poseidon8;
 pub mod poseidon_goldilocks;
@@ -1,115 +1,128 @@
-use alloc::vec;
 use alloc::vec::Vec;
 
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use num::Integer;
+use serde::{Deserialize, Serialize};
 
 use crate::hash::hash_types::RichField;
 use crate::hash::merkle_proofs::MerkleProof;
 use crate::plonk::config::Hasher;
 
-/// Compress multiple Merkle proofs on the same tree by removing redundancy in the Merkle paths.
-pub(crate) fn compress_merkle_proofs<F: RichField, H: Hasher<F>>(
-    cap_height: usize,
-    indices: &[usize],
-    proofs: &[MerkleProof<F, H>],
-) -> Vec<MerkleProof<F, H>> {
-    assert!(!proofs.is_empty());
-    let height = cap_height + proofs[0].siblings.len();
-    let num_leaves = 1 << height;
-    let mut compressed_proofs = Vec::with_capacity(proofs.len());
-    // Holds the known nodes in the tree at a given time. The root is at index 1.
-    // Valid indices are 1 through n, and each element at index `i` has
-    // children at indices `2i` and `2i +1` its parent at index `floor(i ∕ 2)`.
-    let mut known = vec![false; 2 * num_leaves];
-    for &i in indices {
-        // The path from a leaf to the cap is known.
-        for j in 0..(height - cap_height) {
-            known[(i + num_leaves) >> j] = true;
-        }
+/// Merkle proofs for several leaves of the same tree, with the redundancy between their paths
+/// removed. Any sibling which lies on the path of one of the leaves, or which was already included
+/// for an earlier index, is omitted, since the verifier can recompute it from the leaf data.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(bound = "")]
+pub struct CompressedMerkleProofs<F: RichField, H: Hasher<F>> {
+    /// The leaf indices, in the order in which their proofs were given.
+    pub indices: Vec<usize>,
+    /// The number of siblings in each uncompressed proof, i.e. the tree height minus the cap
+    /// height.
+    pub proof_len: usize,
+    /// For each index, the siblings of its proof which can't be inferred, in bottom-up order.
+    pub proofs: Vec<MerkleProof<F, H>>,
+}
+
+impl<F: RichField, H: Hasher<F>> CompressedMerkleProofs<F, H> {
+    /// The total number of siblings across all compressed proofs.
+    pub fn num_siblings(&self) -> usize {
+        self.proofs.iter().map(|p| p.siblings.len()).sum()
     }
-    // For each proof collect all the unknown proof elements.
-    for (&i, p) in indices.iter().zip(proofs) {
-        let mut compressed_proof = MerkleProof {
-            siblings: Vec::new(),
-        };
-        let mut index = i + num_leaves;
-        for &sibling in &p.siblings {
-            let sibling_index = index ^ 1;
-            if !known[sibling_index] {
-                // If the sibling is not yet known, add it to the proof and set it to known.
-                compressed_proof.siblings.push(sibling);
-                known[sibling_index] = true;
+
+    /// Reconstructs the full proof of each index from the leaves' data, which must be given in the
+    /// same order as `indices`.
+    pub fn decompress(&self, leaves_data: &[Vec<F>]) -> Vec<MerkleProof<F, H>> {
+        assert_eq!(leaves_data.len(), self.indices.len());
+        // Holds the already seen nodes in the tree along with their value, keyed by their layer
+        // (with the leaves at layer 0) and their position within it.
+        let mut seen = HashMap::new();
+
+        for (&i, v) in self.indices.iter().zip(leaves_data) {
+            // Observe the leaves.
+            seen.insert((0, i), H::hash_or_noop(v));
+        }
+
+        // Iterators over the siblings.
+        let mut siblings = self
+            .proofs
+            .iter()
+            .map(|p| p.siblings.iter())
+            .collect::<Vec<_>>();
+        // Fill the `seen` map from the bottom of the tree to the cap.
+        for layer in 0..self.proof_len {
+            for (&i, p) in self.indices.iter().zip(siblings.iter_mut()) {
+                let index = i >> layer;
+                let current_hash = seen[&(layer, index)];
+                let sibling_hash = *seen
+                    .entry((layer, index ^ 1))
+                    .or_insert_with(|| *p.next().expect("Missing sibling"));
+                let parent_hash = if index.is_even() {
+                    H::two_to_one(current_hash, sibling_hash)
+                } else {
+                    H::two_to_one(sibling_hash, current_hash)
+                };
+                seen.insert((layer + 1, index >> 1), parent_hash);
             }
-            // Go up the tree and set the parent to known.
-            index >>= 1;
-            known[index] = true;
         }
-        compressed_proofs.push(compressed_proof);
-    }
 
-    compressed_proofs
+        // For every index, go up the tree by querying `seen` to get node values.
+        self.indices
+            .iter()
+            .map(|&i| MerkleProof {
+                siblings: (0..self.proof_len)
+                    .map(|layer| seen[&(layer, (i >> layer) ^ 1)])
+                    .collect(),
+            })
+            .collect()
+    }
 }
 
-/// Decompress compressed Merkle proofs.
-/// Note: The data and indices must be in the same order as in `compress_merkle_proofs`.
-pub(crate) fn decompress_merkle_proofs<F: RichField, H: Hasher<F>>(
-    leaves_data: &[Vec<F>],
-    leaves_indices: &[usize],
-    compressed_proofs: &[MerkleProof<F, H>],
-    height: usize,
-    cap_height: usize,
-) -> Vec<MerkleProof<F, H>> {
-    let num_leaves = 1 << height;
-    let compressed_proofs = compressed_proofs.to_vec();
-    let mut decompressed_proofs = Vec::with_capacity(compressed_proofs.len());
-    // Holds the already seen nodes in the tree along with their value.
-    let mut seen = HashMap::new();
-
-    for (&i, v) in leaves_indices.iter().zip(leaves_data) {
-        // Observe the leaves.
-        seen.insert(i + num_leaves, H::hash_or_noop(v));
+/// Compresses multiple Merkle proofs on the same tree by removing redundancy in the Merkle paths.
+/// The result holds the smallest set of siblings from which all of the paths can be rebuilt given
+/// the leaves. Indices may repeat, in which case the later copies need no siblings at all.
+pub fn compress_merkle_proofs<F: RichField, H: Hasher<F>>(
+    indices: &[usize],
+    proofs: &[MerkleProof<F, H>],
+) -> CompressedMerkleProofs<F, H> {
+    assert_eq!(indices.len(), proofs.len());
+    let proof_len = proofs.first().map_or(0, |p| p.siblings.len());
+    // Holds the known nodes in the tree, keyed by their layer (with the leaves at layer 0) and
+    // their position within it.
+    let mut known = HashSet::new();
+    for &i in indices {
+        // The path from a leaf to the cap is known.
+        for layer in 0..proof_len {
+            known.insert((layer, i >> layer));
+        }
     }
-
-    // Iterators over the siblings.
-    let mut siblings = compressed_proofs
+    // For each proof collect all the unknown proof elements.
+    let compressed_proofs = indices
         .iter()
-        .map(|p| p.siblings.iter())
-        .collect::<Vec<_>>();
-    // Fill the `seen` map from the bottom of the tree to the cap.
-    for layer_height in 0..height - cap_height {
-        for (&i, p) in leaves_indices.iter().zip(siblings.iter_mut()) {
-            let index = (i + num_leaves) >> layer_height;
-            let current_hash = seen[&index];
-            let sibling_index = index ^ 1;
-            let sibling_hash = *seen
-                .entry(sibling_index)
-                .or_insert_with(|| *p.next().unwrap());
-            let parent_hash = if index.is_even() {
-                H::two_to_one(current_hash, sibling_hash)
-            } else {
-                H::two_to_one(sibling_hash, current_hash)
+        .zip(proofs)
+        .map(|(&i, p)| {
+            assert_eq!(
+                p.siblings.len(),
+                proof_len,
+                "Proofs must have the same length"
+            );
+            let mut compressed_proof = MerkleProof {
+                siblings: Vec::new(),
             };
-            seen.insert(index >> 1, parent_hash);
-        }
-    }
-    // For every index, go up the tree by querying `seen` to get node values.
-    for &i in leaves_indices {
-        let mut decompressed_proof = MerkleProof {
-            siblings: Vec::new(),
-        };
-        let mut index = i + num_leaves;
-        for _ in 0..height - cap_height {
-            let sibling_index = index ^ 1;
-            let h = seen[&sibling_index];
-            decompressed_proof.siblings.push(h);
-            index >>= 1;
-        }
-
-        decompressed_proofs.push(decompressed_proof);
+            for (layer, &sibling) in p.siblings.iter().enumerate() {
+                // If the sibling is not yet known, add it to the proof and mark it as known.
+                if known.insert((layer, (i >> layer) ^ 1)) {
+                    compressed_proof.siblings.push(sibling);
+                }
+            }
+            compressed_proof
+        })
+        .collect();
+
+    CompressedMerkleProofs {
+        indices: indices.to_vec(),
+        proof_len,
+        proofs: compressed_proofs,
     }
-
-    decompressed_proofs
 }
 
 #[cfg(test)]
@@ -134,26 +147,36 @@ mod tests {
 
         let mut rng = OsRng;
         let k = rng.gen_range(1..=1 << h);
-        let indices = (0..k).map(|_| rng.gen_range(0..1 << h)).collect::<Vec<_>>();
-        let proofs = indices.iter().map(|&i| mt.prove(i)).collect::<Vec<_>>();
-
-        let compressed_proofs = compress_merkle_proofs(cap_height, &indices, &proofs);
-        let decompressed_proofs = decompress_merkle_proofs(
-            &indices.iter().map(|&i| vs[i].clone()).collect::<Vec<_>>(),
-            &indices,
-            &compressed_proofs,
-            h,
-            cap_height,
-        );
-
-        assert_eq!(proofs, decompressed_proofs);
-
-        let compressed_proof_bytes = serde_cbor::to_vec(&compressed_proofs).unwrap();
-        println!(
-            "Compressed proof length: {} bytes",
-            compressed_proof_bytes.len()
-        );
-        let proof_bytes = serde_cbor::to_vec(&proofs).unwrap();
-        println!("Proof length: {} bytes", proof_bytes.len());
+        let random_indices = (0..k).map(|_| rng.gen_range(0..1 << h)).collect::<Vec<_>>();
+        let index_sets = [
+            random_indices,
+            vec![],
+            vec![5, 5, 5],
+            // Siblings of each other, so the second needs no siblings beyond the first's.
+            vec![6, 7],
+            vec![0, 1 << (h - 1)],
+            (0..1 << h).collect(),
+        ];
+
+        for indices in index_sets {
+            let proofs = indices.iter().map(|&i| mt.prove(i)).collect::<Vec<_>>();
+            let compressed = compress_merkle_proofs(&indices, &proofs);
+
+            // The minimal sibling set is every sibling of a path node which isn't itself on a
+            // path.
+            let path_nodes = indices
+                .iter()
+                .flat_map(|&i| (0..h - cap_height).map(move |layer| (layer, i >> layer)))
+                .collect::<HashSet<_>>();
+            let min_siblings = path_nodes
+                .iter()
+                .map(|&(layer, i)| (layer, i ^ 1))
+                .filter(|node| !path_nodes.contains(node))
+                .count();
+            assert_eq!(compressed.num_siblings(), min_siblings);
+
+            let leaves_data = indices.iter().map(|&i| vs[i].clone()).collect::<Vec<_>>();
+            assert_eq!(compressed.decompress(&leaves_data), proofs);
+        }
     }
 }
@@ -4,10 +4,13 @@
 use alloc::vec;
 use alloc::vec::Vec;
 
+use plonky2_maybe_rayon::*;
 use unroll::unroll_for_loops;
 
 use crate::field::extension::{Extendable, FieldExtension};
-use crate::field::types::{Field, PrimeField64};
+use crate::field::packable::Packable;
+use crate::field::packed::PackedField;
+use crate::field::types::PrimeField64;
 use crate::gates::gate::Gate;
 use crate::gates::poseidon::PoseidonGate;
 use crate::gates::poseidon_mds::PoseidonMdsGate;
@@ -26,7 +29,14 @@ use crate::plonk::config::{AlgebraicHasher, Hasher};
 // the precomputed constant arrays in this file.
 pub const HALF_N_FULL_ROUNDS: usize = 4;
 pub(crate) const N_FULL_ROUNDS_TOTAL: usize = 2 * HALF_N_FULL_ROUNDS;
-pub const N_PARTIAL_ROUNDS: usize = 22;
+const N_PARTIAL_ROUNDS_SECURE: usize = 22;
+#[cfg(not(feature = "poseidon_fast_insecure"))]
+pub const N_PARTIAL_ROUNDS: usize = N_PARTIAL_ROUNDS_SECURE;
+/// With the `poseidon_fast_insecure` feature, the permutation uses far fewer partial rounds than
+/// are needed for security. This is only meant to speed up tests, and must never be used to
+/// produce proofs that anyone relies on.
+#[cfg(feature = "poseidon_fast_insecure")]
+pub const N_PARTIAL_ROUNDS: usize = 4;
 pub const N_ROUNDS: usize = N_FULL_ROUNDS_TOTAL + N_PARTIAL_ROUNDS;
 const MAX_WIDTH: usize = 12; // we only have width 8 and 12, and 12 is bigger. :)
 
@@ -48,9 +58,10 @@ fn reduce_u160<F: PrimeField64>((n_lo, n_hi): (u128, u32)) -> F {
 
 /// Note that these work for the Goldilocks field, but not necessarily others. See
 /// `generate_constants` about how these were generated. We include enough for a WIDTH of 12;
-/// smaller widths just use a subset.
+/// smaller widths just use a subset. With a reduced number of partial rounds, only the constants
+/// for the first `N_ROUNDS` rounds are used.
 #[rustfmt::skip]
-pub const ALL_ROUND_CONSTANTS: [u64; MAX_WIDTH * N_ROUNDS]  = [
+pub const ALL_ROUND_CONSTANTS: [u64; MAX_WIDTH * (N_FULL_ROUNDS_TOTAL + N_PARTIAL_ROUNDS_SECURE)]  = [
     // WARNING: The AVX2 Goldilocks specialization relies on all round constants being in
     // 0..0xfffeeac900011537. If these constants are randomly regenerated, there is a ~.6% chance
     // that this condition will no longer hold.
@@ -325,8 +336,7 @@ pub trait Poseidon: PrimeField64 {
     {
         for i in 0..WIDTH {
             let c = <Self as Poseidon>::FAST_PARTIAL_FIRST_ROUND_CONSTANT[i];
-            let c = Self::Extension::from_canonical_u64(c);
-            let c = builder.constant_extension(c);
+            let c = builder.constant_ext_from_base(Self::from_canonical_u64(c));
             state[i] = builder.add_extension(state[i], c);
         }
     }
@@ -376,8 +386,7 @@ pub trait Poseidon: PrimeField64 {
         for r in 1..WIDTH {
             for c in 1..WIDTH {
                 let t = <Self as Poseidon>::FAST_PARTIAL_ROUND_INITIAL_MATRIX[r - 1][c - 1];
-                let t = Self::Extension::from_canonical_u64(t);
-                let t = builder.constant_extension(t);
+                let t = builder.constant_ext_from_base(Self::from_canonical_u64(t));
                 result[c] = builder.mul_add_extension(t, state[r], result[c]);
             }
         }
@@ -459,8 +468,7 @@ pub trait Poseidon: PrimeField64 {
         let mut d = builder.mul_const_extension(Self::from_canonical_u64(mds0to0), s0);
         for i in 1..WIDTH {
             let t = <Self as Poseidon>::FAST_PARTIAL_ROUND_W_HATS[r][i - 1];
-            let t = Self::Extension::from_canonical_u64(t);
-            let t = builder.constant_extension(t);
+            let t = builder.constant_ext_from_base(Self::from_canonical_u64(t));
             d = builder.mul_add_extension(t, state[i], d);
         }
 
@@ -468,8 +476,7 @@ pub trait Poseidon: PrimeField64 {
         result[0] = d;
         for i in 1..WIDTH {
             let t = <Self as Poseidon>::FAST_PARTIAL_ROUND_VS[r][i - 1];
-            let t = Self::Extension::from_canonical_u64(t);
-            let t = builder.constant_extension(t);
+            let t = builder.constant_ext_from_base(Self::from_canonical_u64(t));
             result[i] = builder.mul_add_extension(t, state[0], state[i]);
         }
         result
@@ -508,8 +515,7 @@ pub trait Poseidon: PrimeField64 {
     {
         for i in 0..WIDTH {
             let c = ALL_ROUND_CONSTANTS[i + WIDTH * round_ctr];
-            let c = Self::Extension::from_canonical_u64(c);
-            let c = builder.constant_extension(c);
+            let c = builder.constant_ext_from_base(Self::from_canonical_u64(c));
             state[i] = builder.add_extension(state[i], c);
         }
     }
@@ -604,6 +610,134 @@ pub trait Poseidon: PrimeField64 {
         state
     }
 
+    /// Applies the permutation to each of `states`. If `Self::Packing` is vectorized (e.g. AVX2
+    /// Goldilocks), states are permuted in lockstep, one state per vector lane; any states left
+    /// over, or all of them if the packing is scalar, go through `poseidon` one at a time.
+    fn poseidon_batch(states: &mut [[Self; WIDTH]]) {
+        let lanes = <<Self as Packable>::Packing as PackedField>::WIDTH;
+        if lanes == 1 {
+            for state in states.iter_mut() {
+                *state = Self::poseidon(*state);
+            }
+            return;
+        }
+
+        // Transposed copy of a chunk of states: element `i` of lane `l` is at `i * lanes + l`.
+        let mut buf = vec![Self::ZERO; WIDTH * lanes];
+        let mut chunks = states.chunks_exact_mut(lanes);
+        for chunk in &mut chunks {
+            for (l, state) in chunk.iter().enumerate() {
+                for i in 0..WIDTH {
+                    buf[i * lanes + l] = state[i];
+                }
+            }
+            let packed: &mut [<Self as Packable>::Packing; WIDTH] =
+                PackedField::pack_slice_mut(&mut buf).try_into().unwrap();
+            Self::poseidon_packed(packed);
+            for (l, state) in chunk.iter_mut().enumerate() {
+                for i in 0..WIDTH {
+                    state[i] = buf[i * lanes + l];
+                }
+            }
+        }
+        for state in chunks.into_remainder() {
+            *state = Self::poseidon(*state);
+        }
+    }
+
+    /// Same as `poseidon` for packed fields, permuting each lane independently.
+    fn poseidon_packed<P: PackedField<Scalar = Self>>(state: &mut [P; WIDTH]) {
+        let mut round_ctr = 0;
+
+        Self::full_rounds_packed(state, &mut round_ctr);
+        Self::partial_rounds_packed(state, &mut round_ctr);
+        Self::full_rounds_packed(state, &mut round_ctr);
+        debug_assert_eq!(round_ctr, N_ROUNDS);
+    }
+
+    /// Same as `full_rounds` for packed fields.
+    #[inline]
+    fn full_rounds_packed<P: PackedField<Scalar = Self>>(
+        state: &mut [P; WIDTH],
+        round_ctr: &mut usize,
+    ) {
+        for _ in 0..HALF_N_FULL_ROUNDS {
+            for i in 0..WIDTH {
+                state[i] += Self::from_canonical_u64(ALL_ROUND_CONSTANTS[i + WIDTH * *round_ctr]);
+                state[i] = Self::sbox_monomial_packed(state[i]);
+            }
+            *state = Self::mds_layer_packed(state);
+            *round_ctr += 1;
+        }
+    }
+
+    /// Same as `partial_rounds` for packed fields.
+    #[inline]
+    fn partial_rounds_packed<P: PackedField<Scalar = Self>>(
+        state: &mut [P; WIDTH],
+        round_ctr: &mut usize,
+    ) {
+        for i in 0..WIDTH {
+            state[i] += Self::from_canonical_u64(Self::FAST_PARTIAL_FIRST_ROUND_CONSTANT[i]);
+        }
+
+        // Same as `mds_partial_layer_init`.
+        let mut result = [P::ZEROS; WIDTH];
+        result[0] = state[0];
+        for r in 1..WIDTH {
+            for c in 1..WIDTH {
+                let t =
+                    Self::from_canonical_u64(Self::FAST_PARTIAL_ROUND_INITIAL_MATRIX[r - 1][c - 1]);
+                result[c] += state[r] * t;
+            }
+        }
+        *state = result;
+
+        for r in 0..N_PARTIAL_ROUNDS {
+            state[0] = Self::sbox_monomial_packed(state[0]);
+            state[0] += Self::from_canonical_u64(Self::FAST_PARTIAL_ROUND_CONSTANTS[r]);
+
+            // Same as `mds_partial_layer_fast`.
+            let s0 = state[0];
+            let mds0to0 = Self::MDS_MATRIX_CIRC[0] + Self::MDS_MATRIX_DIAG[0];
+            let mut d = s0 * Self::from_canonical_u64(mds0to0);
+            for i in 1..WIDTH {
+                d += state[i] * Self::from_canonical_u64(Self::FAST_PARTIAL_ROUND_W_HATS[r][i - 1]);
+            }
+            state[0] = d;
+            for i in 1..WIDTH {
+                state[i] += s0 * Self::from_canonical_u64(Self::FAST_PARTIAL_ROUND_VS[r][i - 1]);
+            }
+        }
+        *round_ctr += N_PARTIAL_ROUNDS;
+    }
+
+    /// Same as `sbox_monomial` for packed fields.
+    #[inline(always)]
+    fn sbox_monomial_packed<P: PackedField<Scalar = Self>>(x: P) -> P {
+        // x |--> x^7
+        let x2 = x.square();
+        let x4 = x2.square();
+        let x3 = x * x2;
+        x3 * x4
+    }
+
+    /// Same as `mds_layer` for packed fields.
+    #[inline(always)]
+    fn mds_layer_packed<P: PackedField<Scalar = Self>>(state: &[P; WIDTH]) -> [P; WIDTH] {
+        let mut result = [P::ZEROS; WIDTH];
+
+        for r in 0..WIDTH {
+            for i in 0..WIDTH {
+                result[r] +=
+                    state[(i + r) % WIDTH] * Self::from_canonical_u64(Self::MDS_MATRIX_CIRC[i]);
+            }
+            result[r] += state[r] * Self::from_canonical_u64(Self::MDS_MATRIX_DIAG[r]);
+        }
+
+        result
+    }
+
     // For testing only, to ensure that various tricks are correct.
     #[inline]
     fn partial_rounds_naive(state: &mut [Self; WIDTH], round_ctr: &mut usize) {
@@ -629,6 +763,31 @@ pub trait Poseidon: PrimeField64 {
     }
 }
 
+/// An implementation of the Poseidon permutation for `GoldilocksField`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PoseidonImpl {
+    /// The portable implementation.
+    Scalar,
+    /// The AVX2/BMI2 implementation in `hash::arch::x86_64`.
+    Avx2,
+    /// The NEON implementation in `hash::arch::aarch64`, used for the S-box and MDS layers.
+    Neon,
+}
+
+/// Returns the Poseidon implementation compiled into `GoldilocksField::poseidon` in this build.
+///
+/// There is no runtime dispatch: the implementation is selected with `cfg` attributes at compile
+/// time, so this only depends on the target and the target features enabled when building, not on
+/// the CPU we happen to run on. Note that the AVX2 implementation is currently disabled, so
+/// `PoseidonImpl::Avx2` is never returned.
+pub const fn compiled_backend() -> PoseidonImpl {
+    if cfg!(all(target_arch = "aarch64", target_feature = "neon")) {
+        PoseidonImpl::Neon
+    } else {
+        PoseidonImpl::Scalar
+    }
+}
+
 pub struct PoseidonPermutation;
 impl<F: RichField> PlonkyPermutation<F> for PoseidonPermutation {
     fn permute(input: [F; SPONGE_WIDTH]) -> [F; SPONGE_WIDTH] {
@@ -639,6 +798,27 @@ impl<F: RichField> PlonkyPermutation<F> for PoseidonPermutation {
 /// Poseidon hash function.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct PoseidonHash;
+
+impl PoseidonHash {
+    /// Hashes each input with `hash_no_pad`, in parallel if the `parallel` feature is enabled.
+    /// The digests are returned in the same order as the inputs.
+    pub fn hash_many<F: RichField>(inputs: &[&[F]]) -> Vec<HashOut<F>> {
+        inputs
+            .par_iter()
+            .map(|input| <Self as Hasher<F>>::hash_no_pad(input))
+            .collect()
+    }
+
+    /// Hashes each row of `matrix` with `hash_no_pad`, in parallel if the `parallel` feature is
+    /// enabled. This gives one commitment per row, e.g. for the leaves of a trace commitment.
+    pub fn hash_rows<F: RichField>(matrix: &[Vec<F>]) -> Vec<HashOut<F>> {
+        matrix
+            .par_iter()
+            .map(|row| <Self as Hasher<F>>::hash_no_pad(row))
+            .collect()
+    }
+}
+
 impl<F: RichField> Hasher<F> for PoseidonHash {
     const HASH_SIZE: usize = 4 * 8;
     type Hash = HashOut<F>;
@@ -651,6 +831,27 @@ impl<F: RichField> Hasher<F> for PoseidonHash {
     fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash {
         compress::<F, Self::Permutation>(left, right)
     }
+
+    fn two_to_one_pairs(hashes: &[Self::Hash]) -> Vec<Self::Hash> {
+        assert_eq!(hashes.len() % 2, 0, "Expected an even number of hashes");
+        // Same as `compress`, but with all the permutations done by `poseidon_batch`.
+        let mut states = hashes
+            .chunks_exact(2)
+            .map(|pair| {
+                let mut state = [F::ZERO; SPONGE_WIDTH];
+                state[..4].copy_from_slice(&pair[0].elements);
+                state[4..8].copy_from_slice(&pair[1].elements);
+                state
+            })
+            .collect::<Vec<_>>();
+        F::poseidon_batch(&mut states);
+        states
+            .iter()
+            .map(|state| HashOut {
+                elements: state[..4].try_into().unwrap(),
+            })
+            .collect()
+    }
 }
 
 impl<F: RichField> AlgebraicHasher<F> for PoseidonHash {
@@ -709,6 +910,21 @@ pub(crate) mod test_helpers {
         }
     }
 
+    pub(crate) fn check_batch_consistency<F: Field>()
+    where
+        F: Poseidon,
+    {
+        // Cover lane counts which aren't multiples of the packing width, as well as zero.
+        for num_states in 0..=9 {
+            let mut states = (0..num_states)
+                .map(|_| F::rand_array::<SPONGE_WIDTH>())
+                .collect::<Vec<_>>();
+            let expected = states.iter().map(|&s| F::poseidon(s)).collect::<Vec<_>>();
+            F::poseidon_batch(&mut states);
+            assert_eq!(states, expected);
+        }
+    }
+
     pub(crate) fn check_consistency<F: Field>()
     where
         F: Poseidon,
@@ -724,3 +940,58 @@ pub(crate) mod test_helpers {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::Sample;
+    use crate::hash::hash_types::HashOut;
+    use crate::hash::poseidon::{compiled_backend, PoseidonHash, PoseidonImpl};
+    use crate::plonk::config::Hasher;
+
+    #[test]
+    fn test_hash_many() {
+        type F = GoldilocksField;
+        let records = (0..50).map(|i| F::rand_vec(i % 20)).collect::<Vec<_>>();
+        let inputs = records.iter().map(|r| r.as_slice()).collect::<Vec<_>>();
+        let hashes = PoseidonHash::hash_many(&inputs);
+        assert_eq!(hashes.len(), records.len());
+        for (record, hash) in records.iter().zip(hashes) {
+            assert_eq!(hash, PoseidonHash::hash_no_pad(record));
+        }
+    }
+
+    #[test]
+    fn test_hash_rows() {
+        type F = GoldilocksField;
+        let matrix = (0..20).map(|_| F::rand_vec(13)).collect::<Vec<_>>();
+        let hashes = PoseidonHash::hash_rows(&matrix);
+        assert_eq!(hashes.len(), matrix.len());
+        for (row, hash) in matrix.iter().zip(hashes) {
+            assert_eq!(hash, PoseidonHash::hash_no_pad(row));
+        }
+    }
+
+    #[test]
+    fn test_two_to_one_pairs() {
+        type F = GoldilocksField;
+        let hashes = (0..14).map(|_| HashOut::<F>::rand()).collect::<Vec<_>>();
+        let compressed = PoseidonHash::two_to_one_pairs(&hashes);
+        assert_eq!(compressed.len(), 7);
+        for (pair, digest) in hashes.chunks_exact(2).zip(compressed) {
+            assert_eq!(digest, PoseidonHash::two_to_one(pair[0], pair[1]));
+        }
+    }
+
+    #[test]
+    fn test_compiled_backend() {
+        let backend = compiled_backend();
+        if cfg!(target_arch = "x86_64") {
+            assert_eq!(backend, PoseidonImpl::Scalar);
+        }
+        if cfg!(all(target_arch = "aarch64", target_feature = "neon")) {
+            assert_eq!(backend, PoseidonImpl::Neon);
+        }
+        assert_ne!(backend, PoseidonImpl::Avx2);
+    }
+}
@@ -0,0 +1,331 @@
+//! A width-8 instance of the Poseidon permutation, alongside the default width-12 one in
+//! `poseidon.rs`. Its smaller state makes it cheaper for small Merkle trees and other uses which
+//! only ever absorb a few elements at a time.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::field::extension::{Extendable, FieldExtension};
+use crate::field::types::PrimeField64;
+use crate::gates::poseidon8::Poseidon8Gate;
+use crate::hash::hash_types::{HashOut, RichField};
+use crate::hash::hashing::{compress, hash_n_to_hash_no_pad, PlonkyPermutation, SPONGE_WIDTH};
+use crate::hash::poseidon::{ALL_ROUND_CONSTANTS, HALF_N_FULL_ROUNDS, N_PARTIAL_ROUNDS, N_ROUNDS};
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::target::Target;
+use crate::plonk::circuit_builder::CircuitBuilder;
+use crate::plonk::config::Hasher;
+
+/// The number of elements in the state of the width-8 permutation.
+pub const POSEIDON8_WIDTH: usize = 8;
+
+/// The number of elements absorbed per permutation by `Poseidon8Hash`. The remaining 4 elements
+/// of the state are the capacity.
+pub const POSEIDON8_RATE: usize = 4;
+
+const WIDTH: usize = POSEIDON8_WIDTH;
+
+/// Returns whether the `round_ctr`-th round is a full round, rather than a partial one.
+pub(crate) fn is_full_round(round_ctr: usize) -> bool {
+    !(HALF_N_FULL_ROUNDS..HALF_N_FULL_ROUNDS + N_PARTIAL_ROUNDS).contains(&round_ctr)
+}
+
+/// Poseidon with a state of 8 elements.
+///
+/// It has the same number of rounds as the width-12 instance and uses the first `8 * N_ROUNDS`
+/// entries of `ALL_ROUND_CONSTANTS`, but its own MDS matrix. Partial rounds are evaluated
+/// directly, without the precomputed constants of the width-12 fast partial rounds.
+pub trait Poseidon8: PrimeField64 {
+    // The MDS matrix we use is C + D, where C is the circulant matrix whose first row is given by
+    // `MDS_MATRIX_CIRC`, and D is the diagonal matrix whose diagonal is given by `MDS_MATRIX_DIAG`.
+    const MDS_MATRIX_CIRC: [u64; WIDTH];
+    const MDS_MATRIX_DIAG: [u64; WIDTH];
+
+    fn mds_layer(state: &[Self; WIDTH]) -> [Self; WIDTH] {
+        // The MDS entries are small, so each row can be accumulated in a `u128` and reduced once.
+        let mut result = [Self::ZERO; WIDTH];
+        for r in 0..WIDTH {
+            let mut acc = state[r].to_noncanonical_u64() as u128
+                * <Self as Poseidon8>::MDS_MATRIX_DIAG[r] as u128;
+            for i in 0..WIDTH {
+                acc += state[(i + r) % WIDTH].to_noncanonical_u64() as u128
+                    * <Self as Poseidon8>::MDS_MATRIX_CIRC[i] as u128;
+            }
+            result[r] = Self::from_noncanonical_u128(acc);
+        }
+        result
+    }
+
+    fn mds_layer_field<F: FieldExtension<D, BaseField = Self>, const D: usize>(
+        state: &[F; WIDTH],
+    ) -> [F; WIDTH] {
+        let mut result = [F::ZERO; WIDTH];
+        for r in 0..WIDTH {
+            result[r] = state[r] * F::from_canonical_u64(<Self as Poseidon8>::MDS_MATRIX_DIAG[r]);
+            for i in 0..WIDTH {
+                result[r] += state[(i + r) % WIDTH]
+                    * F::from_canonical_u64(<Self as Poseidon8>::MDS_MATRIX_CIRC[i]);
+            }
+        }
+        result
+    }
+
+    /// Recursive version of `mds_layer`.
+    fn mds_layer_circuit<const D: usize>(
+        builder: &mut CircuitBuilder<Self, D>,
+        state: &[ExtensionTarget<D>; WIDTH],
+    ) -> [ExtensionTarget<D>; WIDTH]
+    where
+        Self: RichField + Extendable<D>,
+    {
+        let mut result = [builder.zero_extension(); WIDTH];
+        for r in 0..WIDTH {
+            let c = Self::from_canonical_u64(<Self as Poseidon8>::MDS_MATRIX_DIAG[r]);
+            result[r] = builder.mul_const_add_extension(c, state[r], result[r]);
+            for i in 0..WIDTH {
+                let c = Self::from_canonical_u64(<Self as Poseidon8>::MDS_MATRIX_CIRC[i]);
+                result[r] = builder.mul_const_add_extension(c, state[(i + r) % WIDTH], result[r]);
+            }
+        }
+        result
+    }
+
+    fn constant_layer_field<F: FieldExtension<D, BaseField = Self>, const D: usize>(
+        state: &mut [F; WIDTH],
+        round_ctr: usize,
+    ) {
+        for i in 0..WIDTH {
+            state[i] += F::from_canonical_u64(ALL_ROUND_CONSTANTS[i + WIDTH * round_ctr]);
+        }
+    }
+
+    /// Recursive version of `constant_layer_field`.
+    fn constant_layer_circuit<const D: usize>(
+        builder: &mut CircuitBuilder<Self, D>,
+        state: &mut [ExtensionTarget<D>; WIDTH],
+        round_ctr: usize,
+    ) where
+        Self: RichField + Extendable<D>,
+    {
+        for i in 0..WIDTH {
+            let c = ALL_ROUND_CONSTANTS[i + WIDTH * round_ctr];
+            let c = builder.constant_ext_from_base(Self::from_canonical_u64(c));
+            state[i] = builder.add_extension(state[i], c);
+        }
+    }
+
+    fn sbox_monomial<F: FieldExtension<D, BaseField = Self>, const D: usize>(x: F) -> F {
+        // x |--> x^7
+        let x2 = x.square();
+        let x4 = x2.square();
+        let x3 = x * x2;
+        x3 * x4
+    }
+
+    /// Recursive version of `sbox_monomial`.
+    fn sbox_monomial_circuit<const D: usize>(
+        builder: &mut CircuitBuilder<Self, D>,
+        x: ExtensionTarget<D>,
+    ) -> ExtensionTarget<D>
+    where
+        Self: RichField + Extendable<D>,
+    {
+        builder.exp_u64_extension(x, 7)
+    }
+
+    fn poseidon8(input: [Self; WIDTH]) -> [Self; WIDTH] {
+        let mut state = input;
+        for round_ctr in 0..N_ROUNDS {
+            <Self as Poseidon8>::constant_layer_field::<Self, 1>(&mut state, round_ctr);
+            if is_full_round(round_ctr) {
+                for x in state.iter_mut() {
+                    *x = <Self as Poseidon8>::sbox_monomial::<Self, 1>(*x);
+                }
+            } else {
+                state[0] = <Self as Poseidon8>::sbox_monomial::<Self, 1>(state[0]);
+            }
+            state = <Self as Poseidon8>::mds_layer(&state);
+        }
+        state
+    }
+}
+
+/// The width-8 Poseidon permutation.
+///
+/// As a `PlonkyPermutation`, it permutes the first 8 elements of a `SPONGE_WIDTH` state and leaves
+/// the others untouched.
+pub struct Poseidon8Permutation;
+
+impl Poseidon8Permutation {
+    pub fn permute<F: Poseidon8>(input: [F; WIDTH]) -> [F; WIDTH] {
+        F::poseidon8(input)
+    }
+}
+
+impl<F: RichField + Poseidon8> PlonkyPermutation<F> for Poseidon8Permutation {
+    const WIDTH: usize = POSEIDON8_WIDTH;
+    const RATE: usize = POSEIDON8_RATE;
+
+    fn permute(mut input: [F; SPONGE_WIDTH]) -> [F; SPONGE_WIDTH] {
+        let state = F::poseidon8(input[..WIDTH].try_into().unwrap());
+        input[..WIDTH].copy_from_slice(&state);
+        input
+    }
+}
+
+/// Poseidon hash function over the width-8 permutation, as a sponge with a rate of 4 elements and
+/// a capacity of 4, in the same overwrite mode as `PoseidonHash`. Its digests differ from those of
+/// `PoseidonHash`.
+///
+/// The challenger uses the width-8 permutation too, so a config with this hasher also runs
+/// Fiat-Shamir over it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Poseidon8Hash;
+
+impl<F: RichField + Poseidon8> Hasher<F> for Poseidon8Hash {
+    const HASH_SIZE: usize = 4 * 8;
+    type Hash = HashOut<F>;
+    type Permutation = Poseidon8Permutation;
+
+    fn hash_no_pad(input: &[F]) -> Self::Hash {
+        hash_n_to_hash_no_pad::<F, Self::Permutation>(input)
+    }
+
+    fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash {
+        compress::<F, Self::Permutation>(left, right)
+    }
+}
+
+impl<F: RichField + Extendable<D> + Poseidon8, const D: usize> CircuitBuilder<F, D> {
+    /// Applies the width-8 Poseidon permutation to `inputs`, using a `Poseidon8Gate`.
+    pub fn permute_poseidon8(&mut self, inputs: [Target; WIDTH]) -> [Target; WIDTH] {
+        let gate = self.add_gate(Poseidon8Gate::<F, D>::new(), vec![]);
+        for i in 0..WIDTH {
+            let in_wire = Target::wire(gate, Poseidon8Gate::<F, D>::wire_input(i));
+            self.connect(inputs[i], in_wire);
+        }
+        (0..WIDTH)
+            .map(|i| Target::wire(gate, Poseidon8Gate::<F, D>::wire_output(i)))
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::field::goldilocks_field::GoldilocksField;
+    use crate::field::types::{Field, Sample};
+    use crate::hash::hash_types::HashOut;
+    use crate::hash::hashing::{PlonkyPermutation, SPONGE_WIDTH};
+    use crate::hash::merkle_proofs::verify_merkle_proof_to_cap;
+    use crate::hash::merkle_tree::MerkleTree;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::hash::poseidon8::{Poseidon8, Poseidon8Hash, Poseidon8Permutation, POSEIDON8_WIDTH};
+    use crate::iop::challenger::Challenger;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
+
+    type F = GoldilocksField;
+
+    #[test]
+    fn test_hash_no_pad() {
+        let input = F::rand_vec(10);
+        let mut state = [F::ZERO; POSEIDON8_WIDTH];
+        for chunk in input.chunks(4) {
+            state[..chunk.len()].copy_from_slice(chunk);
+            state = Poseidon8Permutation::permute(state);
+        }
+        let hash = Poseidon8Hash::hash_no_pad(&input);
+        assert_eq!(hash.elements, state[..4]);
+        assert_ne!(hash, PoseidonHash::hash_no_pad(&input));
+
+        let (left, right) = (HashOut::<F>::rand(), HashOut::<F>::rand());
+        let mut state = [F::ZERO; POSEIDON8_WIDTH];
+        state[..4].copy_from_slice(&left.elements);
+        state[4..].copy_from_slice(&right.elements);
+        assert_eq!(
+            Poseidon8Hash::two_to_one(left, right).elements,
+            F::poseidon8(state)[..4]
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "poseidon_fast_insecure"))]
+    fn test_vectors_through_hasher() {
+        type P = <Poseidon8Hash as Hasher<F>>::Permutation;
+        assert_eq!(<P as PlonkyPermutation<F>>::WIDTH, 8);
+        assert_eq!(<P as PlonkyPermutation<F>>::RATE, 4);
+
+        // The `0..8` vector from `poseidon_goldilocks::tests::test_vectors8`; the rest of the
+        // state is left alone.
+        #[rustfmt::skip]
+        let expected: [u64; SPONGE_WIDTH] = [
+            0x3894d8cdad0cc639, 0x157cf1c63f7e52f9, 0x82c8256237511769, 0x3cfd74680fe3a144,
+            0x8cb820a7e5109e24, 0xbd59bdd01ffc5027, 0x9abebcdc850eaea7, 0x4a489140f325b331,
+            8, 9, 10, 11,
+        ];
+        let input = core::array::from_fn(F::from_canonical_usize);
+        let output = <P as PlonkyPermutation<F>>::permute(input);
+        assert_eq!(output, expected.map(F::from_canonical_u64));
+
+        let left = HashOut::from_partial(&input[..4]);
+        let right = HashOut::from_partial(&input[4..8]);
+        assert_eq!(Poseidon8Hash::two_to_one(left, right).elements, output[..4]);
+
+        // A single block of zeros gives the all-zeros vector, and so does a challenger which has
+        // observed one. The challenger squeezes from the end of the rate.
+        #[rustfmt::skip]
+        let expected_zeros: [u64; 4] =
+            [0x8974a83d1bfd91c8, 0x5dff62939d9542d2, 0x9a1e1d42009b1a7a, 0xfe4b5c81afc946f8];
+        assert_eq!(
+            Poseidon8Hash::hash_no_pad(&[F::ZERO; 4]).elements,
+            expected_zeros.map(F::from_canonical_u64)
+        );
+        let mut challenger = Challenger::<F, Poseidon8Hash>::new();
+        challenger.observe_elements(&[F::ZERO; 4]);
+        assert_eq!(
+            challenger.get_challenge(),
+            F::from_canonical_u64(expected_zeros[3])
+        );
+    }
+
+    #[test]
+    fn test_merkle_tree() -> Result<()> {
+        type H = Poseidon8Hash;
+        let leaves = (0..16).map(|_| F::rand_vec(6)).collect::<Vec<_>>();
+        let tree = MerkleTree::<F, H>::new(leaves.clone(), 1);
+        for (i, leaf) in leaves.into_iter().enumerate() {
+            verify_merkle_proof_to_cap(leaf, i, &tree.cap, &tree.prove(i))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_permute_poseidon8_circuit() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let inputs = F::rand_array::<POSEIDON8_WIDTH>();
+        let inputs_t = builder.add_virtual_target_arr::<POSEIDON8_WIDTH>();
+        pw.set_target_arr(inputs_t, inputs);
+        let outputs_t = builder.permute_poseidon8(inputs_t);
+        for (t, x) in outputs_t.into_iter().zip(F::poseidon8(inputs)) {
+            let expected = builder.constant(x);
+            builder.connect(t, expected);
+        }
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+}
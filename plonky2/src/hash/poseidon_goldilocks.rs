@@ -9,6 +9,14 @@ use unroll::unroll_for_loops;
 
 use crate::field::goldilocks_field::GoldilocksField;
 use crate::hash::poseidon::{Poseidon, N_PARTIAL_ROUNDS};
+use crate::hash::poseidon8;
+
+// Referencing a deprecated item is the only way to emit a compile-time warning on stable Rust.
+#[cfg(feature = "poseidon_fast_insecure")]
+#[deprecated(note = "poseidon_fast_insecure is INSECURE; only enable it for tests")]
+const POSEIDON_FAST_INSECURE: () = ();
+#[cfg(feature = "poseidon_fast_insecure")]
+const _: () = POSEIDON_FAST_INSECURE;
 
 #[rustfmt::skip]
 impl Poseidon for GoldilocksField {
@@ -24,12 +32,14 @@ impl Poseidon for GoldilocksField {
     const MDS_MATRIX_CIRC: [u64; 12] = [17, 15, 41, 16, 2, 28, 13, 13, 39, 18, 34, 20];
     const MDS_MATRIX_DIAG: [u64; 12] = [8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
 
+    #[cfg(not(feature = "poseidon_fast_insecure"))]
     const FAST_PARTIAL_FIRST_ROUND_CONSTANT: [u64; 12]  = [
         0x3cc3f892184df408, 0xe993fd841e7e97f1, 0xf2831d3575f0f3af, 0xd2500e0a350994ca,
         0xc5571f35d7288633, 0x91d89c5184109a02, 0xf37f925d04e5667b, 0x2d6e448371955a69,
         0x740ef19ce01398a1, 0x694d24c0752fdf45, 0x60936af96ee2f148, 0xc33448feadc78f0c,
     ];
 
+    #[cfg(not(feature = "poseidon_fast_insecure"))]
     const FAST_PARTIAL_ROUND_CONSTANTS: [u64; N_PARTIAL_ROUNDS]  = [
         0x74cb2e819ae421ab, 0xd2559d2370e7f663, 0x62bf78acf843d17c, 0xd5ab7b67e14d1fb4,
         0xb9fe2ae6e0969bdc, 0xe33fdf79f92a10e8, 0x0ea2bb4c2b25989b, 0xca9121fbf9d38f06,
@@ -39,6 +49,7 @@ impl Poseidon for GoldilocksField {
         0x1aca78f31c97c876, 0x0,
     ];
 
+    #[cfg(not(feature = "poseidon_fast_insecure"))]
     const FAST_PARTIAL_ROUND_VS: [[u64; 12 - 1]; N_PARTIAL_ROUNDS] = [
         [0x94877900674181c3, 0xc6c67cc37a2a2bbd, 0xd667c2055387940f, 0x0ba63a63e94b5ff0,
          0x99460cc41b8f079f, 0x7ff02375ed524bb3, 0xea0870b47a8caf0e, 0xabcad82633b7bc9d,
@@ -108,6 +119,7 @@ impl Poseidon for GoldilocksField {
          0x0000000000000010, 0x0000000000000029, 0x000000000000000f, ],
     ];
 
+    #[cfg(not(feature = "poseidon_fast_insecure"))]
     const FAST_PARTIAL_ROUND_W_HATS: [[u64; 12 - 1]; N_PARTIAL_ROUNDS] = [
         [0x3d999c961b7c63b0, 0x814e82efcd172529, 0x2421e5d236704588, 0x887af7d4dd482328,
          0xa5e9c291f6119b27, 0xbdc52b2676a4b4aa, 0x64832009d29bcf57, 0x09c4155174a552cc,
@@ -178,6 +190,7 @@ impl Poseidon for GoldilocksField {
     ];
 
     // NB: This is in ROW-major order to support cache-friendly pre-multiplication.
+    #[cfg(not(feature = "poseidon_fast_insecure"))]
     const FAST_PARTIAL_ROUND_INITIAL_MATRIX: [[u64; 12 - 1]; 12 - 1] = [
         [0x80772dc2645b280b, 0xdc927721da922cf8, 0xc1978156516879ad, 0x90e80c591f48b603,
          0x3a2432625475e3ae, 0x00a2d4321cca94fe, 0x77736f524010c932, 0x904d3f2804a36c54,
@@ -214,6 +227,90 @@ impl Poseidon for GoldilocksField {
          0xdcedab70f40718ba, 0xe796d293a47a64cb, 0x80772dc2645b280b, ],
     ];
 
+    // Constants for the reduced number of partial rounds of the `poseidon_fast_insecure` feature,
+    // generated the same way from the same round constants and MDS matrix.
+    #[cfg(feature = "poseidon_fast_insecure")]
+    const FAST_PARTIAL_FIRST_ROUND_CONSTANT: [u64; 12]  = [
+        0x3cc3f892184df408, 0x6413bbc80e0e251a, 0x5833e6f0d5bed449, 0x1ee6b14fceeaeb79,
+        0xb8877ada947cfa67, 0x898feb4c448d3f6a, 0x4f7d9b5bcde3f911, 0xfd90ad63942ea499,
+        0xd0e9e8d573a1a273, 0xe60a8e4b4236d84c, 0xafcceb41da3e5bcb, 0x57f363f57511f3e5,
+    ];
+
+    #[cfg(feature = "poseidon_fast_insecure")]
+    const FAST_PARTIAL_ROUND_CONSTANTS: [u64; N_PARTIAL_ROUNDS]  = [
+        0x130d6baf3c824ba3, 0xccaf1d2e97ee5c4d, 0xd5bb22364c71ed9b, 0x0,
+    ];
+
+    #[cfg(feature = "poseidon_fast_insecure")]
+    const FAST_PARTIAL_ROUND_VS: [[u64; 12 - 1]; N_PARTIAL_ROUNDS] = [
+        [0x0000000011131738, 0x000000000f56d588, 0x0000000011050f86, 0x000000000f848f4f,
+         0x00000000111527d3, 0x00000000114369a1, 0x00000000106f2f38, 0x0000000011e2ca94,
+         0x00000000110a29f0, 0x000000000fa9f5c1, 0x0000000010f625d1, ],
+        [0x000000000011f718, 0x000000000010b6c8, 0x0000000000134a96, 0x000000000010cf7f,
+         0x0000000000124d03, 0x000000000013f8a1, 0x0000000000117c58, 0x0000000000132c94,
+         0x0000000000134fc0, 0x000000000010a091, 0x0000000000128961, ],
+        [0x0000000000001300, 0x0000000000001750, 0x000000000000114e, 0x000000000000131f,
+         0x000000000000167b, 0x0000000000001371, 0x0000000000001230, 0x000000000000182c,
+         0x0000000000001368, 0x0000000000000f31, 0x00000000000015c9, ],
+        [0x0000000000000014, 0x0000000000000022, 0x0000000000000012, 0x0000000000000027,
+         0x000000000000000d, 0x000000000000000d, 0x000000000000001c, 0x0000000000000002,
+         0x0000000000000010, 0x0000000000000029, 0x000000000000000f, ],
+    ];
+
+    #[cfg(feature = "poseidon_fast_insecure")]
+    const FAST_PARTIAL_ROUND_W_HATS: [[u64; 12 - 1]; N_PARTIAL_ROUNDS] = [
+        [0x49026cc3a4afc5a6, 0xe06dff00ab25b91b, 0x0ab38c561e8850ff, 0x92c3c8275e105eeb,
+         0xb65256e546889bd0, 0x3c0468236ea142f6, 0xee61766b889e18f2, 0xa206f41b12c30415,
+         0x02fe9d756c9f12d1, 0xe9633210630cbf12, 0x1ffea9fe85a0b0b1, ],
+        [0x81d1ae8cc50240f3, 0xf4c77a079a4607d7, 0xed446b2315e3efc1, 0x0b0a6b70915178c3,
+         0xb11ff3e089f15d9a, 0x1d4dba0b7ae9cc18, 0x65d74e2f43b48d05, 0xa2df8c6b8ae0804a,
+         0xa4e6f0a8c33348a6, 0xc0a26efc7be5669b, 0xa6b6582c547d0d60, ],
+        [0x84afc741f1c13213, 0x2f8f43734fc906f3, 0xde682d72da0a02d9, 0x0bb005236adb9ef2,
+         0x5bdf35c10a8b5624, 0x0739a8a343950010, 0x52f515f44785cfbc, 0xcbaf4e5d82856c60,
+         0xac9ea09074e3e150, 0x8f0fa011a2035fb0, 0x1a37905d8450904a, ],
+        [0x3abeb80def61cc85, 0x9d19c9dd4eac4133, 0x075a652d9641a985, 0x9daf69ae1b67e667,
+         0x364f71da77920a18, 0x50bd769f745c95b1, 0xf223d1180dbbf3fc, 0x2f885e584e04aa99,
+         0xb69a0fa70aea684a, 0x09584acaa6e062a0, 0x0bc051640145b19b, ],
+    ];
+
+    // NB: This is in ROW-major order to support cache-friendly pre-multiplication.
+    #[cfg(feature = "poseidon_fast_insecure")]
+    const FAST_PARTIAL_ROUND_INITIAL_MATRIX: [[u64; 12 - 1]; 12 - 1] = [
+        [0x0000000010d29150, 0x000000000fa59e48, 0x000000001115540e, 0x000000000f534277,
+         0x00000000115e3773, 0x000000001140c469, 0x0000000010505020, 0x000000001205b3f4,
+         0x00000000111a9978, 0x000000000f8a5b99, 0x00000000113cbe11, ],
+        [0x000000000f6520b8, 0x000000000e217658, 0x000000000fbdff76, 0x000000000e057297,
+         0x000000000fac42ab, 0x000000000fe4ead9, 0x000000000edd5338, 0x0000000010596724,
+         0x000000000fb3abb0, 0x000000000e3480f9, 0x000000000f8a5b99, ],
+        [0x000000001126ca08, 0x000000000f6c2030, 0x000000001117db38, 0x000000000f99db50,
+         0x000000001130e088, 0x0000000011539bd0, 0x0000000010934ef8, 0x0000000011ed75d0,
+         0x000000001125ef08, 0x000000000fb3abb0, 0x00000000111a9978, ],
+        [0x0000000011ae68c0, 0x00000000106104d0, 0x0000000011f911a8, 0x000000001015c89c,
+         0x00000000122cebdc, 0x00000000122dc8f4, 0x0000000011165d80, 0x0000000012e8b660,
+         0x0000000011ed75d0, 0x0000000010596724, 0x000000001205b3f4, ],
+        [0x00000000103268d8, 0x000000000ed2f6a0, 0x000000001090b358, 0x000000000eb5b548,
+         0x00000000106545c0, 0x0000000010beb878, 0x000000000f9d62a8, 0x0000000011165d80,
+         0x0000000010934ef8, 0x000000000edd5338, 0x0000000010505020, ],
+        [0x00000000114cbe78, 0x000000000fa032c8, 0x0000000011572236, 0x000000000fbbfdb7,
+         0x000000001164b79b, 0x0000000011886019, 0x0000000010beb878, 0x00000000122dc8f4,
+         0x0000000011539bd0, 0x000000000fe4ead9, 0x000000001140c469, ],
+        [0x0000000011007790, 0x000000000fb81f58, 0x0000000011438f4a, 0x000000000f7d0e85,
+         0x0000000011775c99, 0x000000001164b79b, 0x00000000106545c0, 0x00000000122cebdc,
+         0x000000001130e088, 0x000000000fac42ab, 0x00000000115e3773, ],
+        [0x000000000f38e5c8, 0x000000000dee66a8, 0x000000000f9007ea, 0x000000000dde82d9,
+         0x000000000f7d0e85, 0x000000000fbbfdb7, 0x000000000eb5b548, 0x000000001015c89c,
+         0x000000000f99db50, 0x000000000e057297, 0x000000000f534277, ],
+        [0x00000000112b9a48, 0x000000000f762580, 0x000000001120ed8c, 0x000000000f9007ea,
+         0x0000000011438f4a, 0x0000000011572236, 0x000000001090b358, 0x0000000011f911a8,
+         0x000000001117db38, 0x000000000fbdff76, 0x000000001115540e, ],
+        [0x000000000f50c860, 0x000000000e2d8b90, 0x000000000f762580, 0x000000000dee66a8,
+         0x000000000fb81f58, 0x000000000fa032c8, 0x000000000ed2f6a0, 0x00000000106104d0,
+         0x000000000f6c2030, 0x000000000e217658, 0x000000000fa59e48, ],
+        [0x0000000010b404a8, 0x000000000f50c860, 0x00000000112b9a48, 0x000000000f38e5c8,
+         0x0000000011007790, 0x00000000114cbe78, 0x00000000103268d8, 0x0000000011ae68c0,
+         0x000000001126ca08, 0x000000000f6520b8, 0x0000000010d29150, ],
+    ];
+
     #[cfg(target_arch="x86_64")]
     #[inline(always)]
     #[unroll_for_loops]
@@ -304,6 +401,13 @@ impl Poseidon for GoldilocksField {
     }
 }
 
+impl poseidon8::Poseidon8 for GoldilocksField {
+    // The first eight entries of the width-12 circulant row, which also give an MDS matrix: every
+    // square submatrix of C + D is nonsingular over Goldilocks.
+    const MDS_MATRIX_CIRC: [u64; 8] = [17, 15, 41, 16, 2, 28, 13, 13];
+    const MDS_MATRIX_DIAG: [u64; 8] = [8, 0, 0, 0, 0, 0, 0, 0];
+}
+
 // MDS layer helper methods
 // The following code has been adapted from winterfell/crypto/src/hash/mds/mds_f64_12x12.rs
 // located at https://github.com/facebook/winterfell.
@@ -443,10 +547,16 @@ fn block3(x: [i64; 3], y: [i64; 3]) -> [i64; 3] {
 #[cfg(test)]
 mod tests {
     use crate::field::goldilocks_field::GoldilocksField as F;
-    use crate::field::types::{Field, PrimeField64};
-    use crate::hash::poseidon::test_helpers::{check_consistency, check_test_vectors};
+    use crate::field::types::Field;
+    #[cfg(not(feature = "poseidon_fast_insecure"))]
+    use crate::field::types::PrimeField64;
+    #[cfg(not(feature = "poseidon_fast_insecure"))]
+    use crate::hash::poseidon::test_helpers::check_test_vectors;
+    use crate::hash::poseidon::test_helpers::{check_batch_consistency, check_consistency};
+    use crate::hash::poseidon8::Poseidon8;
 
     #[test]
+    #[cfg(not(feature = "poseidon_fast_insecure"))]
     fn test_vectors() {
         // Test inputs are:
         // 1. all zeros
@@ -484,8 +594,114 @@ mod tests {
         check_test_vectors::<F>(test_vectors12);
     }
 
+    #[test]
+    #[cfg(not(feature = "poseidon_fast_insecure"))]
+    fn test_vectors8() {
+        // Same inputs as for width 12, except that the random ones are fresh. Expected outputs
+        // were calculated with a direct Python implementation of the permutation, which also
+        // reproduces the width-12 vectors above.
+        let neg_one: u64 = F::NEG_ONE.to_canonical_u64();
+
+        #[rustfmt::skip]
+        let test_vectors8: Vec<([u64; 8], [u64; 8])> = vec![
+            ([0, 0, 0, 0, 0, 0, 0, 0, ],
+             [0x8974a83d1bfd91c8, 0x5dff62939d9542d2, 0x9a1e1d42009b1a7a, 0xfe4b5c81afc946f8,
+              0x31d46dab3bb0c4e3, 0x77c1ac79a8da82f3, 0xec7a848cdf2836b7, 0x2be7e5038a7a84f1, ]),
+            ([0, 1, 2, 3, 4, 5, 6, 7, ],
+             [0x3894d8cdad0cc639, 0x157cf1c63f7e52f9, 0x82c8256237511769, 0x3cfd74680fe3a144,
+              0x8cb820a7e5109e24, 0xbd59bdd01ffc5027, 0x9abebcdc850eaea7, 0x4a489140f325b331, ]),
+            ([neg_one, neg_one, neg_one, neg_one,
+              neg_one, neg_one, neg_one, neg_one, ],
+             [0xba162a3f15293f11, 0x7564092e59ecaa85, 0xbfc578776cb41079, 0xf386053e63ed64de,
+              0xfbc0b3e1d4f4a86c, 0xa01335cc48a6a506, 0xfbc49265b8fca60f, 0xa3726b10aabc7ce9, ]),
+            ([0x5ed34fe53a096533, 0x6018366cf658f7a7, 0x317017a6205738d1, 0x0b3510b0b46ee1da,
+              0x230824d215ceb3a1, 0xcfaf00103f584ad4, 0x81a0d5b3ffc6e35c, 0x6694f229359b1548, ],
+             [0x429d5e1eda3b48ab, 0xa5a4c6a770a55d8e, 0x3ae8b937a1878d91, 0x588636706d228abd,
+              0x7a91cfcd6f0cfbdb, 0xcb374265b7953229, 0x7bb2cf0e22d93240, 0xc57044ce2eff8ec8, ]),
+        ];
+
+        for (input, expected) in test_vectors8 {
+            let output = F::poseidon8(input.map(F::from_canonical_u64));
+            assert_eq!(output, expected.map(F::from_canonical_u64));
+        }
+    }
+
+    #[test]
+    fn mds8_is_mds() {
+        // Every square submatrix of the width-8 MDS matrix must be nonsingular.
+        let matrix: [[F; 8]; 8] = core::array::from_fn(|r| {
+            core::array::from_fn(|c| {
+                let diag = if r == c {
+                    <F as Poseidon8>::MDS_MATRIX_DIAG[r]
+                } else {
+                    0
+                };
+                F::from_canonical_u64(<F as Poseidon8>::MDS_MATRIX_CIRC[(c + 8 - r) % 8] + diag)
+            })
+        });
+        for rows in 1u32..1 << 8 {
+            for cols in (1u32..1 << 8).filter(|cols| cols.count_ones() == rows.count_ones()) {
+                let mut sub = (0..8)
+                    .filter(|r| rows & (1 << r) != 0)
+                    .map(|r| {
+                        (0..8)
+                            .filter(|c| cols & (1 << c) != 0)
+                            .map(|c| matrix[r][c])
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+                assert!(is_nonsingular(&mut sub), "rows {rows:#b}, cols {cols:#b}");
+            }
+        }
+    }
+
+    /// Returns whether the square matrix `m` has full rank, using Gaussian elimination.
+    fn is_nonsingular(m: &mut [Vec<F>]) -> bool {
+        let n = m.len();
+        for i in 0..n {
+            let pivot = match (i..n).find(|&k| m[k][i].is_nonzero()) {
+                Some(pivot) => pivot,
+                None => return false,
+            };
+            m.swap(i, pivot);
+            let inv = m[i][i].inverse();
+            for k in i + 1..n {
+                let factor = m[k][i] * inv;
+                for j in i..n {
+                    let x = m[i][j];
+                    m[k][j] -= factor * x;
+                }
+            }
+        }
+        true
+    }
+
     #[test]
     fn consistency() {
         check_consistency::<F>();
     }
+
+    #[test]
+    fn batch_consistency() {
+        check_batch_consistency::<F>();
+    }
+
+    #[test]
+    #[cfg(feature = "poseidon_fast_insecure")]
+    fn reduced_rounds_deterministic() {
+        use crate::hash::hashing::SPONGE_WIDTH;
+        use crate::hash::poseidon::{Poseidon, N_PARTIAL_ROUNDS};
+
+        assert_eq!(N_PARTIAL_ROUNDS, 4);
+        let inputs: [[F; SPONGE_WIDTH]; 3] = [
+            [F::ZERO; SPONGE_WIDTH],
+            core::array::from_fn(|i| F::from_canonical_usize(i)),
+            [F::NEG_ONE; SPONGE_WIDTH],
+        ];
+        for input in inputs {
+            let output = F::poseidon(input);
+            assert_eq!(output, F::poseidon(input));
+            assert_eq!(output, F::poseidon_naive(input));
+        }
+    }
 }
@@ -7,9 +7,18 @@ use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField
 use crate::hash::hashing::{PlonkyPermutation, SPONGE_RATE, SPONGE_WIDTH};
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::ext_target::ExtensionTarget;
-use crate::iop::target::Target;
+use crate::iop::target::{BoolTarget, Target};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::{AlgebraicHasher, GenericHashOut, Hasher};
+use crate::util::log2_strict;
+
+/// The number of bits taken from each field challenge by `get_challenge_bits`. Taking only the
+/// low half of a uniformly random element of a ~64-bit field keeps the bias of each bit negligible.
+const CHALLENGE_BITS_PER_ELEMENT: usize = 32;
+
+/// The number of bytes packed into each field element by `observe_bytes`. Seven bytes always fit
+/// below the order of a ~64-bit field, so the packing is injective.
+const BYTES_PER_ELEMENT: usize = 7;
 
 /// Observes prover messages, and generates challenges by hashing the transcript, a la Fiat-Shamir.
 #[derive(Clone)]
@@ -32,8 +41,8 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
     pub fn new() -> Challenger<F, H> {
         Challenger {
             sponge_state: [F::ZERO; SPONGE_WIDTH],
-            input_buffer: Vec::with_capacity(SPONGE_RATE),
-            output_buffer: Vec::with_capacity(SPONGE_RATE),
+            input_buffer: Vec::with_capacity(H::Permutation::RATE),
+            output_buffer: Vec::with_capacity(H::Permutation::RATE),
             _phantom: Default::default(),
         }
     }
@@ -44,7 +53,7 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
 
         self.input_buffer.push(element);
 
-        if self.input_buffer.len() == SPONGE_RATE {
+        if self.input_buffer.len() == H::Permutation::RATE {
             self.duplexing();
         }
     }
@@ -75,6 +84,28 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
         self.observe_elements(&hash.to_vec())
     }
 
+    /// Observes a byte string. The length is observed first, so that strings which differ only in
+    /// trailing zeros give different transcripts, followed by the bytes in little-endian chunks of
+    /// `BYTES_PER_ELEMENT`. `RecursiveChallenger::observe_byte_bits` uses the same packing.
+    pub fn observe_bytes(&mut self, bytes: &[u8]) {
+        self.observe_element(F::from_canonical_usize(bytes.len()));
+        for chunk in bytes.chunks(BYTES_PER_ELEMENT) {
+            let packed = chunk
+                .iter()
+                .rev()
+                .fold(0u64, |acc, &byte| (acc << 8) | byte as u64);
+            self.observe_element(F::from_canonical_u64(packed));
+        }
+    }
+
+    /// Binds a statement's public inputs into the transcript by observing their `IH` digest, as
+    /// computed by `IH::hash_no_pad`. Both the prover and the verifier do this right after observing
+    /// the circuit digest, so that any change to the public inputs changes every subsequent
+    /// challenge.
+    pub fn observe_public_inputs<IH: Hasher<F>>(&mut self, public_inputs_hash: IH::Hash) {
+        self.observe_hash::<IH>(public_inputs_hash)
+    }
+
     pub fn observe_cap<OH: Hasher<F>>(&mut self, cap: &MerkleCap<F, OH>) {
         for &hash in &cap.0 {
             self.observe_hash::<OH>(hash);
@@ -97,6 +128,29 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
         (0..n).map(|_| self.get_challenge()).collect()
     }
 
+    /// Returns `n` (at most 64) random bits, as the low bits of the result. These are taken
+    /// `CHALLENGE_BITS_PER_ELEMENT` at a time from the low bits of successive challenges, starting
+    /// with the least significant.
+    pub fn get_challenge_bits(&mut self, n: usize) -> u64 {
+        assert!(n <= 64, "Can't sample more than 64 bits at once");
+        let mut bits = 0;
+        for offset in (0..n).step_by(CHALLENGE_BITS_PER_ELEMENT) {
+            let num_bits = (n - offset).min(CHALLENGE_BITS_PER_ELEMENT);
+            let low_bits = self.get_challenge().to_canonical_u64() & ((1 << num_bits) - 1);
+            bits |= low_bits << offset;
+        }
+        bits
+    }
+
+    /// Returns `count` uniformly random indices into a domain of size `domain_size`, which must be
+    /// a power of two.
+    pub fn get_n_challenge_indices(&mut self, count: usize, domain_size: usize) -> Vec<usize> {
+        let num_bits = log2_strict(domain_size);
+        (0..count)
+            .map(|_| self.get_challenge_bits(num_bits) as usize)
+            .collect()
+    }
+
     pub fn get_hash(&mut self) -> HashOut<F> {
         HashOut {
             elements: [
@@ -129,7 +183,7 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
     /// Absorb any buffered inputs. After calling this, the input buffer will be empty, and the
     /// output buffer will be full.
     fn duplexing(&mut self) {
-        assert!(self.input_buffer.len() <= SPONGE_RATE);
+        assert!(self.input_buffer.len() <= H::Permutation::RATE);
 
         // Overwrite the first r elements with the inputs. This differs from a standard sponge,
         // where we would xor or add in the inputs. This is a well-known variant, though,
@@ -143,7 +197,7 @@ impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
 
         self.output_buffer.clear();
         self.output_buffer
-            .extend_from_slice(&self.sponge_state[0..SPONGE_RATE]);
+            .extend_from_slice(&self.sponge_state[0..H::Permutation::RATE]);
     }
 
     pub fn compact(&mut self) -> [F; SPONGE_WIDTH] {
@@ -211,6 +265,19 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
         self.observe_elements(&hash.elements)
     }
 
+    /// Observes a byte string given as bits, which must be little-endian within each byte. This
+    /// matches `Challenger::observe_bytes` on the same bytes. The bits are not range checked here,
+    /// so they should come from `BoolTarget`s that are already constrained.
+    pub fn observe_byte_bits(&mut self, bits: &[BoolTarget], builder: &mut CircuitBuilder<F, D>) {
+        assert_eq!(bits.len() % 8, 0, "Bits must come in whole bytes");
+        let len = builder.constant(F::from_canonical_usize(bits.len() / 8));
+        self.observe_element(len);
+        for chunk in bits.chunks(8 * BYTES_PER_ELEMENT) {
+            let packed = builder.le_sum(chunk.iter());
+            self.observe_element(packed);
+        }
+    }
+
     pub fn observe_cap(&mut self, cap: &MerkleCapTarget) {
         for hash in &cap.0 {
             self.observe_hash(hash)
@@ -249,6 +316,37 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
         (0..n).map(|_| self.get_challenge(builder)).collect()
     }
 
+    /// In-circuit version of `Challenger::get_challenge_bits`, returning the `n` bits in
+    /// little-endian order.
+    pub fn get_challenge_bits(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        n: usize,
+    ) -> Vec<BoolTarget> {
+        assert!(n <= 64, "Can't sample more than 64 bits at once");
+        let mut bits = Vec::with_capacity(n);
+        for offset in (0..n).step_by(CHALLENGE_BITS_PER_ELEMENT) {
+            let num_bits = (n - offset).min(CHALLENGE_BITS_PER_ELEMENT);
+            let challenge = self.get_challenge(builder);
+            bits.extend(builder.low_bits(challenge, num_bits, F::BITS));
+        }
+        bits
+    }
+
+    /// In-circuit version of `Challenger::get_n_challenge_indices`, returning the little-endian
+    /// bits of each index.
+    pub fn get_n_challenge_indices(
+        &mut self,
+        builder: &mut CircuitBuilder<F, D>,
+        count: usize,
+        domain_size: usize,
+    ) -> Vec<Vec<BoolTarget>> {
+        let num_bits = log2_strict(domain_size);
+        (0..count)
+            .map(|_| self.get_challenge_bits(builder, num_bits))
+            .collect()
+    }
+
     pub fn get_hash(&mut self, builder: &mut CircuitBuilder<F, D>) -> HashOutTarget {
         HashOutTarget {
             elements: [
@@ -300,11 +398,14 @@ impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
 
 #[cfg(test)]
 mod tests {
+    use rand::rngs::OsRng;
+    use rand::Rng;
+
     use crate::field::types::Sample;
     use crate::iop::challenger::{Challenger, RecursiveChallenger};
     use crate::iop::generator::generate_partial_witness;
-    use crate::iop::target::Target;
-    use crate::iop::witness::{PartialWitness, Witness};
+    use crate::iop::target::{BoolTarget, Target};
+    use crate::iop::witness::{PartialWitness, Witness, WitnessWrite};
     use crate::plonk::circuit_builder::CircuitBuilder;
     use crate::plonk::circuit_data::CircuitConfig;
     use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
@@ -376,4 +477,87 @@ mod tests {
 
         assert_eq!(outputs_per_round, recursive_output_values_per_round);
     }
+
+    /// Tests that `Challenger` and `RecursiveChallenger` pack bytes identically.
+    #[test]
+    fn test_observe_bytes_consistency() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::InnerHasher;
+
+        // Not a multiple of `BYTES_PER_ELEMENT`, so the last chunk is partial.
+        let bytes = (0..30).map(|_| OsRng.gen::<u8>()).collect::<Vec<_>>();
+        let mut challenger = Challenger::<F, H>::new();
+        challenger.observe_bytes(&bytes);
+        let challenges = challenger.get_n_challenges(3);
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let mut pw = PartialWitness::new();
+        let bits_t = bytes
+            .iter()
+            .flat_map(|&byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
+            .map(|b| {
+                let b_t = builder.add_virtual_bool_target_safe();
+                pw.set_bool_target(b_t, b);
+                b_t
+            })
+            .collect::<Vec<_>>();
+        let mut recursive_challenger = RecursiveChallenger::<F, H, D>::new(&mut builder);
+        recursive_challenger.observe_byte_bits(&bits_t, &mut builder);
+        let challenges_t = recursive_challenger.get_n_challenges(&mut builder, 3);
+        let circuit = builder.build::<C>();
+        let witness = generate_partial_witness(pw, &circuit.prover_only, &circuit.common);
+
+        assert_eq!(witness.get_targets(&challenges_t), challenges);
+
+        // The length tag distinguishes trailing zeros.
+        let mut challenger_a = Challenger::<F, H>::new();
+        challenger_a.observe_bytes(&[1]);
+        let mut challenger_b = Challenger::<F, H>::new();
+        challenger_b.observe_bytes(&[1, 0]);
+        assert_ne!(challenger_a.get_challenge(), challenger_b.get_challenge());
+    }
+
+    /// Tests that `Challenger` and `RecursiveChallenger` sample the same bits and indices.
+    #[test]
+    fn test_challenge_bits_consistency() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type H = <C as GenericConfig<D>>::InnerHasher;
+        const NUM_BITS: [usize; 5] = [0, 1, 20, 33, 64];
+        const DOMAIN_SIZE: usize = 1 << 12;
+
+        let transcript = F::rand_vec(7);
+        let mut challenger = Challenger::<F, H>::new();
+        challenger.observe_elements(&transcript);
+        let bits = NUM_BITS.map(|n| challenger.get_challenge_bits(n));
+        let indices = challenger.get_n_challenge_indices(5, DOMAIN_SIZE);
+        assert!(indices.iter().all(|&i| i < DOMAIN_SIZE));
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let mut recursive_challenger = RecursiveChallenger::<F, H, D>::new(&mut builder);
+        recursive_challenger.observe_elements(&builder.constants(&transcript));
+        let bits_t = NUM_BITS.map(|n| recursive_challenger.get_challenge_bits(&mut builder, n));
+        let indices_t = recursive_challenger.get_n_challenge_indices(&mut builder, 5, DOMAIN_SIZE);
+        let circuit = builder.build::<C>();
+        let witness =
+            generate_partial_witness(PartialWitness::new(), &circuit.prover_only, &circuit.common);
+
+        let le_value = |bits: &[BoolTarget]| {
+            bits.iter().rev().fold(0u64, |acc, &b| {
+                (acc << 1) | witness.get_bool_target(b) as u64
+            })
+        };
+        for (n, (&expected, bits_t)) in NUM_BITS.iter().zip(bits.iter().zip(&bits_t)) {
+            assert_eq!(bits_t.len(), *n);
+            assert_eq!(le_value(bits_t), expected);
+        }
+        for (&expected, bits_t) in indices.iter().zip(&indices_t) {
+            assert_eq!(le_value(bits_t), expected as u64);
+        }
+    }
 }
@@ -86,6 +86,11 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         ExtensionTarget(parts)
     }
 
+    /// Lifts a base field constant into the extension field, i.e. `[c, 0, ..., 0]`.
+    pub fn constant_ext_from_base(&mut self, c: F) -> ExtensionTarget<D> {
+        self.constant_extension(F::Extension::from_basefield(c))
+    }
+
     pub fn constant_ext_algebra(
         &mut self,
         c: ExtensionAlgebra<F::Extension, D>,
@@ -145,3 +150,27 @@ pub fn unflatten_target<const D: usize>(l: &[Target]) -> Vec<ExtensionTarget<D>>
         .map(|c| c.to_vec().try_into().unwrap())
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::field::types::Field;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    #[test]
+    fn test_constant_ext_from_base() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let c = F::from_canonical_u64(4);
+        let ext = builder.constant_ext_from_base(c);
+
+        // Constants are deduplicated, so each limb should be the same target as the corresponding
+        // base field constant.
+        let expected = [builder.constant(c), builder.zero()];
+        assert_eq!(ext.0, expected);
+    }
+}
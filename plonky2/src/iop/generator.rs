@@ -1,10 +1,26 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
+use core::any::Any;
 use core::fmt::Debug;
 use core::marker::PhantomData;
 
+#[cfg(any(test, feature = "parallel"))]
+use plonky2_maybe_rayon::*;
+
 use crate::field::extension::Extendable;
 use crate::field::types::Field;
+use crate::gadgets::arithmetic::{
+    EqualityGenerator, InverseGenerator, SqrtGenerator, SubWithBorrowGenerator,
+};
+use crate::gadgets::arithmetic_extension::QuotientGeneratorExtension;
+use crate::gadgets::nonnative::CarryGenerator;
+use crate::gadgets::range_check::LowHighGenerator;
+use crate::gadgets::sorted_set::SortedSetIndexGenerator;
+use crate::gadgets::split_base::BaseSumGenerator;
+use crate::gadgets::split_join::{SplitGenerator, WireSplitGenerator};
 use crate::hash::hash_types::RichField;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
@@ -12,6 +28,7 @@ use crate::iop::wire::Wire;
 use crate::iop::witness::{PartialWitness, PartitionWitness, Witness, WitnessWrite};
 use crate::plonk::circuit_data::{CommonCircuitData, ProverOnlyCircuitData};
 use crate::plonk::config::GenericConfig;
+use crate::util::serialization::{Buffer, IoError, IoResult, Read, Write};
 
 /// Given a `PartitionWitness` that has only inputs set, populates the rest of the witness using the
 /// given set of generators.
@@ -25,9 +42,34 @@ pub(crate) fn generate_partial_witness<
     prover_data: &'a ProverOnlyCircuitData<F, C, D>,
     common_data: &'a CommonCircuitData<F, D>,
 ) -> PartitionWitness<'a, F> {
+    let (witness, outcome) = generate_partial_witness_unchecked(inputs, prover_data, common_data);
+    if let Some((target, old_value, value)) = outcome.first_conflict {
+        panic!(
+            "Partition containing {:?} was set twice with different values: {} != {}",
+            target, old_value, value
+        );
+    }
+    assert_eq!(
+        outcome.num_unfinished_generators, 0,
+        "{} generators weren't run",
+        outcome.num_unfinished_generators,
+    );
+    witness
+}
+
+/// Same as `generate_partial_witness`, but rather than panicking on a bad witness, reports any
+/// problems in the returned `GenerationOutcome`, alongside whatever witness could be generated.
+pub(crate) fn generate_partial_witness_unchecked<
+    'a,
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    inputs: PartialWitness<F>,
+    prover_data: &'a ProverOnlyCircuitData<F, C, D>,
+    common_data: &'a CommonCircuitData<F, D>,
+) -> (PartitionWitness<'a, F>, GenerationOutcome<F>) {
     let config = &common_data.config;
-    let generators = &prover_data.generators;
-    let generator_indices_by_watches = &prover_data.generator_indices_by_watches;
 
     let mut witness = PartitionWitness::new(
         config.num_wires,
@@ -35,10 +77,71 @@ pub(crate) fn generate_partial_witness<
         &prover_data.representative_map,
     );
 
+    let mut outcome = GenerationOutcome {
+        num_unfinished_generators: 0,
+        first_conflict: None,
+    };
     for (t, v) in inputs.target_values.into_iter() {
-        witness.set_target(t, v);
+        outcome.set_target(&mut witness, t, v);
+    }
+
+    #[cfg(feature = "parallel")]
+    let run_generators = if prover_data.parallel_generators {
+        run_generators_parallel
+    } else {
+        run_generators_sequential
+    };
+    #[cfg(not(feature = "parallel"))]
+    let run_generators = run_generators_sequential;
+    run_generators(
+        &mut witness,
+        &prover_data.generators,
+        &prover_data.generator_indices_by_watches,
+        &mut outcome,
+    );
+
+    (witness, outcome)
+}
+
+/// What went wrong, if anything, while generating a witness.
+#[derive(Debug)]
+pub(crate) struct GenerationOutcome<F: Field> {
+    /// The number of generators which never finished, because some targets they depend on were
+    /// never set.
+    pub(crate) num_unfinished_generators: usize,
+    /// The first value which conflicted with the value already held by its target's partition, as
+    /// `(target, old_value, value)`. The old value is kept, so the gate which produced the new value
+    /// is left unsatisfied.
+    pub(crate) first_conflict: Option<(Target, F, F)>,
+}
+
+impl<F: Field> GenerationOutcome<F> {
+    /// Sets `target`, recording any conflict. Returns the representative index of the target if it
+    /// was newly set.
+    fn set_target(
+        &mut self,
+        witness: &mut PartitionWitness<F>,
+        target: Target,
+        value: F,
+    ) -> Option<usize> {
+        match witness.try_set_target_returning_rep(target, value) {
+            Ok(rep) => rep,
+            Err(old_value) => {
+                self.first_conflict
+                    .get_or_insert((target, old_value, value));
+                None
+            }
+        }
     }
+}
 
+/// Runs generators one at a time, until none of them can make progress.
+fn run_generators_sequential<F: Field>(
+    witness: &mut PartitionWitness<F>,
+    generators: &[Box<dyn WitnessGenerator<F>>],
+    generator_indices_by_watches: &BTreeMap<usize, Vec<usize>>,
+    outcome: &mut GenerationOutcome<F>,
+) {
     // Build a list of "pending" generators which are queued to be run. Initially, all generators
     // are queued.
     let mut pending_generator_indices: Vec<_> = (0..generators.len()).collect();
@@ -58,7 +161,7 @@ pub(crate) fn generate_partial_witness<
                 continue;
             }
 
-            let finished = generators[generator_idx].run(&witness, &mut buffer);
+            let finished = generators[generator_idx].run(witness, &mut buffer);
             if finished {
                 generator_is_expired[generator_idx] = true;
                 remaining_generators -= 1;
@@ -69,7 +172,7 @@ pub(crate) fn generate_partial_witness<
             let new_target_reps = buffer
                 .target_values
                 .drain(..)
-                .flat_map(|(t, v)| witness.set_target_returning_rep(t, v));
+                .flat_map(|(t, v)| outcome.set_target(witness, t, v));
 
             // Enqueue unfinished generators that were watching one of the newly populated targets.
             for watch in new_target_reps {
@@ -87,13 +190,69 @@ pub(crate) fn generate_partial_witness<
         pending_generator_indices = next_pending_generator_indices;
     }
 
-    assert_eq!(
-        remaining_generators, 0,
-        "{} generators weren't run",
-        remaining_generators,
-    );
+    outcome.num_unfinished_generators = remaining_generators;
+}
 
-    witness
+/// Runs generators in rounds. Within a round, all pending generators run concurrently against the
+/// witness as it was at the start of the round. Their outputs are then merged in order of generator
+/// index, so the resulting witness doesn't depend on how threads were scheduled, and conflicting
+/// writes to a partition are caught just as in the sequential solver.
+///
+/// A chain of dependent generators takes one round per link, so circuits dominated by long chains
+/// can be slower than with the sequential solver, which can be selected instead by clearing
+/// `ProverOnlyCircuitData::parallel_generators`.
+#[cfg(any(test, feature = "parallel"))]
+fn run_generators_parallel<F: Field>(
+    witness: &mut PartitionWitness<F>,
+    generators: &[Box<dyn WitnessGenerator<F>>],
+    generator_indices_by_watches: &BTreeMap<usize, Vec<usize>>,
+    outcome: &mut GenerationOutcome<F>,
+) {
+    let mut pending_generator_indices: Vec<_> = (0..generators.len()).collect();
+    let mut generator_is_expired = vec![false; generators.len()];
+    let mut remaining_generators = generators.len();
+
+    while !pending_generator_indices.is_empty() {
+        // A generator may have been queued by several of its watches, or have finished since it
+        // was queued.
+        pending_generator_indices.sort_unstable();
+        pending_generator_indices.dedup();
+        pending_generator_indices.retain(|&i| !generator_is_expired[i]);
+
+        let outputs: Vec<_> = {
+            let witness = &*witness;
+            pending_generator_indices
+                .par_iter()
+                .map(|&generator_idx| {
+                    let mut buffer = GeneratedValues::empty();
+                    let finished = generators[generator_idx].run(witness, &mut buffer);
+                    (finished, buffer)
+                })
+                .collect()
+        };
+
+        let mut next_pending_generator_indices = Vec::new();
+        for (&generator_idx, (finished, buffer)) in pending_generator_indices.iter().zip(outputs) {
+            if finished {
+                generator_is_expired[generator_idx] = true;
+                remaining_generators -= 1;
+            }
+
+            let new_target_reps = buffer
+                .target_values
+                .into_iter()
+                .flat_map(|(t, v)| outcome.set_target(witness, t, v));
+            for watch in new_target_reps {
+                if let Some(watchers) = generator_indices_by_watches.get(&watch) {
+                    next_pending_generator_indices.extend(watchers);
+                }
+            }
+        }
+
+        pending_generator_indices = next_pending_generator_indices;
+    }
+
+    outcome.num_unfinished_generators = remaining_generators;
 }
 
 /// A generator participates in the generation of the witness.
@@ -106,6 +265,111 @@ pub trait WitnessGenerator<F: Field>: 'static + Send + Sync + Debug {
     /// flag is true, the generator will never be run again, otherwise it will be queued for another
     /// run next time a target in its watch list is populated.
     fn run(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) -> bool;
+
+    /// This generator as `Any`, so that a `WitnessGeneratorSerializer` can recover its type.
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// Reads and writes the generators of a serialized `CircuitBuilder`. Unlike gates, generators hold
+/// data such as the targets they read and write, so each type of generator which a builder may
+/// hold needs to be known to the serializer. A serializer for custom generators can handle its own
+/// types and defer to `DefaultGeneratorSerializer` for the others, with a tag telling them apart.
+pub trait WitnessGeneratorSerializer<F: RichField + Extendable<D>, const D: usize> {
+    /// Writes `generator` to `dst`. Fails if its type isn't supported by this serializer.
+    fn write_generator(
+        &self,
+        generator: &dyn WitnessGenerator<F>,
+        dst: &mut Vec<u8>,
+    ) -> IoResult<()>;
+
+    /// Reads a generator written by `write_generator`.
+    fn read_generator(&self, src: &mut Buffer) -> IoResult<Box<dyn WitnessGenerator<F>>>;
+}
+
+/// Implements `WitnessGeneratorSerializer` for `$serializer`, which supports the given
+/// `SimpleGenerator` types, each tagged by its position in the list.
+macro_rules! impl_generator_serializer {
+    ($serializer:ty, $($generator:ty),+ $(,)?) => {
+        impl<F: RichField + Extendable<D>, const D: usize> WitnessGeneratorSerializer<F, D>
+            for $serializer
+        {
+            #[allow(unused_assignments)]
+            fn write_generator(
+                &self,
+                generator: &dyn WitnessGenerator<F>,
+                dst: &mut Vec<u8>,
+            ) -> IoResult<()> {
+                let generator = generator.as_any();
+                let mut tag = 0;
+                $(
+                    if let Some(adapter) =
+                        generator.downcast_ref::<SimpleGeneratorAdapter<F, $generator>>()
+                    {
+                        dst.write_u32(tag)?;
+                        return SimpleGenerator::<F>::serialize(&adapter.inner, dst);
+                    }
+                    tag += 1;
+                )+
+                Err(IoError)
+            }
+
+            #[allow(unused_assignments)]
+            fn read_generator(&self, src: &mut Buffer) -> IoResult<Box<dyn WitnessGenerator<F>>> {
+                let tag = src.read_u32()?;
+                let mut i = 0;
+                $(
+                    if tag == i {
+                        let generator = <$generator as SimpleGenerator<F>>::deserialize(src)?;
+                        return Ok(Box::new(generator.adapter()));
+                    }
+                    i += 1;
+                )+
+                Err(IoError)
+            }
+        }
+    };
+}
+
+/// Serializes the generators which this crate's gadgets add to a `CircuitBuilder`. Generators
+/// which are generic over other parameters, such as those of non-native arithmetic or of dummy
+/// proofs, aren't supported.
+#[derive(Debug, Default)]
+pub struct DefaultGeneratorSerializer;
+
+impl_generator_serializer! {
+    DefaultGeneratorSerializer,
+    CopyGenerator,
+    RandomValueGenerator,
+    NonzeroTestGenerator,
+    BaseSumGenerator<2>,
+    LowHighGenerator,
+    SplitGenerator,
+    WireSplitGenerator,
+    SortedSetIndexGenerator<F>,
+    QuotientGeneratorExtension<D>,
+    CarryGenerator,
+    EqualityGenerator,
+    SqrtGenerator,
+    InverseGenerator,
+    SubWithBorrowGenerator,
+}
+
+/// A generator held behind an `Arc`, so that clones of a `CircuitBuilder` can share it.
+#[derive(Debug)]
+pub(crate) struct SharedGenerator<F: Field>(pub(crate) Arc<dyn WitnessGenerator<F>>);
+
+impl<F: Field> WitnessGenerator<F> for SharedGenerator<F> {
+    fn watch_list(&self) -> Vec<Target> {
+        self.0.watch_list()
+    }
+
+    fn run(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) -> bool {
+        self.0.run(witness, out_buffer)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self.0.as_any()
+    }
 }
 
 /// Values generated by a generator invocation.
@@ -162,6 +426,20 @@ pub trait SimpleGenerator<F: Field>: 'static + Send + Sync + Debug {
 
     fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>);
 
+    /// Writes this generator, so that it can be restored with `deserialize`. Generators which
+    /// don't override this can't be part of a serialized `CircuitBuilder`.
+    fn serialize(&self, _dst: &mut Vec<u8>) -> IoResult<()> {
+        Err(IoError)
+    }
+
+    /// Reads a generator written by `serialize`.
+    fn deserialize(_src: &mut Buffer) -> IoResult<Self>
+    where
+        Self: Sized,
+    {
+        Err(IoError)
+    }
+
     fn adapter(self) -> SimpleGeneratorAdapter<F, Self>
     where
         Self: Sized,
@@ -192,6 +470,10 @@ impl<F: Field, SG: SimpleGenerator<F>> WitnessGenerator<F> for SimpleGeneratorAd
             false
         }
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 }
 
 /// A generator which copies one wire to another.
@@ -210,6 +492,20 @@ impl<F: Field> SimpleGenerator<F> for CopyGenerator {
         let value = witness.get_target(self.src);
         out_buffer.set_target(self.dst, value);
     }
+
+    fn serialize(&self, dst: &mut Vec<u8>) -> IoResult<()> {
+        dst.write_target(self.src)?;
+        dst.write_target(self.dst)
+    }
+
+    fn deserialize(src: &mut Buffer) -> IoResult<Self> {
+        let src_target = src.read_target()?;
+        let dst = src.read_target()?;
+        Ok(Self {
+            src: src_target,
+            dst,
+        })
+    }
 }
 
 /// A generator for including a random value
@@ -223,10 +519,23 @@ impl<F: Field> SimpleGenerator<F> for RandomValueGenerator {
         Vec::new()
     }
 
-    fn run_once(&self, _witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+        // Keep a value that was supplied up front, e.g. by a witness generated earlier.
+        if witness.contains(self.target) {
+            return;
+        }
         let random_value = F::rand();
         out_buffer.set_target(self.target, random_value);
     }
+
+    fn serialize(&self, dst: &mut Vec<u8>) -> IoResult<()> {
+        dst.write_target(self.target)
+    }
+
+    fn deserialize(src: &mut Buffer) -> IoResult<Self> {
+        let target = src.read_target()?;
+        Ok(Self { target })
+    }
 }
 
 /// A generator for testing if a value equals zero
@@ -252,6 +561,17 @@ impl<F: Field> SimpleGenerator<F> for NonzeroTestGenerator {
 
         out_buffer.set_target(self.dummy, dummy_value);
     }
+
+    fn serialize(&self, dst: &mut Vec<u8>) -> IoResult<()> {
+        dst.write_target(self.to_test)?;
+        dst.write_target(self.dummy)
+    }
+
+    fn deserialize(src: &mut Buffer) -> IoResult<Self> {
+        let to_test = src.read_target()?;
+        let dummy = src.read_target()?;
+        Ok(Self { to_test, dummy })
+    }
 }
 
 /// Generator used to fill an extra constant.
@@ -278,3 +598,75 @@ impl<F: Field> SimpleGenerator<F> for ConstantGenerator<F> {
         out_buffer.set_target(Target::wire(self.row, self.wire_index), self.constant);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::types::Sample;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    #[test]
+    fn test_parallel_witness_matches_sequential() {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        // Many independent chains, each mixing arithmetic and hashing.
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let mut pw = PartialWitness::new();
+        for _ in 0..32 {
+            let mut x = builder.add_virtual_target();
+            pw.set_target(x, F::rand());
+            for _ in 0..4 {
+                let y = builder.mul_add(x, x, x);
+                x = builder
+                    .hash_n_to_hash_no_pad::<PoseidonHash>(vec![x, y])
+                    .elements[0];
+            }
+            builder.register_public_input(x);
+        }
+        let data = builder.build::<C>();
+
+        type RunGenerators = fn(
+            &mut PartitionWitness<F>,
+            &[Box<dyn WitnessGenerator<F>>],
+            &BTreeMap<usize, Vec<usize>>,
+            &mut GenerationOutcome<F>,
+        );
+        let run = |run_generators: RunGenerators| {
+            let mut witness = PartitionWitness::new(
+                data.common.config.num_wires,
+                data.common.degree(),
+                &data.prover_only.representative_map,
+            );
+            for (&t, &v) in &pw.target_values {
+                witness.set_target(t, v);
+            }
+            let mut outcome = GenerationOutcome {
+                num_unfinished_generators: 0,
+                first_conflict: None,
+            };
+            run_generators(
+                &mut witness,
+                &data.prover_only.generators,
+                &data.prover_only.generator_indices_by_watches,
+                &mut outcome,
+            );
+            assert_eq!(outcome.num_unfinished_generators, 0);
+            assert!(outcome.first_conflict.is_none());
+            // Random values differ between runs, so ignore them.
+            for &t in &data.prover_only.random_targets {
+                let rep = witness.representative_map[witness.target_index(t)];
+                witness.values[rep] = None;
+            }
+            witness.values
+        };
+
+        let sequential = run(run_generators_sequential);
+        assert!(sequential.iter().any(Option::is_some));
+        assert_eq!(run(run_generators_parallel), sequential);
+    }
+}
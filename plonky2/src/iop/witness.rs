@@ -5,7 +5,7 @@ use hashbrown::HashMap;
 use itertools::Itertools;
 
 use crate::field::extension::{Extendable, FieldExtension};
-use crate::field::types::Field;
+use crate::field::types::{Field, PrimeField64};
 use crate::fri::structure::{FriOpenings, FriOpeningsTarget};
 use crate::fri::witness_util::set_fri_proof_target;
 use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
@@ -16,6 +16,7 @@ use crate::iop::wire::Wire;
 use crate::plonk::circuit_data::{VerifierCircuitTarget, VerifierOnlyCircuitData};
 use crate::plonk::config::{AlgebraicHasher, GenericConfig, Hasher};
 use crate::plonk::proof::{Proof, ProofTarget, ProofWithPublicInputs, ProofWithPublicInputsTarget};
+use crate::util::serialization::{Buffer, Read, Remaining, Write};
 
 pub trait WitnessWrite<F: Field> {
     fn set_target(&mut self, target: Target, value: F);
@@ -59,7 +60,13 @@ pub trait WitnessWrite<F: Field> {
     ) where
         F: RichField + Extendable<D>,
     {
-        debug_assert_eq!(ets.len(), values.len());
+        assert_eq!(
+            ets.len(),
+            values.len(),
+            "Got {} values for {} extension targets",
+            values.len(),
+            ets.len()
+        );
         ets.iter()
             .zip(values)
             .for_each(|(&et, &v)| self.set_extension_target(et, v));
@@ -82,6 +89,7 @@ pub trait WitnessWrite<F: Field> {
         let ProofWithPublicInputs {
             proof,
             public_inputs,
+            ..
         } = proof_with_pis;
         let ProofWithPublicInputsTarget {
             proof: pt,
@@ -278,6 +286,24 @@ impl<F: Field> PartialWitness<F> {
     }
 }
 
+impl<F: PrimeField64> PartialWitness<F> {
+    /// Encodes each assigned target along with its value.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        buffer
+            .write_partial_witness(self)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> anyhow::Result<Self> {
+        let mut buffer = Buffer::new(bytes);
+        let witness = buffer.read_partial_witness().map_err(anyhow::Error::msg)?;
+        anyhow::ensure!(buffer.is_empty(), "Trailing bytes after witness");
+        Ok(witness)
+    }
+}
+
 impl<F: Field> WitnessWrite<F> for PartialWitness<F> {
     fn set_target(&mut self, target: Target, value: F) {
         let opt_old_value = self.target_values.insert(target, value);
@@ -320,18 +346,31 @@ impl<'a, F: Field> PartitionWitness<'a, F> {
     /// Set a `Target`. On success, returns the representative index of the newly-set target. If the
     /// target was already set, returns `None`.
     pub(crate) fn set_target_returning_rep(&mut self, target: Target, value: F) -> Option<usize> {
-        let rep_index = self.representative_map[self.target_index(target)];
-        let rep_value = &mut self.values[rep_index];
-        if let Some(old_value) = *rep_value {
-            assert_eq!(
-                value, old_value,
+        match self.try_set_target_returning_rep(target, value) {
+            Ok(rep) => rep,
+            Err(old_value) => panic!(
                 "Partition containing {:?} was set twice with different values: {} != {}",
                 target, old_value, value
-            );
-            None
-        } else {
-            *rep_value = Some(value);
-            Some(rep_index)
+            ),
+        }
+    }
+
+    /// Same as `set_target_returning_rep`, but if the target's partition already holds a different
+    /// value, that value is kept and returned as an error.
+    pub(crate) fn try_set_target_returning_rep(
+        &mut self,
+        target: Target,
+        value: F,
+    ) -> Result<Option<usize>, F> {
+        let rep_index = self.representative_map[self.target_index(target)];
+        let rep_value = &mut self.values[rep_index];
+        match *rep_value {
+            Some(old_value) if old_value != value => Err(old_value),
+            Some(_) => Ok(None),
+            None => {
+                *rep_value = Some(value);
+                Ok(Some(rep_index))
+            }
         }
     }
 
@@ -339,6 +378,30 @@ impl<'a, F: Field> PartitionWitness<'a, F> {
         target.index(self.num_wires, self.degree)
     }
 
+    /// Returns an iterator over every target which has a value, along with that value. A target has
+    /// a value if it, or another target in its partition, was set; targets which were never
+    /// assigned are skipped rather than defaulted. Targets are visited in order of their index,
+    /// i.e. wires row by row, followed by virtual targets.
+    pub fn iter_set_values(&self) -> impl Iterator<Item = (Target, F)> + '_ {
+        let num_wire_targets = self.degree * self.num_wires;
+        (0..self.representative_map.len()).filter_map(move |i| {
+            let value = self.values[self.representative_map[i]]?;
+            let target = if i < num_wire_targets {
+                Target::wire(i / self.num_wires, i % self.num_wires)
+            } else {
+                Target::VirtualTarget {
+                    index: i - num_wire_targets,
+                }
+            };
+            Some((target, value))
+        })
+    }
+
+    /// Collects the output of `iter_set_values`.
+    pub fn to_vec(&self) -> Vec<(Target, F)> {
+        self.iter_set_values().collect()
+    }
+
     pub fn full_witness(self) -> MatrixWitness<F> {
         let mut wire_values = vec![vec![F::ZERO; self.degree]; self.num_wires];
         for i in 0..self.degree {
@@ -366,3 +429,168 @@ impl<'a, F: Field> Witness<F> for PartitionWitness<'a, F> {
         self.values[rep_index]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use anyhow::Result;
+
+    use crate::field::extension::FieldExtension;
+    use crate::field::types::{Field, Sample};
+    use crate::hash::hash_types::{HashOut, HashOutTarget};
+    use crate::iop::ext_target::ExtensionTarget;
+    use crate::iop::generator::{generate_partial_witness, GeneratedValues, SimpleGenerator};
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, PartitionWitness, Witness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type FF = <C as GenericConfig<D>>::FE;
+
+    /// Writes `x, x + 1, ...` to a hash and to some extension targets, with the bulk setters.
+    #[derive(Debug)]
+    struct BulkGenerator {
+        x: Target,
+        hash: HashOutTarget,
+        exts: Vec<ExtensionTarget<D>>,
+    }
+
+    impl BulkGenerator {
+        fn hash_value(x: F) -> HashOut<F> {
+            HashOut {
+                elements: core::array::from_fn(|i| x + F::from_canonical_usize(i)),
+            }
+        }
+
+        fn ext_values(x: F, n: usize) -> Vec<FF> {
+            (0..n)
+                .map(|i| FF::from_basefield_array([x, F::from_canonical_usize(i)]))
+                .collect()
+        }
+    }
+
+    impl SimpleGenerator<F> for BulkGenerator {
+        fn dependencies(&self) -> Vec<Target> {
+            vec![self.x]
+        }
+
+        fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) {
+            let x = witness.get_target(self.x);
+            out_buffer.set_hash_target(self.hash, Self::hash_value(x));
+            out_buffer.set_extension_targets(&self.exts, &Self::ext_values(x, self.exts.len()));
+        }
+    }
+
+    #[test]
+    fn test_generated_values_bulk_setters() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let hash = builder.add_virtual_hash();
+        let exts = builder.add_virtual_extension_targets(3);
+        builder.register_public_inputs(&hash.elements);
+        builder.add_simple_generator(BulkGenerator {
+            x,
+            hash,
+            exts: exts.clone(),
+        });
+        let data = builder.build::<C>();
+
+        let x_value = F::rand();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, x_value);
+        let witness = generate_partial_witness(pw, &data.prover_only, &data.common);
+        assert_eq!(
+            witness.get_hash_target(hash),
+            BulkGenerator::hash_value(x_value)
+        );
+        assert_eq!(
+            witness.get_extension_targets(&exts),
+            BulkGenerator::ext_values(x_value, exts.len())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Got 2 values for 3 extension targets")]
+    fn test_set_extension_targets_length_mismatch() {
+        let ets = (0..3)
+            .map(|i| {
+                ExtensionTarget([
+                    Target::VirtualTarget { index: 2 * i },
+                    Target::VirtualTarget { index: 2 * i + 1 },
+                ])
+            })
+            .collect::<Vec<ExtensionTarget<D>>>();
+        let mut values = GeneratedValues::<F>::empty();
+        values.set_extension_targets(&ets, &[FF::ONE, FF::TWO]);
+    }
+
+    #[test]
+    fn test_iter_set_values() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_target();
+        let y = builder.mul_add(x, x, x);
+        let unset = builder.add_virtual_target();
+        pw.set_target(x, F::from_canonical_u64(3));
+
+        let data = builder.build::<C>();
+        let witness = generate_partial_witness(pw, &data.prover_only, &data.common);
+        let values = witness.to_vec();
+
+        // `y` is computed by the arithmetic gate's generator.
+        assert!(values.contains(&(x, F::from_canonical_u64(3))));
+        assert!(values.contains(&(y, F::from_canonical_u64(12))));
+        assert!(values.iter().all(|&(t, _)| t != unset));
+
+        // Exactly the targets with a value are reported, in a stable order.
+        let num_targets = witness.representative_map.len();
+        let num_set = (0..num_targets)
+            .filter(|&i| witness.values[witness.representative_map[i]].is_some())
+            .count();
+        assert_eq!(values.len(), num_set);
+        assert!(values.iter().all(|&(t, v)| witness.get_target(t) == v));
+        assert_eq!(witness.iter_set_values().collect::<Vec<_>>(), values);
+    }
+
+    #[test]
+    fn test_witness_bytes_round_trip() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let z = builder.mul_add(x, y, x);
+        builder.register_public_input(z);
+        pw.set_target(x, F::from_canonical_u64(3));
+        pw.set_target(y, F::from_canonical_u64(5));
+        let data = builder.build::<C>();
+
+        // Serialize the generated witness, as if handing it over to another machine for proving.
+        let witness = data.generate_witness(pw);
+        let bytes = witness.to_bytes();
+        let restored = PartialWitness::from_bytes(bytes.clone())?;
+        assert_eq!(restored.to_bytes(), bytes);
+        assert!(PartialWitness::<F>::from_bytes(bytes[..bytes.len() - 1].to_vec()).is_err());
+
+        let proof = data.prove(witness)?;
+        let restored_proof = data.prove(restored)?;
+        assert_eq!(restored_proof.to_bytes(), proof.to_bytes());
+        data.verify(restored_proof)
+    }
+
+    #[test]
+    fn test_witness_from_bytes_rejects_oversized_length() {
+        let bytes = u32::MAX.to_le_bytes().to_vec();
+        assert!(PartialWitness::<F>::from_bytes(bytes).is_err());
+    }
+}
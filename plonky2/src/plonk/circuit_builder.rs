@@ -1,11 +1,15 @@
 use alloc::boxed::Box;
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::cmp::max;
+use core::ops::Range;
 #[cfg(feature = "std")]
 use std::time::Instant;
 
+use anyhow::{anyhow, ensure, Result};
 use hashbrown::{HashMap, HashSet};
 use itertools::Itertools;
 use log::{debug, info, Level};
@@ -23,16 +27,20 @@ use crate::gadgets::polynomial::PolynomialCoeffsExtTarget;
 use crate::gates::arithmetic_base::ArithmeticGate;
 use crate::gates::arithmetic_extension::ArithmeticExtensionGate;
 use crate::gates::constant::ConstantGate;
-use crate::gates::gate::{CurrentSlot, Gate, GateInstance, GateRef};
+use crate::gates::gate::{
+    eval_on_random_low_degree_witness, CurrentSlot, Gate, GateInstance, GateRef,
+};
 use crate::gates::noop::NoopGate;
 use crate::gates::public_input::PublicInputGate;
 use crate::gates::selectors::selector_polynomials;
 use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_proofs::MerkleProofTarget;
 use crate::hash::merkle_tree::MerkleCap;
+use crate::hash::poseidon::PoseidonHash;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::generator::{
-    ConstantGenerator, CopyGenerator, RandomValueGenerator, SimpleGenerator, WitnessGenerator,
+    ConstantGenerator, CopyGenerator, RandomValueGenerator, SharedGenerator, SimpleGenerator,
+    WitnessGenerator, WitnessGeneratorSerializer,
 };
 use crate::iop::target::{BoolTarget, Target};
 use crate::iop::wire::Wire;
@@ -46,10 +54,19 @@ use crate::plonk::permutation_argument::Forest;
 use crate::plonk::plonk_common::PlonkOracle;
 use crate::timed;
 use crate::util::context_tree::ContextTree;
+#[cfg(feature = "graph")]
+use crate::util::dot::circuit_to_dot;
 use crate::util::partial_products::num_partial_products;
+use crate::util::serialization::{Buffer, IoError, IoResult, Read, Remaining, Write};
 use crate::util::timing::TimingTree;
-use crate::util::{log2_ceil, log2_strict, transpose, transpose_poly_values};
-
+use crate::util::{ceil_div_usize, log2_ceil, log2_strict, transpose, transpose_poly_values};
+
+/// Incrementally builds a circuit, which can then be finalized with `build`.
+///
+/// When many circuits share a common prefix, the prefix can be built once and then cloned, or saved
+/// with `to_bytes` and restored with `from_bytes`, for each of them; finalizing the copy yields
+/// exactly the circuit that building everything in one pass would.
+#[derive(Clone)]
 pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     pub config: CircuitConfig,
 
@@ -76,9 +93,16 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     context_log: ContextTree,
 
     /// Generators used to generate the witness.
-    generators: Vec<Box<dyn WitnessGenerator<F>>>,
+    generators: Vec<Arc<dyn WitnessGenerator<F>>>,
+
+    /// Targets which the prover fills with random values, in the order they were added.
+    random_targets: Vec<Target>,
 
-    constants_to_targets: HashMap<F, Target>,
+    /// The row and column of the next free routed wire in the current `NoopGate` row reserved by
+    /// `add_scratch_wires`, if it has any left.
+    scratch_slot: Option<(usize, usize)>,
+
+    pub(crate) constants_to_targets: HashMap<F, Target>,
     targets_to_constants: HashMap<Target, F>,
 
     /// Memoized results of `arithmetic` calls.
@@ -90,9 +114,20 @@ pub struct CircuitBuilder<F: RichField + Extendable<D>, const D: usize> {
     /// Map between gate type and the current gate of this type with available slots.
     current_slots: HashMap<GateRef<F, D>, CurrentSlot<F, D>>,
 
+    /// Rows whose operations were all placed by `find_relocatable_slot`, so that their wires are
+    /// only referenced by copy constraints and `build` may move their operations to another row.
+    relocatable_rows: HashSet<usize>,
+
+    /// Rows emptied by `compact_arithmetic_rows`, which `add_gate` fills before appending new ones.
+    free_rows: Vec<usize>,
+
     /// List of constant generators used to fill the constant wires.
     constant_generators: Vec<ConstantGenerator<F>>,
 
+    /// Targets to be copied straight into a `ConstantGate` slot holding the paired constant, added
+    /// by `assert_equal_constant_slot`.
+    constant_slot_targets: Vec<(F, Target)>,
+
     /// Optional common data. When it is `Some(goal_data)`, the `build` function panics if the resulting
     /// common data doesn't equal `goal_data`.
     /// This is used in cyclic recursion.
@@ -115,12 +150,17 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             copy_constraints: Vec::new(),
             context_log: ContextTree::new(),
             generators: Vec::new(),
+            random_targets: Vec::new(),
+            scratch_slot: None,
             constants_to_targets: HashMap::new(),
             targets_to_constants: HashMap::new(),
             base_arithmetic_results: HashMap::new(),
             arithmetic_results: HashMap::new(),
             current_slots: HashMap::new(),
+            relocatable_rows: HashSet::new(),
+            free_rows: Vec::new(),
             constant_generators: Vec::new(),
+            constant_slot_targets: Vec::new(),
             goal_common_data: None,
             verifier_data_public_input: None,
         };
@@ -151,6 +191,391 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         );
     }
 
+    /// Serializes this partially built circuit, so that it can be restored with `from_bytes` and
+    /// extended further, e.g. to build a common prefix once and append to it in other processes.
+    /// As in `VerifierCircuitData::to_bytes`, gates are code rather than data, so only their IDs
+    /// are written. Generators are written with `generator_serializer`, and this fails if it
+    /// doesn't support one of them.
+    pub fn to_bytes(
+        &self,
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+    ) -> Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        self.write_state(&mut buffer)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+            .write_u32(self.generators.len() as u32)
+            .expect("Writing to a byte-vector cannot fail.");
+        for generator in &self.generators {
+            generator_serializer
+                .write_generator(generator.as_ref(), &mut buffer)
+                .map_err(|_| {
+                    anyhow!("Generator {generator:?} isn't supported by the serializer")
+                })?;
+        }
+        Ok(buffer)
+    }
+
+    /// Restores a builder serialized with `to_bytes`, given its gates, or any superset of them,
+    /// and a serializer supporting its generators. Finalizing the restored builder, with or without
+    /// appending to it, yields exactly the circuit that the original builder would.
+    pub fn from_bytes(
+        bytes: Vec<u8>,
+        gates: &[GateRef<F, D>],
+        generator_serializer: &dyn WitnessGeneratorSerializer<F, D>,
+    ) -> Result<Self> {
+        let gates_by_id = gates
+            .iter()
+            .map(|g| (g.0.id(), g.clone()))
+            .collect::<HashMap<_, _>>();
+        let mut buffer = Buffer::new(bytes);
+        let mut builder =
+            Self::read_state(&mut buffer, &gates_by_id).map_err(anyhow::Error::msg)?;
+        let num_generators = buffer.read_u32().map_err(anyhow::Error::msg)?;
+        for _ in 0..num_generators {
+            let generator = generator_serializer
+                .read_generator(&mut buffer)
+                .map_err(anyhow::Error::msg)?;
+            builder.generators.push(Arc::from(generator));
+        }
+        ensure!(buffer.is_empty(), "Trailing bytes after the builder");
+        builder.check_config();
+        Ok(builder)
+    }
+
+    /// Writes everything but the generators. Gates are referred to by their index in a list of IDs
+    /// written first, which also covers the gates of `goal_common_data`.
+    fn write_state(&self, dst: &mut Vec<u8>) -> IoResult<()> {
+        let gate_ids = self
+            .gates
+            .iter()
+            .chain(self.goal_common_data.iter().flat_map(|c| &c.gates))
+            .map(|g| g.0.id())
+            .collect::<BTreeSet<_>>();
+        dst.write_u32(gate_ids.len() as u32)?;
+        for id in &gate_ids {
+            dst.write_str(id)?;
+        }
+        let gate_index = |gate: &GateRef<F, D>| {
+            let id = gate.0.id();
+            gate_ids.iter().position(|i| *i == id).unwrap() as u32
+        };
+
+        dst.write_circuit_config(&self.config)?;
+        match &self.domain_separator {
+            Some(separator) => {
+                dst.write_bool(true)?;
+                dst.write_u32(separator.len() as u32)?;
+                dst.write_field_vec(separator)?;
+            }
+            None => dst.write_bool(false)?,
+        }
+
+        dst.write_u32(self.gates.len() as u32)?;
+        for gate in &self.gates {
+            dst.write_u32(gate_index(gate))?;
+        }
+        dst.write_u32(self.gate_instances.len() as u32)?;
+        for instance in &self.gate_instances {
+            dst.write_u32(gate_index(&instance.gate_ref))?;
+            dst.write_u32(instance.constants.len() as u32)?;
+            dst.write_field_vec(&instance.constants)?;
+        }
+
+        dst.write_target_vec(&self.public_inputs)?;
+        dst.write_u32(self.virtual_target_index as u32)?;
+        dst.write_u32(self.copy_constraints.len() as u32)?;
+        for constraint in &self.copy_constraints {
+            dst.write_target(constraint.pair.0)?;
+            dst.write_target(constraint.pair.1)?;
+            dst.write_str(&constraint.name)?;
+        }
+        self.context_log.write(dst)?;
+        dst.write_target_vec(&self.random_targets)?;
+        match self.scratch_slot {
+            Some((row, column)) => {
+                dst.write_bool(true)?;
+                dst.write_u32(row as u32)?;
+                dst.write_u32(column as u32)?;
+            }
+            None => dst.write_bool(false)?,
+        }
+
+        dst.write_u32(self.constants_to_targets.len() as u32)?;
+        for (&c, &t) in &self.constants_to_targets {
+            dst.write_field(c)?;
+            dst.write_target(t)?;
+        }
+        dst.write_u32(self.targets_to_constants.len() as u32)?;
+        for (&t, &c) in &self.targets_to_constants {
+            dst.write_target(t)?;
+            dst.write_field(c)?;
+        }
+        dst.write_u32(self.base_arithmetic_results.len() as u32)?;
+        for (op, &result) in &self.base_arithmetic_results {
+            op.write(dst)?;
+            dst.write_target(result)?;
+        }
+        dst.write_u32(self.arithmetic_results.len() as u32)?;
+        for (op, &result) in &self.arithmetic_results {
+            op.write(dst)?;
+            dst.write_target_ext(result)?;
+        }
+
+        dst.write_u32(self.current_slots.len() as u32)?;
+        for (gate, slots) in &self.current_slots {
+            dst.write_u32(gate_index(gate))?;
+            dst.write_u32(slots.current_slot.len() as u32)?;
+            for (constants, &(row, op)) in &slots.current_slot {
+                dst.write_u32(constants.len() as u32)?;
+                dst.write_field_vec(constants)?;
+                dst.write_u32(row as u32)?;
+                dst.write_u32(op as u32)?;
+            }
+        }
+        dst.write_usize_vec(&self.relocatable_rows.iter().copied().collect::<Vec<_>>())?;
+        dst.write_usize_vec(&self.free_rows)?;
+
+        dst.write_u32(self.constant_generators.len() as u32)?;
+        for generator in &self.constant_generators {
+            dst.write_u32(generator.row as u32)?;
+            dst.write_u32(generator.constant_index as u32)?;
+            dst.write_u32(generator.wire_index as u32)?;
+            dst.write_field(generator.constant)?;
+        }
+        dst.write_u32(self.constant_slot_targets.len() as u32)?;
+        for &(c, t) in &self.constant_slot_targets {
+            dst.write_field(c)?;
+            dst.write_target(t)?;
+        }
+
+        match &self.goal_common_data {
+            Some(common) => {
+                dst.write_bool(true)?;
+                dst.write_circuit_config(&common.config)?;
+                dst.write_fri_params(&common.fri_params)?;
+                dst.write_u32(common.gates.len() as u32)?;
+                for gate in &common.gates {
+                    dst.write_u32(gate_index(gate))?;
+                }
+                dst.write_selectors_info(&common.selectors_info)?;
+                dst.write_u32(common.quotient_degree_factor as u32)?;
+                dst.write_u32(common.num_gate_constraints as u32)?;
+                dst.write_u32(common.num_constants as u32)?;
+                dst.write_u32(common.num_public_inputs as u32)?;
+                dst.write_u32(common.k_is.len() as u32)?;
+                dst.write_field_vec(&common.k_is)?;
+                dst.write_u32(common.num_partial_products as u32)?;
+            }
+            None => dst.write_bool(false)?,
+        }
+        match &self.verifier_data_public_input {
+            Some(verifier_data) => {
+                dst.write_bool(true)?;
+                dst.write_u32(verifier_data.constants_sigmas_cap.0.len() as u32)?;
+                for hash in &verifier_data.constants_sigmas_cap.0 {
+                    dst.write_target_vec(&hash.elements)?;
+                }
+                dst.write_target_vec(&verifier_data.circuit_digest.elements)?;
+            }
+            None => dst.write_bool(false)?,
+        }
+        Ok(())
+    }
+
+    /// Reads everything written by `write_state`.
+    fn read_state(
+        src: &mut Buffer,
+        gates_by_id: &HashMap<String, GateRef<F, D>>,
+    ) -> IoResult<Self> {
+        let num_gate_ids = src.read_u32()? as usize;
+        let gate_list = (0..num_gate_ids)
+            .map(|_| gates_by_id.get(&src.read_string()?).cloned().ok_or(IoError))
+            .collect::<IoResult<Vec<_>>>()?;
+        let read_gate = |src: &mut Buffer| {
+            gate_list
+                .get(src.read_u32()? as usize)
+                .cloned()
+                .ok_or(IoError)
+        };
+        let read_hash_target = |src: &mut Buffer| -> IoResult<HashOutTarget> {
+            let elements = src.read_target_vec()?;
+            Ok(HashOutTarget {
+                elements: elements.try_into().map_err(|_| IoError)?,
+            })
+        };
+
+        let config = src.read_circuit_config()?;
+        let domain_separator = if src.read_bool()? {
+            let len = src.read_u32()? as usize;
+            Some(src.read_field_vec(len)?)
+        } else {
+            None
+        };
+
+        let num_gates = src.read_u32()? as usize;
+        let gates = (0..num_gates)
+            .map(|_| read_gate(src))
+            .collect::<IoResult<HashSet<_>>>()?;
+        let num_instances = src.read_u32()? as usize;
+        let gate_instances = (0..num_instances)
+            .map(|_| {
+                let gate_ref = read_gate(src)?;
+                let len = src.read_u32()? as usize;
+                let constants = src.read_field_vec(len)?;
+                Ok(GateInstance {
+                    gate_ref,
+                    constants,
+                })
+            })
+            .collect::<IoResult<Vec<_>>>()?;
+
+        let public_inputs = src.read_target_vec()?;
+        let virtual_target_index = src.read_u32()? as usize;
+        let num_copy_constraints = src.read_u32()? as usize;
+        let copy_constraints = (0..num_copy_constraints)
+            .map(|_| {
+                let pair = (src.read_target()?, src.read_target()?);
+                Ok(CopyConstraint::new(pair, src.read_string()?))
+            })
+            .collect::<IoResult<Vec<_>>>()?;
+        let context_log = ContextTree::read(src)?;
+        let random_targets = src.read_target_vec()?;
+        let scratch_slot = if src.read_bool()? {
+            Some((src.read_u32()? as usize, src.read_u32()? as usize))
+        } else {
+            None
+        };
+
+        let num_constants = src.read_u32()? as usize;
+        let constants_to_targets = (0..num_constants)
+            .map(|_| Ok((src.read_field()?, src.read_target()?)))
+            .collect::<IoResult<HashMap<_, _>>>()?;
+        let num_constant_targets = src.read_u32()? as usize;
+        let targets_to_constants = (0..num_constant_targets)
+            .map(|_| Ok((src.read_target()?, src.read_field()?)))
+            .collect::<IoResult<HashMap<_, _>>>()?;
+        let num_base_results = src.read_u32()? as usize;
+        let base_arithmetic_results = (0..num_base_results)
+            .map(|_| Ok((BaseArithmeticOperation::read(src)?, src.read_target()?)))
+            .collect::<IoResult<HashMap<_, _>>>()?;
+        let num_results = src.read_u32()? as usize;
+        let arithmetic_results = (0..num_results)
+            .map(|_| {
+                Ok((
+                    ExtensionArithmeticOperation::read(src)?,
+                    src.read_target_ext()?,
+                ))
+            })
+            .collect::<IoResult<HashMap<_, _>>>()?;
+
+        let num_current_slots = src.read_u32()? as usize;
+        let current_slots = (0..num_current_slots)
+            .map(|_| {
+                let gate = read_gate(src)?;
+                let num_slots = src.read_u32()? as usize;
+                let current_slot = (0..num_slots)
+                    .map(|_| {
+                        let len = src.read_u32()? as usize;
+                        let constants = src.read_field_vec(len)?;
+                        let slot = (src.read_u32()? as usize, src.read_u32()? as usize);
+                        Ok((constants, slot))
+                    })
+                    .collect::<IoResult<HashMap<_, _>>>()?;
+                Ok((gate, CurrentSlot { current_slot }))
+            })
+            .collect::<IoResult<HashMap<_, _>>>()?;
+        let relocatable_rows = src.read_usize_vec()?.into_iter().collect();
+        let free_rows = src.read_usize_vec()?;
+
+        let num_constant_generators = src.read_u32()? as usize;
+        let constant_generators = (0..num_constant_generators)
+            .map(|_| {
+                Ok(ConstantGenerator {
+                    row: src.read_u32()? as usize,
+                    constant_index: src.read_u32()? as usize,
+                    wire_index: src.read_u32()? as usize,
+                    constant: src.read_field()?,
+                })
+            })
+            .collect::<IoResult<Vec<_>>>()?;
+        let num_constant_slot_targets = src.read_u32()? as usize;
+        let constant_slot_targets = (0..num_constant_slot_targets)
+            .map(|_| Ok((src.read_field()?, src.read_target()?)))
+            .collect::<IoResult<Vec<_>>>()?;
+
+        let goal_common_data = if src.read_bool()? {
+            let config = src.read_circuit_config()?;
+            let fri_params = src.read_fri_params()?;
+            let num_gates = src.read_u32()? as usize;
+            let gates = (0..num_gates)
+                .map(|_| read_gate(src))
+                .collect::<IoResult<Vec<_>>>()?;
+            let selectors_info = src.read_selectors_info()?;
+            let quotient_degree_factor = src.read_u32()? as usize;
+            let num_gate_constraints = src.read_u32()? as usize;
+            let num_constants = src.read_u32()? as usize;
+            let num_public_inputs = src.read_u32()? as usize;
+            let num_k_is = src.read_u32()? as usize;
+            let k_is = src.read_field_vec(num_k_is)?;
+            let num_partial_products = src.read_u32()? as usize;
+            Some(CommonCircuitData {
+                config,
+                fri_params,
+                gates,
+                selectors_info,
+                quotient_degree_factor,
+                num_gate_constraints,
+                num_constants,
+                num_public_inputs,
+                k_is,
+                num_partial_products,
+            })
+        } else {
+            None
+        };
+        let verifier_data_public_input = if src.read_bool()? {
+            let cap_len = src.read_u32()? as usize;
+            let constants_sigmas_cap = MerkleCapTarget(
+                (0..cap_len)
+                    .map(|_| read_hash_target(src))
+                    .collect::<IoResult<Vec<_>>>()?,
+            );
+            let circuit_digest = read_hash_target(src)?;
+            Some(VerifierCircuitTarget {
+                constants_sigmas_cap,
+                circuit_digest,
+            })
+        } else {
+            None
+        };
+
+        Ok(Self {
+            config,
+            domain_separator,
+            gates,
+            gate_instances,
+            public_inputs,
+            virtual_target_index,
+            copy_constraints,
+            context_log,
+            generators: Vec::new(),
+            random_targets,
+            scratch_slot,
+            constants_to_targets,
+            targets_to_constants,
+            base_arithmetic_results,
+            arithmetic_results,
+            current_slots,
+            relocatable_rows,
+            free_rows,
+            constant_generators,
+            constant_slot_targets,
+            goal_common_data,
+            verifier_data_public_input,
+        })
+    }
+
     pub fn set_domain_separator(&mut self, separator: Vec<F>) {
         assert!(self.domain_separator.is_none());
         self.domain_separator = Some(separator);
@@ -160,6 +585,23 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.gate_instances.len()
     }
 
+    /// Appends `NoopGate`s until the circuit has `n` gates, e.g. so that two circuits end up with
+    /// the same degree. Note that `build` adds a few gates of its own, such as the public input
+    /// gate, before padding the number of rows to a power of two.
+    ///
+    /// Panics if the circuit already has more than `n` gates.
+    pub fn pad_to_num_gates(&mut self, n: usize) {
+        assert!(
+            self.num_gates() <= n,
+            "Circuit already has {} gates, more than the target of {}",
+            self.num_gates(),
+            n
+        );
+        while self.num_gates() < n {
+            self.add_gate(NoopGate, vec![]);
+        }
+    }
+
     /// Registers the given target as a public input.
     pub fn register_public_input(&mut self, target: Target) {
         self.public_inputs.push(target);
@@ -167,9 +609,30 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
 
     /// Registers the given targets as public inputs.
     pub fn register_public_inputs(&mut self, targets: &[Target]) {
+        self.public_inputs.reserve(targets.len());
         targets.iter().for_each(|&t| self.register_public_input(t));
     }
 
+    /// Registers the four elements of `hash` as public inputs, in order. The hash can be read back
+    /// with `ProofWithPublicInputs::public_inputs_hash`, or with
+    /// `ProofWithPublicInputsTarget::public_inputs_hash` in a recursive circuit.
+    pub fn register_public_input_hash(&mut self, hash: HashOutTarget) {
+        self.register_public_inputs(&hash.elements);
+    }
+
+    /// Hashes the given targets with `PoseidonHash` and registers only the resulting digest as
+    /// public inputs. This keeps the public input count, and thus the cost of verifying the proof
+    /// recursively, constant regardless of `targets.len()`.
+    ///
+    /// The preimage must be supplied to the verifier out of band; see
+    /// `ProofWithPublicInputs::verify_public_inputs_preimage`. For that check to be meaningful, the
+    /// digest should be the circuit's only public inputs.
+    pub fn register_public_inputs_hashed(&mut self, targets: &[Target]) -> HashOutTarget {
+        let hash = self.hash_n_to_hash_no_pad::<PoseidonHash>(targets.to_vec());
+        self.register_public_inputs(&hash.elements);
+        hash
+    }
+
     pub fn num_public_inputs(&self) -> usize {
         self.public_inputs.len()
     }
@@ -188,6 +651,34 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         (0..n).map(|_i| self.add_virtual_target()).collect()
     }
 
+    /// Reserves `n` routed wires as scratch space, packed `num_routed_wires` to a row in `NoopGate`
+    /// rows.
+    ///
+    /// Unlike virtual targets, these are concrete wires whose values are committed to with the rest
+    /// of the witness, so they can be filled by generators and copied to or from other targets.
+    ///
+    /// # Safety contract
+    ///
+    /// No gate constrains these wires: the prover may put any value in them. A scratch wire is only
+    /// meaningful once it has been tied to the rest of the circuit, e.g. with `connect` or by
+    /// feeding it to gadgets which constrain it. Unset scratch wires are left at zero.
+    pub fn add_scratch_wires(&mut self, n: usize) -> Vec<Target> {
+        let mut targets = Vec::with_capacity(n);
+        for _ in 0..n {
+            let (row, column) = match self.scratch_slot {
+                Some(slot) => slot,
+                None => (self.add_gate(NoopGate, vec![]), 0),
+            };
+            self.scratch_slot = if column + 1 < self.config.num_routed_wires {
+                Some((row, column + 1))
+            } else {
+                None
+            };
+            targets.push(Target::wire(row, column));
+        }
+        targets
+    }
+
     pub fn add_virtual_target_arr<const N: usize>(&mut self) -> [Target; N] {
         [0; N].map(|_| self.add_virtual_target())
     }
@@ -245,6 +736,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         t
     }
 
+    /// Adds targets for the verifier data of an inner circuit whose Merkle caps have height
+    /// `cap_height`, to be passed to `verify_proof`.
     pub fn add_virtual_verifier_data(&mut self, cap_height: usize) -> VerifierCircuitTarget {
         VerifierCircuitTarget {
             constants_sigmas_cap: self.add_virtual_cap(cap_height),
@@ -281,7 +774,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         );
         constants.resize(gate_type.num_constants(), F::ZERO);
 
-        let row = self.gate_instances.len();
+        let row = self.free_rows.pop().unwrap_or(self.gate_instances.len());
 
         self.constant_generators
             .extend(gate_type.extra_constant_wires().into_iter().map(
@@ -301,10 +794,15 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let gate_ref = GateRef::new(gate_type);
         self.gates.insert(gate_ref.clone());
 
-        self.gate_instances.push(GateInstance {
+        let instance = GateInstance {
             gate_ref,
             constants,
-        });
+        };
+        if row == self.gate_instances.len() {
+            self.gate_instances.push(instance);
+        } else {
+            self.gate_instances[row] = instance;
+        }
 
         row
     }
@@ -326,6 +824,45 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         );
     }
 
+    /// Checks that the constraints of each gate have at most the degree the gate declares, which
+    /// is what the selector polynomials are laid out for, and at most `max_quotient_degree_factor`.
+    /// Degrees are measured as in `test_low_degree`, by evaluating the constraints on a random
+    /// low-degree witness, so a gate whose constraints are of higher degree than it claims is
+    /// caught here rather than when proving.
+    fn check_gate_degrees(&self) -> Result<()> {
+        // Any witness of nonzero degree will do, and a small one keeps this cheap.
+        const WITNESS_SIZE: usize = 4;
+        let max_degree = self.config.max_quotient_degree_factor;
+        for gate in &self.gates {
+            let declared_degree = gate.0.degree();
+            // The domain must be large enough to tell either bound from one more than it.
+            let rate_bits = log2_ceil(max(declared_degree, max_degree) + 2);
+            let degree =
+                eval_on_random_low_degree_witness(gate.0.as_ref(), WITNESS_SIZE, rate_bits)
+                    .into_iter()
+                    .map(|p| {
+                        ceil_div_usize(p.degree_plus_one().saturating_sub(1), WITNESS_SIZE - 1)
+                    })
+                    .max()
+                    .unwrap_or(0);
+            ensure!(
+                degree <= declared_degree,
+                "{} has constraints of degree {}, but declares degree {}",
+                gate.0.id(),
+                degree,
+                declared_degree
+            );
+            ensure!(
+                degree <= max_degree,
+                "{} has constraints of degree {}, but max_quotient_degree_factor is {}",
+                gate.0.id(),
+                degree,
+                max_degree
+            );
+        }
+        Ok(())
+    }
+
     pub fn add_gate_to_gate_set(&mut self, gate: GateRef<F, D>) {
         self.gates.insert(gate);
     }
@@ -356,22 +893,60 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             .push(CopyConstraint::new((x, y), self.context_log.open_stack()));
     }
 
+    /// Connects each target in `xs` to the target at the same position in `ys`.
+    pub fn connect_slice(&mut self, xs: &[Target], ys: &[Target]) {
+        assert_eq!(
+            xs.len(),
+            ys.len(),
+            "Tried to connect slices of different lengths"
+        );
+        for (&x, &y) in xs.iter().zip(ys) {
+            self.connect(x, y);
+        }
+    }
+
+    /// Connects each extension target in `xs` to the one at the same position in `ys`.
+    pub fn connect_extension_slice(
+        &mut self,
+        xs: &[ExtensionTarget<D>],
+        ys: &[ExtensionTarget<D>],
+    ) {
+        assert_eq!(
+            xs.len(),
+            ys.len(),
+            "Tried to connect extension slices of different lengths"
+        );
+        for (&x, &y) in xs.iter().zip(ys) {
+            self.connect_extension(x, y);
+        }
+    }
+
+    /// Asserts that `x` is zero. This only adds a copy constraint to the shared zero target.
     pub fn assert_zero(&mut self, x: Target) {
         let zero = self.zero();
         self.connect(x, zero);
     }
 
+    /// Asserts that `x` is one. This only adds a copy constraint to the shared one target.
     pub fn assert_one(&mut self, x: Target) {
         let one = self.one();
         self.connect(x, one);
     }
 
+    /// Asserts that `x` equals `c` by copying `x` into a `ConstantGate` slot whose constant is `c`.
+    /// Unlike `assert_equal_constant`, this creates no target holding `c` and no generator writing
+    /// it, so `x` must be generated elsewhere. The slot isn't shared with `constant(c)`.
+    pub(crate) fn assert_equal_constant_slot(&mut self, x: Target, c: F) {
+        self.constant_slot_targets.push((c, x));
+    }
+
     pub fn add_generators(&mut self, generators: Vec<Box<dyn WitnessGenerator<F>>>) {
-        self.generators.extend(generators);
+        self.generators
+            .extend(generators.into_iter().map(Arc::from));
     }
 
     pub fn add_simple_generator<G: SimpleGenerator<F>>(&mut self, generator: G) {
-        self.generators.push(Box::new(generator.adapter()));
+        self.generators.push(Arc::new(generator.adapter()));
     }
 
     /// Returns a routable target with a value of 0.
@@ -428,6 +1003,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
     }
 
+    /// Returns a hash target whose limbs are the constants in `h`. Like `constant`, repeated limbs
+    /// share a target.
     pub fn constant_hash(&mut self, h: HashOut<F>) -> HashOutTarget {
         HashOutTarget {
             elements: h.elements.map(|x| self.constant(x)),
@@ -495,7 +1072,34 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         params: &[F],
         constants: &[F],
     ) -> (usize, usize) {
-        let num_gates = self.num_gates();
+        let (row, op) = self.find_slot_inner(gate, params, constants);
+        // The caller may hold on to this row's wires, so its operations must stay where they are.
+        self.relocatable_rows.remove(&row);
+        (row, op)
+    }
+
+    /// Like `find_slot`, but the caller promises to only refer to the slot's wires through copy
+    /// constraints, so that `build` may move the operation to a slot in another row. See
+    /// `compact_arithmetic_rows`.
+    pub(crate) fn find_relocatable_slot<G: Gate<F, D> + Clone>(
+        &mut self,
+        gate: G,
+        params: &[F],
+        constants: &[F],
+    ) -> (usize, usize) {
+        let (row, op) = self.find_slot_inner(gate, params, constants);
+        if op == 0 {
+            self.relocatable_rows.insert(row);
+        }
+        (row, op)
+    }
+
+    fn find_slot_inner<G: Gate<F, D> + Clone>(
+        &mut self,
+        gate: G,
+        params: &[F],
+        constants: &[F],
+    ) -> (usize, usize) {
         let num_ops = gate.num_ops();
         let gate_ref = GateRef::new(gate.clone());
         let gate_slot = self.current_slots.entry(gate_ref.clone()).or_default();
@@ -503,8 +1107,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         let (gate_idx, slot_idx) = if let Some(&s) = slot {
             s
         } else {
-            self.add_gate(gate, constants.to_vec());
-            (num_gates, 0)
+            (self.add_gate(gate, constants.to_vec()), 0)
         };
         let current_slot = &mut self.current_slots.get_mut(&gate_ref).unwrap().current_slot;
         if slot_idx == num_ops - 1 {
@@ -518,6 +1121,162 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         (gate_idx, slot_idx)
     }
 
+    /// Merges partially filled rows of the multi-operation arithmetic gates. `find_slot` keeps one
+    /// open row per set of constants, so e.g. products, with constants `(1, 0)`, and sums, with
+    /// constants `(1, 1)`, each tend to leave a partially filled row behind. An operation with
+    /// constants `(c_0, c_1)` can run in a row with constants `(k_0, k_1)` as long as each `c_i` is
+    /// either `k_i` or zero: a term whose constant is zero gets a zero input instead.
+    ///
+    /// Only rows filled through `find_relocatable_slot` are moved, since their wires are only
+    /// referenced by copy constraints, which are rewritten here. The emptied rows are reused by the
+    /// gates `build` adds afterwards, such as the public input gate.
+    fn compact_arithmetic_rows(&mut self) {
+        /// The wires of an operation's first multiplicand, second multiplicand, addend and output.
+        type OpWires = fn(usize) -> [Range<usize>; 4];
+        let base_wires: OpWires = |i| {
+            [
+                ArithmeticGate::wire_ith_multiplicand_0(i),
+                ArithmeticGate::wire_ith_multiplicand_1(i),
+                ArithmeticGate::wire_ith_addend(i),
+                ArithmeticGate::wire_ith_output(i),
+            ]
+            .map(|w| w..w + 1)
+        };
+        let ext_wires: OpWires = |i| {
+            [
+                ArithmeticExtensionGate::<D>::wires_ith_multiplicand_0(i),
+                ArithmeticExtensionGate::<D>::wires_ith_multiplicand_1(i),
+                ArithmeticExtensionGate::<D>::wires_ith_addend(i),
+                ArithmeticExtensionGate::<D>::wires_ith_output(i),
+            ]
+        };
+        let gates = [
+            (
+                GateRef::new(ArithmeticGate::new_from_config(&self.config)),
+                base_wires,
+            ),
+            (
+                GateRef::new(ArithmeticExtensionGate::new_from_config(&self.config)),
+                ext_wires,
+            ),
+        ];
+
+        // New locations of the moved wires, or `None` for inputs which are replaced by zero.
+        let mut moved_wires = HashMap::<Target, Option<Target>>::new();
+        let mut zero_wires = Vec::new();
+        for (gate_ref, op_wires) in gates {
+            let slots = match self.current_slots.get_mut(&gate_ref) {
+                Some(slots) => slots,
+                None => continue,
+            };
+            let num_ops = gate_ref.0.num_ops();
+            // Open rows as `(row, number of used operations)`, sorted so that this is deterministic.
+            let mut open_rows = slots
+                .current_slot
+                .values()
+                .copied()
+                .sorted()
+                .collect::<Vec<_>>();
+            let mut receivers = HashSet::new();
+
+            // Empty the least used rows first.
+            let donors = open_rows
+                .iter()
+                .copied()
+                .filter(|(row, _)| self.relocatable_rows.contains(row))
+                .sorted_by_key(|&(row, used)| (used, row))
+                .collect::<Vec<_>>();
+            for (donor, donor_used) in donors {
+                // A row which received operations can't give them away again.
+                if receivers.contains(&donor) {
+                    continue;
+                }
+                let donor_constants = &self.gate_instances[donor].constants;
+                let receiver_index = open_rows.iter().position(|&(row, used)| {
+                    let constants = &self.gate_instances[row].constants;
+                    row != donor
+                        && used + donor_used <= num_ops
+                        && donor_constants
+                            .iter()
+                            .zip(constants)
+                            .all(|(&c, &k)| c == k || c == F::ZERO)
+                });
+                let receiver_index = match receiver_index {
+                    Some(index) => index,
+                    None => continue,
+                };
+                let (receiver, receiver_used) = open_rows[receiver_index];
+                let receiver_constants = &self.gate_instances[receiver].constants;
+                // Whether each input keeps its value, or is replaced by zero since its term's
+                // constant is zero in the donor row but not in the receiver row.
+                let keep = [
+                    donor_constants[0] == receiver_constants[0],
+                    true,
+                    donor_constants[1] == receiver_constants[1],
+                    true,
+                ];
+                for op in 0..donor_used {
+                    let from = op_wires(op);
+                    let to = op_wires(receiver_used + op);
+                    for ((from, to), keep) in from.into_iter().zip(to).zip(keep) {
+                        for (from, to) in from.zip(to) {
+                            let to = Target::wire(receiver, to);
+                            if keep {
+                                moved_wires.insert(Target::wire(donor, from), Some(to));
+                            } else {
+                                moved_wires.insert(Target::wire(donor, from), None);
+                                zero_wires.push(to);
+                            }
+                        }
+                    }
+                }
+
+                receivers.insert(receiver);
+                open_rows[receiver_index].1 += donor_used;
+                open_rows.retain(|&(row, _)| row != donor);
+                self.relocatable_rows.remove(&donor);
+                self.gate_instances[donor] = GateInstance {
+                    gate_ref: GateRef::new(NoopGate),
+                    constants: vec![],
+                };
+                self.free_rows.push(donor);
+            }
+
+            // Record the new state of the open rows.
+            let slots = self.current_slots.get_mut(&gate_ref).unwrap();
+            slots.current_slot.retain(|_, (row, used)| {
+                match open_rows.iter().find(|&&(r, _)| r == *row) {
+                    Some(&(_, new_used)) => {
+                        *used = new_used;
+                        new_used < num_ops
+                    }
+                    None => false,
+                }
+            });
+        }
+
+        if moved_wires.is_empty() {
+            return;
+        }
+        // Fill the lowest free rows first.
+        self.free_rows.sort_unstable_by(|a, b| b.cmp(a));
+        self.copy_constraints = self
+            .copy_constraints
+            .drain(..)
+            .filter_map(|CopyConstraint { pair: (x, y), name }| {
+                let x = moved_wires.get(&x).copied().unwrap_or(Some(x))?;
+                let y = moved_wires.get(&y).copied().unwrap_or(Some(y))?;
+                Some(CopyConstraint::new((x, y), name))
+            })
+            .collect();
+        if !zero_wires.is_empty() {
+            let zero = self.zero();
+            for w in zero_wires {
+                self.connect(zero, w);
+            }
+        }
+    }
+
     fn fri_params(&self, degree_bits: usize) -> FriParams {
         self.config
             .fri_config
@@ -592,6 +1351,12 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             self.blind();
         }
 
+        // Rows emptied by `compact_arithmetic_rows` and not reused become no-ops.
+        if !self.free_rows.is_empty() {
+            self.gates.insert(GateRef::new(NoopGate));
+            self.free_rows.clear();
+        }
+
         while !self.gate_instances.len().is_power_of_two() {
             self.add_gate(NoopGate, vec![]);
         }
@@ -612,9 +1377,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         for _ in 0..regular_poly_openings {
             let row = self.add_gate(NoopGate, vec![]);
             for w in 0..num_wires {
-                self.add_simple_generator(RandomValueGenerator {
-                    target: Target::Wire(Wire { row, column: w }),
-                });
+                self.add_random_value(Target::Wire(Wire { row, column: w }));
             }
         }
 
@@ -626,12 +1389,10 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             let gate_2 = self.add_gate(NoopGate, vec![]);
 
             for w in 0..num_routed_wires {
-                self.add_simple_generator(RandomValueGenerator {
-                    target: Target::Wire(Wire {
-                        row: gate_1,
-                        column: w,
-                    }),
-                });
+                self.add_random_value(Target::Wire(Wire {
+                    row: gate_1,
+                    column: w,
+                }));
                 self.generate_copy(
                     Target::Wire(Wire {
                         row: gate_1,
@@ -722,6 +1483,51 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
                 .count();
             debug!("- {} instances of {}", count, gate.0.id());
         }
+        debug!(
+            "Unused operations in partially filled rows: {}",
+            self.num_unused_gate_ops()
+        );
+    }
+
+    /// The number of operations left unused in partially filled rows of gates which perform several
+    /// operations per row. `find_slot` keeps at most one open row per gate type and set of
+    /// parameters, so this is at most `num_ops - 1` for each combination used. `build` merges some
+    /// of these rows, see `compact_arithmetic_rows`, so this is an upper bound on the waste in the
+    /// final circuit. Beyond that, the way to reduce it is to use fewer distinct parameters, e.g.
+    /// fewer distinct `arithmetic` coefficients.
+    pub fn num_unused_gate_ops(&self) -> usize {
+        self.current_slots
+            .iter()
+            .map(|(gate, slots)| {
+                let num_ops = gate.0.num_ops();
+                slots
+                    .current_slot
+                    .values()
+                    .map(|&(_row, op)| num_ops - op)
+                    .sum::<usize>()
+            })
+            .sum()
+    }
+
+    /// Renders the circuit built so far as a Graphviz DOT graph, for debugging. Each gate row is a
+    /// node labeled with its row and gate ID, each virtual target touched by a copy constraint is a
+    /// point node, and each copy constraint is an edge labeled with the wires it connects.
+    ///
+    /// If `max_nodes` is set, at most that many nodes are emitted: gate rows first, then virtual
+    /// targets as they are encountered. Copy constraints touching an omitted node are dropped, and
+    /// a note node records how much was left out.
+    #[cfg(feature = "graph")]
+    pub fn to_dot(&self, max_nodes: Option<usize>) -> String {
+        let gate_ids = self
+            .gate_instances
+            .iter()
+            .map(|inst| inst.gate_ref.0.id())
+            .collect::<Vec<_>>();
+        circuit_to_dot(
+            &gate_ids,
+            self.copy_constraints.iter().map(|c| c.pair),
+            max_nodes,
+        )
     }
 
     /// In PLONK's permutation argument, there's a slight chance of division by zero. We can
@@ -730,14 +1536,24 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     /// See https://github.com/mir-protocol/plonky2/issues/456
     fn randomize_unused_pi_wires(&mut self, pi_gate: usize) {
         for wire in PublicInputGate::wires_public_inputs_hash().end..self.config.num_wires {
-            self.add_simple_generator(RandomValueGenerator {
-                target: Target::wire(pi_gate, wire),
-            });
+            self.add_random_value(Target::wire(pi_gate, wire));
         }
     }
 
+    /// Has the prover fill `target` with a random value.
+    fn add_random_value(&mut self, target: Target) {
+        self.random_targets.push(target);
+        self.add_simple_generator(RandomValueGenerator { target });
+    }
+
     /// Builds a "full circuit", with both prover and verifier data.
     pub fn build<C: GenericConfig<D, F = F>>(mut self) -> CircuitData<F, C, D> {
+        if let Err(e) = self.check_gate_degrees() {
+            panic!("{e}");
+        }
+
+        self.compact_arithmetic_rows();
+
         let mut timing = TimingTree::new("preprocess", Level::Trace);
         #[cfg(feature = "std")]
         let start = Instant::now();
@@ -760,13 +1576,10 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         self.randomize_unused_pi_wires(pi_gate);
 
         // Make sure we have enough constant generators. If not, add a `ConstantGate`.
-        while self.constants_to_targets.len() > self.constant_generators.len() {
-            self.add_gate(
-                ConstantGate {
-                    num_consts: self.config.num_constants,
-                },
-                vec![],
-            );
+        while self.constants_to_targets.len() + self.constant_slot_targets.len()
+            > self.constant_generators.len()
+        {
+            self.add_gate(ConstantGate::new(self.config.num_constants), vec![]);
         }
 
         // For each constant-target pair used in the circuit, use a constant generator to fill this target.
@@ -788,6 +1601,19 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
             self.add_simple_generator(const_gen);
         }
 
+        // The remaining slots take the targets asserted to equal a constant. These get no
+        // generator, since the targets' values come from elsewhere.
+        let num_cached_constants = self.constants_to_targets.len();
+        for ((c, t), const_gen) in self
+            .constant_slot_targets
+            .clone()
+            .into_iter()
+            .zip(self.constant_generators[num_cached_constants..].to_vec())
+        {
+            self.gate_instances[const_gen.row].constants[const_gen.constant_index] = c;
+            self.connect(Target::wire(const_gen.row, const_gen.wire_index), t);
+        }
+
         debug!(
             "Degree before blinding & padding: {}",
             self.gate_instances.len()
@@ -797,10 +1623,9 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         debug!("Degree after blinding & padding: {}", degree);
         let degree_bits = log2_strict(degree);
         let fri_params = self.fri_params(degree_bits);
-        assert!(
-            fri_params.total_arities() <= degree_bits + rate_bits - cap_height,
-            "FRI total reduction arity is too large.",
-        );
+        if let Err(e) = fri_params.validate_cap_height() {
+            panic!("{e}");
+        }
 
         let quotient_degree_factor = self.config.max_quotient_degree_factor;
         let mut gates = self.gates.iter().cloned().collect::<Vec<_>>();
@@ -848,9 +1673,10 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
                 .enumerate()
                 .flat_map(|(index, gate)| {
                     let mut gens = gate.gate_ref.0.generators(index, &gate.constants);
-                    // Remove unused generators, if any.
+                    // Remove unused generators, if any. Gates whose operations need no generators
+                    // have nothing to remove.
                     if let Some(&op) = incomplete_gates.get(&index) {
-                        gens.drain(op..);
+                        gens.truncate(op);
                     }
                     gens
                 })
@@ -897,6 +1723,18 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         ];
         let circuit_digest = C::Hasher::hash_no_pad(&circuit_digest_parts.concat());
 
+        let row_gate_indices = {
+            let gate_indices = gates
+                .iter()
+                .enumerate()
+                .map(|(i, gate)| (gate, i))
+                .collect::<HashMap<_, _>>();
+            self.gate_instances
+                .iter()
+                .map(|inst| gate_indices[&inst.gate_ref])
+                .collect()
+        };
+
         let common = CommonCircuitData {
             config: self.config,
             fri_params,
@@ -914,13 +1752,20 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         }
 
         let prover_only = ProverOnlyCircuitData {
-            generators: self.generators,
+            generators: self
+                .generators
+                .into_iter()
+                .map(|g| Box::new(SharedGenerator(g)) as Box<dyn WitnessGenerator<F>>)
+                .collect(),
             generator_indices_by_watches,
             constants_sigmas_commitment,
             sigmas: transpose_poly_values(sigma_vecs),
             subgroup,
             public_inputs: self.public_inputs,
+            random_targets: self.random_targets,
             representative_map: forest.parents,
+            row_gate_indices,
+            parallel_generators: true,
             fft_root_table: Some(fft_root_table),
             circuit_digest,
         };
@@ -954,3 +1799,559 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         circuit_data.verifier_data()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use alloc::{format, vec};
+
+    use anyhow::Result;
+
+    use crate::field::extension::Extendable;
+    use crate::field::types::Field;
+    use crate::gates::arithmetic_base::ArithmeticGate;
+    use crate::gates::arithmetic_extension::ArithmeticExtensionGate;
+    use crate::gates::gate::{Gate, GateRef};
+    use crate::gates::noop::NoopGate;
+    use crate::gates::util::StridedConstraintConsumer;
+    use crate::hash::hash_types::RichField;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::ext_target::ExtensionTarget;
+    use crate::iop::generator::{DefaultGeneratorSerializer, WitnessGenerator};
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::vars::{EvaluationTargets, EvaluationVars, EvaluationVarsBase};
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// A gate constraining its only wire to `x^power = 0`, which may declare the wrong degree.
+    #[derive(Debug)]
+    struct PowerGate {
+        power: u64,
+        declared_degree: usize,
+    }
+
+    impl<F: RichField + Extendable<D>, const D: usize> Gate<F, D> for PowerGate {
+        fn id(&self) -> String {
+            format!("{self:?}")
+        }
+
+        fn eval_unfiltered(&self, vars: EvaluationVars<F, D>) -> Vec<F::Extension> {
+            vec![vars.local_wires[0].exp_u64(self.power)]
+        }
+
+        fn eval_unfiltered_base_one(
+            &self,
+            vars: EvaluationVarsBase<F>,
+            mut yield_constr: StridedConstraintConsumer<F>,
+        ) {
+            yield_constr.one(vars.local_wires[0].exp_u64(self.power));
+        }
+
+        fn eval_unfiltered_circuit(
+            &self,
+            builder: &mut CircuitBuilder<F, D>,
+            vars: EvaluationTargets<D>,
+        ) -> Vec<ExtensionTarget<D>> {
+            vec![builder.exp_u64_extension(vars.local_wires[0], self.power)]
+        }
+
+        fn generators(
+            &self,
+            _row: usize,
+            _local_constants: &[F],
+        ) -> Vec<Box<dyn WitnessGenerator<F>>> {
+            Vec::new()
+        }
+
+        fn num_wires(&self) -> usize {
+            1
+        }
+
+        fn num_constants(&self) -> usize {
+            0
+        }
+
+        fn degree(&self) -> usize {
+            self.declared_degree
+        }
+
+        fn num_constraints(&self) -> usize {
+            1
+        }
+    }
+
+    #[cfg(feature = "graph")]
+    fn small_circuit() -> CircuitBuilder<F, D> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let z = builder.mul(x, y);
+        let w = builder.add_const(z, F::ONE);
+        builder.register_public_input(w);
+        builder
+    }
+
+    #[test]
+    fn test_batch_virtual_targets() {
+        let config = CircuitConfig::standard_recursion_config();
+
+        let mut one_at_a_time = CircuitBuilder::<F, D>::new(config.clone());
+        let xs = [(); 3].map(|_| one_at_a_time.add_virtual_target());
+        let es = [(); 2].map(|_| one_at_a_time.add_virtual_extension_target());
+        let h = one_at_a_time.add_virtual_target_arr::<4>();
+        let sum = one_at_a_time.add_many(xs);
+        one_at_a_time.connect_extension(es[0], es[1]);
+        for t in xs.into_iter().chain([sum]).chain(h) {
+            one_at_a_time.register_public_input(t);
+        }
+
+        let mut batched = CircuitBuilder::<F, D>::new(config);
+        let xs = batched.add_virtual_targets(3);
+        let es = batched.add_virtual_extension_targets(2);
+        let h = batched.add_virtual_hash();
+        let sum = batched.add_many(&xs);
+        batched.connect_extension(es[0], es[1]);
+        batched.register_public_inputs(&xs);
+        batched.register_public_input(sum);
+        batched.register_public_inputs(&h.elements);
+
+        let one_at_a_time = one_at_a_time.build::<C>();
+        let batched = batched.build::<C>();
+        assert_eq!(one_at_a_time.common, batched.common);
+        assert_eq!(one_at_a_time.verifier_only, batched.verifier_only);
+    }
+
+    /// `build` collects the gates from a hash set, so this relies on it sorting them by
+    /// `(degree, id)` before computing the selectors.
+    #[test]
+    fn test_build_deterministic() {
+        let build = || {
+            let config = CircuitConfig::standard_recursion_config();
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let xs = builder.add_virtual_targets(4);
+            let hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(xs.clone());
+            let power = builder.exp_u64(xs[0], 12345);
+            let product = builder.mul_many(&xs);
+            let sum = builder.add_const(product, F::from_canonical_u64(7));
+            let selected = builder.random_access(xs[1], xs.clone());
+            builder.range_check(xs[2], 16);
+            builder.register_public_inputs(&hash.elements);
+            builder.register_public_inputs(&[power, sum, selected]);
+            builder.build::<C>()
+        };
+
+        let first = build();
+        let first_bytes = first.verifier_data().to_bytes();
+        for _ in 0..5 {
+            let data = build();
+            assert_eq!(data.common, first.common);
+            assert_eq!(data.verifier_data().to_bytes(), first_bytes);
+        }
+    }
+
+    #[test]
+    fn test_cap_heights() -> Result<()> {
+        for cap_height in [0, 3, 5] {
+            let config = CircuitConfig::standard_recursion_config().with_cap_height(cap_height);
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let x = builder.add_virtual_public_input();
+            let y = builder.mul(x, x);
+            builder.register_public_input(y);
+            // Enough rows for FRI to have commit phase trees too.
+            for _ in 0..200 {
+                builder.add_gate(NoopGate, vec![]);
+            }
+            let data = builder.build::<C>();
+            assert!(!data.common.fri_params.reduction_arity_bits.is_empty());
+
+            let mut pw = PartialWitness::new();
+            pw.set_target(x, F::from_canonical_u64(5));
+            let proof = data.prove(pw)?;
+
+            let cap_len = 1 << cap_height;
+            assert_eq!(data.verifier_only.constants_sigmas_cap.len(), cap_len);
+            assert_eq!(proof.proof.wires_cap.len(), cap_len);
+            assert_eq!(proof.proof.plonk_zs_partial_products_cap.len(), cap_len);
+            assert_eq!(proof.proof.quotient_polys_cap.len(), cap_len);
+            for cap in proof.proof.opening_proof.commit_phase_caps() {
+                assert_eq!(cap.len(), cap_len);
+            }
+            data.verify(proof)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_scratch_wires() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let num_routed_wires = config.num_routed_wires;
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        // Scratch wires are packed into as few rows as possible.
+        let scratch = builder.add_scratch_wires(num_routed_wires + 1);
+        assert_eq!(builder.num_gates(), 2);
+        assert_eq!(builder.add_scratch_wires(1)[0], Target::wire(1, 1));
+        assert_eq!(builder.num_gates(), 2);
+
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.connect(scratch[0], x);
+        builder.connect(scratch[num_routed_wires], y);
+        let z = builder.mul(scratch[0], scratch[num_routed_wires]);
+        builder.register_public_input(z);
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::TWO);
+        // An unconnected scratch wire may hold anything.
+        pw.set_target(scratch[1], F::from_canonical_u64(1234));
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        assert_eq!(proof.public_inputs[1], F::from_canonical_u64(8));
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_check_gate_degrees() {
+        let config = CircuitConfig::standard_recursion_config();
+        assert_eq!(config.max_quotient_degree_factor, 8);
+        let check = |power, declared_degree| {
+            let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+            let gate = PowerGate {
+                power,
+                declared_degree,
+            };
+            builder.add_gate(gate, vec![]);
+            builder.check_gate_degrees()
+        };
+
+        assert!(check(8, 8).is_ok());
+        // Overestimating the degree is harmless.
+        assert!(check(2, 3).is_ok());
+
+        let err = check(3, 2).unwrap_err().to_string();
+        assert!(err.contains("PowerGate { power: 3, declared_degree: 2 }"));
+        assert!(err.contains("has constraints of degree 3, but declares degree 2"));
+        let err = check(9, 9).unwrap_err().to_string();
+        assert!(err.contains("has constraints of degree 9, but max_quotient_degree_factor is 8"));
+        // Degrees far beyond what the evaluation domain can represent are caught too.
+        assert!(check(100, 2).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "has constraints of degree 9, but max_quotient_degree_factor is 8")]
+    fn test_build_rejects_high_degree_gate() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let gate = PowerGate {
+            power: 9,
+            declared_degree: 9,
+        };
+        builder.add_gate(gate, vec![]);
+        builder.build::<C>();
+    }
+
+    #[test]
+    #[should_panic(expected = "is too large")]
+    fn test_cap_height_too_large() {
+        let config = CircuitConfig::standard_recursion_config().with_cap_height(20);
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        builder.register_public_input(x);
+        builder.build::<C>();
+    }
+
+    #[test]
+    fn test_connect_slice() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let xs = builder.add_virtual_targets(3);
+        let ys = builder.add_virtual_targets(3);
+        builder.connect_slice(&xs, &ys);
+
+        let pairs = builder
+            .copy_constraints
+            .iter()
+            .map(|c| c.pair)
+            .collect::<Vec<_>>();
+        let expected = xs.into_iter().zip(ys).collect::<Vec<_>>();
+        assert_eq!(pairs, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Tried to connect slices of different lengths")]
+    fn test_connect_slice_length_mismatch() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let xs = builder.add_virtual_targets(3);
+        let ys = builder.add_virtual_targets(2);
+        builder.connect_slice(&xs, &ys);
+    }
+
+    #[test]
+    fn test_pad_to_num_gates() {
+        const NUM_GATES: usize = 1000;
+        let config = CircuitConfig::standard_recursion_config();
+
+        // An arithmetic-heavy circuit.
+        let mut arithmetic = CircuitBuilder::<F, D>::new(config.clone());
+        let mut x = arithmetic.add_virtual_target();
+        for _ in 0..500 {
+            x = arithmetic.mul_add(x, x, x);
+        }
+        arithmetic.register_public_input(x);
+        arithmetic.pad_to_num_gates(NUM_GATES);
+        assert_eq!(arithmetic.num_gates(), NUM_GATES);
+
+        // A hash-heavy circuit.
+        let mut hashing = CircuitBuilder::<F, D>::new(config);
+        let mut h = hashing.add_virtual_hash();
+        for _ in 0..50 {
+            h = hashing.hash_n_to_hash_no_pad::<PoseidonHash>(h.elements.to_vec());
+        }
+        hashing.register_public_inputs(&h.elements);
+        hashing.pad_to_num_gates(NUM_GATES);
+        assert_eq!(hashing.num_gates(), NUM_GATES);
+
+        let arithmetic = arithmetic.build::<C>();
+        let hashing = hashing.build::<C>();
+        assert_ne!(arithmetic.common.gates, hashing.common.gates);
+        assert_eq!(arithmetic.common.degree(), hashing.common.degree());
+    }
+
+    #[test]
+    #[should_panic(expected = "more than the target")]
+    fn test_pad_to_num_gates_too_small() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_hash();
+        builder.hash_n_to_hash_no_pad::<PoseidonHash>(x.elements.to_vec());
+        builder.pad_to_num_gates(0);
+    }
+
+    #[test]
+    fn test_constants_packed_into_rows() -> Result<()> {
+        let count_constant_gates = |config: CircuitConfig| {
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let mut pw = PartialWitness::new();
+            // These include zero, which `build` also uses, so there are exactly twelve constants.
+            for i in 0..12 {
+                let c = F::from_canonical_usize(i);
+                let t = builder.constant(c);
+                let x = builder.add_virtual_target();
+                builder.connect(t, x);
+                pw.set_target(x, c);
+            }
+            let data = builder.build::<C>();
+            let proof = data.prove(pw)?;
+            data.verify(proof)?;
+
+            let num_gates = data
+                .stats()
+                .gate_counts
+                .into_iter()
+                .find(|(id, _)| id.starts_with("ConstantGate"))
+                .map(|(_, count)| count)
+                .unwrap();
+            Ok::<_, anyhow::Error>(num_gates)
+        };
+
+        // The standard config has two constant columns, so two constants fit in each row.
+        let config = CircuitConfig::standard_recursion_config();
+        assert_eq!(count_constant_gates(config.clone())?, 6);
+
+        // With twelve constant columns, all twelve constants fit in a single row.
+        let config = CircuitConfig {
+            num_constants: 12,
+            ..config
+        };
+        assert_eq!(count_constant_gates(config)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_num_unused_gate_ops() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let num_ops = ArithmeticGate::new_from_config(&config).num_ops;
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let mut pw = PartialWitness::new();
+        let x = builder.add_virtual_target();
+        pw.set_target(x, F::TWO);
+        assert_eq!(builder.num_unused_gate_ops(), 0);
+
+        // `mul` and `add` use different coefficients, so each opens its own row.
+        let y = builder.mul(x, x);
+        let z = builder.add(y, x);
+        assert_eq!(builder.num_unused_gate_ops(), 2 * (num_ops - 1));
+
+        // Further multiplications fill the existing row.
+        let w = builder.mul(z, z);
+        assert_eq!(builder.num_unused_gate_ops(), 2 * (num_ops - 1) - 1);
+
+        builder.register_public_input(w);
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_compact_arithmetic_rows() -> Result<()> {
+        for use_base_arithmetic_gate in [true, false] {
+            let config = CircuitConfig {
+                use_base_arithmetic_gate,
+                ..CircuitConfig::standard_recursion_config()
+            };
+            let gate: GateRef<F, D> = if use_base_arithmetic_gate {
+                GateRef::new(ArithmeticGate::new_from_config(&config))
+            } else {
+                GateRef::new(ArithmeticExtensionGate::new_from_config(&config))
+            };
+            let num_ops = gate.0.num_ops();
+            let mut builder = CircuitBuilder::<F, D>::new(config);
+            let mut pw = PartialWitness::new();
+            let xs = builder.add_virtual_targets(num_ops + 2);
+            for (i, &x) in xs.iter().enumerate() {
+                pw.set_target(x, F::from_canonical_usize(i + 1));
+            }
+
+            // A full row of products and one with a single product, with constants `(1, 0)`, and a
+            // row with a single sum, with constants `(1, 1)`. The last product fits in the sum's
+            // row, with its addend replaced by zero.
+            let products = (0..=num_ops)
+                .map(|i| builder.mul(xs[i], xs[i + 1]))
+                .collect::<Vec<_>>();
+            let sum = builder.add(xs[0], xs[1]);
+            builder.register_public_input(products[num_ops]);
+            builder.register_public_input(sum);
+            assert_eq!(builder.num_unused_gate_ops(), 2 * (num_ops - 1));
+
+            // `build` adds a `PoseidonGate` to hash the public inputs, the public input gate and a
+            // `ConstantGate`. Without compaction, that would take us past 64 rows.
+            builder.pad_to_num_gates(62);
+            let data = builder.build::<C>();
+            assert_eq!(data.common.degree(), 64);
+            let gate_counts = data.stats().gate_counts;
+            let (_, num_arithmetic_rows) = gate_counts
+                .iter()
+                .find(|(id, _)| *id == gate.0.id())
+                .unwrap();
+            assert_eq!(*num_arithmetic_rows, 2);
+
+            let proof = data.prove(pw)?;
+            let expected = [
+                F::from_canonical_usize((num_ops + 1) * (num_ops + 2)),
+                F::from_canonical_usize(3),
+            ];
+            assert_eq!(proof.public_inputs, expected);
+            data.verify(proof)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_resume_from_prefix() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let prefix = |builder: &mut CircuitBuilder<F, D>| {
+            let xs = builder.add_virtual_targets(4);
+            let h = builder.hash_n_to_hash_no_pad::<PoseidonHash>(xs.clone());
+            let p = builder.mul_many(&xs);
+            builder.split_le(p, 64);
+            let inv = builder.inverse(xs[0]);
+            builder.register_public_inputs(&h.elements);
+            builder.register_public_input(inv);
+            (xs, p)
+        };
+        let suffix = |builder: &mut CircuitBuilder<F, D>, p| {
+            let q = builder.exp_u64(p, 5);
+            let r = builder.add_const(q, F::TWO);
+            let s = builder.mul_many([p, q, r]);
+            builder.register_public_input(s);
+        };
+
+        let mut one_pass = CircuitBuilder::<F, D>::new(config.clone());
+        let (_, p) = prefix(&mut one_pass);
+        suffix(&mut one_pass, p);
+        let one_pass = one_pass.build::<C>();
+
+        let mut saved = CircuitBuilder::<F, D>::new(config);
+        let (xs, p) = prefix(&mut saved);
+        let mut resumed = saved.clone();
+        suffix(&mut resumed, p);
+        let resumed = resumed.build::<C>();
+
+        assert_eq!(resumed.common, one_pass.common);
+        assert_eq!(
+            resumed.verifier_only.circuit_digest,
+            one_pass.verifier_only.circuit_digest
+        );
+
+        // The saved prefix is restored from bytes, given the gates of the finished circuit.
+        let bytes = saved.to_bytes(&DefaultGeneratorSerializer)?;
+        let mut restored = CircuitBuilder::<F, D>::from_bytes(
+            bytes.clone(),
+            &one_pass.common.gates,
+            &DefaultGeneratorSerializer,
+        )?;
+        suffix(&mut restored, p);
+        let data = restored.build::<C>();
+        assert_eq!(data.common, one_pass.common);
+        assert_eq!(
+            data.verifier_only.circuit_digest,
+            one_pass.verifier_only.circuit_digest
+        );
+
+        let mut pw = PartialWitness::new();
+        for (i, &x) in xs.iter().enumerate() {
+            pw.set_target(x, F::from_canonical_usize(i + 1));
+        }
+        let proof = data.prove(pw)?;
+        // The restored prefix's generators ran, e.g. the one inverting `xs[0] = 1`.
+        assert!(proof.public_inputs[4].is_one());
+        one_pass.verify(proof)?;
+
+        // A prefix can't be restored without all of its gates.
+        let gates = one_pass
+            .common
+            .gates
+            .iter()
+            .filter(|g| !g.0.id().starts_with("PoseidonGate"))
+            .cloned()
+            .collect::<Vec<_>>();
+        assert!(
+            CircuitBuilder::<F, D>::from_bytes(bytes, &gates, &DefaultGeneratorSerializer).is_err()
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "graph")]
+    #[test]
+    fn test_to_dot() {
+        let builder = small_circuit();
+        let dot = builder.to_dot(None);
+
+        assert!(dot.starts_with("graph circuit {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("g0 [shape=box, label=\"0: ArithmeticGate"));
+        assert!(dot.contains("v0 [shape=point"));
+        assert!(dot.contains(" -- "));
+        assert!(!dot.contains("truncated"));
+    }
+
+    #[cfg(feature = "graph")]
+    #[test]
+    fn test_to_dot_truncated() {
+        let builder = small_circuit();
+        let dot = builder.to_dot(Some(1));
+
+        let num_nodes = dot.lines().filter(|l| l.contains("[shape=")).count();
+        // One real node plus the truncation note.
+        assert_eq!(num_nodes, 2);
+        assert!(dot.contains("truncated [shape=note"));
+        assert!(!dot.contains(" -- v"));
+    }
+}
@@ -1,5 +1,6 @@
 use alloc::boxed::Box;
 use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::ops::{Range, RangeFrom};
@@ -18,18 +19,32 @@ use crate::fri::structure::{
 use crate::fri::{FriConfig, FriParams};
 use crate::gates::gate::GateRef;
 use crate::gates::selectors::SelectorsInfo;
-use crate::hash::hash_types::{HashOutTarget, MerkleCapTarget, RichField};
+use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_tree::MerkleCap;
+use crate::hash::poseidon::PoseidonHash;
 use crate::iop::ext_target::ExtensionTarget;
-use crate::iop::generator::WitnessGenerator;
+use crate::iop::generator::{generate_partial_witness, WitnessGenerator};
 use crate::iop::target::Target;
-use crate::iop::witness::PartialWitness;
+#[cfg(debug_assertions)]
+use crate::iop::witness::PartitionWitness;
+use crate::iop::witness::{MatrixWitness, PartialWitness, Witness, WitnessWrite};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::config::{GenericConfig, Hasher};
-use crate::plonk::plonk_common::PlonkOracle;
-use crate::plonk::proof::{CompressedProofWithPublicInputs, ProofWithPublicInputs};
-use crate::plonk::prover::prove;
-use crate::plonk::verifier::verify;
+use crate::plonk::plonk_common::{salt_size, PlonkOracle};
+use crate::plonk::proof::{
+    CompressedProofWithPublicInputs, FingerprintedProof, ProofWithPublicInputs,
+};
+#[cfg(feature = "rand_chacha")]
+use crate::plonk::prover::prove_with_seed;
+use crate::plonk::prover::{prove, try_prove, ProveError};
+#[cfg(feature = "timing")]
+use crate::plonk::prover::{prove_with_timing, ProverTiming};
+use crate::plonk::vanishing_poly::evaluate_gate_constraints_base_batch;
+use crate::plonk::vars::EvaluationVarsBaseBatch;
+use crate::plonk::verifier::{verify, verify_batch};
+#[cfg(feature = "graph")]
+use crate::util::dot::circuit_to_dot;
+use crate::util::serialization::{Buffer, Read, Write};
 use crate::util::timing::TimingTree;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -103,6 +118,14 @@ impl CircuitConfig {
             ..Self::standard_recursion_config()
         }
     }
+
+    /// Returns a copy of this config with the given Merkle cap height; see `FriConfig::cap_height`.
+    /// `CircuitBuilder::build` panics if it is too large for the circuit's degree.
+    pub fn with_cap_height(&self, cap_height: usize) -> Self {
+        let mut config = self.clone();
+        config.fri_config.cap_height = cap_height;
+        config
+    }
 }
 
 /// Circuit data required by the prover or the verifier.
@@ -124,10 +147,76 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         )
     }
 
+    /// Same as `prove`, but returns a `ProveError` describing why a bad witness was rejected.
+    pub fn try_prove(
+        &self,
+        inputs: PartialWitness<F>,
+    ) -> core::result::Result<ProofWithPublicInputs<F, C, D>, ProveError> {
+        try_prove(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+        )
+    }
+
+    /// Same as `prove`, but derives all of the prover's randomness from `seed`. See
+    /// `prove_with_seed` for details.
+    #[cfg(feature = "rand_chacha")]
+    pub fn prove_with_seed(
+        &self,
+        inputs: PartialWitness<F>,
+        seed: u64,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        prove_with_seed(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            seed,
+            &mut TimingTree::default(),
+        )
+    }
+
+    /// Same as `prove`, but also reports how long each phase of proving took.
+    #[cfg(feature = "timing")]
+    pub fn prove_with_timing(
+        &self,
+        inputs: PartialWitness<F>,
+    ) -> Result<(ProofWithPublicInputs<F, C, D>, ProverTiming)> {
+        prove_with_timing(&self.prover_only, &self.common, inputs)
+    }
+
     pub fn verify(&self, proof_with_pis: ProofWithPublicInputs<F, C, D>) -> Result<()> {
         verify(proof_with_pis, &self.verifier_only, &self.common)
     }
 
+    /// Verifies a proof whose public inputs were registered with
+    /// `CircuitBuilder::register_public_inputs_hashed`, given their preimage.
+    pub fn verify_with_public_inputs_preimage(
+        &self,
+        proof_with_pis: ProofWithPublicInputs<F, C, D>,
+        preimage: &[F],
+    ) -> Result<()> {
+        proof_with_pis.verify_public_inputs_preimage(preimage)?;
+        verify(proof_with_pis, &self.verifier_only, &self.common)
+    }
+
+    /// Verifies a proof after checking that it was generated for this circuit, so that a proof of
+    /// another circuit fails with a clear error.
+    pub fn verify_fingerprinted(
+        &self,
+        proof_with_pis: FingerprintedProof<F, ProofWithPublicInputs<F, C, D>>,
+    ) -> Result<()> {
+        let proof_with_pis = proof_with_pis.check_circuit_fingerprint(&self.common)?;
+        verify(proof_with_pis, &self.verifier_only, &self.common)
+    }
+
+    /// Verifies several proofs of this circuit, failing with the index of the first invalid one.
+    /// This is a plain loop over the proofs.
+    pub fn verify_batch(&self, proofs_with_pis: &[ProofWithPublicInputs<F, C, D>]) -> Result<()> {
+        verify_batch(proofs_with_pis, &self.verifier_only, &self.common)
+    }
+
     pub fn verify_compressed(
         &self,
         compressed_proof_with_pis: CompressedProofWithPublicInputs<F, C, D>,
@@ -149,6 +238,15 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         proof.decompress(&self.verifier_only.circuit_digest, &self.common)
     }
 
+    /// Whether `other` is structurally the same circuit, i.e. whether the two accept exactly the
+    /// same proofs. Besides `CommonCircuitData::equivalent`, this compares the commitment to the
+    /// constant and sigma polynomials, which pins down every gate's constants and the wiring.
+    /// Witness generators and other prover-side bookkeeping are ignored.
+    pub fn equivalent(&self, other: &Self) -> bool {
+        self.common.equivalent(&other.common)
+            && self.verifier_only.constants_sigmas_cap == other.verifier_only.constants_sigmas_cap
+    }
+
     pub fn verifier_data(&self) -> VerifierCircuitData<F, C, D> {
         let CircuitData {
             verifier_only,
@@ -172,6 +270,239 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             common,
         }
     }
+
+    /// Returns a breakdown of this circuit's size and the expected cost of proving it.
+    pub fn stats(&self) -> CircuitStats {
+        let common = &self.common;
+        let mut counts = vec![0; common.gates.len()];
+        for &gate_index in &self.prover_only.row_gate_indices {
+            counts[gate_index] += 1;
+        }
+        let gate_counts = common
+            .gates
+            .iter()
+            .zip(counts)
+            .map(|(g, count)| (g.0.id(), count))
+            .collect();
+
+        // A routed wire is used if a copy constraint puts it in a partition with other targets.
+        let representative_map = &self.prover_only.representative_map;
+        let mut partition_sizes = vec![0; representative_map.len()];
+        for &representative in representative_map {
+            partition_sizes[representative] += 1;
+        }
+        let num_wires = common.config.num_wires;
+        let num_routed_wires_used = representative_map[..common.degree() * num_wires]
+            .iter()
+            .enumerate()
+            .filter(|&(index, &representative)| {
+                index % num_wires < common.config.num_routed_wires
+                    && partition_sizes[representative] > 1
+            })
+            .count();
+
+        CircuitStats {
+            num_gates: common.degree(),
+            gate_counts,
+            num_routed_wires_used,
+            num_generators: self.prover_only.generators.len(),
+            degree_bits: common.degree_bits(),
+            constraint_degree: common.constraint_degree(),
+            proof_size_bytes: common.proof_size_bytes(C::Hasher::HASH_SIZE),
+        }
+    }
+
+    /// Renders the circuit's structure as a Graphviz DOT graph. Each gate row is a node labeled with
+    /// its row and gate ID, and each copy constraint between two wires is an edge labeled with the
+    /// columns it connects.
+    ///
+    /// Unlike `CircuitBuilder::to_dot`, this works from the built circuit, where copy constraints
+    /// only survive as partitions of the wires. Each partition is rendered as a path through its
+    /// wires, so a partition of `k` wires contributes `k - 1` edges.
+    #[cfg(feature = "graph")]
+    pub fn to_dot(&self) -> String {
+        let common = &self.common;
+        let num_wires = common.config.num_wires;
+        let gate_ids = self
+            .prover_only
+            .row_gate_indices
+            .iter()
+            .map(|&gate_index| common.gates[gate_index].0.id())
+            .collect::<Vec<_>>();
+
+        let num_wire_targets = common.degree() * num_wires;
+        let mut last_wire_by_representative = BTreeMap::new();
+        let mut copy_constraints = Vec::new();
+        for (index, &representative) in self.prover_only.representative_map[..num_wire_targets]
+            .iter()
+            .enumerate()
+        {
+            if let Some(prev) = last_wire_by_representative.insert(representative, index) {
+                copy_constraints.push((
+                    Target::wire(prev / num_wires, prev % num_wires),
+                    Target::wire(index / num_wires, index % num_wires),
+                ));
+            }
+        }
+        circuit_to_dot(&gate_ids, copy_constraints, None)
+    }
+
+    /// Runs witness generation on `inputs`, returning a witness which assigns every partition of
+    /// targets that received a value. Proving this witness gives the same proof as proving
+    /// `inputs`, including any values which were sampled at random, so it can be serialized with
+    /// `PartialWitness::to_bytes` and proven later or elsewhere.
+    pub fn generate_witness(&self, inputs: PartialWitness<F>) -> PartialWitness<F> {
+        let partition_witness = generate_partial_witness(inputs, &self.prover_only, &self.common);
+        // One target per partition is enough to restore the whole partition.
+        let mut witness = PartialWitness::new();
+        for (t, value) in partition_witness.iter_set_values() {
+            let index = partition_witness.target_index(t);
+            if partition_witness.representative_map[index] == index {
+                witness.set_target(t, value);
+            }
+        }
+        witness
+    }
+
+    /// Runs witness generation on `inputs`, then evaluates every gate constraint at every row.
+    ///
+    /// Returns one `(row, constraint_index, value)` entry per row and per constraint slot, i.e.
+    /// `degree * num_gate_constraints` entries in total. A satisfying witness yields all zeros.
+    /// Unlike `prove`, this doesn't stop at the first unsatisfied constraint, which makes it
+    /// suitable for feeding external analysis tools.
+    pub fn evaluate_all_constraints(&self, inputs: &PartialWitness<F>) -> Vec<(usize, usize, F)> {
+        let common = &self.common;
+        let degree = common.degree();
+
+        let partition_witness = generate_partial_witness(inputs.clone(), &self.prover_only, common);
+        let public_inputs = partition_witness.get_targets(&self.prover_only.public_inputs);
+        let public_inputs_hash = C::InnerHasher::hash_no_pad(&public_inputs);
+        let witness = partition_witness.full_witness();
+
+        // Both vectors are laid out point-major, as expected by `EvaluationVarsBaseBatch`.
+        let local_constants = self.prover_only.constants_sigmas_commitment.polynomials
+            [common.constants_range()]
+        .iter()
+        .flat_map(|p| p.clone().fft().values)
+        .collect::<Vec<_>>();
+        let local_wires = witness.wire_values.concat();
+        let vars_batch = EvaluationVarsBaseBatch::new(
+            degree,
+            &local_constants,
+            &local_wires,
+            &public_inputs_hash,
+        );
+
+        let constraints = evaluate_gate_constraints_base_batch(common, vars_batch);
+        let mut result = Vec::with_capacity(constraints.len());
+        for row in 0..degree {
+            for i in 0..common.num_gate_constraints {
+                result.push((row, i, constraints[i * degree + row]));
+            }
+        }
+        result
+    }
+
+    /// Evaluates each gate's constraints on `witness`, row by row, and returns the first constraint
+    /// which doesn't vanish, or `None` if every gate is satisfied. Copy constraints aren't checked.
+    ///
+    /// No generators are run, so `witness` should assign every partition of targets, as returned by
+    /// `generate_witness`; unassigned wires are taken to be zero. This is a debugging aid, which
+    /// turns an opaque proving failure into the row and constraint at fault.
+    #[cfg(debug_assertions)]
+    pub fn find_failing_constraint(
+        &self,
+        witness: &PartialWitness<F>,
+    ) -> Option<FailingConstraint<F>> {
+        let common = &self.common;
+
+        let mut partition_witness = PartitionWitness::new(
+            common.config.num_wires,
+            common.degree(),
+            &self.prover_only.representative_map,
+        );
+        for (&t, &value) in &witness.target_values {
+            partition_witness.set_target(t, value);
+        }
+        let public_inputs = partition_witness.get_targets(&self.prover_only.public_inputs);
+        let public_inputs_hash = C::InnerHasher::hash_no_pad(&public_inputs);
+        let wires = partition_witness.full_witness();
+        self.prover_only
+            .find_failing_constraint(common, &wires, &public_inputs_hash)
+    }
+}
+
+/// A gate constraint which doesn't vanish on a witness, as returned by
+/// [`CircuitData::find_failing_constraint`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FailingConstraint<F: Field> {
+    /// The row of the gate.
+    pub row: usize,
+    /// The ID of the gate.
+    pub gate_id: String,
+    /// The index of the constraint among the gate's constraints.
+    pub constraint_index: usize,
+    /// The nonzero value of the constraint.
+    pub value: F,
+}
+
+/// A summary of a circuit's size, as returned by [`CircuitData::stats`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CircuitStats {
+    /// The total number of gates, i.e. rows, including padding.
+    pub num_gates: usize,
+    /// The number of instances of each gate type, keyed by gate ID, in the order of
+    /// `CommonCircuitData::gates`.
+    pub gate_counts: Vec<(String, usize)>,
+    /// The number of routed wires, across all rows, which are connected to other targets by copy
+    /// constraints.
+    pub num_routed_wires_used: usize,
+    /// The number of witness generators.
+    pub num_generators: usize,
+    /// The log2 of the number of rows.
+    pub degree_bits: usize,
+    /// The largest degree of any gate's constraints.
+    pub constraint_degree: usize,
+    /// The size in bytes of a serialized, uncompressed `ProofWithPublicInputs`.
+    pub proof_size_bytes: usize,
+}
+
+/// Where one of a circuit's gates lives in its constant columns, as returned by
+/// [`CommonCircuitData::gate_layout`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GateLayoutInfo {
+    pub id: String,
+    /// The gate's index in `CommonCircuitData::gates`, which is also the value its selector
+    /// polynomial takes in rows where the gate is used.
+    pub index: usize,
+    /// The constant column holding this gate's selector polynomial.
+    pub selector_index: usize,
+    /// The indices of the gates which share that selector polynomial.
+    pub selector_group: Range<usize>,
+    /// The constant columns holding the gate's own constants, which follow all selectors.
+    pub constants: Range<usize>,
+    pub num_wires: usize,
+    pub degree: usize,
+    pub num_constraints: usize,
+}
+
+/// The sizes in bytes of the parts of a serialized, uncompressed `ProofWithPublicInputs`, as
+/// returned by [`CommonCircuitData::proof_size_breakdown`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ProofSizeBreakdown {
+    /// The Merkle caps of the wires, `Z`s and partial products, and quotient polynomials.
+    pub merkle_caps: usize,
+    /// The openings of all polynomials at `zeta` and `g * zeta`.
+    pub openings: usize,
+    /// The FRI proof, including the caps of its commit phase.
+    pub fri: usize,
+    pub public_inputs: usize,
+}
+
+impl ProofSizeBreakdown {
+    pub fn total(&self) -> usize {
+        self.merkle_caps + self.openings + self.fri + self.public_inputs
+    }
 }
 
 /// Circuit data required by the prover. This may be thought of as a proving key, although it
@@ -201,10 +532,23 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             &mut TimingTree::default(),
         )
     }
+
+    /// Same as `prove`, but returns a `ProveError` describing why a bad witness was rejected.
+    pub fn try_prove(
+        &self,
+        inputs: PartialWitness<F>,
+    ) -> core::result::Result<ProofWithPublicInputs<F, C, D>, ProveError> {
+        try_prove(
+            &self.prover_only,
+            &self.common,
+            inputs,
+            &mut TimingTree::default(),
+        )
+    }
 }
 
 /// Circuit data required by the prover.
-#[derive(Debug)]
+#[derive(Debug, Eq, PartialEq)]
 pub struct VerifierCircuitData<
     F: RichField + Extendable<D>,
     C: GenericConfig<D, F = F>,
@@ -221,12 +565,67 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         verify(proof_with_pis, &self.verifier_only, &self.common)
     }
 
+    /// Serializes this data into a compact artifact for verifiers. Gates are code rather than data,
+    /// so they are left out, along with anything which can be recomputed; see `from_bytes`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        buffer
+            .write_verifier_circuit_data(self)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    /// Restores data serialized with `to_bytes`, given the circuit's gates in any order. Fails if
+    /// they aren't the gates the data was serialized with.
+    pub fn from_bytes(bytes: Vec<u8>, mut gates: Vec<GateRef<F, D>>) -> Result<Self> {
+        // Sort the gates as `CircuitBuilder::build` does.
+        gates.sort_unstable_by_key(|g| (g.0.degree(), g.0.id()));
+        let mut buffer = Buffer::new(bytes);
+        let data = buffer
+            .read_verifier_circuit_data(gates)
+            .map_err(anyhow::Error::msg)?;
+        Ok(data)
+    }
+
+    /// Verifies a proof whose public inputs were registered with
+    /// `CircuitBuilder::register_public_inputs_hashed`, given their preimage.
+    pub fn verify_with_public_inputs_preimage(
+        &self,
+        proof_with_pis: ProofWithPublicInputs<F, C, D>,
+        preimage: &[F],
+    ) -> Result<()> {
+        proof_with_pis.verify_public_inputs_preimage(preimage)?;
+        verify(proof_with_pis, &self.verifier_only, &self.common)
+    }
+
+    /// Verifies a proof after checking that it was generated for this circuit, so that a proof of
+    /// another circuit fails with a clear error.
+    pub fn verify_fingerprinted(
+        &self,
+        proof_with_pis: FingerprintedProof<F, ProofWithPublicInputs<F, C, D>>,
+    ) -> Result<()> {
+        let proof_with_pis = proof_with_pis.check_circuit_fingerprint(&self.common)?;
+        verify(proof_with_pis, &self.verifier_only, &self.common)
+    }
+
+    /// Verifies several proofs of this circuit, failing with the index of the first invalid one.
+    /// This is a plain loop over the proofs.
+    pub fn verify_batch(&self, proofs_with_pis: &[ProofWithPublicInputs<F, C, D>]) -> Result<()> {
+        verify_batch(proofs_with_pis, &self.verifier_only, &self.common)
+    }
+
     pub fn verify_compressed(
         &self,
         compressed_proof_with_pis: CompressedProofWithPublicInputs<F, C, D>,
     ) -> Result<()> {
         compressed_proof_with_pis.verify(&self.verifier_only, &self.common)
     }
+
+    /// Whether `other` is structurally the same circuit. See `CircuitData::equivalent`.
+    pub fn equivalent(&self, other: &Self) -> bool {
+        self.common.equivalent(&other.common)
+            && self.verifier_only.constants_sigmas_cap == other.verifier_only.constants_sigmas_cap
+    }
 }
 
 /// Circuit data required by the prover, but not the verifier.
@@ -247,9 +646,17 @@ pub struct ProverOnlyCircuitData<
     pub subgroup: Vec<F>,
     /// Targets to be made public.
     pub public_inputs: Vec<Target>,
+    /// Targets which the prover fills with random values, such as blinding wires.
+    pub random_targets: Vec<Target>,
     /// A map from each `Target`'s index to the index of its representative in the disjoint-set
     /// forest.
     pub representative_map: Vec<usize>,
+    /// The index in `CommonCircuitData::gates` of the gate in each row.
+    pub row_gate_indices: Vec<usize>,
+    /// Whether to run independent witness generators concurrently, when the `parallel` feature is
+    /// enabled. Circuits dominated by long chains of dependent generators can be faster with this
+    /// turned off.
+    pub parallel_generators: bool,
     /// Pre-computed roots for faster FFT.
     pub fft_root_table: Option<FftRootTable<F>>,
     /// A digest of the "circuit" (i.e. the instance, minus public inputs), which can be used to
@@ -257,6 +664,47 @@ pub struct ProverOnlyCircuitData<
     pub circuit_digest: <<C as GenericConfig<D>>::Hasher as Hasher<F>>::Hash,
 }
 
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    ProverOnlyCircuitData<F, C, D>
+{
+    /// Evaluates each gate's constraints on `wires`, row by row, and returns the first constraint
+    /// which doesn't vanish, or `None` if every gate is satisfied.
+    pub(crate) fn find_failing_constraint(
+        &self,
+        common: &CommonCircuitData<F, D>,
+        wires: &MatrixWitness<F>,
+        public_inputs_hash: &HashOut<F>,
+    ) -> Option<FailingConstraint<F>> {
+        // Gates only see the constants after the selectors.
+        let num_selectors = common.selectors_info.num_selectors();
+        let constants = self.constants_sigmas_commitment.polynomials
+            [num_selectors..common.num_constants]
+            .iter()
+            .map(|p| p.clone().fft())
+            .collect::<Vec<_>>();
+
+        for (row, &gate_index) in self.row_gate_indices.iter().enumerate() {
+            let gate = &common.gates[gate_index].0;
+            let local_constants = constants.iter().map(|c| c.values[row]).collect::<Vec<_>>();
+            let local_wires = wires.wire_values.iter().map(|w| w[row]).collect::<Vec<_>>();
+            let vars =
+                EvaluationVarsBaseBatch::new(1, &local_constants, &local_wires, public_inputs_hash);
+            let constraints = gate.eval_unfiltered_base_batch(vars);
+            if let Some((constraint_index, &value)) =
+                constraints.iter().enumerate().find(|(_, c)| c.is_nonzero())
+            {
+                return Some(FailingConstraint {
+                    row,
+                    gate_id: gate.id(),
+                    constraint_index,
+                    value,
+                });
+            }
+        }
+        None
+    }
+}
+
 /// Circuit data required by the verifier, but not the prover.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct VerifierOnlyCircuitData<C: GenericConfig<D>, const D: usize> {
@@ -327,6 +775,145 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
         self.quotient_degree_factor * self.degree()
     }
 
+    /// Describes where each gate's selector and constants live, in the order of `self.gates`.
+    /// Gates are sorted by degree and then ID when building, so this only depends on which gates a
+    /// circuit uses, not on the order they were added in.
+    pub fn gate_layout(&self) -> Vec<GateLayoutInfo> {
+        let num_selectors = self.selectors_info.num_selectors();
+        self.gates
+            .iter()
+            .enumerate()
+            .map(|(index, gate)| {
+                let selector_index = self.selectors_info.selector_indices[index];
+                GateLayoutInfo {
+                    id: gate.0.id(),
+                    index,
+                    selector_index,
+                    selector_group: self.selectors_info.groups[selector_index].clone(),
+                    constants: num_selectors..num_selectors + gate.0.num_constants(),
+                    num_wires: gate.0.num_wires(),
+                    degree: gate.0.degree(),
+                    num_constraints: gate.0.num_constraints(),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns a copy of this data with the number of FRI query rounds set to `num_query_rounds`,
+    /// for verifying proofs generated by `prover::prove_with_query_count`.
+    pub fn with_num_query_rounds(&self, num_query_rounds: usize) -> Self {
+        let mut common = self.clone();
+        common.config.fri_config.num_query_rounds = num_query_rounds;
+        common.fri_params.config.num_query_rounds = num_query_rounds;
+        common
+    }
+
+    /// A hash of this circuit's configuration, shape, selector layout and gate set. Two `CommonCircuitData`s with
+    /// different fingerprints cannot verify the same proofs.
+    pub fn fingerprint(&self) -> HashOut<F> {
+        let config = &self.config;
+        let fri_params = &self.fri_params;
+        let mut elements = [
+            config.num_wires,
+            config.num_routed_wires,
+            config.num_constants,
+            config.use_base_arithmetic_gate as usize,
+            config.security_bits,
+            config.num_challenges,
+            config.zero_knowledge as usize,
+            fri_params.config.rate_bits,
+            fri_params.config.cap_height,
+            fri_params.config.proof_of_work_bits as usize,
+            fri_params.config.num_query_rounds,
+            fri_params.hiding as usize,
+            fri_params.degree_bits,
+            self.quotient_degree_factor,
+            self.num_gate_constraints,
+            self.num_constants,
+            self.num_public_inputs,
+            self.num_partial_products,
+            fri_params.reduction_arity_bits.len(),
+        ]
+        .map(F::from_canonical_usize)
+        .to_vec();
+        elements.extend(
+            fri_params
+                .reduction_arity_bits
+                .iter()
+                .map(|&bits| F::from_canonical_usize(bits)),
+        );
+        let strategy: Vec<usize> = match &fri_params.config.reduction_strategy {
+            FriReductionStrategy::Fixed(arities) => [0, arities.len()]
+                .into_iter()
+                .chain(arities.iter().copied())
+                .collect(),
+            &FriReductionStrategy::ConstantArityBits(arity_bits, final_poly_bits) => {
+                vec![1, arity_bits, final_poly_bits]
+            }
+            FriReductionStrategy::MinSize(None) => vec![2],
+            &FriReductionStrategy::MinSize(Some(max_arity_bits)) => vec![3, max_arity_bits],
+        };
+        elements.extend(strategy.into_iter().map(F::from_canonical_usize));
+        let selectors = &self.selectors_info;
+        elements.push(F::from_canonical_usize(selectors.selector_indices.len()));
+        elements.extend(
+            selectors
+                .selector_indices
+                .iter()
+                .map(|&i| F::from_canonical_usize(i)),
+        );
+        elements.push(F::from_canonical_usize(selectors.groups.len()));
+        for group in &selectors.groups {
+            elements.push(F::from_canonical_usize(group.start));
+            elements.push(F::from_canonical_usize(group.end));
+        }
+        elements.push(F::from_canonical_usize(self.gates.len()));
+        for gate in &self.gates {
+            let id = gate.0.id();
+            elements.push(F::from_canonical_usize(id.len()));
+            elements.extend(id.bytes().map(F::from_canonical_u8));
+        }
+        elements.extend(&self.k_is);
+        PoseidonHash::hash_no_pad(&elements)
+    }
+
+    /// Whether `other` describes the same circuit shape: the same gates in the same order, the same
+    /// selector layout, the same permutation structure and the same FRI parameters, along with the
+    /// same number of public inputs and the same proof shape.
+    ///
+    /// Unlike `==`, this ignores allocation details: the builder's limits in `config` which don't
+    /// show up in the circuit itself, and counts derived from the gates and selectors. It also
+    /// says nothing about wiring or constant values, which are only committed to in
+    /// `VerifierOnlyCircuitData::constants_sigmas_cap`; see `CircuitData::equivalent` to compare
+    /// those as well.
+    pub fn equivalent(&self, other: &Self) -> bool {
+        // Destructure so that a new field has to be classified here.
+        let Self {
+            config,
+            fri_params,
+            gates,
+            selectors_info,
+            quotient_degree_factor,
+            num_gate_constraints: _,
+            num_constants: _,
+            num_public_inputs,
+            k_is,
+            num_partial_products,
+        } = self;
+        *gates == other.gates
+            && *selectors_info == other.selectors_info
+            && *quotient_degree_factor == other.quotient_degree_factor
+            // The permutation argument's structure.
+            && config.num_wires == other.config.num_wires
+            && config.num_routed_wires == other.config.num_routed_wires
+            && *k_is == other.k_is
+            && *num_partial_products == other.num_partial_products
+            && *fri_params == other.fri_params
+            && *num_public_inputs == other.num_public_inputs
+            && config.num_challenges == other.config.num_challenges
+            && config.zero_knowledge == other.config.zero_knowledge
+    }
+
     /// Range of the constants polynomials in the `constants_sigmas_commitment`.
     pub fn constants_range(&self) -> Range<usize> {
         0..self.num_constants
@@ -347,6 +934,57 @@ impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
         self.config.num_challenges..
     }
 
+    /// The size in bytes of a serialized, uncompressed `ProofWithPublicInputs` for this circuit,
+    /// given the size of a serialized hash. This is exact, not an estimate, since the proof shape
+    /// is fully determined by the circuit.
+    pub fn proof_size_bytes(&self, hash_size: usize) -> usize {
+        self.proof_size_breakdown(hash_size).total()
+    }
+
+    /// Like `proof_size_bytes`, but split into the parts of the serialized proof.
+    pub fn proof_size_breakdown(&self, hash_size: usize) -> ProofSizeBreakdown {
+        const FIELD_SIZE: usize = 8;
+        let ext_size = D * FIELD_SIZE;
+        let params = &self.fri_params;
+        let cap_size = (1 << params.config.cap_height) * hash_size;
+        // Merkle proofs are prefixed by a one-byte length.
+        let merkle_proof_size =
+            |num_leaves_bits: usize| 1 + (num_leaves_bits - params.config.cap_height) * hash_size;
+
+        let openings_size = (self.num_preprocessed_polys()
+            + self.config.num_wires
+            + self.num_zs_partial_products_polys()
+            + self.config.num_challenges
+            + self.num_quotient_polys())
+            * ext_size;
+
+        let initial_trees_size = self
+            .fri_oracles()
+            .iter()
+            .map(|o| {
+                let salt = salt_size(o.blinding && params.hiding);
+                (o.num_polys + salt) * FIELD_SIZE + merkle_proof_size(params.lde_bits())
+            })
+            .sum::<usize>();
+        let mut steps_size = 0;
+        let mut num_leaves_bits = params.lde_bits();
+        for &arity_bits in &params.reduction_arity_bits {
+            num_leaves_bits -= arity_bits;
+            steps_size += (1 << arity_bits) * ext_size + merkle_proof_size(num_leaves_bits);
+        }
+        let fri_size = params.reduction_arity_bits.len() * cap_size
+            + params.config.num_query_rounds * (initial_trees_size + steps_size)
+            + params.final_poly_len() * ext_size
+            + FIELD_SIZE;
+
+        ProofSizeBreakdown {
+            merkle_caps: 3 * cap_size,
+            openings: openings_size,
+            fri: fri_size,
+            public_inputs: self.num_public_inputs * FIELD_SIZE,
+        }
+    }
+
     pub(crate) fn get_fri_instance(&self, zeta: F::Extension) -> FriInstanceInfo<F, D> {
         // All polynomials are opened at zeta.
         let zeta_batch = FriBatchInfo {
@@ -478,3 +1116,469 @@ pub struct VerifierCircuitTarget {
     /// seed Fiat-Shamir.
     pub circuit_digest: HashOutTarget,
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "graph")]
+    use alloc::collections::BTreeMap;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use anyhow::Result;
+
+    #[cfg(feature = "rand_chacha")]
+    use crate::field::fft::fft_root_table;
+    use crate::field::types::Field;
+    #[cfg(debug_assertions)]
+    use crate::gates::exponentiation::ExponentiationGate;
+    #[cfg(debug_assertions)]
+    use crate::iop::target::Target;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData, VerifierCircuitData};
+    use crate::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
+    use crate::plonk::prover::{prove_with_query_count, ProveError};
+    use crate::plonk::verifier::verify;
+    #[cfg(feature = "rand_chacha")]
+    use crate::util::log2_ceil;
+    use crate::util::serialization::Write;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    /// Builds a circuit proving knowledge of `x, y` such that `x * y + 1` is the public input.
+    fn small_circuit(config: CircuitConfig) -> (CircuitData<F, C, D>, PartialWitness<F>) {
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let z = builder.mul(x, y);
+        let w = builder.add_const(z, F::ONE);
+        builder.register_public_input(w);
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        pw.set_target(y, F::from_canonical_u64(5));
+        (builder.build::<C>(), pw)
+    }
+
+    #[test]
+    fn test_circuit_equality() {
+        let config = CircuitConfig::standard_recursion_config();
+        let (data, _) = small_circuit(config.clone());
+        let (same, _) = small_circuit(config.clone());
+        assert_eq!(data.verifier_data(), same.verifier_data());
+        assert!(data.equivalent(&same));
+        assert!(data.verifier_data().equivalent(&same.verifier_data()));
+
+        // Same gates, but a different constant. Neither constant is one, which `add_const` uses
+        // anyway, so that both circuits need the same number of `ConstantGate`s.
+        let add_const_circuit = |c: F| {
+            let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+            let x = builder.add_virtual_target();
+            let y = builder.add_virtual_target();
+            let z = builder.mul(x, y);
+            let w = builder.add_const(z, c);
+            builder.register_public_input(w);
+            builder.build::<C>()
+        };
+        let plus_two = add_const_circuit(F::TWO);
+        let plus_three = add_const_circuit(F::from_canonical_u64(3));
+        assert_eq!(plus_two.common, plus_three.common);
+        assert_ne!(plus_two.verifier_data(), plus_three.verifier_data());
+        assert!(plus_two.common.equivalent(&plus_three.common));
+        assert!(!plus_two.equivalent(&plus_three));
+
+        // Same gates and constants, but wired differently.
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let x = builder.add_virtual_target();
+        let z = builder.mul(x, x);
+        let w = builder.add_const(z, F::ONE);
+        builder.register_public_input(w);
+        let other_wiring = builder.build::<C>();
+        assert_eq!(data.common, other_wiring.common);
+        assert_ne!(data.verifier_data(), other_wiring.verifier_data());
+        assert!(data.common.equivalent(&other_wiring.common));
+        assert!(!data.equivalent(&other_wiring));
+
+        // An extra gate type.
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let z = builder.mul(x, y);
+        let w = builder.add_const(z, F::ONE);
+        let v = builder.exp_u64(w, 5);
+        builder.register_public_input(v);
+        let other_gates = builder.build::<C>();
+        assert_ne!(data.common, other_gates.common);
+        assert!(!data.common.equivalent(&other_gates.common));
+        assert!(!data.equivalent(&other_gates));
+
+        // Reordering the gates alone is a semantic difference too, since selectors are assigned by
+        // position.
+        let mut reordered = data.common.clone();
+        reordered.gates.reverse();
+        assert_ne!(data.common, reordered);
+        assert!(!data.common.equivalent(&reordered));
+
+        // So are different FRI parameters.
+        let mut other_fri = data.common.clone();
+        other_fri.fri_params.config.num_query_rounds += 1;
+        assert!(!data.common.equivalent(&other_fri));
+
+        // A builder limit which the circuit doesn't reach is only an allocation detail.
+        let mut other_limit = data.common.clone();
+        other_limit.config.max_quotient_degree_factor += 1;
+        assert_ne!(data.common, other_limit);
+        assert!(data.common.equivalent(&other_limit));
+    }
+
+    #[test]
+    fn test_verifier_data_to_bytes() -> Result<()> {
+        let (data, pw) = small_circuit(CircuitConfig::standard_recursion_config());
+        let proof = data.prove(pw)?;
+        let verifier_data = data.verifier_data();
+        let bytes = verifier_data.to_bytes();
+
+        let mut gates = data.common.gates.clone();
+        gates.reverse();
+        let restored = VerifierCircuitData::<F, C, D>::from_bytes(bytes.clone(), gates)?;
+        assert_eq!(restored.common, data.common);
+        assert_eq!(restored.verifier_only, data.verifier_only);
+        verifier_data.verify(proof.clone())?;
+        restored.verify(proof)?;
+
+        // The artifact is smaller than one which also holds the gate IDs and permutation shifts.
+        let mut full = Vec::new();
+        full.write_verifier_circuit_data(&verifier_data).unwrap();
+        for gate in &data.common.gates {
+            full.write_all(gate.0.id().as_bytes()).unwrap();
+        }
+        full.write_field_vec(&data.common.k_is).unwrap();
+        assert!(bytes.len() < full.len());
+
+        // Without its largest gate, the gate set no longer matches.
+        let mut gates = data.common.gates.clone();
+        gates.pop();
+        assert!(VerifierCircuitData::<F, C, D>::from_bytes(bytes, gates).is_err());
+
+        // A degree too large for the field is rejected rather than overflowing.
+        let mut too_large = data.verifier_data();
+        too_large.common.fri_params.degree_bits = 100;
+        let bytes = too_large.to_bytes();
+        let gates = data.common.gates.clone();
+        assert!(VerifierCircuitData::<F, C, D>::from_bytes(bytes, gates).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats() {
+        let (data, _) = small_circuit(CircuitConfig::standard_recursion_config());
+        let stats = data.stats();
+
+        let count = |prefix: &str| {
+            stats
+                .gate_counts
+                .iter()
+                .find(|(id, _)| id.starts_with(prefix))
+                .map(|&(_, count)| count)
+                .unwrap()
+        };
+        // The multiplication and the constant addition use different arithmetic constants, but
+        // `build` merges their rows.
+        assert_eq!(count("ArithmeticGate"), 1);
+        assert_eq!(count("PoseidonGate"), 1);
+        assert_eq!(count("PublicInputGate"), 1);
+        assert_eq!(count("ConstantGate"), 1);
+        assert_eq!(stats.gate_counts.len(), 4);
+        assert_eq!(stats.num_gates, 4);
+        assert_eq!(stats.degree_bits, 2);
+        assert_eq!(stats.num_generators, data.prover_only.generators.len());
+        assert!(stats.num_routed_wires_used > 0);
+        assert!(
+            stats.num_routed_wires_used <= stats.num_gates * data.common.config.num_routed_wires
+        );
+    }
+
+    #[test]
+    fn test_gate_layout() {
+        let (data, _) = small_circuit(CircuitConfig::standard_recursion_config());
+        let layout = data.common.gate_layout();
+
+        assert_eq!(layout.len(), data.common.gates.len());
+        let num_selectors = data.common.selectors_info.num_selectors();
+        for (i, (info, gate)) in layout.iter().zip(&data.common.gates).enumerate() {
+            assert_eq!(info.id, gate.0.id());
+            assert_eq!(info.index, i);
+            assert!(info.selector_index < num_selectors);
+            assert!(info.selector_group.contains(&i));
+            assert_eq!(info.constants.start, num_selectors);
+            assert!(info.constants.end <= data.common.num_constants);
+        }
+        for prefix in [
+            "ArithmeticGate",
+            "PoseidonGate",
+            "PublicInputGate",
+            "ConstantGate",
+        ] {
+            assert!(layout.iter().any(|info| info.id.starts_with(prefix)));
+        }
+
+        let (rebuilt, _) = small_circuit(CircuitConfig::standard_recursion_config());
+        assert_eq!(rebuilt.common.gate_layout(), layout);
+    }
+
+    #[cfg(feature = "rand_chacha")]
+    #[test]
+    fn test_repeated_proofs() -> Result<()> {
+        // Every proof reuses the FFT root table cached in the prover data, both for the LDE and
+        // quotient domains. Its rows don't depend on the domain size, so for each domain it must
+        // start with the table computed for that domain alone.
+        let (mut data, pw) = small_circuit(CircuitConfig::standard_recursion_config());
+        let table = data.prover_only.fft_root_table.clone().unwrap();
+        let lde_size = data.common.lde_size();
+        let quotient_size = data.common.degree() << log2_ceil(data.common.quotient_degree_factor);
+        for size in [lde_size, quotient_size] {
+            let fresh = fft_root_table::<F>(size);
+            assert_eq!(table[..fresh.len()], fresh[..]);
+        }
+
+        // Unused wires are filled with randomness, so the proofs are seeded to be comparable.
+        let mut proofs = Vec::new();
+        for seed in 0..3 {
+            let proof = data.prove_with_seed(pw.clone(), seed)?;
+            data.verify(proof.clone())?;
+            proofs.push(proof);
+        }
+
+        // Without the cache, each FFT builds its own table, and the proofs are the same.
+        data.prover_only.fft_root_table = None;
+        for (seed, proof) in (0..).zip(proofs) {
+            assert_eq!(data.prove_with_seed(pw.clone(), seed)?, proof);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats_proof_size() -> Result<()> {
+        let (data, pw) = small_circuit(CircuitConfig::standard_recursion_config());
+        let proof = data.prove(pw)?;
+        assert_eq!(data.stats().proof_size_bytes, proof.to_bytes().len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_size_breakdown() -> Result<()> {
+        let (data, pw) = small_circuit(CircuitConfig::standard_recursion_config());
+        let proof = data.prove(pw)?;
+        let breakdown = data
+            .common
+            .proof_size_breakdown(<<C as GenericConfig<D>>::Hasher as Hasher<F>>::HASH_SIZE);
+
+        let mut caps = Vec::new();
+        caps.write_merkle_cap(&proof.proof.wires_cap).unwrap();
+        caps.write_merkle_cap(&proof.proof.plonk_zs_partial_products_cap)
+            .unwrap();
+        caps.write_merkle_cap(&proof.proof.quotient_polys_cap)
+            .unwrap();
+        let mut openings = Vec::new();
+        openings.write_opening_set(&proof.proof.openings).unwrap();
+        let mut fri = Vec::new();
+        fri.write_fri_proof::<F, C, D>(&proof.proof.opening_proof)
+            .unwrap();
+        let mut public_inputs = Vec::new();
+        public_inputs.write_field_vec(&proof.public_inputs).unwrap();
+
+        assert_eq!(breakdown.merkle_caps, caps.len());
+        assert_eq!(breakdown.openings, openings.len());
+        assert_eq!(breakdown.fri, fri.len());
+        assert_eq!(breakdown.public_inputs, public_inputs.len());
+        assert_eq!(breakdown.total(), proof.to_bytes().len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_evaluate_all_constraints() {
+        let (data, pw) = small_circuit(CircuitConfig::standard_recursion_config());
+        let constraints = data.evaluate_all_constraints(&pw);
+        assert_eq!(
+            constraints.len(),
+            data.common.degree() * data.common.num_gate_constraints
+        );
+        assert!(constraints.iter().all(|&(_, _, c)| c == F::ZERO));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn test_find_failing_constraint() {
+        let config = CircuitConfig::standard_recursion_config();
+        let gate = ExponentiationGate::<F, D>::new_from_config(&config);
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.exp_u64(x, 5);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+        let row = match y {
+            Target::Wire(wire) => wire.row,
+            Target::VirtualTarget { .. } => unreachable!(),
+        };
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(3));
+        let mut witness = data.generate_witness(pw);
+        assert_eq!(data.find_failing_constraint(&witness), None);
+
+        // Intermediate values aren't routed, so this only affects the exponentiation gate.
+        let bad_target = Target::wire(row, gate.wire_intermediate_value(0));
+        let bad_value = witness.target_values[&bad_target] + F::ONE;
+        witness.target_values.insert(bad_target, bad_value);
+
+        let failing = data.find_failing_constraint(&witness).unwrap();
+        assert_eq!(failing.row, row);
+        assert!(failing.gate_id.starts_with("ExponentiationGate"));
+        assert_eq!(failing.constraint_index, 0);
+        assert_eq!(failing.value, F::NEG_ONE);
+    }
+
+    #[test]
+    fn test_try_prove_reports_unsatisfied_constraint() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.mul(x, x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+        // The product is only a copy of the arithmetic gate's output, so look its row up.
+        let row = data
+            .prover_only
+            .row_gate_indices
+            .iter()
+            .position(|&i| data.common.gates[i].0.id().starts_with("ArithmeticGate"))
+            .unwrap();
+
+        // Inputs are set before any generator runs, so the product computed by the arithmetic gate's
+        // generator is the value which gets dropped.
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::TWO);
+        pw.set_target(y, F::from_canonical_u64(5));
+        let err = data.try_prove(pw.clone()).unwrap_err();
+        match &err {
+            ProveError::ConstraintUnsatisfied {
+                row: failing_row,
+                gate_id,
+                constraint_index,
+            } => {
+                assert_eq!(*failing_row, row);
+                assert!(gate_id.starts_with("ArithmeticGate"));
+                assert_eq!(*constraint_index, 0);
+            }
+            _ => panic!("Unexpected error: {err:?}"),
+        }
+
+        // `prove` reports the same error, wrapped for `?`.
+        let wrapped = data.prove(pw).unwrap_err();
+        assert_eq!(wrapped.downcast_ref::<ProveError>(), Some(&err));
+    }
+
+    #[test]
+    fn test_try_prove_reports_incomplete_witness() {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_target();
+        let y = builder.mul(x, x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let err = data.try_prove(PartialWitness::new()).unwrap_err();
+        assert!(matches!(err, ProveError::WitnessIncomplete { .. }));
+    }
+
+    #[test]
+    fn test_zero_knowledge_proofs() -> Result<()> {
+        let (data, pw) = small_circuit(CircuitConfig::standard_recursion_zk_config());
+        let proof0 = data.prove(pw.clone())?;
+        let proof1 = data.prove(pw)?;
+        // Blinding is random, so two proofs of the same statement should not share commitments.
+        assert_ne!(proof0.proof.wires_cap, proof1.proof.wires_cap);
+        data.verify(proof0)?;
+        data.verify(proof1)
+    }
+
+    #[test]
+    fn test_prove_with_query_count() -> Result<()> {
+        let (data, pw) = small_circuit(CircuitConfig::standard_recursion_config());
+        let verifier_data = data.verifier_data();
+        let proof = prove_with_query_count(&data.prover_data(), pw, 2)?;
+        assert_eq!(proof.proof.opening_proof.query_round_proofs.len(), 2);
+
+        // The circuit's own verifier expects the full number of queries.
+        assert!(verifier_data.verify(proof.clone()).is_err());
+        let common = verifier_data.common.with_num_query_rounds(2);
+        verify(proof, &verifier_data.verifier_only, &common)
+    }
+
+    #[cfg(feature = "rand_chacha")]
+    #[test]
+    fn test_prove_with_seed() -> Result<()> {
+        let (data, pw) = small_circuit(CircuitConfig::standard_recursion_zk_config());
+        let proof0 = data.prove_with_seed(pw.clone(), 42)?;
+        let proof1 = data.prove_with_seed(pw.clone(), 42)?;
+        assert_eq!(proof0.to_bytes(), proof1.to_bytes());
+
+        // A different seed gives different blinding.
+        let proof2 = data.prove_with_seed(pw, 43)?;
+        assert_ne!(proof0.proof.wires_cap, proof2.proof.wires_cap);
+        data.verify(proof0)?;
+        data.verify(proof2)
+    }
+
+    #[cfg(feature = "timing")]
+    #[test]
+    fn test_prove_with_timing() -> Result<()> {
+        let (data, pw) = small_circuit(CircuitConfig::standard_recursion_zk_config());
+        let (proof, timing) = data.prove_with_timing(pw)?;
+        assert!(!timing.witness_generation.is_zero());
+        assert!(!timing.wires_commitment.is_zero());
+        assert!(!timing.permutation_commitment.is_zero());
+        assert!(!timing.quotient_commitment.is_zero());
+        assert!(!timing.fri.is_zero());
+        data.verify(proof)
+    }
+
+    #[cfg(feature = "graph")]
+    #[test]
+    fn test_to_dot() {
+        let (data, _) = small_circuit(CircuitConfig::standard_recursion_config());
+        let dot = data.to_dot();
+
+        assert!(dot.starts_with("graph circuit {\n"));
+        assert!(dot.ends_with("}\n"));
+        let num_nodes = dot.lines().filter(|l| l.contains("[shape=box")).count();
+        assert_eq!(num_nodes, 8);
+        assert!(dot.contains("label=\"0: "));
+
+        // Each partition of `k` wires is drawn as a path with `k - 1` edges.
+        let num_wire_targets = data.common.degree() * data.common.config.num_wires;
+        let mut partition_sizes = BTreeMap::new();
+        for &representative in &data.prover_only.representative_map[..num_wire_targets] {
+            *partition_sizes.entry(representative).or_insert(0) += 1;
+        }
+        let expected_edges: usize = partition_sizes.values().map(|&k| k - 1).sum();
+        let num_edges = dot.lines().filter(|l| l.contains(" -- ")).count();
+        assert!(num_edges > 0);
+        assert_eq!(num_edges, expected_edges);
+    }
+
+    #[test]
+    fn test_verify_batch() -> Result<()> {
+        let (data, pw) = small_circuit(CircuitConfig::standard_recursion_config());
+        let proof = data.prove(pw)?;
+        let mut proofs = vec![proof.clone(), proof.clone(), proof];
+        data.verify_batch(&proofs)?;
+
+        proofs[1].public_inputs[0] += F::ONE;
+        let err = data.verify_batch(&proofs).unwrap_err();
+        assert_eq!(err.to_string(), "Proof 1 of the batch is invalid");
+        Ok(())
+    }
+}
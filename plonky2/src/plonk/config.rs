@@ -6,10 +6,14 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::field::extension::quadratic::QuadraticExtension;
+use crate::field::extension::quartic::QuarticExtension;
 use crate::field::extension::{Extendable, FieldExtension};
 use crate::field::goldilocks_field::GoldilocksField;
 use crate::hash::hash_types::{HashOut, RichField};
-use crate::hash::hashing::{PlonkyPermutation, SPONGE_WIDTH};
+use crate::hash::hashing::{
+    compress_with_domain, hash_n_to_hash_no_pad_with_domain, hash_n_to_hash_no_pad_with_iv,
+    PlonkyPermutation, SPONGE_CAPACITY, SPONGE_WIDTH,
+};
 use crate::hash::keccak::KeccakHash;
 use crate::hash::poseidon::PoseidonHash;
 use crate::iop::target::{BoolTarget, Target};
@@ -43,7 +47,7 @@ pub trait Hasher<F: RichField>: Sized + Clone + Debug + Eq + PartialEq {
     fn hash_pad(input: &[F]) -> Self::Hash {
         let mut padded_input = input.to_vec();
         padded_input.push(F::ONE);
-        while (padded_input.len() + 1) % SPONGE_WIDTH != 0 {
+        while (padded_input.len() + 1) % Self::Permutation::WIDTH != 0 {
             padded_input.push(F::ZERO);
         }
         padded_input.push(F::ONE);
@@ -66,6 +70,16 @@ pub trait Hasher<F: RichField>: Sized + Clone + Debug + Eq + PartialEq {
     }
 
     fn two_to_one(left: Self::Hash, right: Self::Hash) -> Self::Hash;
+
+    /// Compresses each pair of consecutive hashes with `two_to_one`, halving the length. Hashers
+    /// may override this to process the pairs in a batch.
+    fn two_to_one_pairs(hashes: &[Self::Hash]) -> Vec<Self::Hash> {
+        assert_eq!(hashes.len() % 2, 0, "Expected an even number of hashes");
+        hashes
+            .chunks_exact(2)
+            .map(|pair| Self::two_to_one(pair[0], pair[1]))
+            .collect()
+    }
 }
 
 /// Trait for algebraic hash functions, built from a permutation using the sponge construction.
@@ -82,6 +96,25 @@ pub trait AlgebraicHasher<F: RichField>: Hasher<F, Hash = HashOut<F>> {
     ) -> [Target; SPONGE_WIDTH]
     where
         F: RichField + Extendable<D>;
+
+    /// Like `hash_no_pad`, but separated by `domain`; see `hashing::hash_n_to_m_no_pad_with_domain`.
+    /// Domain 0 gives the same digests as `hash_no_pad`.
+    fn hash_no_pad_with_domain(domain: u64, input: &[F]) -> HashOut<F> {
+        hash_n_to_hash_no_pad_with_domain::<F, Self::Permutation>(domain, input)
+    }
+
+    /// Like `hash_no_pad`, but with the sponge's capacity initialized to `iv` instead of zero; see
+    /// `hashing::hash_n_to_m_no_pad_with_iv`. An all-zero IV gives the same digests as
+    /// `hash_no_pad` for non-empty inputs.
+    fn hash_no_pad_with_iv(iv: [F; SPONGE_CAPACITY], input: &[F]) -> HashOut<F> {
+        hash_n_to_hash_no_pad_with_iv::<F, Self::Permutation>(iv, input)
+    }
+
+    /// Like `two_to_one`, but separated by `domain`; see `hashing::compress_with_domain`. Domain 0
+    /// gives the same digests as `two_to_one`.
+    fn two_to_one_with_domain(domain: u64, left: HashOut<F>, right: HashOut<F>) -> HashOut<F> {
+        compress_with_domain::<F, Self::Permutation>(domain, left, right)
+    }
 }
 
 /// Generic configuration trait.
@@ -108,6 +141,15 @@ impl GenericConfig<2> for PoseidonGoldilocksConfig {
     type InnerHasher = PoseidonHash;
 }
 
+/// The degree 4 extension gives more soundness per FRI query than the usual degree 2 one, at the
+/// cost of larger extension elements in proofs and circuits.
+impl GenericConfig<4> for PoseidonGoldilocksConfig {
+    type F = GoldilocksField;
+    type FE = QuarticExtension<Self::F>;
+    type Hasher = PoseidonHash;
+    type InnerHasher = PoseidonHash;
+}
+
 /// Configuration using truncated Keccak over the Goldilocks field.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct KeccakGoldilocksConfig;
@@ -22,6 +22,25 @@ use crate::plonk::proof::{
 };
 use crate::util::reverse_bits;
 
+impl<F: RichField, H: Hasher<F>> Challenger<F, H> {
+    /// Observes all opened values, first those at `zeta` and then those at `g * zeta`.
+    pub fn observe_opening_set<const D: usize>(&mut self, openings: &OpeningSet<F, D>)
+    where
+        F: RichField + Extendable<D>,
+    {
+        self.observe_openings(&openings.to_fri_openings());
+    }
+}
+
+impl<F: RichField + Extendable<D>, H: AlgebraicHasher<F>, const D: usize>
+    RecursiveChallenger<F, H, D>
+{
+    /// In-circuit version of `Challenger::observe_opening_set`.
+    pub fn observe_opening_set(&mut self, openings: &OpeningSetTarget<D>) {
+        self.observe_openings(&openings.to_fri_openings());
+    }
+}
+
 fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
     wires_cap: &MerkleCap<F, C::Hasher>,
@@ -41,7 +60,7 @@ fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, cons
 
     // Observe the instance.
     challenger.observe_hash::<C::Hasher>(*circuit_digest);
-    challenger.observe_hash::<C::InnerHasher>(public_inputs_hash);
+    challenger.observe_public_inputs::<C::InnerHasher>(public_inputs_hash);
 
     challenger.observe_cap(wires_cap);
     let plonk_betas = challenger.get_n_challenges(num_challenges);
@@ -53,7 +72,7 @@ fn get_challenges<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, cons
     challenger.observe_cap(quotient_polys_cap);
     let plonk_zeta = challenger.get_extension_challenge::<D>();
 
-    challenger.observe_openings(&openings.to_fri_openings());
+    challenger.observe_opening_set(openings);
 
     Ok(ProofChallenges {
         plonk_betas,
@@ -270,7 +289,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         challenger.observe_cap(quotient_polys_cap);
         let plonk_zeta = challenger.get_extension_challenge(self);
 
-        challenger.observe_openings(&openings.to_fri_openings());
+        challenger.observe_opening_set(openings);
 
         ProofChallengesTarget {
             plonk_betas,
@@ -327,3 +346,192 @@ impl<const D: usize> ProofWithPublicInputsTarget<D> {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::field::types::{Field, PrimeField64};
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+    type H = <C as GenericConfig<D>>::Hasher;
+
+    /// The openings in the order in which they're committed to.
+    fn manual_openings<T>(openings: [&Vec<T>; 7]) -> impl Iterator<Item = &T> {
+        openings.into_iter().flatten()
+    }
+
+    #[test]
+    fn test_observe_helpers_match_manual_transcript() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let x = builder.add_virtual_public_input();
+        let y = builder.mul(x, x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(7));
+        let proof = data.prove(pw)?;
+
+        let fri_config = &data.common.config.fri_config;
+        let degree_bits = data.common.degree_bits();
+        let Proof {
+            wires_cap,
+            openings,
+            opening_proof,
+            ..
+        } = &proof.proof;
+
+        // The transcript observed element by element.
+        let mut manual = Challenger::<F, H>::new();
+        for hash in &wires_cap.0 {
+            manual.observe_elements(&hash.elements);
+        }
+        for x in manual_openings([
+            &openings.constants,
+            &openings.plonk_sigmas,
+            &openings.wires,
+            &openings.plonk_zs,
+            &openings.partial_products,
+            &openings.quotient_polys,
+            &openings.plonk_zs_next,
+        ]) {
+            manual.observe_extension_element::<D>(x);
+        }
+        let fri_alpha = manual.get_extension_challenge::<D>();
+        let mut fri_betas = Vec::new();
+        for cap in &opening_proof.commit_phase_merkle_caps {
+            for hash in &cap.0 {
+                manual.observe_elements(&hash.elements);
+            }
+            fri_betas.push(manual.get_extension_challenge::<D>());
+        }
+        for x in &opening_proof.final_poly.coeffs {
+            manual.observe_extension_element::<D>(x);
+        }
+        manual.observe_element(opening_proof.pow_witness);
+        let fri_pow_response = manual.get_challenge();
+        let lde_size = 1 << (degree_bits + fri_config.rate_bits);
+        let fri_query_indices = (0..fri_config.num_query_rounds)
+            .map(|_| manual.get_challenge().to_canonical_u64() as usize % lde_size)
+            .collect::<Vec<_>>();
+
+        let mut challenger = Challenger::<F, H>::new();
+        challenger.observe_cap(wires_cap);
+        challenger.observe_opening_set(openings);
+        let challenges =
+            challenger.fri_proof_challenges::<C, D>(opening_proof, degree_bits, fri_config);
+        assert_eq!(challenges.fri_alpha, fri_alpha);
+        assert_eq!(challenges.fri_betas, fri_betas);
+        assert_eq!(challenges.fri_pow_response, fri_pow_response);
+        assert_eq!(challenges.fri_query_indices, fri_query_indices);
+        assert_eq!(challenger.get_challenge(), manual.get_challenge());
+
+        // The same comparison in-circuit.
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let proof_t = builder.add_virtual_proof_with_pis(&data.common);
+        let ProofTarget {
+            wires_cap,
+            openings,
+            opening_proof,
+            ..
+        } = &proof_t.proof;
+
+        let mut manual = RecursiveChallenger::<F, H, D>::new(&mut builder);
+        for hash in &wires_cap.0 {
+            manual.observe_elements(&hash.elements);
+        }
+        for &x in manual_openings([
+            &openings.constants,
+            &openings.plonk_sigmas,
+            &openings.wires,
+            &openings.plonk_zs,
+            &openings.partial_products,
+            &openings.quotient_polys,
+            &openings.plonk_zs_next,
+        ]) {
+            manual.observe_extension_element(x);
+        }
+        let fri_alpha = manual.get_extension_challenge(&mut builder);
+        let mut fri_betas = Vec::new();
+        for cap in &opening_proof.commit_phase_merkle_caps {
+            for hash in &cap.0 {
+                manual.observe_elements(&hash.elements);
+            }
+            fri_betas.push(manual.get_extension_challenge(&mut builder));
+        }
+        for &x in &opening_proof.final_poly.0 {
+            manual.observe_extension_element(x);
+        }
+        manual.observe_element(opening_proof.pow_witness);
+        let fri_pow_response = manual.get_challenge(&mut builder);
+        let fri_query_indices = manual.get_n_challenges(&mut builder, fri_config.num_query_rounds);
+
+        let mut challenger = RecursiveChallenger::<F, H, D>::new(&mut builder);
+        challenger.observe_cap(wires_cap);
+        challenger.observe_opening_set(openings);
+        let challenges = challenger.fri_proof_challenges(&mut builder, opening_proof, fri_config);
+        builder.connect_extension(challenges.fri_alpha, fri_alpha);
+        for (a, b) in challenges.fri_betas.into_iter().zip(fri_betas) {
+            builder.connect_extension(a, b);
+        }
+        builder.connect(challenges.fri_pow_response, fri_pow_response);
+        builder.connect_slice(&challenges.fri_query_indices, &fri_query_indices);
+
+        let recursive_data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&proof_t, &proof);
+        let recursive_proof = recursive_data.prove(pw)?;
+        recursive_data.verify(recursive_proof)
+    }
+
+    #[test]
+    fn test_public_inputs_bound_into_transcript() -> Result<()> {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let x = builder.add_virtual_public_input();
+        let y = builder.mul(x, x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(7));
+        let proof = data.prove(pw)?;
+        let digest = &data.verifier_only.circuit_digest;
+
+        // The prover's first challenges follow from the circuit digest, the public inputs and the
+        // wires commitment, in that order.
+        let challenges =
+            proof.get_challenges(proof.get_public_inputs_hash(), digest, &data.common)?;
+        let mut challenger = Challenger::<F, H>::new();
+        challenger.observe_hash::<H>(*digest);
+        challenger.observe_public_inputs::<<C as GenericConfig<D>>::InnerHasher>(
+            proof.get_public_inputs_hash(),
+        );
+        challenger.observe_cap(&proof.proof.wires_cap);
+        assert_eq!(
+            challenger.get_n_challenges(data.common.config.num_challenges),
+            challenges.plonk_betas
+        );
+
+        // Changing a public input changes every challenge, so the proof no longer verifies.
+        let mut tampered = proof.clone();
+        tampered.public_inputs[1] += F::ONE;
+        let tampered_challenges =
+            tampered.get_challenges(tampered.get_public_inputs_hash(), digest, &data.common)?;
+        assert_ne!(tampered_challenges.plonk_betas, challenges.plonk_betas);
+        assert_ne!(tampered_challenges.plonk_zeta, challenges.plonk_zeta);
+        assert_ne!(
+            tampered_challenges.fri_challenges.fri_query_indices,
+            challenges.fri_challenges.fri_query_indices
+        );
+        assert!(data.verify(tampered).is_err());
+
+        data.verify(proof)
+    }
+}
@@ -0,0 +1,201 @@
+//! A machine-readable description of a circuit's public parameters, intended as input for
+//! generating verifiers in other languages.
+//!
+//! The schema is versioned by [`MANIFEST_VERSION`], which is bumped whenever a field is added,
+//! removed or reinterpreted. Field elements are encoded as decimal strings of their canonical
+//! representatives, since many JSON consumers can't represent 64-bit integers exactly. The output
+//! only depends on the circuit, so building the same circuit twice gives byte-identical manifests.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use crate::field::extension::Extendable;
+use crate::field::types::PrimeField64;
+use crate::hash::hash_types::RichField;
+use crate::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
+use crate::plonk::config::{GenericConfig, GenericHashOut};
+
+/// The version of the manifest schema produced by this crate.
+pub const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct CircuitManifest {
+    /// Always [`MANIFEST_VERSION`] for manifests produced by this crate.
+    pub version: u32,
+    /// The extension degree `D` of the field challenges are drawn from.
+    pub extension_degree: usize,
+    /// The number of rows is `2^degree_bits`.
+    pub degree_bits: usize,
+    pub num_wires: usize,
+    pub num_routed_wires: usize,
+    /// The number of constant polynomials, including selectors.
+    pub num_constants: usize,
+    pub num_public_inputs: usize,
+    pub num_challenges: usize,
+    pub num_partial_products: usize,
+    pub num_gate_constraints: usize,
+    pub quotient_degree_factor: usize,
+    pub zero_knowledge: bool,
+    /// The coset shifts `k_i` of the permutation argument, one per routed wire.
+    pub k_is: Vec<String>,
+    pub fri: FriManifest,
+    /// The circuit's gates, in the order their selectors are assigned.
+    pub gates: Vec<GateManifest>,
+    /// The range of gate indices covered by each selector polynomial.
+    pub selector_groups: Vec<(usize, usize)>,
+    /// The Merkle cap of the constant and sigma polynomials, one array of elements per digest.
+    pub constants_sigmas_cap: Vec<Vec<String>>,
+    /// The circuit digest, which seeds the Fiat-Shamir transcript.
+    pub circuit_digest: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct FriManifest {
+    pub rate_bits: usize,
+    pub cap_height: usize,
+    pub proof_of_work_bits: u32,
+    pub num_query_rounds: usize,
+    /// Whether Merkle leaves are salted.
+    pub hiding: bool,
+    /// The log2 of each FRI reduction's arity.
+    pub reduction_arity_bits: Vec<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct GateManifest {
+    /// The gate's ID, which includes any parameters its constraints depend on.
+    pub id: String,
+    /// The index of the selector polynomial which enables this gate.
+    pub selector_index: usize,
+    pub degree: usize,
+    pub num_wires: usize,
+    /// The number of gate-specific constants, which follow the selectors in the constant columns.
+    pub num_constants: usize,
+    pub num_constraints: usize,
+}
+
+fn encode_elements<F: PrimeField64>(elements: &[F]) -> Vec<String> {
+    elements
+        .iter()
+        .map(|x| x.to_canonical_u64().to_string())
+        .collect()
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CommonCircuitData<F, D> {
+    /// Describes this circuit and its verifier-only data, which holds the commitments, in the
+    /// schema documented on [`CircuitManifest`].
+    pub fn to_manifest<C: GenericConfig<D, F = F>>(
+        &self,
+        verifier_only: &VerifierOnlyCircuitData<C, D>,
+    ) -> CircuitManifest {
+        let fri = FriManifest {
+            rate_bits: self.fri_params.config.rate_bits,
+            cap_height: self.fri_params.config.cap_height,
+            proof_of_work_bits: self.fri_params.config.proof_of_work_bits,
+            num_query_rounds: self.fri_params.config.num_query_rounds,
+            hiding: self.fri_params.hiding,
+            reduction_arity_bits: self.fri_params.reduction_arity_bits.clone(),
+        };
+        let gates = self
+            .gate_layout()
+            .into_iter()
+            .map(|info| GateManifest {
+                id: info.id,
+                selector_index: info.selector_index,
+                degree: info.degree,
+                num_wires: info.num_wires,
+                num_constants: info.constants.len(),
+                num_constraints: info.num_constraints,
+            })
+            .collect();
+
+        CircuitManifest {
+            version: MANIFEST_VERSION,
+            extension_degree: D,
+            degree_bits: self.degree_bits(),
+            num_wires: self.config.num_wires,
+            num_routed_wires: self.config.num_routed_wires,
+            num_constants: self.num_constants,
+            num_public_inputs: self.num_public_inputs,
+            num_challenges: self.config.num_challenges,
+            num_partial_products: self.num_partial_products,
+            num_gate_constraints: self.num_gate_constraints,
+            quotient_degree_factor: self.quotient_degree_factor,
+            zero_knowledge: self.config.zero_knowledge,
+            k_is: encode_elements(&self.k_is),
+            fri,
+            gates,
+            selector_groups: self
+                .selectors_info
+                .groups
+                .iter()
+                .map(|g| (g.start, g.end))
+                .collect(),
+            constants_sigmas_cap: verifier_only
+                .constants_sigmas_cap
+                .0
+                .iter()
+                .map(|h| encode_elements(&h.to_vec()))
+                .collect(),
+            circuit_digest: encode_elements(&verifier_only.circuit_digest.to_vec()),
+        }
+    }
+
+    /// Serializes the output of [`Self::to_manifest`] as JSON.
+    pub fn to_manifest_json<C: GenericConfig<D, F = F>>(
+        &self,
+        verifier_only: &VerifierOnlyCircuitData<C, D>,
+    ) -> String {
+        serde_json::to_string(&self.to_manifest(verifier_only))
+            .expect("Manifest serialization is infallible")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plonk::circuit_builder::CircuitBuilder;
+    use crate::plonk::circuit_data::{CircuitConfig, CircuitData};
+    use crate::plonk::config::PoseidonGoldilocksConfig;
+
+    const D: usize = 2;
+    type C = PoseidonGoldilocksConfig;
+    type F = <C as GenericConfig<D>>::F;
+
+    fn build_circuit() -> CircuitData<F, C, D> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.square(x);
+        builder.register_public_input(y);
+        builder.build::<C>()
+    }
+
+    #[test]
+    fn test_manifest_json() {
+        let data = build_circuit();
+        let json = data.common.to_manifest_json(&data.verifier_only);
+
+        let manifest: CircuitManifest = serde_json::from_str(&json).unwrap();
+        assert_eq!(manifest.version, MANIFEST_VERSION);
+        assert_eq!(manifest.degree_bits, data.common.degree_bits());
+        assert_eq!(
+            manifest.fri.num_query_rounds,
+            data.common.config.fri_config.num_query_rounds
+        );
+        assert_eq!(manifest.num_public_inputs, 2);
+        assert_eq!(manifest.gates.len(), data.common.gates.len());
+        assert_eq!(
+            manifest.constants_sigmas_cap.len(),
+            1 << data.common.config.fri_config.cap_height
+        );
+
+        // Rebuilding the same circuit gives the same manifest.
+        let rebuilt = build_circuit();
+        assert_eq!(
+            rebuilt.common.to_manifest_json(&rebuilt.verifier_only),
+            json
+        );
+    }
+}
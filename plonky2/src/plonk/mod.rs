@@ -3,6 +3,8 @@ pub mod circuit_data;
 pub mod config;
 pub(crate) mod copy_constraint;
 mod get_challenges;
+#[cfg(feature = "serde")]
+pub mod manifest;
 pub(crate) mod permutation_argument;
 pub mod plonk_common;
 pub mod proof;
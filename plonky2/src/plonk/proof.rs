@@ -1,5 +1,6 @@
 use alloc::vec;
 use alloc::vec::Vec;
+use core::ops::Range;
 
 use anyhow::ensure;
 use plonky2_maybe_rayon::*;
@@ -14,16 +15,15 @@ use crate::fri::structure::{
     FriOpeningBatch, FriOpeningBatchTarget, FriOpenings, FriOpeningsTarget,
 };
 use crate::fri::FriParams;
-use crate::hash::hash_types::{MerkleCapTarget, RichField};
+use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_tree::MerkleCap;
+use crate::hash::poseidon::PoseidonHash;
 use crate::iop::ext_target::ExtensionTarget;
 use crate::iop::target::Target;
 use crate::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
 use crate::plonk::config::{GenericConfig, Hasher};
 use crate::plonk::verifier::verify_with_challenges;
-use crate::util::serialization::Write;
-#[cfg(feature = "std")]
-use crate::util::serialization::{Buffer, Read};
+use crate::util::serialization::{Buffer, Read, Write};
 
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 #[serde(bound = "")]
@@ -84,6 +84,14 @@ pub struct ProofWithPublicInputs<
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     ProofWithPublicInputs<F, C, D>
 {
+    /// Pairs this proof with the fingerprint of the circuit described by `common_data`.
+    pub fn with_circuit_fingerprint(
+        self,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> FingerprintedProof<F, Self> {
+        FingerprintedProof::new(self, common_data)
+    }
+
     pub fn compress(
         self,
         circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
@@ -103,6 +111,25 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         C::InnerHasher::hash_no_pad(&self.public_inputs)
     }
 
+    /// Reads back a hash registered with `CircuitBuilder::register_public_input_hash`, given the
+    /// range of its four elements in the public inputs.
+    pub fn public_inputs_hash(&self, range: Range<usize>) -> HashOut<F> {
+        assert_eq!(range.len(), 4, "A hash spans 4 public inputs");
+        HashOut::from_vec(self.public_inputs[range].to_vec())
+    }
+
+    /// Checks that this proof's public inputs are exactly the `PoseidonHash` digest of `preimage`,
+    /// as registered by `CircuitBuilder::register_public_inputs_hashed`. This does not verify the
+    /// proof itself.
+    pub fn verify_public_inputs_preimage(&self, preimage: &[F]) -> anyhow::Result<()> {
+        let hash = PoseidonHash::hash_no_pad(preimage);
+        ensure!(
+            self.public_inputs == hash.elements,
+            "Public inputs do not match the hash of the given preimage"
+        );
+        Ok(())
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut buffer = Vec::new();
         buffer
@@ -111,7 +138,6 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         buffer
     }
 
-    #[cfg(feature = "std")]
     pub fn from_bytes(
         bytes: Vec<u8>,
         common_data: &CommonCircuitData<F, D>,
@@ -182,6 +208,14 @@ pub struct CompressedProofWithPublicInputs<
 impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     CompressedProofWithPublicInputs<F, C, D>
 {
+    /// Pairs this proof with the fingerprint of the circuit described by `common_data`.
+    pub fn with_circuit_fingerprint(
+        self,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> FingerprintedProof<F, Self> {
+        FingerprintedProof::new(self, common_data)
+    }
+
     pub fn decompress(
         self,
         circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
@@ -219,7 +253,7 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
             self.proof
                 .decompress(&challenges, fri_inferred_elements, &common_data.fri_params);
         verify_with_challenges(
-            decompressed_proof,
+            &decompressed_proof,
             public_inputs_hash,
             challenges,
             verifier_data,
@@ -241,7 +275,6 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
         buffer
     }
 
-    #[cfg(feature = "std")]
     pub fn from_bytes(
         bytes: Vec<u8>,
         common_data: &CommonCircuitData<F, D>,
@@ -254,6 +287,115 @@ impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
     }
 }
 
+/// A proof, either plain or compressed, paired with the `CommonCircuitData::fingerprint` of the
+/// circuit it was generated for. Checking the fingerprint before verifying makes a proof checked
+/// against the wrong circuit fail with a clear error, rather than an obscure one.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(bound(serialize = "P: Serialize", deserialize = "P: Deserialize<'de>"))]
+pub struct FingerprintedProof<F: RichField, P> {
+    pub proof: P,
+    pub circuit_fingerprint: HashOut<F>,
+}
+
+impl<F: RichField, P> FingerprintedProof<F, P> {
+    pub fn new<const D: usize>(proof: P, common_data: &CommonCircuitData<F, D>) -> Self
+    where
+        F: Extendable<D>,
+    {
+        Self {
+            proof,
+            circuit_fingerprint: common_data.fingerprint(),
+        }
+    }
+
+    /// Checks the fingerprint against `common_data`, and returns the proof to be verified against
+    /// it.
+    pub fn check_circuit_fingerprint<const D: usize>(
+        self,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<P>
+    where
+        F: Extendable<D>,
+    {
+        ensure!(
+            self.circuit_fingerprint == common_data.fingerprint(),
+            "Circuit fingerprint mismatch: the proof was generated for a different circuit than \
+            the one described by the verifier's common data."
+        );
+        Ok(self.proof)
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    FingerprintedProof<F, ProofWithPublicInputs<F, C, D>>
+{
+    /// Compresses the proof, keeping its fingerprint.
+    pub fn compress(
+        self,
+        circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<FingerprintedProof<F, CompressedProofWithPublicInputs<F, C, D>>> {
+        Ok(FingerprintedProof {
+            proof: self.proof.compress(circuit_digest, common_data)?,
+            circuit_fingerprint: self.circuit_fingerprint,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        buffer
+            .write_fingerprinted_proof(self)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    pub fn from_bytes(
+        bytes: Vec<u8>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<Self> {
+        let mut buffer = Buffer::new(bytes);
+        let proof = buffer
+            .read_fingerprinted_proof(common_data)
+            .map_err(anyhow::Error::msg)?;
+        Ok(proof)
+    }
+}
+
+impl<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>
+    FingerprintedProof<F, CompressedProofWithPublicInputs<F, C, D>>
+{
+    /// Decompresses the proof, keeping its fingerprint.
+    pub fn decompress(
+        self,
+        circuit_digest: &<<C as GenericConfig<D>>::Hasher as Hasher<C::F>>::Hash,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<FingerprintedProof<F, ProofWithPublicInputs<F, C, D>>> {
+        Ok(FingerprintedProof {
+            proof: self.proof.decompress(circuit_digest, common_data)?,
+            circuit_fingerprint: self.circuit_fingerprint,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        buffer
+            .write_fingerprinted_compressed_proof(self)
+            .expect("Writing to a byte-vector cannot fail.");
+        buffer
+    }
+
+    pub fn from_bytes(
+        bytes: Vec<u8>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> anyhow::Result<Self> {
+        let mut buffer = Buffer::new(bytes);
+        let proof = buffer
+            .read_fingerprinted_compressed_proof(common_data)
+            .map_err(anyhow::Error::msg)?;
+        Ok(proof)
+    }
+}
+
 pub(crate) struct ProofChallenges<F: RichField + Extendable<D>, const D: usize> {
     /// Random values used in Plonk's permutation argument.
     pub plonk_betas: Vec<F>,
@@ -289,6 +431,14 @@ pub struct ProofWithPublicInputsTarget<const D: usize> {
     pub public_inputs: Vec<Target>,
 }
 
+impl<const D: usize> ProofWithPublicInputsTarget<D> {
+    /// In-circuit version of `ProofWithPublicInputs::public_inputs_hash`.
+    pub fn public_inputs_hash(&self, range: Range<usize>) -> HashOutTarget {
+        assert_eq!(range.len(), 4, "A hash spans 4 public inputs");
+        HashOutTarget::from_vec(self.public_inputs[range].to_vec())
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 /// The purported values of each polynomial at a single point.
 pub struct OpeningSet<F: RichField + Extendable<D>, const D: usize> {
@@ -395,10 +545,14 @@ mod tests {
     use crate::field::types::Sample;
     use crate::fri::reduction_strategies::FriReductionStrategy;
     use crate::gates::noop::NoopGate;
-    use crate::iop::witness::PartialWitness;
+    use crate::hash::poseidon::PoseidonHash;
+    use crate::iop::witness::{PartialWitness, WitnessWrite};
     use crate::plonk::circuit_builder::CircuitBuilder;
     use crate::plonk::circuit_data::CircuitConfig;
-    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig};
+    use crate::plonk::proof::{
+        CompressedProofWithPublicInputs, FingerprintedProof, ProofWithPublicInputs,
+    };
     use crate::plonk::verifier::verify;
 
     #[test]
@@ -438,4 +592,126 @@ mod tests {
         verify(proof, &data.verifier_only, &data.common)?;
         data.verify_compressed(compressed_proof)
     }
+
+    #[test]
+    fn test_verify_proof_from_bytes() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let x = builder.add_virtual_public_input();
+        let y = builder.mul(x, x);
+        builder.register_public_input(y);
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::rand());
+        let bytes = data.prove(pw)?.to_bytes();
+
+        let verifier_data = data.verifier_data();
+        let proof = ProofWithPublicInputs::<F, C, D>::from_bytes(bytes, &verifier_data.common)?;
+        verifier_data.verify(proof)
+    }
+
+    #[test]
+    fn test_public_input_hash() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+
+        // Register the hash after another public input, so that it doesn't start at zero.
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let x = builder.add_virtual_public_input();
+        let preimage = builder.add_virtual_targets(10);
+        let hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(preimage.clone());
+        builder.register_public_input_hash(hash);
+        let data = builder.build::<C>();
+
+        let preimage_values = F::rand_vec(10);
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::rand());
+        for (&t, &v) in preimage.iter().zip(&preimage_values) {
+            pw.set_target(t, v);
+        }
+        let proof = data.prove(pw)?;
+        let expected = PoseidonHash::hash_no_pad(&preimage_values);
+        assert_eq!(proof.public_inputs_hash(1..5), expected);
+
+        // The same hash can be recomposed in a circuit verifying the proof.
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let proof_t = builder.add_virtual_proof_with_pis(&data.common);
+        let verifier_data_t =
+            builder.add_virtual_verifier_data(data.common.config.fri_config.cap_height);
+        builder.verify_proof::<C>(&proof_t, &verifier_data_t, &data.common);
+        let hash_t = proof_t.public_inputs_hash(1..5);
+        let expected_t = builder.constant_hash(expected);
+        builder.connect_hashes(hash_t, expected_t);
+        let outer_data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&proof_t, &proof);
+        pw.set_verifier_data_target(&verifier_data_t, &data.verifier_only);
+        outer_data.verify(outer_data.prove(pw)?)
+    }
+
+    #[test]
+    fn test_circuit_fingerprint_mismatch() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        // Two circuits with the same public inputs but different sizes.
+        let build = |num_noops: usize| {
+            let mut builder =
+                CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+            let x = builder.add_virtual_public_input();
+            let y = builder.mul(x, x);
+            builder.register_public_input(y);
+            for _ in 0..num_noops {
+                builder.add_gate(NoopGate, vec![]);
+            }
+            (builder.build::<C>(), x)
+        };
+        let (data, x) = build(0);
+        let (other_data, _) = build(100);
+        assert_ne!(data.common.fingerprint(), other_data.common.fingerprint());
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::rand());
+        let proof = data.prove(pw)?;
+        let fingerprinted = proof.clone().with_circuit_fingerprint(&data.common);
+        assert_eq!(fingerprinted.circuit_fingerprint, data.common.fingerprint());
+
+        // The fingerprinted encoding round-trips, and adds just the fingerprint to the default one.
+        let bytes = fingerprinted.to_bytes();
+        assert_eq!(bytes.len(), proof.to_bytes().len() + 4 * 8);
+        let fingerprinted_from_bytes =
+            FingerprintedProof::<F, ProofWithPublicInputs<F, C, D>>::from_bytes(
+                bytes,
+                &data.common,
+            )?;
+        assert_eq!(fingerprinted, fingerprinted_from_bytes);
+
+        // The fingerprint survives compression, including serialization of the compressed proof.
+        let digest = &data.verifier_only.circuit_digest;
+        let compressed = fingerprinted.clone().compress(digest, &data.common)?;
+        let bytes = compressed.to_bytes();
+        let compressed_from_bytes = FingerprintedProof::<
+            F,
+            CompressedProofWithPublicInputs<F, C, D>,
+        >::from_bytes(bytes, &data.common)?;
+        assert_eq!(compressed, compressed_from_bytes);
+        assert_eq!(
+            compressed_from_bytes.decompress(digest, &data.common)?,
+            fingerprinted
+        );
+        data.verify_compressed(compressed.check_circuit_fingerprint(&data.common)?)?;
+
+        data.verify_fingerprinted(fingerprinted.clone())?;
+        let err = other_data.verify_fingerprinted(fingerprinted).unwrap_err();
+        assert!(err.to_string().contains("Circuit fingerprint mismatch"));
+        Ok(())
+    }
 }
@@ -1,20 +1,33 @@
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use alloc::{format, vec};
+use core::fmt::{Display, Formatter};
 use core::mem::swap;
+#[cfg(feature = "timing")]
+use std::time::Duration;
 
-use anyhow::{ensure, Result};
+use anyhow::Result;
+#[cfg(feature = "timing")]
+use log::Level;
 use plonky2_maybe_rayon::*;
+use rand::RngCore;
+#[cfg(feature = "rand_chacha")]
+use rand::SeedableRng;
+#[cfg(feature = "rand_chacha")]
+use rand_chacha::ChaCha8Rng;
 
 use crate::field::extension::Extendable;
 use crate::field::polynomial::{PolynomialCoeffs, PolynomialValues};
 use crate::field::types::Field;
 use crate::field::zero_poly_coset::ZeroPolyOnCoset;
 use crate::fri::oracle::PolynomialBatch;
-use crate::hash::hash_types::RichField;
+use crate::hash::hash_types::{HashOut, RichField};
 use crate::iop::challenger::Challenger;
-use crate::iop::generator::generate_partial_witness;
-use crate::iop::witness::{MatrixWitness, PartialWitness, Witness};
-use crate::plonk::circuit_data::{CommonCircuitData, ProverOnlyCircuitData};
+use crate::iop::generator::generate_partial_witness_unchecked;
+use crate::iop::witness::{MatrixWitness, PartialWitness, Witness, WitnessWrite};
+use crate::plonk::circuit_data::{
+    CommonCircuitData, FailingConstraint, ProverCircuitData, ProverOnlyCircuitData,
+};
 use crate::plonk::config::{GenericConfig, Hasher};
 use crate::plonk::plonk_common::PlonkOracle;
 use crate::plonk::proof::{OpeningSet, Proof, ProofWithPublicInputs};
@@ -25,22 +38,208 @@ use crate::util::partial_products::{partial_products_and_z_gx, quotient_chunk_pr
 use crate::util::timing::TimingTree;
 use crate::util::{ceil_div_usize, log2_ceil, transpose};
 
+/// Same as `prove`, but overrides the circuit's number of FRI query rounds with `num_queries`.
+///
+/// This is meant for tests only: with fewer queries, proofs are cheaper but offer less security
+/// than the circuit was configured for. The resulting proof must be verified against
+/// `common.with_num_query_rounds(num_queries)` rather than the circuit's own common data.
+pub fn prove_with_query_count<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverCircuitData<F, C, D>,
+    inputs: PartialWitness<F>,
+    num_queries: usize,
+) -> Result<ProofWithPublicInputs<F, C, D>> {
+    let common_data = prover_data.common.with_num_query_rounds(num_queries);
+    prove(
+        &prover_data.prover_only,
+        &common_data,
+        inputs,
+        &mut TimingTree::default(),
+    )
+}
+
+/// Wall-clock time spent in each major phase of `prove`.
+#[cfg(feature = "timing")]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ProverTiming {
+    /// Running the witness generators and building the wire polynomials.
+    pub witness_generation: Duration,
+    /// Committing to the wire polynomials.
+    pub wires_commitment: Duration,
+    /// Computing and committing to the partial products and `Z`s of the permutation argument.
+    pub permutation_commitment: Duration,
+    /// Computing, splitting and committing to the quotient polynomials.
+    pub quotient_commitment: Duration,
+    /// Computing the opening set and the FRI opening proof.
+    pub fri: Duration,
+}
+
+#[cfg(feature = "timing")]
+impl ProverTiming {
+    /// Sums the top-level scopes recorded by `prove` into their phases.
+    fn from_timing_tree(timing: &TimingTree) -> Self {
+        let mut res = Self::default();
+        for (name, duration) in timing.child_durations() {
+            let phase = match name {
+                "compute full witness" | "compute wire polynomials" => &mut res.witness_generation,
+                _ if name.starts_with("run ") && name.ends_with(" generators") => {
+                    &mut res.witness_generation
+                }
+                "compute wires commitment" => &mut res.wires_commitment,
+                "compute partial products" | "commit to partial products and Z's" => {
+                    &mut res.permutation_commitment
+                }
+                "compute quotient polys"
+                | "split up quotient polys"
+                | "commit to quotient polys" => &mut res.quotient_commitment,
+                "construct the opening set" | "compute opening proofs" => &mut res.fri,
+                _ => continue,
+            };
+            *phase += duration;
+        }
+        res
+    }
+}
+
+/// Same as `prove`, but also reports how long each phase of proving took.
+#[cfg(feature = "timing")]
+pub fn prove_with_timing<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+) -> Result<(ProofWithPublicInputs<F, C, D>, ProverTiming)> {
+    let mut timing = TimingTree::new("prove", Level::Debug);
+    let proof = prove(prover_data, common_data, inputs, &mut timing)?;
+    Ok((proof, ProverTiming::from_timing_tree(&timing)))
+}
+
+/// Why `try_prove` failed to prove a witness.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ProveError {
+    /// The witness violates a gate constraint.
+    ConstraintUnsatisfied {
+        /// The row of the gate.
+        row: usize,
+        /// The ID of the gate.
+        gate_id: String,
+        /// The index of the violated constraint among the gate's constraints.
+        constraint_index: usize,
+    },
+    /// Some generators never finished, because targets they depend on were never set.
+    WitnessIncomplete { num_unfinished_generators: usize },
+    /// The prover failed for a reason which couldn't be traced back to the witness.
+    Internal(String),
+}
+
+impl Display for ProveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ProveError::ConstraintUnsatisfied {
+                row,
+                gate_id,
+                constraint_index,
+            } => write!(
+                f,
+                "Constraint {constraint_index} of gate {gate_id} is unsatisfied at row {row}"
+            ),
+            ProveError::WitnessIncomplete {
+                num_unfinished_generators,
+            } => write!(f, "{num_unfinished_generators} generators weren't run"),
+            ProveError::Internal(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ProveError {}
+
+/// Proves `inputs`. A witness which the circuit doesn't accept is reported as an error wrapping a
+/// `ProveError`, which can be recovered with `downcast_ref`; see `try_prove`.
 pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
     prover_data: &ProverOnlyCircuitData<F, C, D>,
     common_data: &CommonCircuitData<F, D>,
     inputs: PartialWitness<F>,
     timing: &mut TimingTree,
 ) -> Result<ProofWithPublicInputs<F, C, D>> {
+    try_prove(prover_data, common_data, inputs, timing).map_err(anyhow::Error::msg)
+}
+
+/// Same as `prove`, but returns a `ProveError` directly.
+///
+/// A witness which violates a constraint, whether a gate constraint or a copy constraint, is
+/// reported rather than causing a panic. Generators which panic on inputs they can't handle, such
+/// as an integer division by zero, still do so.
+pub fn try_prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    timing: &mut TimingTree,
+) -> core::result::Result<ProofWithPublicInputs<F, C, D>, ProveError> {
+    prove_with_rng(prover_data, common_data, inputs, timing, None)
+}
+
+/// Same as `prove`, but draws all of the prover's randomness from a ChaCha RNG seeded with `seed`,
+/// so that proving the same statement twice with the same seed gives byte-identical proofs.
+///
+/// The seed replaces the prover's only sources of nondeterminism: the random values of the
+/// circuit's `random_targets` (the blinding rows added in zero-knowledge mode and the unused
+/// wires of the public input gate), and the salts blinding the wires, `Z`s and quotient
+/// commitments in zero-knowledge mode. The FRI proof-of-work witness is already deterministic.
+///
+/// Reusing a seed for different witnesses reuses their blinding, which undermines zero knowledge,
+/// so this is meant for tests and reproducible test vectors.
+#[cfg(feature = "rand_chacha")]
+pub fn prove_with_seed<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    inputs: PartialWitness<F>,
+    seed: u64,
+    timing: &mut TimingTree,
+) -> Result<ProofWithPublicInputs<F, C, D>> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    prove_with_rng(prover_data, common_data, inputs, timing, Some(&mut rng))
+        .map_err(anyhow::Error::msg)
+}
+
+/// Proves, sampling randomness from `rng` if given, or from the OS's randomness otherwise.
+fn prove_with_rng<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    mut inputs: PartialWitness<F>,
+    timing: &mut TimingTree,
+    mut rng: Option<&mut dyn RngCore>,
+) -> core::result::Result<ProofWithPublicInputs<F, C, D>, ProveError> {
     let config = &common_data.config;
     let num_challenges = config.num_challenges;
     let quotient_degree = common_data.quotient_degree();
     let degree = common_data.degree();
 
-    let partition_witness = timed!(
+    // Assign the random targets up front, so that their generators leave them untouched.
+    if let Some(rng) = rng.as_deref_mut() {
+        for &target in &prover_data.random_targets {
+            if !inputs.contains(target) {
+                inputs.set_target(target, F::sample(rng));
+            }
+        }
+    }
+
+    let (partition_witness, outcome) = timed!(
         timing,
         &format!("run {} generators", prover_data.generators.len()),
-        generate_partial_witness(inputs, prover_data, common_data)
+        generate_partial_witness_unchecked(inputs, prover_data, common_data)
     );
+    if outcome.num_unfinished_generators > 0 {
+        return Err(ProveError::WitnessIncomplete {
+            num_unfinished_generators: outcome.num_unfinished_generators,
+        });
+    }
 
     let public_inputs = partition_witness.get_targets(&prover_data.public_inputs);
     let public_inputs_hash = C::InnerHasher::hash_no_pad(&public_inputs);
@@ -51,6 +250,20 @@ pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D:
         partition_witness.full_witness()
     );
 
+    // A conflicting value was dropped, which leaves the gate that produced it unsatisfied.
+    if let Some((target, old_value, value)) = outcome.first_conflict {
+        return Err(unsatisfied_constraint_error(
+            prover_data,
+            common_data,
+            &witness,
+            &public_inputs_hash,
+            format!(
+                "Partition containing {target:?} was set twice with different values: \
+                 {old_value} != {value}"
+            ),
+        ));
+    }
+
     let wires_values: Vec<PolynomialValues<F>> = timed!(
         timing,
         "compute wire polynomials",
@@ -64,13 +277,14 @@ pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D:
     let wires_commitment = timed!(
         timing,
         "compute wires commitment",
-        PolynomialBatch::from_values(
+        PolynomialBatch::from_values_with_rng(
             wires_values,
             config.fri_config.rate_bits,
             config.zero_knowledge && PlonkOracle::WIRES.blinding,
             config.fri_config.cap_height,
             timing,
             prover_data.fft_root_table.as_ref(),
+            reborrow(&mut rng),
         )
     );
 
@@ -78,7 +292,7 @@ pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D:
 
     // Observe the instance.
     challenger.observe_hash::<C::Hasher>(prover_data.circuit_digest);
-    challenger.observe_hash::<C::InnerHasher>(public_inputs_hash);
+    challenger.observe_public_inputs::<C::InnerHasher>(public_inputs_hash);
 
     challenger.observe_cap(&wires_commitment.merkle_tree.cap);
     let betas = challenger.get_n_challenges(num_challenges);
@@ -104,13 +318,14 @@ pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D:
     let partial_products_and_zs_commitment = timed!(
         timing,
         "commit to partial products and Z's",
-        PolynomialBatch::from_values(
+        PolynomialBatch::from_values_with_rng(
             zs_partial_products,
             config.fri_config.rate_bits,
             config.zero_knowledge && PlonkOracle::ZS_PARTIAL_PRODUCTS.blinding,
             config.fri_config.cap_height,
             timing,
             prover_data.fft_root_table.as_ref(),
+            reborrow(&mut rng),
         )
     );
 
@@ -133,32 +348,44 @@ pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D:
         )
     );
 
+    // The quotients only have the expected degree if the vanishing polynomials are divisible by
+    // `Z_H`, i.e. if the witness satisfies every constraint.
+    let mut quotient_polys = quotient_polys;
+    if quotient_polys
+        .iter_mut()
+        .any(|quotient_poly| quotient_poly.trim_to_len(quotient_degree).is_err())
+    {
+        return Err(unsatisfied_constraint_error(
+            prover_data,
+            common_data,
+            &witness,
+            &public_inputs_hash,
+            "Quotient has failed, the vanishing polynomial is not divisible by Z_H".to_string(),
+        ));
+    }
+
     // Compute the quotient polynomials, aka `t` in the Plonk paper.
     let all_quotient_poly_chunks = timed!(
         timing,
         "split up quotient polys",
         quotient_polys
             .into_par_iter()
-            .flat_map(|mut quotient_poly| {
-                quotient_poly.trim_to_len(quotient_degree).expect(
-                    "Quotient has failed, the vanishing polynomial is not divisible by Z_H",
-                );
-                // Split quotient into degree-n chunks.
-                quotient_poly.chunks(degree)
-            })
+            // Split quotient into degree-n chunks.
+            .flat_map(|quotient_poly| quotient_poly.chunks(degree))
             .collect()
     );
 
     let quotient_polys_commitment = timed!(
         timing,
         "commit to quotient polys",
-        PolynomialBatch::from_coeffs(
+        PolynomialBatch::from_coeffs_with_rng(
             all_quotient_poly_chunks,
             config.fri_config.rate_bits,
             config.zero_knowledge && PlonkOracle::QUOTIENT.blinding,
             config.fri_config.cap_height,
             timing,
             prover_data.fft_root_table.as_ref(),
+            rng,
         )
     );
 
@@ -169,10 +396,11 @@ pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D:
     // `g * zeta`, are not in our subgroup `H`. It suffices to check `zeta` only, since
     // `(g * zeta)^n = zeta^n`, where `n` is the order of `g`.
     let g = F::Extension::primitive_root_of_unity(common_data.degree_bits());
-    ensure!(
-        zeta.exp_power_of_2(common_data.degree_bits()) != F::Extension::ONE,
-        "Opening point is in the subgroup."
-    );
+    if zeta.exp_power_of_2(common_data.degree_bits()) == F::Extension::ONE {
+        return Err(ProveError::Internal(
+            "Opening point is in the subgroup.".to_string(),
+        ));
+    }
 
     let openings = timed!(
         timing,
@@ -187,7 +415,7 @@ pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D:
             common_data,
         )
     );
-    challenger.observe_openings(&openings.to_fri_openings());
+    challenger.observe_opening_set(&openings);
 
     let opening_proof = timed!(
         timing,
@@ -219,6 +447,39 @@ pub fn prove<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D:
     })
 }
 
+/// Identifies the gate constraint which `witness` violates, falling back to an internal error with
+/// the given message if every gate is satisfied.
+fn unsatisfied_constraint_error<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    prover_data: &ProverOnlyCircuitData<F, C, D>,
+    common_data: &CommonCircuitData<F, D>,
+    witness: &MatrixWitness<F>,
+    public_inputs_hash: &HashOut<F>,
+    msg: String,
+) -> ProveError {
+    match prover_data.find_failing_constraint(common_data, witness, public_inputs_hash) {
+        Some(FailingConstraint {
+            row,
+            gate_id,
+            constraint_index,
+            ..
+        }) => ProveError::ConstraintUnsatisfied {
+            row,
+            gate_id,
+            constraint_index,
+        },
+        None => ProveError::Internal(msg),
+    }
+}
+
+/// Reborrows an optional RNG, so that it can be passed on more than once.
+fn reborrow<'a>(rng: &'a mut Option<&mut dyn RngCore>) -> Option<&'a mut dyn RngCore> {
+    rng.as_mut().map(|rng| &mut **rng as _)
+}
+
 /// Compute the partial products used in the `Z` polynomials.
 fn all_wires_permutation_partial_products<
     F: RichField + Extendable<D>,
@@ -449,6 +710,8 @@ fn compute_quotient_polys<
     transpose(&quotient_values)
         .into_par_iter()
         .map(PolynomialValues::new)
-        .map(|values| values.coset_ifft(F::coset_shift()))
+        .map(|values| {
+            values.coset_ifft_with_options(F::coset_shift(), prover_data.fft_root_table.as_ref())
+        })
         .collect()
 }
@@ -17,6 +17,7 @@ where
     let ProofWithPublicInputs {
         proof,
         public_inputs,
+        ..
     } = proof_with_pis;
     validate_proof_shape(proof, common_data)?;
     ensure!(
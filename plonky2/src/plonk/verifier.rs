@@ -1,4 +1,6 @@
-use anyhow::{ensure, Result};
+use alloc::format;
+
+use anyhow::{ensure, Context, Result};
 
 use crate::field::extension::Extendable;
 use crate::field::types::Field;
@@ -17,7 +19,36 @@ pub(crate) fn verify<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, c
     verifier_data: &VerifierOnlyCircuitData<C, D>,
     common_data: &CommonCircuitData<F, D>,
 ) -> Result<()> {
-    validate_proof_with_pis_shape(&proof_with_pis, common_data)?;
+    verify_ref(&proof_with_pis, verifier_data, common_data)
+}
+
+/// Verifies several proofs of the same circuit, failing with the index of the first invalid one.
+///
+/// This is a plain loop over the proofs, verifying each one in turn. The challenger and FRI work
+/// isn't shared, since each proof's challenges are drawn from its own transcript.
+pub(crate) fn verify_batch<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    proofs_with_pis: &[ProofWithPublicInputs<F, C, D>],
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<()> {
+    for (i, proof_with_pis) in proofs_with_pis.iter().enumerate() {
+        verify_ref(proof_with_pis, verifier_data, common_data)
+            .with_context(|| format!("Proof {i} of the batch is invalid"))?;
+    }
+    Ok(())
+}
+
+/// Like `verify`, but with the proof borrowed.
+fn verify_ref<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, const D: usize>(
+    proof_with_pis: &ProofWithPublicInputs<F, C, D>,
+    verifier_data: &VerifierOnlyCircuitData<C, D>,
+    common_data: &CommonCircuitData<F, D>,
+) -> Result<()> {
+    validate_proof_with_pis_shape(proof_with_pis, common_data)?;
 
     let public_inputs_hash = proof_with_pis.get_public_inputs_hash();
     let challenges = proof_with_pis.get_challenges(
@@ -27,7 +58,7 @@ pub(crate) fn verify<F: RichField + Extendable<D>, C: GenericConfig<D, F = F>, c
     )?;
 
     verify_with_challenges(
-        proof_with_pis.proof,
+        &proof_with_pis.proof,
         public_inputs_hash,
         challenges,
         verifier_data,
@@ -40,7 +71,7 @@ pub(crate) fn verify_with_challenges<
     C: GenericConfig<D, F = F>,
     const D: usize,
 >(
-    proof: Proof<F, C, D>,
+    proof: &Proof<F, C, D>,
     public_inputs_hash: <<C as GenericConfig<D>>::InnerHasher as Hasher<F>>::Hash,
     challenges: ProofChallenges<F, D>,
     verifier_data: &VerifierOnlyCircuitData<C, D>,
@@ -92,9 +123,9 @@ pub(crate) fn verify_with_challenges<
 
     let merkle_caps = &[
         verifier_data.constants_sigmas_cap.clone(),
-        proof.wires_cap,
-        proof.plonk_zs_partial_products_cap,
-        proof.quotient_polys_cap,
+        proof.wires_cap.clone(),
+        proof.plonk_zs_partial_products_cap.clone(),
+        proof.quotient_polys_cap.clone(),
     ];
 
     verify_fri_proof::<F, C, D>(
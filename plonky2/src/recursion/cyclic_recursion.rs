@@ -1,17 +1,24 @@
 #![allow(clippy::int_plus_one)] // Makes more sense for some inequalities below.
 
+use alloc::vec;
+
 use anyhow::{ensure, Result};
+use hashbrown::HashMap;
 
 use crate::field::extension::Extendable;
+use crate::gates::constant::ConstantGate;
+use crate::gates::noop::NoopGate;
 use crate::hash::hash_types::{HashOut, HashOutTarget, MerkleCapTarget, RichField};
 use crate::hash::merkle_tree::MerkleCap;
 use crate::iop::target::{BoolTarget, Target};
+use crate::iop::witness::{PartialWitness, WitnessWrite};
 use crate::plonk::circuit_builder::CircuitBuilder;
 use crate::plonk::circuit_data::{
-    CommonCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
+    CircuitConfig, CommonCircuitData, VerifierCircuitTarget, VerifierOnlyCircuitData,
 };
 use crate::plonk::config::{AlgebraicHasher, GenericConfig};
 use crate::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+use crate::recursion::dummy_circuit::cyclic_base_proof;
 
 impl<C: GenericConfig<D>, const D: usize> VerifierOnlyCircuitData<C, D> {
     fn from_slice(slice: &[C::F], common_data: &CommonCircuitData<C::F, D>) -> Result<Self>
@@ -155,6 +162,140 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
     }
 }
 
+/// The targets of a circuit which verifies either a previous proof of itself or a base-case proof.
+/// See `CircuitBuilder::add_cyclic_recursion`.
+#[derive(Clone, Debug)]
+pub struct CyclicRecursion<const D: usize> {
+    /// Whether `inner_proof` is a previous proof of this circuit, rather than a base-case proof.
+    pub condition: BoolTarget,
+    /// The inner proof. Its public inputs can be used to constrain the current step.
+    pub inner_proof: ProofWithPublicInputsTarget<D>,
+    /// The verifier data of this circuit, registered as its last public inputs.
+    pub verifier_data: VerifierCircuitTarget,
+}
+
+impl<const D: usize> CyclicRecursion<D> {
+    /// Sets the witness for the base case, where no previous proof exists. `nonzero_public_inputs`
+    /// gives the public inputs of the (unverified) base-case inner proof that should not be zero.
+    pub fn set_base_case<F, C>(
+        &self,
+        pw: &mut PartialWitness<F>,
+        verifier_data: &VerifierOnlyCircuitData<C, D>,
+        common_data: &CommonCircuitData<F, D>,
+        nonzero_public_inputs: HashMap<usize, F>,
+    ) where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        pw.set_bool_target(self.condition, false);
+        pw.set_proof_with_pis_target::<C, D>(
+            &self.inner_proof,
+            &cyclic_base_proof(common_data, verifier_data, nonzero_public_inputs),
+        );
+        pw.set_verifier_data_target(&self.verifier_data, verifier_data);
+    }
+
+    /// Sets the witness for a recursive step, which verifies `previous_proof`.
+    pub fn set_previous_proof<F, C>(
+        &self,
+        pw: &mut PartialWitness<F>,
+        verifier_data: &VerifierOnlyCircuitData<C, D>,
+        previous_proof: &ProofWithPublicInputs<F, C, D>,
+    ) where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        pw.set_bool_target(self.condition, true);
+        pw.set_proof_with_pis_target(&self.inner_proof, previous_proof);
+        pw.set_verifier_data_target(&self.verifier_data, verifier_data);
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Prepares this circuit to verify a previous proof of itself. This registers the verifier data
+    /// public inputs, so it must be called after all other public inputs have been registered. It
+    /// also sets `common_data.num_public_inputs` to match this circuit; `common_data` would
+    /// typically come from `common_data_for_recursion`.
+    ///
+    /// The caller can then constrain the current step using the public inputs of the returned
+    /// `inner_proof`, and must finally call `verify_cyclic_recursion`.
+    pub fn add_cyclic_recursion(
+        &mut self,
+        common_data: &mut CommonCircuitData<F, D>,
+    ) -> CyclicRecursion<D> {
+        let verifier_data = self.add_verifier_data_public_inputs();
+        common_data.num_public_inputs = self.num_public_inputs();
+        let condition = self.add_virtual_bool_target_safe();
+        let inner_proof = self.add_virtual_proof_with_pis(common_data);
+        CyclicRecursion {
+            condition,
+            inner_proof,
+            verifier_data,
+        }
+    }
+
+    /// Verifies the inner proof of `cyclic` if its condition is set, and a dummy proof otherwise.
+    /// This should be the last thing added to the circuit, since it pads the circuit to match
+    /// `common_data`.
+    pub fn verify_cyclic_recursion<C: GenericConfig<D, F = F> + 'static>(
+        &mut self,
+        cyclic: &CyclicRecursion<D>,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> Result<()>
+    where
+        C::Hasher: AlgebraicHasher<F>,
+    {
+        self.conditionally_verify_cyclic_proof_or_dummy::<C>(
+            cyclic.condition,
+            &cyclic.inner_proof,
+            common_data,
+        )
+    }
+}
+
+/// Generates `CommonCircuitData` for a circuit built with `config` which can recursively verify a
+/// proof of itself: its shape is that of a circuit containing one recursive verifier, padded to
+/// `2^degree_bits` gates. `degree_bits` must leave room for the verifier and the rest of the
+/// cyclic circuit. `num_public_inputs` is left at zero; `CircuitBuilder::add_cyclic_recursion`
+/// sets it.
+pub fn common_data_for_recursion<
+    F: RichField + Extendable<D>,
+    C: GenericConfig<D, F = F>,
+    const D: usize,
+>(
+    config: &CircuitConfig,
+    degree_bits: usize,
+) -> CommonCircuitData<F, D>
+where
+    C::Hasher: AlgebraicHasher<F>,
+{
+    let builder = CircuitBuilder::<F, D>::new(config.clone());
+    let data = builder.build::<C>();
+    let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+    let proof = builder.add_virtual_proof_with_pis(&data.common);
+    let verifier_data = builder.add_virtual_verifier_data(data.common.config.fri_config.cap_height);
+    builder.verify_proof::<C>(&proof, &verifier_data, &data.common);
+    let data = builder.build::<C>();
+
+    let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+    let proof = builder.add_virtual_proof_with_pis(&data.common);
+    let verifier_data = builder.add_virtual_verifier_data(data.common.config.fri_config.cap_height);
+    builder.verify_proof::<C>(&proof, &verifier_data, &data.common);
+    // A cyclic circuit always has constants, e.g. the dummy proof's verifier data, but the verifier
+    // alone might not, depending on how many rounds the hash uses.
+    builder.add_gate(ConstantGate::new(builder.config.num_constants), vec![]);
+    assert!(
+        builder.num_gates() <= 1 << degree_bits,
+        "The recursive verifier alone needs more than 2^{degree_bits} gates"
+    );
+    while builder.num_gates() < 1 << degree_bits {
+        builder.add_gate(NoopGate, vec![]);
+    }
+    builder.build::<C>().common
+}
+
 /// Additional checks to be performed on a cyclic recursive proof in addition to verifying the proof.
 /// Checks that the purported verifier data in the public inputs match the real verifier data.
 pub fn check_cyclic_proof_verifier_data<
@@ -179,52 +320,21 @@ where
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
+    use hashbrown::HashMap;
 
-    use crate::field::extension::Extendable;
     use crate::field::types::{Field, PrimeField64};
-    use crate::gates::noop::NoopGate;
     use crate::hash::hash_types::{HashOutTarget, RichField};
     use crate::hash::hashing::hash_n_to_hash_no_pad;
     use crate::hash::poseidon::{PoseidonHash, PoseidonPermutation};
     use crate::iop::witness::{PartialWitness, WitnessWrite};
     use crate::plonk::circuit_builder::CircuitBuilder;
-    use crate::plonk::circuit_data::{CircuitConfig, CommonCircuitData};
-    use crate::plonk::config::{AlgebraicHasher, GenericConfig, PoseidonGoldilocksConfig};
-    use crate::recursion::cyclic_recursion::check_cyclic_proof_verifier_data;
+    use crate::plonk::circuit_data::CircuitConfig;
+    use crate::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+    use crate::recursion::cyclic_recursion::{
+        check_cyclic_proof_verifier_data, common_data_for_recursion,
+    };
     use crate::recursion::dummy_circuit::cyclic_base_proof;
 
-    // Generates `CommonCircuitData` usable for recursion.
-    fn common_data_for_recursion<
-        F: RichField + Extendable<D>,
-        C: GenericConfig<D, F = F>,
-        const D: usize,
-    >() -> CommonCircuitData<F, D>
-    where
-        C::Hasher: AlgebraicHasher<F>,
-    {
-        let config = CircuitConfig::standard_recursion_config();
-        let builder = CircuitBuilder::<F, D>::new(config);
-        let data = builder.build::<C>();
-        let config = CircuitConfig::standard_recursion_config();
-        let mut builder = CircuitBuilder::<F, D>::new(config);
-        let proof = builder.add_virtual_proof_with_pis(&data.common);
-        let verifier_data =
-            builder.add_virtual_verifier_data(data.common.config.fri_config.cap_height);
-        builder.verify_proof::<C>(&proof, &verifier_data, &data.common);
-        let data = builder.build::<C>();
-
-        let config = CircuitConfig::standard_recursion_config();
-        let mut builder = CircuitBuilder::<F, D>::new(config);
-        let proof = builder.add_virtual_proof_with_pis(&data.common);
-        let verifier_data =
-            builder.add_virtual_verifier_data(data.common.config.fri_config.cap_height);
-        builder.verify_proof::<C>(&proof, &verifier_data, &data.common);
-        while builder.num_gates() < 1 << 12 {
-            builder.add_gate(NoopGate, vec![]);
-        }
-        builder.build::<C>().common
-    }
-
     /// Uses cyclic recursion to build a hash chain.
     /// The circuit has the following public input structure:
     /// - Initial hash (4)
@@ -238,7 +348,7 @@ mod tests {
         type F = <C as GenericConfig<D>>::F;
 
         let config = CircuitConfig::standard_recursion_config();
-        let mut builder = CircuitBuilder::<F, D>::new(config);
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
         let one = builder.one();
 
         // Circuit that computes a repeated hash.
@@ -250,7 +360,7 @@ mod tests {
         builder.register_public_inputs(&current_hash_out.elements);
         let counter = builder.add_virtual_public_input();
 
-        let mut common_data = common_data_for_recursion::<F, C, D>();
+        let mut common_data = common_data_for_recursion::<F, C, D>(&config, 12);
         let verifier_data_target = builder.add_verifier_data_public_inputs();
         common_data.num_public_inputs = builder.num_public_inputs();
 
@@ -344,6 +454,46 @@ mod tests {
         cyclic_circuit_data.verify(proof)
     }
 
+    /// Uses the `CyclicRecursion` helper to build a counter, whose only public input (besides the
+    /// cyclic verifier data) is the number of steps taken so far.
+    #[test]
+    fn test_cyclic_recursion_counter() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let counter = builder.add_virtual_public_input();
+
+        let mut common_data = common_data_for_recursion::<F, C, D>(&config, 12);
+        let cyclic = builder.add_cyclic_recursion(&mut common_data);
+
+        // The counter is the inner counter plus one, or one in the base case.
+        let one = builder.one();
+        let inner_counter = cyclic.inner_proof.public_inputs[0];
+        let new_counter = builder.mul_add(cyclic.condition.target, inner_counter, one);
+        builder.connect(counter, new_counter);
+
+        builder.verify_cyclic_recursion::<C>(&cyclic, &common_data)?;
+        let data = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        cyclic.set_base_case(&mut pw, &data.verifier_only, &common_data, HashMap::new());
+        let mut proof = data.prove(pw)?;
+
+        const NUM_STEPS: u64 = 3;
+        for _ in 1..NUM_STEPS {
+            let mut pw = PartialWitness::new();
+            cyclic.set_previous_proof(&mut pw, &data.verifier_only, &proof);
+            proof = data.prove(pw)?;
+        }
+
+        check_cyclic_proof_verifier_data(&proof, &data.verifier_only, &data.common)?;
+        assert_eq!(proof.public_inputs[0], F::from_canonical_u64(NUM_STEPS));
+        data.verify(proof)
+    }
+
     fn iterate_poseidon<F: RichField>(initial_state: [F; 4], n: usize) -> [F; 4] {
         let mut current = initial_state;
         for _ in 0..n {
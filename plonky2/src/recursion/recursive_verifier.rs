@@ -13,7 +13,11 @@ use crate::util::reducing::ReducingFactorTarget;
 use crate::with_context;
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
-    /// Recursively verifies an inner proof.
+    /// Recursively verifies an inner proof. This observes the inner public inputs and proof in an
+    /// in-circuit challenger, and checks the vanishing polynomial identity and the FRI proof, so
+    /// nothing else needs to be wired up. `proof_with_pis` and `inner_verifier_data` are typically
+    /// created with `add_virtual_proof_with_pis` and `add_virtual_verifier_data`, and filled with
+    /// `set_proof_with_pis_target` and `set_verifier_data_target`.
     pub fn verify_proof<C: GenericConfig<D, F = F>>(
         &mut self,
         proof_with_pis: &ProofWithPublicInputsTarget<D>,
@@ -126,6 +130,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
         );
     }
 
+    /// Adds targets for a proof of the circuit described by `common_data`, and its public inputs.
     pub fn add_virtual_proof_with_pis(
         &mut self,
         common_data: &CommonCircuitData<F, D>,
@@ -182,6 +187,7 @@ mod tests {
     use log::{info, Level};
 
     use super::*;
+    use crate::field::types::{Field, Sample};
     use crate::fri::reduction_strategies::FriReductionStrategy;
     use crate::fri::FriConfig;
     use crate::gates::noop::NoopGate;
@@ -314,6 +320,81 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_recursive_verifier_hashed_public_inputs() -> Result<()> {
+        init_logger();
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let inputs = builder.add_virtual_targets(100);
+        builder.register_public_inputs_hashed(&inputs);
+        let data = builder.build::<C>();
+        assert_eq!(data.common.num_public_inputs, 4);
+
+        let preimage = F::rand_vec(inputs.len());
+        let mut pw = PartialWitness::new();
+        for (&t, &x) in inputs.iter().zip(&preimage) {
+            pw.set_target(t, x);
+        }
+        let proof = data.prove(pw)?;
+        data.verify_with_public_inputs_preimage(proof.clone(), &preimage)?;
+
+        let mut wrong_preimage = preimage.clone();
+        wrong_preimage[0] += F::ONE;
+        assert!(data
+            .verify_with_public_inputs_preimage(proof.clone(), &wrong_preimage)
+            .is_err());
+
+        recursive_proof::<F, C, C, D>(
+            proof,
+            data.verifier_only,
+            data.common,
+            &config,
+            None,
+            false,
+            false,
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_verifier_public_inputs() -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        let config = CircuitConfig::standard_recursion_config();
+
+        // An inner circuit proving knowledge of a square root of its public input.
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let x = builder.add_virtual_target();
+        let square = builder.square(x);
+        builder.register_public_input(square);
+        let inner = builder.build::<C>();
+        let mut pw = PartialWitness::new();
+        pw.set_target(x, F::from_canonical_u64(7));
+        let inner_proof = inner.prove(pw)?;
+
+        // An outer circuit verifying it, and exposing the inner public input as its own.
+        let mut builder = CircuitBuilder::<F, D>::new(config.clone());
+        let proof_t = builder.add_virtual_proof_with_pis(&inner.common);
+        let verifier_data_t =
+            builder.add_virtual_verifier_data(inner.common.config.fri_config.cap_height);
+        builder.verify_proof::<C>(&proof_t, &verifier_data_t, &inner.common);
+        builder.register_public_inputs(&proof_t.public_inputs);
+        let outer = builder.build::<C>();
+
+        let mut pw = PartialWitness::new();
+        pw.set_proof_with_pis_target(&proof_t, &inner_proof);
+        pw.set_verifier_data_target(&verifier_data_t, &inner.verifier_only);
+        let outer_proof = outer.prove(pw)?;
+        assert_eq!(outer_proof.public_inputs, vec![F::from_canonical_u64(49)]);
+        outer.verify(outer_proof)
+    }
+
     type Proof<F, C, const D: usize> = (
         ProofWithPublicInputs<F, C, D>,
         VerifierOnlyCircuitData<C, D>,
@@ -4,7 +4,10 @@ use alloc::vec::Vec;
 
 use log::{log, Level};
 
+use crate::util::serialization::{Buffer, IoError, IoResult, Read, Write};
+
 /// The hierarchy of contexts, and the gate count contributed by each one. Useful for debugging.
+#[derive(Clone)]
 pub(crate) struct ContextTree {
     /// The name of this scope.
     name: String,
@@ -106,6 +109,55 @@ impl ContextTree {
         }
     }
 
+    /// Writes this tree to `dst`, for a serialized `CircuitBuilder`.
+    pub(crate) fn write(&self, dst: &mut Vec<u8>) -> IoResult<()> {
+        dst.write_str(&self.name)?;
+        dst.write_u8(self.level as u8)?;
+        dst.write_u32(self.enter_gate_count as u32)?;
+        match self.exit_gate_count {
+            Some(count) => {
+                dst.write_bool(true)?;
+                dst.write_u32(count as u32)?;
+            }
+            None => dst.write_bool(false)?,
+        }
+        dst.write_u32(self.children.len() as u32)?;
+        for child in &self.children {
+            child.write(dst)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a tree written by `write`.
+    pub(crate) fn read(src: &mut Buffer) -> IoResult<Self> {
+        let name = src.read_string()?;
+        let level = match src.read_u8()? {
+            1 => Level::Error,
+            2 => Level::Warn,
+            3 => Level::Info,
+            4 => Level::Debug,
+            5 => Level::Trace,
+            _ => return Err(IoError),
+        };
+        let enter_gate_count = src.read_u32()? as usize;
+        let exit_gate_count = if src.read_bool()? {
+            Some(src.read_u32()? as usize)
+        } else {
+            None
+        };
+        let num_children = src.read_u32()? as usize;
+        let children = (0..num_children)
+            .map(|_| Self::read(src))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            name,
+            level,
+            enter_gate_count,
+            exit_gate_count,
+            children,
+        })
+    }
+
     pub fn print(&self, current_gate_count: usize) {
         self.print_helper(current_gate_count, 0);
     }
@@ -0,0 +1,105 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use hashbrown::HashSet;
+
+use crate::iop::target::Target;
+use crate::iop::wire::Wire;
+
+/// Renders a circuit as a Graphviz DOT graph. Each gate row is a node labeled with its row and
+/// gate ID, each virtual target touched by a copy constraint is a point node, and each copy
+/// constraint is an edge labeled with the wires it connects.
+///
+/// If `max_nodes` is set, at most that many nodes are emitted: gate rows first, then virtual
+/// targets as they are encountered. Copy constraints touching an omitted node are dropped, and a
+/// note node records how much was left out.
+pub(crate) fn circuit_to_dot(
+    gate_ids: &[String],
+    copy_constraints: impl IntoIterator<Item = (Target, Target)>,
+    max_nodes: Option<usize>,
+) -> String {
+    fn escape(s: &str) -> String {
+        s.replace('\\', "\\\\").replace('"', "\\\"")
+    }
+
+    fn node(t: Target) -> String {
+        match t {
+            Target::Wire(Wire { row, .. }) => format!("g{row}"),
+            Target::VirtualTarget { index } => format!("v{index}"),
+        }
+    }
+
+    fn wire_label(t: Target) -> String {
+        match t {
+            Target::Wire(Wire { column, .. }) => format!("w{column}"),
+            Target::VirtualTarget { index } => format!("v{index}"),
+        }
+    }
+
+    let limit = max_nodes.unwrap_or(usize::MAX);
+    let num_rows = gate_ids.len();
+    let shown_rows = num_rows.min(limit);
+    let mut budget = limit - shown_rows;
+
+    let mut out = String::from("graph circuit {\n");
+    for (row, id) in gate_ids.iter().enumerate().take(shown_rows) {
+        writeln!(
+            out,
+            "  g{row} [shape=box, label=\"{row}: {}\"];",
+            escape(id)
+        )
+        .unwrap();
+    }
+
+    let mut shown_virtuals = HashSet::new();
+    let mut omitted_virtuals = HashSet::new();
+    let mut omitted_edges = 0;
+    for (a, b) in copy_constraints {
+        let mut new_virtuals = Vec::new();
+        let mut fits = true;
+        for t in [a, b] {
+            match t {
+                Target::Wire(Wire { row, .. }) => fits &= row < shown_rows,
+                Target::VirtualTarget { index } => {
+                    if !shown_virtuals.contains(&index) && !new_virtuals.contains(&index) {
+                        new_virtuals.push(index);
+                    }
+                }
+            }
+        }
+        if !fits || new_virtuals.len() > budget {
+            omitted_virtuals.extend(new_virtuals);
+            omitted_edges += 1;
+            continue;
+        }
+
+        for index in new_virtuals {
+            omitted_virtuals.remove(&index);
+            shown_virtuals.insert(index);
+            budget -= 1;
+            writeln!(out, "  v{index} [shape=point, xlabel=\"v{index}\"];").unwrap();
+        }
+        writeln!(
+            out,
+            "  {} -- {} [label=\"{} = {}\"];",
+            node(a),
+            node(b),
+            wire_label(a),
+            wire_label(b)
+        )
+        .unwrap();
+    }
+
+    let omitted_nodes = num_rows - shown_rows + omitted_virtuals.len();
+    if omitted_nodes > 0 || omitted_edges > 0 {
+        writeln!(
+            out,
+            "  truncated [shape=note, label=\"{omitted_nodes} nodes and {omitted_edges} copy constraints omitted\"];"
+        )
+        .unwrap();
+    }
+    out.push_str("}\n");
+    out
+}
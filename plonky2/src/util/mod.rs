@@ -8,6 +8,8 @@ use crate::field::polynomial::PolynomialValues;
 use crate::field::types::Field;
 
 pub(crate) mod context_tree;
+#[cfg(feature = "graph")]
+pub(crate) mod dot;
 pub(crate) mod partial_products;
 
 pub mod reducing;
@@ -272,6 +272,35 @@ impl<const D: usize> ReducingFactorTarget<D> {
     }
 }
 
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilder<F, D> {
+    /// Evaluates `sum coeffs[i] * base^i` with Horner's method, where `base` is an arbitrary
+    /// witness value rather than a constant challenge.
+    ///
+    /// `ReducingExtensionGate` takes its alpha on routed wires, so a variable base costs nothing
+    /// extra there: each row still has constraints of degree 2, as `base * acc` is the only product
+    /// of two wires. If the config is too narrow to fit a single coefficient in that gate, this
+    /// falls back to a chain of `mul_add_extension` calls, which also have degree 2 but use one
+    /// arithmetic operation per coefficient.
+    pub fn reduce_with_base_target(
+        &mut self,
+        coeffs: &[ExtensionTarget<D>],
+        base: ExtensionTarget<D>,
+    ) -> ExtensionTarget<D> {
+        // The gate needs `3*D` routed wires for the output, alpha and old accumulator, plus at
+        // least one coefficient.
+        let fits_gate = self.config.num_routed_wires >= 4 * D && self.config.num_wires >= 4 * D;
+        if fits_gate {
+            ReducingFactorTarget::new(base).reduce(coeffs, self)
+        } else {
+            let zero = self.zero_extension();
+            coeffs
+                .iter()
+                .rev()
+                .fold(zero, |acc, &c| self.mul_add_extension(base, acc, c))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
@@ -345,6 +374,98 @@ mod tests {
         verify(proof, &data.verifier_only, &data.common)
     }
 
+    /// Evaluates a degree-64 polynomial with base field coefficients at an extension point, and
+    /// compares it against a scalar Horner evaluation.
+    fn test_reduce_base_degree_64(config: CircuitConfig) -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = FF::rand();
+        let coeffs = F::rand_vec(65);
+
+        let horner = coeffs
+            .iter()
+            .rev()
+            .fold(FF::ZERO, |acc, &c| acc * x + FF::from(c));
+        let horner = builder.constant_extension(horner);
+
+        let mut x_t = ReducingFactorTarget::new(builder.constant_extension(x));
+        let coeffs_t = builder.add_virtual_targets(coeffs.len());
+        for (&c, &c_t) in coeffs.iter().zip(&coeffs_t) {
+            pw.set_target(c_t, c);
+        }
+        let circuit_eval = x_t.reduce_base(&coeffs_t, &mut builder);
+
+        builder.connect_extension(horner, circuit_eval);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_reduce_base_degree_64_standard_config() -> Result<()> {
+        test_reduce_base_degree_64(CircuitConfig::standard_recursion_config())
+    }
+
+    #[test]
+    fn test_reduce_base_degree_64_wide_config() -> Result<()> {
+        // Wide enough for a 64-coefficient `ReducingGate`.
+        let config = CircuitConfig {
+            num_wires: 200,
+            ..CircuitConfig::standard_recursion_config()
+        };
+        assert!(ReducingGate::<2>::max_coeffs_len(config.num_wires, config.num_routed_wires) >= 64);
+        test_reduce_base_degree_64(config)
+    }
+
+    fn test_reduce_with_base_target(n: usize) -> Result<()> {
+        const D: usize = 2;
+        type C = PoseidonGoldilocksConfig;
+        type F = <C as GenericConfig<D>>::F;
+        type FF = <C as GenericConfig<D>>::FE;
+
+        let config = CircuitConfig::standard_recursion_config();
+
+        let mut pw = PartialWitness::new();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let x = FF::rand();
+        let coeffs = FF::rand_vec(n);
+
+        let horner = coeffs.iter().rev().fold(FF::ZERO, |acc, &c| acc * x + c);
+        let horner = builder.constant_extension(horner);
+
+        let x_t = builder.add_virtual_extension_target();
+        pw.set_extension_target(x_t, x);
+        let coeffs_t = builder.add_virtual_extension_targets(n);
+        pw.set_extension_targets(&coeffs_t, &coeffs);
+        let circuit_eval = builder.reduce_with_base_target(&coeffs_t, x_t);
+
+        builder.connect_extension(horner, circuit_eval);
+
+        let data = builder.build::<C>();
+        let proof = data.prove(pw)?;
+
+        verify(proof, &data.verifier_only, &data.common)
+    }
+
+    #[test]
+    fn test_reduce_with_base_target_small() -> Result<()> {
+        test_reduce_with_base_target(3)
+    }
+
+    #[test]
+    fn test_reduce_with_base_target_100() -> Result<()> {
+        test_reduce_with_base_target(100)
+    }
+
     #[test]
     fn test_reduce_gadget_even() -> Result<()> {
         test_reduce_gadget(10)
@@ -1,11 +1,14 @@
+use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::convert::Infallible;
 use core::fmt::{Debug, Display, Formatter};
 use core::mem::size_of;
+use core::ops::Range;
 
 use hashbrown::HashMap;
 
+use crate::field::cosets::get_unique_coset_shifts;
 use crate::field::extension::{Extendable, FieldExtension};
 use crate::field::polynomial::PolynomialCoeffs;
 use crate::field::types::{Field64, PrimeField64};
@@ -13,14 +16,25 @@ use crate::fri::proof::{
     CompressedFriProof, CompressedFriQueryRounds, FriInitialTreeProof, FriProof, FriQueryRound,
     FriQueryStep,
 };
-use crate::hash::hash_types::RichField;
+use crate::fri::reduction_strategies::FriReductionStrategy;
+use crate::fri::{FriConfig, FriParams};
+use crate::gates::gate::GateRef;
+use crate::gates::selectors::SelectorsInfo;
+use crate::hash::hash_types::{HashOut, RichField};
 use crate::hash::merkle_proofs::MerkleProof;
 use crate::hash::merkle_tree::MerkleCap;
-use crate::plonk::circuit_data::CommonCircuitData;
+use crate::iop::ext_target::ExtensionTarget;
+use crate::iop::target::{BoolTarget, Target};
+use crate::iop::wire::Wire;
+use crate::iop::witness::PartialWitness;
+use crate::plonk::circuit_data::{
+    CircuitConfig, CommonCircuitData, VerifierCircuitData, VerifierOnlyCircuitData,
+};
 use crate::plonk::config::{GenericConfig, GenericHashOut, Hasher};
 use crate::plonk::plonk_common::salt_size;
 use crate::plonk::proof::{
-    CompressedProof, CompressedProofWithPublicInputs, OpeningSet, Proof, ProofWithPublicInputs,
+    CompressedProof, CompressedProofWithPublicInputs, FingerprintedProof, OpeningSet, Proof,
+    ProofWithPublicInputs,
 };
 
 /// A no_std compatible variant of `std::io::Error`
@@ -347,6 +361,30 @@ pub trait Read {
         })
     }
 
+    /// Reads a value of type [`FingerprintedProof`] of a [`ProofWithPublicInputs`] from `self` with
+    /// `common_data`.
+    #[inline]
+    fn read_fingerprinted_proof<F, C, const D: usize>(
+        &mut self,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<FingerprintedProof<F, ProofWithPublicInputs<F, C, D>>>
+    where
+        Self: Remaining,
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        let proof = self.read_proof(common_data)?;
+        let circuit_fingerprint = HashOut::from_vec(self.read_field_vec(4)?);
+        let public_inputs = self.read_field_vec(self.remaining() / size_of::<u64>())?;
+        Ok(FingerprintedProof {
+            proof: ProofWithPublicInputs {
+                proof,
+                public_inputs,
+            },
+            circuit_fingerprint,
+        })
+    }
+
     /// Reads a value of type [`CompressedFriQueryRounds`] from `self` with `common_data`.
     #[inline]
     fn read_compressed_fri_query_rounds<F, C, const D: usize>(
@@ -465,6 +503,251 @@ pub trait Read {
             public_inputs,
         })
     }
+
+    /// Reads a value of type [`FingerprintedProof`] of a [`CompressedProofWithPublicInputs`] from
+    /// `self` with `common_data`.
+    #[inline]
+    fn read_fingerprinted_compressed_proof<F, C, const D: usize>(
+        &mut self,
+        common_data: &CommonCircuitData<F, D>,
+    ) -> IoResult<FingerprintedProof<F, CompressedProofWithPublicInputs<F, C, D>>>
+    where
+        Self: Remaining,
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        let proof = self.read_compressed_proof(common_data)?;
+        let circuit_fingerprint = HashOut::from_vec(self.read_field_vec(4)?);
+        let public_inputs = self.read_field_vec(self.remaining() / size_of::<u64>())?;
+        Ok(FingerprintedProof {
+            proof: CompressedProofWithPublicInputs {
+                proof,
+                public_inputs,
+            },
+            circuit_fingerprint,
+        })
+    }
+
+    /// Reads a value of type [`Target`] from `self`.
+    #[inline]
+    fn read_target(&mut self) -> IoResult<Target> {
+        match self.read_u8()? {
+            0 => {
+                let row = self.read_u32()? as usize;
+                let column = self.read_u32()? as usize;
+                Ok(Target::wire(row, column))
+            }
+            1 => Ok(Target::VirtualTarget {
+                index: self.read_u32()? as usize,
+            }),
+            _ => Err(IoError),
+        }
+    }
+
+    /// Reads a length-prefixed vector of [`Target`]s from `self`.
+    #[inline]
+    fn read_target_vec(&mut self) -> IoResult<Vec<Target>> {
+        let len = self.read_u32()? as usize;
+        (0..len)
+            .map(|_| self.read_target())
+            .collect::<Result<Vec<_>, _>>()
+    }
+
+    /// Reads a value of type [`BoolTarget`] from `self`.
+    #[inline]
+    fn read_bool_target(&mut self) -> IoResult<BoolTarget> {
+        Ok(BoolTarget::new_unsafe(self.read_target()?))
+    }
+
+    /// Reads a value of type [`ExtensionTarget`] from `self`.
+    #[inline]
+    fn read_target_ext<const D: usize>(&mut self) -> IoResult<ExtensionTarget<D>> {
+        let mut targets = [Target::VirtualTarget { index: 0 }; D];
+        for t in targets.iter_mut() {
+            *t = self.read_target()?;
+        }
+        Ok(ExtensionTarget(targets))
+    }
+
+    /// Reads a length-prefixed UTF-8 string from `self`.
+    #[inline]
+    fn read_string(&mut self) -> IoResult<String>
+    where
+        Self: Remaining,
+    {
+        let len = self.read_u32()? as usize;
+        if len > self.remaining() {
+            return Err(IoError);
+        }
+        let mut bytes = vec![0; len];
+        self.read_exact(&mut bytes)?;
+        String::from_utf8(bytes).map_err(|_| IoError)
+    }
+
+    /// Reads a value of type [`PartialWitness`] from `self`.
+    #[inline]
+    fn read_partial_witness<F>(&mut self) -> IoResult<PartialWitness<F>>
+    where
+        Self: Remaining,
+        F: Field64,
+    {
+        // The smallest entry is a virtual target (a tag and an index) followed by a field element.
+        // Rejecting lengths which can't fit in the remaining bytes also bounds the allocation below.
+        const MIN_ENTRY_SIZE: usize = 1 + size_of::<u32>() + size_of::<u64>();
+        let len = self.read_u32()? as usize;
+        if len > self.remaining() / MIN_ENTRY_SIZE {
+            return Err(IoError);
+        }
+        let mut target_values = HashMap::with_capacity(len);
+        for _ in 0..len {
+            let target = self.read_target()?;
+            let value = self.read_field()?;
+            if target_values.insert(target, value).is_some() {
+                return Err(IoError);
+            }
+        }
+        Ok(PartialWitness { target_values })
+    }
+
+    /// Reads a `bool` value from `self`.
+    #[inline]
+    fn read_bool(&mut self) -> IoResult<bool> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(IoError),
+        }
+    }
+
+    /// Reads a length-prefixed vector of `usize` values from `self`.
+    #[inline]
+    fn read_usize_vec(&mut self) -> IoResult<Vec<usize>> {
+        let len = self.read_u32()? as usize;
+        (0..len)
+            .map(|_| Ok(self.read_u32()? as usize))
+            .collect::<Result<Vec<_>, _>>()
+    }
+
+    /// Reads a value of type [`FriConfig`] from `self`.
+    #[inline]
+    fn read_fri_config(&mut self) -> IoResult<FriConfig> {
+        let rate_bits = self.read_u32()? as usize;
+        let cap_height = self.read_u32()? as usize;
+        let proof_of_work_bits = self.read_u32()?;
+        let reduction_strategy = match self.read_u8()? {
+            0 => FriReductionStrategy::Fixed(self.read_usize_vec()?),
+            1 => FriReductionStrategy::ConstantArityBits(
+                self.read_u32()? as usize,
+                self.read_u32()? as usize,
+            ),
+            2 => FriReductionStrategy::MinSize(None),
+            3 => FriReductionStrategy::MinSize(Some(self.read_u32()? as usize)),
+            _ => return Err(IoError),
+        };
+        let num_query_rounds = self.read_u32()? as usize;
+        Ok(FriConfig {
+            rate_bits,
+            cap_height,
+            proof_of_work_bits,
+            reduction_strategy,
+            num_query_rounds,
+        })
+    }
+
+    /// Reads a value of type [`FriParams`] from `self`.
+    #[inline]
+    fn read_fri_params(&mut self) -> IoResult<FriParams> {
+        Ok(FriParams {
+            config: self.read_fri_config()?,
+            hiding: self.read_bool()?,
+            degree_bits: self.read_u32()? as usize,
+            reduction_arity_bits: self.read_usize_vec()?,
+        })
+    }
+
+    /// Reads a value of type [`CircuitConfig`] from `self`.
+    #[inline]
+    fn read_circuit_config(&mut self) -> IoResult<CircuitConfig> {
+        Ok(CircuitConfig {
+            num_wires: self.read_u32()? as usize,
+            num_routed_wires: self.read_u32()? as usize,
+            num_constants: self.read_u32()? as usize,
+            use_base_arithmetic_gate: self.read_bool()?,
+            security_bits: self.read_u32()? as usize,
+            num_challenges: self.read_u32()? as usize,
+            zero_knowledge: self.read_bool()?,
+            max_quotient_degree_factor: self.read_u32()? as usize,
+            fri_config: self.read_fri_config()?,
+        })
+    }
+
+    /// Reads a value of type [`SelectorsInfo`] from `self`.
+    #[inline]
+    fn read_selectors_info(&mut self) -> IoResult<SelectorsInfo> {
+        let selector_indices = self.read_usize_vec()?;
+        let num_groups = self.read_u32()? as usize;
+        let groups = (0..num_groups)
+            .map(|_| Ok(self.read_u32()? as usize..self.read_u32()? as usize))
+            .collect::<Result<Vec<Range<usize>>, _>>()?;
+        Ok(SelectorsInfo {
+            selector_indices,
+            groups,
+        })
+    }
+
+    /// Reads a value of type [`VerifierCircuitData`] from `self`, as written by
+    /// `write_verifier_circuit_data`, given the circuit's `gates` sorted as in
+    /// `CommonCircuitData::gates`. Fails if the restored data doesn't match the stored fingerprint,
+    /// e.g. because the gates differ.
+    #[inline]
+    fn read_verifier_circuit_data<F, C, const D: usize>(
+        &mut self,
+        gates: Vec<GateRef<F, D>>,
+    ) -> IoResult<VerifierCircuitData<F, C, D>>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        let config = self.read_circuit_config()?;
+        let constants_sigmas_cap = self.read_merkle_cap(config.fri_config.cap_height)?;
+        let circuit_digest = self.read_hash::<F, C::Hasher>()?;
+        let fri_params = self.read_fri_params()?;
+        let selectors_info = self.read_selectors_info()?;
+        let quotient_degree_factor = self.read_u32()? as usize;
+        let num_gate_constraints = self.read_u32()? as usize;
+        let num_constants = self.read_u32()? as usize;
+        let num_public_inputs = self.read_u32()? as usize;
+        let num_partial_products = self.read_u32()? as usize;
+        let fingerprint = HashOut::from_vec(self.read_field_vec(4)?);
+
+        // The LDE subgroup must exist in `F`; this also keeps `1 << degree_bits` from overflowing.
+        if fri_params.degree_bits + fri_params.config.rate_bits > F::TWO_ADICITY {
+            return Err(IoError);
+        }
+        let k_is = get_unique_coset_shifts(1 << fri_params.degree_bits, config.num_routed_wires);
+        let common = CommonCircuitData {
+            config,
+            fri_params,
+            gates,
+            selectors_info,
+            quotient_degree_factor,
+            num_gate_constraints,
+            num_constants,
+            num_public_inputs,
+            k_is,
+            num_partial_products,
+        };
+        if common.fingerprint() != fingerprint {
+            return Err(IoError);
+        }
+        Ok(VerifierCircuitData {
+            verifier_only: VerifierOnlyCircuitData {
+                constants_sigmas_cap,
+                circuit_digest,
+            },
+            common,
+        })
+    }
 }
 
 /// Writing
@@ -671,7 +954,7 @@ pub trait Write {
         self.write_fri_proof::<F, C, D>(&proof.opening_proof)
     }
 
-    /// Writes a value `proof_with_pis` of type [`ProofWithPublicInputs`] to `self.`
+    /// Writes a value `proof_with_pis` of type [`ProofWithPublicInputs`] to `self`.
     #[inline]
     fn write_proof_with_public_inputs<F, C, const D: usize>(
         &mut self,
@@ -689,6 +972,26 @@ pub trait Write {
         self.write_field_vec(public_inputs)
     }
 
+    /// Writes a value `fingerprinted` of type [`FingerprintedProof`] of a [`ProofWithPublicInputs`]
+    /// to `self`, with the circuit fingerprint between the proof and the public inputs.
+    #[inline]
+    fn write_fingerprinted_proof<F, C, const D: usize>(
+        &mut self,
+        fingerprinted: &FingerprintedProof<F, ProofWithPublicInputs<F, C, D>>,
+    ) -> IoResult<()>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        let ProofWithPublicInputs {
+            proof,
+            public_inputs,
+        } = &fingerprinted.proof;
+        self.write_proof(proof)?;
+        self.write_field_vec(&fingerprinted.circuit_fingerprint.elements)?;
+        self.write_field_vec(public_inputs)
+    }
+
     /// Writes a value `cfqrs` of type [`CompressedFriQueryRounds`] to `self.`
     #[inline]
     fn write_compressed_fri_query_rounds<F, C, const D: usize>(
@@ -752,7 +1055,7 @@ pub trait Write {
         self.write_compressed_fri_proof::<F, C, D>(&proof.opening_proof)
     }
 
-    /// Writes a value `proof_with_pis` of type [`CompressedProofWithPublicInputs`] to `self.`
+    /// Writes a value `proof_with_pis` of type [`CompressedProofWithPublicInputs`] to `self`.
     #[inline]
     fn write_compressed_proof_with_public_inputs<F, C, const D: usize>(
         &mut self,
@@ -769,6 +1072,197 @@ pub trait Write {
         self.write_compressed_proof(proof)?;
         self.write_field_vec(public_inputs)
     }
+
+    /// Writes a value `fingerprinted` of type [`FingerprintedProof`] of a
+    /// [`CompressedProofWithPublicInputs`] to `self`, with the circuit fingerprint between the proof
+    /// and the public inputs.
+    #[inline]
+    fn write_fingerprinted_compressed_proof<F, C, const D: usize>(
+        &mut self,
+        fingerprinted: &FingerprintedProof<F, CompressedProofWithPublicInputs<F, C, D>>,
+    ) -> IoResult<()>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        let CompressedProofWithPublicInputs {
+            proof,
+            public_inputs,
+        } = &fingerprinted.proof;
+        self.write_compressed_proof(proof)?;
+        self.write_field_vec(&fingerprinted.circuit_fingerprint.elements)?;
+        self.write_field_vec(public_inputs)
+    }
+
+    /// Writes a target `t` to `self.`
+    #[inline]
+    fn write_target(&mut self, t: Target) -> IoResult<()> {
+        match t {
+            Target::Wire(Wire { row, column }) => {
+                self.write_u8(0)?;
+                self.write_u32(row as u32)?;
+                self.write_u32(column as u32)
+            }
+            Target::VirtualTarget { index } => {
+                self.write_u8(1)?;
+                self.write_u32(index as u32)
+            }
+        }
+    }
+
+    /// Writes a vector `v` of [`Target`]s to `self`, prefixed by its length.
+    #[inline]
+    fn write_target_vec(&mut self, v: &[Target]) -> IoResult<()> {
+        self.write_u32(v.len() as u32)?;
+        for &t in v {
+            self.write_target(t)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a value `b` of type [`BoolTarget`] to `self`.
+    #[inline]
+    fn write_bool_target(&mut self, b: BoolTarget) -> IoResult<()> {
+        self.write_target(b.target)
+    }
+
+    /// Writes a value `et` of type [`ExtensionTarget`] to `self`.
+    #[inline]
+    fn write_target_ext<const D: usize>(&mut self, et: ExtensionTarget<D>) -> IoResult<()> {
+        for t in et.0 {
+            self.write_target(t)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a string `s` to `self`, prefixed by its length in bytes.
+    #[inline]
+    fn write_str(&mut self, s: &str) -> IoResult<()> {
+        self.write_u32(s.len() as u32)?;
+        self.write_all(s.as_bytes())
+    }
+
+    /// Writes a value `witness` of type [`PartialWitness`] to `self.` Entries are sorted by target,
+    /// so that equal witnesses are always encoded the same way.
+    #[inline]
+    fn write_partial_witness<F>(&mut self, witness: &PartialWitness<F>) -> IoResult<()>
+    where
+        F: PrimeField64,
+    {
+        let mut entries = witness.target_values.iter().collect::<Vec<_>>();
+        entries.sort_unstable_by_key(|&(&t, _)| match t {
+            Target::Wire(Wire { row, column }) => (0, row, column),
+            Target::VirtualTarget { index } => (1, index, 0),
+        });
+        self.write_u32(entries.len() as u32)?;
+        for (&t, &value) in entries {
+            self.write_target(t)?;
+            self.write_field(value)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a bool `b` to `self`.
+    #[inline]
+    fn write_bool(&mut self, b: bool) -> IoResult<()> {
+        self.write_u8(b as u8)
+    }
+
+    /// Writes a vector `v` of `usize` values to `self`, prefixed by its length.
+    #[inline]
+    fn write_usize_vec(&mut self, v: &[usize]) -> IoResult<()> {
+        self.write_u32(v.len() as u32)?;
+        for &x in v {
+            self.write_u32(x as u32)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a value `config` of type [`FriConfig`] to `self`.
+    #[inline]
+    fn write_fri_config(&mut self, config: &FriConfig) -> IoResult<()> {
+        self.write_u32(config.rate_bits as u32)?;
+        self.write_u32(config.cap_height as u32)?;
+        self.write_u32(config.proof_of_work_bits)?;
+        match &config.reduction_strategy {
+            FriReductionStrategy::Fixed(arity_bits) => {
+                self.write_u8(0)?;
+                self.write_usize_vec(arity_bits)?;
+            }
+            FriReductionStrategy::ConstantArityBits(arity_bits, final_poly_bits) => {
+                self.write_u8(1)?;
+                self.write_u32(*arity_bits as u32)?;
+                self.write_u32(*final_poly_bits as u32)?;
+            }
+            FriReductionStrategy::MinSize(None) => self.write_u8(2)?,
+            FriReductionStrategy::MinSize(Some(max_arity_bits)) => {
+                self.write_u8(3)?;
+                self.write_u32(*max_arity_bits as u32)?;
+            }
+        }
+        self.write_u32(config.num_query_rounds as u32)
+    }
+
+    /// Writes a value `params` of type [`FriParams`] to `self`.
+    #[inline]
+    fn write_fri_params(&mut self, params: &FriParams) -> IoResult<()> {
+        self.write_fri_config(&params.config)?;
+        self.write_bool(params.hiding)?;
+        self.write_u32(params.degree_bits as u32)?;
+        self.write_usize_vec(&params.reduction_arity_bits)
+    }
+
+    /// Writes a value `config` of type [`CircuitConfig`] to `self`.
+    #[inline]
+    fn write_circuit_config(&mut self, config: &CircuitConfig) -> IoResult<()> {
+        self.write_u32(config.num_wires as u32)?;
+        self.write_u32(config.num_routed_wires as u32)?;
+        self.write_u32(config.num_constants as u32)?;
+        self.write_bool(config.use_base_arithmetic_gate)?;
+        self.write_u32(config.security_bits as u32)?;
+        self.write_u32(config.num_challenges as u32)?;
+        self.write_bool(config.zero_knowledge)?;
+        self.write_u32(config.max_quotient_degree_factor as u32)?;
+        self.write_fri_config(&config.fri_config)
+    }
+
+    /// Writes a value `data` of type [`VerifierCircuitData`] to `self`. Gates aren't written, and
+    /// neither are the `k_is`, which are recomputed on reading; a fingerprint of the whole common
+    /// data is written instead, to check that they are restored correctly.
+    #[inline]
+    fn write_verifier_circuit_data<F, C, const D: usize>(
+        &mut self,
+        data: &VerifierCircuitData<F, C, D>,
+    ) -> IoResult<()>
+    where
+        F: RichField + Extendable<D>,
+        C: GenericConfig<D, F = F>,
+    {
+        let common = &data.common;
+        self.write_circuit_config(&common.config)?;
+        self.write_merkle_cap(&data.verifier_only.constants_sigmas_cap)?;
+        self.write_hash::<F, C::Hasher>(data.verifier_only.circuit_digest)?;
+        self.write_fri_params(&common.fri_params)?;
+        self.write_selectors_info(&common.selectors_info)?;
+        self.write_u32(common.quotient_degree_factor as u32)?;
+        self.write_u32(common.num_gate_constraints as u32)?;
+        self.write_u32(common.num_constants as u32)?;
+        self.write_u32(common.num_public_inputs as u32)?;
+        self.write_u32(common.num_partial_products as u32)?;
+        self.write_field_vec(&common.fingerprint().elements)
+    }
+
+    /// Writes a value `info` of type [`SelectorsInfo`] to `self`.
+    #[inline]
+    fn write_selectors_info(&mut self, info: &SelectorsInfo) -> IoResult<()> {
+        self.write_usize_vec(&info.selector_indices)?;
+        self.write_u32(info.groups.len() as u32)?;
+        for group in &info.groups {
+            self.write_u32(group.start as u32)?;
+            self.write_u32(group.end as u32)?;
+        }
+        Ok(())
+    }
 }
 
 impl Write for Vec<u8> {
@@ -782,14 +1276,12 @@ impl Write for Vec<u8> {
 }
 
 /// Buffer
-#[cfg(feature = "std")]
 #[derive(Debug)]
 pub struct Buffer {
     bytes: Vec<u8>,
     pos: usize,
 }
 
-#[cfg(feature = "std")]
 impl Buffer {
     /// Builds a new [`Buffer`] over `buffer`.
     #[inline]
@@ -804,14 +1296,12 @@ impl Buffer {
     }
 }
 
-#[cfg(feature = "std")]
 impl Remaining for Buffer {
     fn remaining(&self) -> usize {
         self.bytes.len() - self.pos
     }
 }
 
-#[cfg(feature = "std")]
 impl Read for Buffer {
     #[inline]
     fn read_exact(&mut self, bytes: &mut [u8]) -> IoResult<()> {
@@ -127,6 +127,14 @@ impl TimingTree {
             .duration_since(self.enter_time)
     }
 
+    /// The name and duration of each direct child scope, in the order they were opened.
+    #[cfg(feature = "timing")]
+    pub fn child_durations(&self) -> impl Iterator<Item = (&str, Duration)> + '_ {
+        self.children
+            .iter()
+            .map(|c| (c.name.as_str(), c.duration()))
+    }
+
     /// Filter out children with a low duration.
     #[cfg(feature = "timing")]
     pub fn filter(&self, min_delta: Duration) -> Self {